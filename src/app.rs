@@ -1,4 +1,15 @@
-use crate::db::{read_sqlite_article_table, read_sqlite_book_table, Article, Book, RowSelect};
+use crate::bibtex::{export_bibtex, BIBTEX_EXPORT_FILE};
+use crate::csv::{import_table, ColumnMapping, Table};
+use crate::db::{
+    publisher_suggestions, read_catalog_entries_with_keys, read_sqlite_publisher_table, Article,
+    Book, Catalog, DbError, DbResult, EntryType, Publisher, RowSelect, PUBLISHER_LINE,
+};
+use crate::item::{ItemType, ITEM_PUBLISHER_LINE, ITEM_TYPES};
+use crate::listing::{CachedRows, ListFilter, ListView, ListWindow, SortKey};
+use crate::organization::{read_organization_table, Organization};
+use crate::query::advanced_search;
+use crate::relationship::{select_links, Relationship};
+use crate::search::{search_catalog, SearchResults};
 use crate::ui::*;
 use crossterm::event;
 use crossterm::event::{Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
@@ -6,14 +17,14 @@ use crossterm::terminal::disable_raw_mode;
 use ratatui::backend::Backend;
 use ratatui::widgets::ListState;
 use ratatui::Terminal;
+use std::collections::HashSet;
 use std::io::Error;
 use std::sync::{mpsc, Arc, Mutex};
 use std::thread;
 use std::time::{Duration, Instant};
-use tui_textarea::TextArea;
+use tui_textarea::{CursorMove, TextArea};
 
 /// The app module structs and functions
-// todo! Future implementation show ALL items sorted by cite_key?
 #[derive(Copy, Clone, Debug)]
 pub(crate) enum InputMode {
     Command,
@@ -29,59 +40,159 @@ pub(crate) enum AppEvent<I> {
 #[derive(Copy, Clone, Debug)]
 pub(crate) enum MenuItem {
     // todo! Future implementation: do sub menus:
-    // `Home` `Display All Items` `Books` `Articles` `Quit`
     // `Books`: `Display All` `Add New Book` `Delete Book` `Find Book`
     // `Articles`: `Display All` `Add New Article` `Delete Article` `Find Article`
     Home,
+    /// Every record whatever its type; opens the list of the selected record's type
+    AllItems,
+    /// Search prompt over the whole catalog, with the records found below it
+    Search(InputMode),
     ShowBooks,
     NewBook(InputMode),
     ListArticles,
     InsertArticle(InputMode),
+    /// Items of one of the other item types, see `App::item_type`
+    Items,
+    NewItem(InputMode),
+    Publishers,
+    EditPublisher(InputMode),
+    Organizations,
+    EditOrganization(InputMode),
+    /// Link form for the record selected in the list it was opened from
+    EditLink(InputMode),
+    /// Filter form for the list it was opened from
+    EditFilter(InputMode),
+    /// Column mapping of the spreadsheet opened with `import csv <file>`, see `App::open_csv_import`
+    ImportCsv(InputMode),
 }
 
 impl MenuItem {
     fn ordinal(&self) -> usize {
         match self {
-            MenuItem::Home => 0,
-            MenuItem::ShowBooks => 1,
-            MenuItem::NewBook(_) => 2,
-            MenuItem::ListArticles => 3,
-            MenuItem::InsertArticle(_) => 4,
+            MenuItem::Home | MenuItem::ImportCsv(_) => 0,
+            MenuItem::AllItems | MenuItem::Search(_) => 1,
+            MenuItem::ShowBooks => 2,
+            MenuItem::NewBook(_) => 3,
+            MenuItem::ListArticles => 4,
+            MenuItem::InsertArticle(_) => 5,
+            MenuItem::Items | MenuItem::NewItem(_) => 6,
+            MenuItem::Publishers | MenuItem::EditPublisher(_) => 7,
+            MenuItem::Organizations | MenuItem::EditOrganization(_) => 8,
+            // shown under the list they were opened from, see `App::menu_index`
+            MenuItem::EditLink(_) | MenuItem::EditFilter(_) => 2,
         }
     }
 }
 
-#[derive(Clone)]
-pub struct App {
+pub struct App<'c> {
     pub menu_titles: Vec<&'static str>,
     pub index: usize,
     active_menu_item: MenuItem,
-    pub book_list_state: Arc<Mutex<ListState>>,
-    pub article_list_state: Arc<Mutex<ListState>>,
+    /// Every record under `Catalog`
+    catalog_list: ListWindow,
+    pub search_list_state: Arc<Mutex<ListState>>,
+    /// Words typed in the search prompt
+    search_text: String,
+    /// Read the search prompt as a fielded query, see `query::parse_query`
+    advanced_search: bool,
+    /// Records found for `search_text`, searched again when it or the catalog changes
+    search_results: SearchResults,
+    book_list: ListWindow,
+    article_list: ListWindow,
+    /// Items of the type listed under `Item Types`; the order and filter are shared by every type
+    item_list: ListWindow,
+    /// Publishers with the number of records using each, read again when the catalog changes
+    publisher_list: CachedRows<(Publisher, i64)>,
+    pub publisher_list_state: Arc<Mutex<ListState>>,
+    /// Organizations with their parent body and number of records, read again when the catalog
+    /// changes
+    organization_list: CachedRows<(Organization, String, i64)>,
+    pub organization_list_state: Arc<Mutex<ListState>>,
     update_item_id: String,
     update_flag: bool,
+    /// Position in `ITEM_TYPES` of the type listed under `Item Types`
+    item_type_index: usize,
+    /// Link picked with `Tab` in the detail pane of the selected record
+    link_index: Option<usize>,
+    /// List to return to when the link or filter form is closed
+    form_source: MenuItem,
+    /// `cite_key` of the records marked with `M`, exported together with `Ctrl-E`
+    marked: HashSet<String>,
+    catalog: Catalog<'c>,
+    message: Option<String>,
+    /// What the last command did, shown like `message` but not as an error
+    notice: Option<String>,
+    /// Path and rows of the spreadsheet shown under `ImportCsv`
+    csv_import: Option<(String, Table)>,
+    /// Report of the last import or check of the spreadsheet, shown until the mapping is edited
+    csv_report: Option<String>,
 }
 
-impl App {
-    pub fn new() -> App {
+impl<'c> App<'c> {
+    pub fn new(catalog: Catalog<'c>) -> App<'c> {
         App {
             menu_titles: vec![
                 "Home",
+                "Catalog",
                 "Show Books",
                 "Book Add",
                 "List Articles",
                 "Article Add",
+                "Item Types",
+                "Publishers",
+                "Organizations",
                 "Quit",
             ],
             index: 0,
             active_menu_item: MenuItem::Home,
-            book_list_state: Arc::new(Mutex::new(ListState::default())),
-            article_list_state: Arc::new(Mutex::new(ListState::default())),
+            catalog_list: ListWindow::new(None, ListView::new(SortKey::Title)),
+            search_list_state: Arc::new(Mutex::new(ListState::default())),
+            search_text: String::new(),
+            advanced_search: false,
+            search_results: SearchResults::new(),
+            book_list: ListWindow::new(Some(EntryType::Book), ListView::new(SortKey::Added)),
+            article_list: ListWindow::new(Some(EntryType::Article), ListView::new(SortKey::Added)),
+            item_list: ListWindow::new(
+                Some(ITEM_TYPES[0].entry_type),
+                ListView::new(SortKey::Added),
+            ),
+            publisher_list: CachedRows::new(),
+            publisher_list_state: Arc::new(Mutex::new(ListState::default())),
+            organization_list: CachedRows::new(),
+            organization_list_state: Arc::new(Mutex::new(ListState::default())),
             update_item_id: "".to_string(),
             update_flag: false,
+            item_type_index: 0,
+            link_index: None,
+            form_source: MenuItem::ShowBooks,
+            marked: HashSet::new(),
+            catalog,
+            message: None,
+            notice: None,
+            csv_import: None,
+            csv_report: None,
         }
     }
 
+    /// Start on the column mapping of the spreadsheet read from `path`, filled in from
+    /// `<path>.map` if an earlier import kept one there, or else guessed from the header
+    pub fn open_csv_import(&mut self, path: String, table: Table) {
+        self.active_menu_item = MenuItem::ImportCsv(InputMode::Input);
+        self.csv_import = Some((path, table));
+    }
+
+    /// Lines of the column mapping form for the spreadsheet opened with `open_csv_import`
+    fn csv_mapping_lines(&self) -> Vec<String> {
+        let Some((path, table)) = &self.csv_import else {
+            return Vec::new();
+        };
+        std::fs::read_to_string(format!("{path}.map"))
+            .ok()
+            .and_then(|contents| ColumnMapping::from_file(&contents).ok())
+            .unwrap_or_else(|| ColumnMapping::guess(table))
+            .form_lines()
+    }
+
     /// Run the terminal loop with event handlers
     pub fn run<B: Backend>(&mut self, terminal: &mut Terminal<B>) -> Result<(), Error> {
         // setup mpsc to handle the channels in the rendering loop
@@ -105,24 +216,93 @@ impl App {
 
         let mut book_text_area = TextArea::default();
         let mut article_text_area = TextArea::default();
+        let mut item_text_area = TextArea::default();
+        let mut publisher_text_area = TextArea::default();
+        let mut organization_text_area = TextArea::default();
+        let mut link_text_area = TextArea::default();
+        let mut search_text_area = TextArea::default();
+        let mut filter_text_area = TextArea::default();
+        let mut mapping_text_area = TextArea::new(self.csv_mapping_lines());
 
         loop {
+            let suggestions = match self.active_menu_item {
+                MenuItem::NewBook(InputMode::Input) => {
+                    self.suggestions(&book_text_area, PUBLISHER_LINE)
+                }
+                MenuItem::InsertArticle(InputMode::Input) => {
+                    self.suggestions(&article_text_area, PUBLISHER_LINE)
+                }
+                MenuItem::NewItem(InputMode::Input) => {
+                    self.suggestions(&item_text_area, ITEM_PUBLISHER_LINE)
+                }
+                _ => Vec::new(),
+            };
             let terminal_size = terminal.size().expect("should size terminal");
+            // only the rows that fit in the list pane are read, inside its borders
+            let list_height = show_panes(panes(terminal_size))[0].height.saturating_sub(2);
+            if let Some(refreshed) = self
+                .with_window(|window, catalog| window.refresh(catalog, usize::from(list_height)))
+            {
+                self.report_error(refreshed);
+            }
+            let refreshed = self.refresh_table();
+            self.report_error(refreshed);
+            self.refresh_search();
             let menu_titles = self.menu_titles.iter().cloned();
             let active_menu_item = self.active_menu_item;
-            let book_list_state = self.book_list_state.clone();
-            let article_list_state = self.article_list_state.clone();
+            let menu_index = self.menu_index();
+            let link_index = self.link_index;
+            let catalog_list = &self.catalog_list;
+            let search_list_state = self.search_list_state.clone();
+            let search_text = self.search_text.clone();
+            let search_results = &self.search_results;
+            let advanced_search = self.advanced_search;
+            let book_list = &self.book_list;
+            let article_list = &self.article_list;
+            let item_list = &self.item_list;
+            let item_type = self.item_type();
+            let publisher_list = self.publisher_list.rows();
+            let publisher_list_state = self.publisher_list_state.clone();
+            let organization_list = self.organization_list.rows();
+            let organization_list_state = self.organization_list_state.clone();
+            let shown_suggestions = suggestions.clone();
+            let message = self.message.clone();
+            let notice = self.notice.clone();
+            let marked = self.marked.clone();
+            let csv_preview = match (&self.csv_import, active_menu_item) {
+                (Some((path, table)), MenuItem::ImportCsv(_)) => Some(render_csv_preview(
+                    path,
+                    table,
+                    mapping_text_area.lines(),
+                    self.csv_report.as_deref(),
+                )),
+                _ => None,
+            };
             book_text_area.set_block(new_book_block(self.update_flag));
             let book_text_widget = book_text_area.widget();
             article_text_area.set_block(new_article_block(self.update_flag));
             let article_text_widget = article_text_area.widget();
+            item_text_area.set_block(new_item_block(item_type, self.update_flag));
+            let item_text_widget = item_text_area.widget();
+            publisher_text_area.set_block(edit_publisher_block());
+            let publisher_text_widget = publisher_text_area.widget();
+            organization_text_area.set_block(edit_organization_block(self.update_flag));
+            let organization_text_widget = organization_text_area.widget();
+            link_text_area.set_block(edit_link_block());
+            let link_text_widget = link_text_area.widget();
+            filter_text_area.set_block(edit_filter_block());
+            let filter_text_widget = filter_text_area.widget();
+            mapping_text_area.set_block(csv_mapping_block());
+            let mapping_text_widget = mapping_text_area.widget();
+            search_text_area.set_block(search_block(self.advanced_search));
+            let search_text_widget = search_text_area.widget();
 
             // todo! Future implementation: Move the terminal to a tui.rs?
             terminal.draw(move |frame| {
                 let chunks = panes(terminal_size);
 
                 // Main menu section
-                frame.render_widget(menu(menu_titles, active_menu_item.ordinal()), chunks[0]);
+                frame.render_widget(menu(menu_titles, menu_index), chunks[0]);
 
                 // Change to a different menu item
                 match active_menu_item {
@@ -131,15 +311,116 @@ impl App {
                         frame.render_widget(left, home_panes(chunks.clone())[0]);
                         frame.render_widget(right, home_panes(chunks.clone())[1]);
                     }
+                    MenuItem::AllItems => {
+                        let (left, middle, right) = render_all_items(catalog_list, &marked);
+                        let mut state = ListState::default();
+                        state.select(catalog_list.selected_row());
+                        frame.render_stateful_widget(
+                            left,
+                            show_panes(chunks.clone())[0],
+                            &mut state,
+                        );
+                        frame.render_widget(middle, show_panes(chunks.clone())[1]);
+                        frame.render_widget(right, show_panes(chunks.clone())[2]);
+                    }
+                    MenuItem::Search(..) => {
+                        let panes = search_panes(chunks.clone());
+                        frame.render_widget(search_text_widget, panes[0]);
+                        let results = match render_search_results(
+                            search_results.found(),
+                            &search_text,
+                            advanced_search,
+                        ) {
+                            Ok(results) => results,
+                            Err(problem) => {
+                                frame.render_widget(*problem, panes[1]);
+                                return;
+                            }
+                        };
+                        let mut lock = search_list_state.lock().expect("should lock state");
+                        frame.render_stateful_widget(results, panes[1], &mut *lock);
+                    }
                     MenuItem::ShowBooks => {
-                        let mut lock = book_list_state.lock().expect("should lock state");
+                        let (left, middle, right) = render_books(book_list, link_index, &marked);
+                        let mut state = ListState::default();
+                        state.select(book_list.selected_row());
+                        frame.render_stateful_widget(
+                            left,
+                            show_panes(chunks.clone())[0],
+                            &mut state,
+                        );
+                        frame.render_widget(middle, show_panes(chunks.clone())[1]);
+                        frame.render_widget(right, show_panes(chunks.clone())[2]);
+                    }
+                    MenuItem::NewBook(..) => {
+                        frame.render_widget(render_add_book(), add_panes(chunks.clone())[0]);
+                        if shown_suggestions.is_empty() {
+                            frame.render_widget(book_text_widget, add_panes(chunks.clone())[1]);
+                        } else {
+                            let panes = suggestion_panes(add_panes(chunks.clone())[1]);
+                            frame.render_widget(book_text_widget, panes[0]);
+                            frame.render_widget(render_suggestions(shown_suggestions), panes[1]);
+                        }
+                    }
+                    MenuItem::ListArticles => {
+                        let (left, middle, right) =
+                            render_articles(article_list, link_index, &marked);
+                        let mut state = ListState::default();
+                        state.select(article_list.selected_row());
+                        frame.render_stateful_widget(
+                            left,
+                            show_panes(chunks.clone())[0],
+                            &mut state,
+                        );
+                        frame.render_widget(middle, show_panes(chunks.clone())[1]);
+                        frame.render_widget(right, show_panes(chunks.clone())[2]);
+                    }
+                    MenuItem::InsertArticle(..) => {
+                        frame.render_widget(render_add_article(), add_panes(chunks.clone())[0]);
+                        if shown_suggestions.is_empty() {
+                            frame.render_widget(article_text_widget, add_panes(chunks.clone())[1]);
+                        } else {
+                            let panes = suggestion_panes(add_panes(chunks.clone())[1]);
+                            frame.render_widget(article_text_widget, panes[0]);
+                            frame.render_widget(render_suggestions(shown_suggestions), panes[1]);
+                        }
+                    }
+                    MenuItem::Items => {
+                        let (left, middle, right) =
+                            render_items(item_list, item_type, link_index, &marked);
+                        let mut state = ListState::default();
+                        state.select(item_list.selected_row());
+                        frame.render_stateful_widget(
+                            left,
+                            show_panes(chunks.clone())[0],
+                            &mut state,
+                        );
+                        frame.render_widget(middle, show_panes(chunks.clone())[1]);
+                        frame.render_widget(right, show_panes(chunks.clone())[2]);
+                    }
+                    MenuItem::NewItem(..) => {
+                        frame.render_widget(
+                            render_add_item(item_type),
+                            add_panes(chunks.clone())[0],
+                        );
+                        if shown_suggestions.is_empty() {
+                            frame.render_widget(item_text_widget, add_panes(chunks.clone())[1]);
+                        } else {
+                            let panes = suggestion_panes(add_panes(chunks.clone())[1]);
+                            frame.render_widget(item_text_widget, panes[0]);
+                            frame.render_widget(render_suggestions(shown_suggestions), panes[1]);
+                        }
+                    }
+                    MenuItem::Publishers => {
+                        let mut lock = publisher_list_state.lock().expect("should lock state");
                         if lock.selected().is_none() {
                             lock.select(Some(0));
                         }
                         drop(lock);
 
-                        let (left, middle, right) = render_books(book_list_state.clone());
-                        let mut lock = book_list_state.lock().expect("should lock state");
+                        let (left, middle, right) =
+                            render_publishers(publisher_list_state.clone(), publisher_list);
+                        let mut lock = publisher_list_state.lock().expect("should lock state");
                         frame.render_stateful_widget(
                             left,
                             show_panes(chunks.clone())[0],
@@ -149,19 +430,22 @@ impl App {
                         frame.render_widget(right, show_panes(chunks.clone())[2]);
                         drop(lock);
                     }
-                    MenuItem::NewBook(..) => {
-                        frame.render_widget(render_add_book(), add_panes(chunks.clone())[0]);
-                        frame.render_widget(book_text_widget, add_panes(chunks.clone())[1]);
+                    MenuItem::EditPublisher(..) => {
+                        frame.render_widget(render_edit_publisher(), add_panes(chunks.clone())[0]);
+                        frame.render_widget(publisher_text_widget, add_panes(chunks.clone())[1]);
                     }
-                    MenuItem::ListArticles => {
-                        let mut lock = article_list_state.lock().expect("should lock state");
+                    MenuItem::Organizations => {
+                        let mut lock = organization_list_state.lock().expect("should lock state");
                         if lock.selected().is_none() {
                             lock.select(Some(0));
                         }
                         drop(lock);
 
-                        let (left, middle, right) = render_articles(article_list_state.clone());
-                        let mut lock = article_list_state.lock().expect("should lock state");
+                        let (left, middle, right) = render_organizations(
+                            organization_list_state.clone(),
+                            organization_list,
+                        );
+                        let mut lock = organization_list_state.lock().expect("should lock state");
                         frame.render_stateful_widget(
                             left,
                             show_panes(chunks.clone())[0],
@@ -171,18 +455,52 @@ impl App {
                         frame.render_widget(right, show_panes(chunks.clone())[2]);
                         drop(lock);
                     }
-                    MenuItem::InsertArticle(..) => {
-                        frame.render_widget(render_add_article(), add_panes(chunks.clone())[0]);
-                        frame.render_widget(article_text_widget, add_panes(chunks.clone())[1]);
+                    MenuItem::EditOrganization(..) => {
+                        frame.render_widget(
+                            render_edit_organization(),
+                            add_panes(chunks.clone())[0],
+                        );
+                        frame.render_widget(organization_text_widget, add_panes(chunks.clone())[1]);
+                    }
+                    MenuItem::EditLink(..) => {
+                        frame.render_widget(render_edit_link(), add_panes(chunks.clone())[0]);
+                        frame.render_widget(link_text_widget, add_panes(chunks.clone())[1]);
+                    }
+                    MenuItem::EditFilter(..) => {
+                        frame.render_widget(render_edit_filter(), add_panes(chunks.clone())[0]);
+                        frame.render_widget(filter_text_widget, add_panes(chunks.clone())[1]);
+                    }
+                    MenuItem::ImportCsv(..) => {
+                        frame.render_widget(render_csv_mapping(), add_panes(chunks.clone())[0]);
+                        let panes = mapping_panes(add_panes(chunks.clone())[1]);
+                        frame.render_widget(mapping_text_widget, panes[0]);
+                        if let Some(csv_preview) = csv_preview {
+                            frame.render_widget(csv_preview, panes[1]);
+                        }
                     }
                 }
 
-                // Copyright section
-                frame.render_widget(copyright(), chunks[2]);
+                // Copyright section, or the last error if there is one
+                match (message, notice) {
+                    (Some(message), _) => frame.render_widget(error_message(message), chunks[2]),
+                    (None, Some(notice)) => frame.render_widget(notice_message(notice), chunks[2]),
+                    (None, None) => frame.render_widget(copyright(), chunks[2]),
+                }
             })?;
 
             // Match key events to move around the app menu and edit in text areas
-            match rx.recv().unwrap() {
+            let app_event = rx.recv().unwrap();
+            if let AppEvent::Input(_) = app_event {
+                self.message = None;
+                self.notice = None;
+            }
+            // a picked link belongs to the selected record; any other key lets it go
+            if let AppEvent::Input(Event::Key(key)) = app_event {
+                if !matches!(key.code, KeyCode::Tab | KeyCode::Enter | KeyCode::Char('x')) {
+                    self.link_index = None;
+                }
+            }
+            match app_event {
                 AppEvent::Input(Event::Key(KeyEvent {
                     code: KeyCode::Char('q'), // Quit
                     ..
@@ -195,6 +513,16 @@ impl App {
                     code: KeyCode::Char('h'), // Home menu
                     ..
                 })) if self.is_command_mode() => self.active_menu_item = MenuItem::Home,
+                AppEvent::Input(Event::Key(KeyEvent {
+                    code: KeyCode::Char('c'), // Show every record in the catalog
+                    ..
+                })) if self.is_command_mode() => self.active_menu_item = MenuItem::AllItems,
+                AppEvent::Input(Event::Key(KeyEvent {
+                    code: KeyCode::Char('/'), // Search the catalog
+                    ..
+                })) if self.is_command_mode() => {
+                    self.active_menu_item = MenuItem::Search(InputMode::Input);
+                }
                 AppEvent::Input(Event::Key(KeyEvent {
                     code: KeyCode::Char('s'), // Show list of books
                     ..
@@ -205,6 +533,17 @@ impl App {
                 })) if self.is_command_mode() => {
                     self.active_menu_item = MenuItem::NewBook(InputMode::Command);
                 }
+                AppEvent::Input(Event::Key(KeyEvent {
+                    code: KeyCode::Char('l'), // Link selected item to another record
+                    modifiers,
+                    ..
+                })) if KeyModifiers::CONTROL == modifiers && self.shows_links() => {
+                    let selected = self.get_item_id();
+                    if self.report_error(selected).is_some() {
+                        self.form_source = self.active_menu_item;
+                        self.active_menu_item = MenuItem::EditLink(InputMode::Input);
+                    }
+                }
                 AppEvent::Input(Event::Key(KeyEvent {
                     code: KeyCode::Char('l'), // Show a list of articles
                     ..
@@ -215,6 +554,93 @@ impl App {
                 })) if self.is_command_mode() => {
                     self.active_menu_item = MenuItem::InsertArticle(InputMode::Command);
                 }
+                AppEvent::Input(Event::Key(KeyEvent {
+                    code: KeyCode::Char('i'), // Show a list of the other item types
+                    ..
+                })) if self.is_command_mode() => self.active_menu_item = MenuItem::Items,
+                AppEvent::Input(Event::Key(KeyEvent {
+                    code: KeyCode::Char('p'), // Show a list of publishers
+                    ..
+                })) if self.is_command_mode() => self.active_menu_item = MenuItem::Publishers,
+                AppEvent::Input(Event::Key(KeyEvent {
+                    code: KeyCode::Char('o'), // Show a list of organizations
+                    ..
+                })) if self.is_command_mode() => self.active_menu_item = MenuItem::Organizations,
+                AppEvent::Input(Event::Key(KeyEvent {
+                    code: KeyCode::Char('r'), // Sort the list shown by the next key
+                    ..
+                })) if self.is_command_mode() && self.window().is_some() => {
+                    let changed = self.change_view(|view| view.sort = view.sort.next());
+                    self.report_error(changed);
+                }
+                AppEvent::Input(Event::Key(KeyEvent {
+                    code: KeyCode::Char('v'), // Reverse the order of the list shown
+                    ..
+                })) if self.is_command_mode() && self.window().is_some() => {
+                    let changed = self.change_view(|view| view.descending = !view.descending);
+                    self.report_error(changed);
+                }
+                AppEvent::Input(Event::Key(KeyEvent {
+                    code: KeyCode::Char('f'), // Filter the list shown
+                    ..
+                })) if self.is_command_mode() && self.window().is_some() => {
+                    if let Some(window) = self.window() {
+                        filter_text_area = TextArea::new(window.view.filter.form_lines());
+                    }
+                    self.form_source = self.active_menu_item;
+                    self.active_menu_item = MenuItem::EditFilter(InputMode::Input);
+                }
+                AppEvent::Input(Event::Key(KeyEvent {
+                    code: KeyCode::Char('m'), // Mark or unmark the selected record for export
+                    ..
+                })) if self.is_command_mode() && self.window().is_some() => {
+                    if let Some(cite_key) = self
+                        .window()
+                        .and_then(|window| window.selected_key())
+                        .map(str::to_string)
+                    {
+                        if !self.marked.remove(&cite_key) {
+                            self.marked.insert(cite_key);
+                        }
+                    }
+                }
+                AppEvent::Input(Event::Key(KeyEvent {
+                    code: KeyCode::Char('e'), // Export to BibTeX
+                    modifiers,
+                    ..
+                })) if KeyModifiers::CONTROL == modifiers
+                    && (self.window().is_some()
+                        || matches!(self.active_menu_item, MenuItem::Search(_))) =>
+                {
+                    let exported = self.export_keys().and_then(|cite_keys| {
+                        let cite_keys: Vec<&str> = cite_keys.iter().map(String::as_str).collect();
+                        export_bibtex(&self.catalog, &cite_keys)
+                    });
+                    if let Some((text, count)) = self.report_error(exported) {
+                        match std::fs::write(BIBTEX_EXPORT_FILE, text) {
+                            Ok(()) => {
+                                self.notice =
+                                    Some(format!("{count} records exported to {BIBTEX_EXPORT_FILE}"))
+                            }
+                            Err(err) => {
+                                self.message =
+                                    Some(format!("cannot write {BIBTEX_EXPORT_FILE}: {err}"))
+                            }
+                        }
+                    }
+                }
+                AppEvent::Input(Event::Key(KeyEvent {
+                    code: KeyCode::Tab, // Complete the Publisher line with the first suggestion
+                    ..
+                })) if !suggestions.is_empty() => {
+                    if let MenuItem::NewBook(_) = self.active_menu_item {
+                        complete_line(&mut book_text_area, &suggestions[0]);
+                    } else if let MenuItem::InsertArticle(_) = self.active_menu_item {
+                        complete_line(&mut article_text_area, &suggestions[0]);
+                    } else if let MenuItem::NewItem(_) = self.active_menu_item {
+                        complete_line(&mut item_text_area, &suggestions[0]);
+                    }
+                }
                 AppEvent::Input(Event::Key(KeyEvent {
                     code: KeyCode::F(2), // Enter edit mode
                     ..
@@ -227,19 +653,67 @@ impl App {
                 })) => {
                     self.update_flag = false;
                     self.exit_input_mode();
+                    if let MenuItem::EditPublisher(_) = self.active_menu_item {
+                        self.active_menu_item = MenuItem::Publishers;
+                    } else if let MenuItem::NewItem(_) = self.active_menu_item {
+                        self.active_menu_item = MenuItem::Items;
+                    } else if let MenuItem::EditOrganization(_) = self.active_menu_item {
+                        self.active_menu_item = MenuItem::Organizations;
+                    } else if let MenuItem::EditLink(_) | MenuItem::EditFilter(_) =
+                        self.active_menu_item
+                    {
+                        self.active_menu_item = self.form_source;
+                    }
                 }
                 AppEvent::Input(Event::Key(KeyEvent {
                     code: KeyCode::F(9), // Save to database
                     ..
                 })) => {
+                    // Keep the text area filled in if the item was not saved
                     if let MenuItem::NewBook(_) = self.active_menu_item {
-                        self.save_as_item_type(&book_text_area);
-                        book_text_area = TextArea::default();
-                        book_text_area.set_block(new_book_block(false));
+                        let saved = self.save_as_item_type(&book_text_area);
+                        if self.report_error(saved).is_some() {
+                            book_text_area = TextArea::default();
+                            book_text_area.set_block(new_book_block(false));
+                        }
                     } else if let MenuItem::InsertArticle(_) = self.active_menu_item {
-                        self.save_as_item_type(&article_text_area);
-                        article_text_area = TextArea::default();
-                        article_text_area.set_block(new_article_block(false));
+                        let saved = self.save_as_item_type(&article_text_area);
+                        if self.report_error(saved).is_some() {
+                            article_text_area = TextArea::default();
+                            article_text_area.set_block(new_article_block(false));
+                        }
+                    } else if let MenuItem::NewItem(_) = self.active_menu_item {
+                        let saved = self.save_as_item_type(&item_text_area);
+                        if self.report_error(saved).is_some() {
+                            item_text_area = TextArea::default();
+                            self.active_menu_item = MenuItem::Items;
+                        }
+                    } else if let MenuItem::EditPublisher(_) = self.active_menu_item {
+                        let saved = self.save_as_item_type(&publisher_text_area);
+                        if self.report_error(saved).is_some() {
+                            self.active_menu_item = MenuItem::Publishers;
+                        }
+                    } else if let MenuItem::EditOrganization(_) = self.active_menu_item {
+                        let saved = self.save_as_item_type(&organization_text_area);
+                        if self.report_error(saved).is_some() {
+                            self.active_menu_item = MenuItem::Organizations;
+                        }
+                    } else if let MenuItem::EditLink(_) = self.active_menu_item {
+                        let saved = self.save_as_item_type(&link_text_area);
+                        if self.report_error(saved).is_some() {
+                            link_text_area = TextArea::default();
+                            self.active_menu_item = self.form_source;
+                        }
+                    } else if let MenuItem::EditFilter(_) = self.active_menu_item {
+                        let filter = ListFilter::from_form(filter_text_area.lines().to_vec());
+                        if let Some(filter) = self.report_error(filter) {
+                            self.active_menu_item = self.form_source;
+                            let changed = self.change_view(|view| view.filter = filter);
+                            self.report_error(changed);
+                        }
+                    } else if let MenuItem::ImportCsv(_) = self.active_menu_item {
+                        let imported = self.import_csv(&mapping_text_area, false);
+                        self.report_error(imported);
                     }
                     self.update_flag = false;
                     self.exit_input_mode();
@@ -249,133 +723,255 @@ impl App {
                     modifiers,
                     ..
                 })) if KeyModifiers::CONTROL == modifiers => {
+                    // from the whole catalog, edit the record in the list of its type
+                    if let MenuItem::AllItems | MenuItem::Search(InputMode::Command) =
+                        self.active_menu_item
+                    {
+                        let shown = self.open_selected_entry();
+                        self.report_error(shown);
+                    }
                     if let MenuItem::ShowBooks = self.active_menu_item {
-                        self.update_flag = true;
-                        self.get_item_id();
-                        let text_vec = Book::select(&self.update_item_id);
-                        book_text_area = TextArea::new(text_vec);
-                        book_text_area.set_block(new_book_block(self.update_flag));
-                        self.active_menu_item = MenuItem::NewBook(InputMode::Input);
+                        let selected = self
+                            .get_item_id()
+                            .and_then(|_| Book::select(&self.catalog, &self.update_item_id));
+                        if let Some(text_vec) = self.report_error(selected) {
+                            self.update_flag = true;
+                            book_text_area = TextArea::new(text_vec);
+                            book_text_area.set_block(new_book_block(self.update_flag));
+                            self.active_menu_item = MenuItem::NewBook(InputMode::Input);
+                        }
                     } else if let MenuItem::ListArticles = self.active_menu_item {
-                        self.update_flag = true;
-                        self.get_item_id();
-                        let text_vec = Article::select(&self.update_item_id);
-                        article_text_area = TextArea::new(text_vec);
-                        article_text_area.set_block(new_article_block(self.update_flag));
-                        self.active_menu_item = MenuItem::InsertArticle(InputMode::Input);
+                        let selected = self
+                            .get_item_id()
+                            .and_then(|_| Article::select(&self.catalog, &self.update_item_id));
+                        if let Some(text_vec) = self.report_error(selected) {
+                            self.update_flag = true;
+                            article_text_area = TextArea::new(text_vec);
+                            article_text_area.set_block(new_article_block(self.update_flag));
+                            self.active_menu_item = MenuItem::InsertArticle(InputMode::Input);
+                        }
+                    } else if let MenuItem::Items = self.active_menu_item {
+                        let item_type = self.item_type();
+                        let selected = self
+                            .get_item_id()
+                            .and_then(|_| item_type.select(&self.catalog, &self.update_item_id));
+                        if let Some(text_vec) = self.report_error(selected) {
+                            self.update_flag = true;
+                            item_text_area = TextArea::new(text_vec);
+                            self.active_menu_item = MenuItem::NewItem(InputMode::Input);
+                        }
+                    } else if let MenuItem::Publishers = self.active_menu_item {
+                        let selected = self
+                            .get_item_id()
+                            .and_then(|_| Publisher::select(&self.catalog, &self.update_item_id));
+                        if let Some(text_vec) = self.report_error(selected) {
+                            self.update_flag = true;
+                            publisher_text_area = TextArea::new(text_vec);
+                            self.active_menu_item = MenuItem::EditPublisher(InputMode::Input);
+                        }
+                    } else if let MenuItem::Organizations = self.active_menu_item {
+                        let selected = self.get_item_id().and_then(|_| {
+                            Organization::select(&self.catalog, &self.update_item_id)
+                        });
+                        if let Some(text_vec) = self.report_error(selected) {
+                            self.update_flag = true;
+                            organization_text_area = TextArea::new(text_vec);
+                            self.active_menu_item = MenuItem::EditOrganization(InputMode::Input);
+                        }
                     }
                 }
+                AppEvent::Input(Event::Key(KeyEvent {
+                    code: KeyCode::Char('n'), // Add a new item of the type listed
+                    modifiers,
+                    ..
+                })) if KeyModifiers::CONTROL == modifiers
+                    && matches!(self.active_menu_item, MenuItem::Items) =>
+                {
+                    self.update_flag = false;
+                    item_text_area = TextArea::default();
+                    self.active_menu_item = MenuItem::NewItem(InputMode::Input);
+                }
+                AppEvent::Input(Event::Key(KeyEvent {
+                    code: KeyCode::Char('n'), // Add a new organization
+                    modifiers,
+                    ..
+                })) if KeyModifiers::CONTROL == modifiers
+                    && matches!(self.active_menu_item, MenuItem::Organizations) =>
+                {
+                    self.update_flag = false;
+                    organization_text_area = TextArea::default();
+                    self.active_menu_item = MenuItem::EditOrganization(InputMode::Input);
+                }
+                AppEvent::Input(Event::Key(KeyEvent {
+                    code: KeyCode::Tab, // Switch between keyword and advanced search
+                    ..
+                })) if matches!(self.active_menu_item, MenuItem::Search(_)) => {
+                    self.advanced_search = !self.advanced_search;
+                    self.search_list_state
+                        .lock()
+                        .expect("should lock state")
+                        .select(Some(0));
+                }
+                AppEvent::Input(Event::Key(KeyEvent {
+                    code: KeyCode::Tab, // Pick the next link of the selected item
+                    ..
+                })) if self.shows_links() => {
+                    let links = self
+                        .get_item_id()
+                        .and_then(|_| select_links(&self.catalog, &self.update_item_id));
+                    let amount_links = self.report_error(links).map_or(0, |links| links.len());
+                    self.link_index = match self.link_index {
+                        _ if amount_links == 0 => None,
+                        Some(picked) if picked + 1 < amount_links => Some(picked + 1),
+                        _ => Some(0),
+                    };
+                }
+                AppEvent::Input(Event::Key(KeyEvent {
+                    code: KeyCode::Enter, // Open the record selected in the whole catalog
+                    ..
+                })) if matches!(
+                    self.active_menu_item,
+                    MenuItem::AllItems | MenuItem::Search(InputMode::Command)
+                ) =>
+                {
+                    let shown = self.open_selected_entry();
+                    self.report_error(shown);
+                }
+                AppEvent::Input(Event::Key(KeyEvent {
+                    code: KeyCode::Enter, // Done typing the search; pick from the results
+                    ..
+                })) if matches!(self.active_menu_item, MenuItem::Search(InputMode::Input)) => {
+                    self.exit_input_mode();
+                }
+                AppEvent::Input(Event::Key(KeyEvent {
+                    code: KeyCode::Enter, // Open the record the picked link points to
+                    ..
+                })) if self.shows_links() && self.link_index.is_some() => {
+                    let followed = self.follow_link();
+                    self.report_error(followed);
+                }
+                AppEvent::Input(Event::Key(KeyEvent {
+                    code: KeyCode::Char('x'), // Remove the picked link
+                    ..
+                })) if self.shows_links() && self.link_index.is_some() => {
+                    let removed = self.picked_link().and_then(|link| {
+                        Relationship::unlink(&self.catalog, &link.relationship_id)
+                    });
+                    self.report_error(removed);
+                    self.link_index = None;
+                }
+                AppEvent::Input(Event::Key(KeyEvent {
+                    code: KeyCode::Char('d'), // Check every row of the spreadsheet, saving nothing
+                    modifiers,
+                    ..
+                })) if KeyModifiers::CONTROL == modifiers
+                    && matches!(self.active_menu_item, MenuItem::ImportCsv(_)) =>
+                {
+                    let checked = self.import_csv(&mapping_text_area, true);
+                    self.report_error(checked);
+                }
                 AppEvent::Input(Event::Key(KeyEvent {
                     code: KeyCode::Char('d'), // Delete selected item
                     modifiers,
                     ..
                 })) if KeyModifiers::CONTROL == modifiers => {
+                    // a list of records keeps the selection in place when it is read again
                     if let MenuItem::ShowBooks = self.active_menu_item {
-                        let book_list = read_sqlite_book_table().expect("should fetch book list");
-                        if book_list.is_empty() {
-                        } else {
-                            self.get_item_id();
-                            Book::delete_book(self.update_item_id.clone());
-
-                            // if last item in list move selected item back to top of list
-                            let mut lock = self.book_list_state.lock().expect("should lock state");
-                            if let Some(selected) = lock.selected() {
-                                let amount_books = read_sqlite_book_table()
-                                    .expect("should fetch book list")
-                                    .len();
-                                if amount_books == 0 {}
-                                else if selected >= amount_books - 1 {
-                                    lock.select(Some(0));
-                                } else {
-                                    lock.select(Some(selected + 1));
-                                }
-                            }
+                        if self.item_count() > 0 {
+                            let deleted = self.get_item_id().and_then(|_| {
+                                Book::delete_book(&self.catalog, self.update_item_id.clone())
+                            });
+                            self.report_error(deleted);
                         }
                     } else if let MenuItem::ListArticles = self.active_menu_item {
-                        let article_list = read_sqlite_article_table().expect("should fetch book list");
-                        if article_list.is_empty() {
-                        } else {
-                            self.get_item_id();
-                            Article::delete_article(self.update_item_id.clone());
+                        if self.item_count() > 0 {
+                            let deleted = self.get_item_id().and_then(|_| {
+                                Article::delete_article(&self.catalog, self.update_item_id.clone())
+                            });
+                            self.report_error(deleted);
+                        }
+                    } else if let MenuItem::Items = self.active_menu_item {
+                        if self.item_count() > 0 {
+                            let item_type = self.item_type();
+                            let deleted = self.get_item_id().and_then(|_| {
+                                item_type.delete_item(&self.catalog, self.update_item_id.clone())
+                            });
+                            self.report_error(deleted);
+                        }
+                    } else if let MenuItem::Organizations = self.active_menu_item {
+                        if self.item_count() > 0 {
+                            let deleted = self.get_item_id().and_then(|_| {
+                                Organization::delete_organization(
+                                    &self.catalog,
+                                    self.update_item_id.clone(),
+                                )
+                            });
+                            self.report_error(deleted);
 
-                            // if last item in list move selected item back to top of list
-                            let mut lock = self.article_list_state.lock().expect("should lock state");
+                            // stay in place unless the last organization in the list went
+                            let amount_organizations = self.item_count();
+                            let mut lock = self
+                                .organization_list_state
+                                .lock()
+                                .expect("should lock state");
                             if let Some(selected) = lock.selected() {
-                                let amount_books = read_sqlite_article_table()
-                                    .expect("should fetch book list")
-                                    .len();
-                                if amount_books == 0 {}
-                                else if selected >= amount_books - 1 {
-                                    lock.select(Some(0));
-                                } else {
-                                    lock.select(Some(selected + 1));
-                                }
+                                lock.select(Some(
+                                    selected.min(amount_organizations.saturating_sub(1)),
+                                ));
                             }
                         }
                     }
                 }
                 AppEvent::Input(Event::Key(KeyEvent {
-                    code: KeyCode::Down, .. // Move down in the list of books or articles; wraps around
+                    code: KeyCode::Down, .. // Move down in the list shown; wraps around
                 })) if self.is_command_mode() => {
-                    if let MenuItem::ShowBooks = self.active_menu_item {
-                        let mut lock = self.book_list_state.lock().expect("should lock state");
-                        if let Some(selected) = lock.selected() {
-                            let amount_books = read_sqlite_book_table()
-                                .expect("should fetch book list")
-                                .len();
-                            if selected >= amount_books - 1 {
-                                lock.select(Some(0));
-                            } else {
-                                lock.select(Some(selected + 1));
-                            }
-                        }
-                        drop(lock);
-                    } else if let MenuItem::ListArticles = self.active_menu_item {
-                        let mut lock = self.article_list_state.lock().expect("should lock state");
+                    let amount_items = self.item_count();
+                    if let Some(moved) =
+                        self.with_window(|window, catalog| window.select_next(catalog))
+                    {
+                        self.report_error(moved);
+                    } else if let Some(list_state) = self.list_state() {
+                        let mut lock = list_state.lock().expect("should lock state");
                         if let Some(selected) = lock.selected() {
-                            let amount_articles = read_sqlite_article_table()
-                                .expect("should fetch book list")
-                                .len();
-                            if selected >= amount_articles - 1 {
+                            if selected + 1 >= amount_items {
                                 lock.select(Some(0));
                             } else {
                                 lock.select(Some(selected + 1));
                             }
                         }
-                        drop(lock);
                     }
                 }
                 AppEvent::Input(Event::Key(KeyEvent {
-                    code: KeyCode::Up, .. // Move up in the list of books or articles; wraps around
+                    code: KeyCode::Up, .. // Move up in the list shown; wraps around
                 })) if self.is_command_mode() => {
-                    if let MenuItem::ShowBooks = self.active_menu_item {
-                        let mut lock = self.book_list_state.lock().expect("should lock state");
-                        if let Some(selected) = lock.selected() {
-                            let amount_books = read_sqlite_book_table()
-                                .expect("should fetch book list")
-                                .len();
-                            if selected > 0 {
-                                lock.select(Some(selected - 1));
-                            } else {
-                                lock.select(Some(amount_books - 1));
-                            }
-                        }
-                        drop(lock);
-                    } else if let MenuItem::ListArticles = self.active_menu_item {
-                        let mut lock = self.article_list_state.lock().expect("should lock state");
+                    let amount_items = self.item_count();
+                    if let Some(moved) =
+                        self.with_window(|window, catalog| window.select_previous(catalog))
+                    {
+                        self.report_error(moved);
+                    } else if let Some(list_state) = self.list_state() {
+                        let mut lock = list_state.lock().expect("should lock state");
                         if let Some(selected) = lock.selected() {
-                            let amount_articles = read_sqlite_article_table()
-                                .expect("should fetch book list")
-                                .len();
                             if selected > 0 {
                                 lock.select(Some(selected - 1));
                             } else {
-                                lock.select(Some(amount_articles - 1));
+                                lock.select(Some(amount_items.saturating_sub(1)));
                             }
                         }
-                        drop(lock);
                     }
                 }
+                AppEvent::Input(Event::Key(KeyEvent {
+                    code: code @ (KeyCode::Left | KeyCode::Right), // Change the item type listed
+                    ..
+                })) if matches!(self.active_menu_item, MenuItem::Items) => {
+                    let amount_types = ITEM_TYPES.len();
+                    self.item_type_index = match code {
+                        KeyCode::Left => (self.item_type_index + amount_types - 1) % amount_types,
+                        _ => (self.item_type_index + 1) % amount_types,
+                    };
+                    self.item_list
+                        .set_entry_type(Some(self.item_type().entry_type));
+                }
                 AppEvent::Tick => {}
                 AppEvent::Input(input) if !self.is_command_mode() => {
                     // Text area input mode
@@ -384,6 +980,29 @@ impl App {
                     } else if let MenuItem::InsertArticle(InputMode::Input) = self.active_menu_item
                     {
                         article_text_area.input(input);
+                    } else if let MenuItem::NewItem(InputMode::Input) = self.active_menu_item {
+                        item_text_area.input(input);
+                    } else if let MenuItem::EditPublisher(InputMode::Input) = self.active_menu_item
+                    {
+                        publisher_text_area.input(input);
+                    } else if let MenuItem::EditOrganization(InputMode::Input) =
+                        self.active_menu_item
+                    {
+                        organization_text_area.input(input);
+                    } else if let MenuItem::EditLink(InputMode::Input) = self.active_menu_item {
+                        link_text_area.input(input);
+                    } else if let MenuItem::EditFilter(InputMode::Input) = self.active_menu_item {
+                        filter_text_area.input(input);
+                    } else if let MenuItem::ImportCsv(InputMode::Input) = self.active_menu_item {
+                        mapping_text_area.input(input);
+                        self.csv_report = None;
+                    } else if let MenuItem::Search(InputMode::Input) = self.active_menu_item {
+                        search_text_area.input(input);
+                        self.search_text = search_text_area.lines()[0].clone();
+                        self.search_list_state
+                            .lock()
+                            .expect("should lock state")
+                            .select(Some(0));
                     }
                 }
                 AppEvent::Input(_) => {}
@@ -392,55 +1011,371 @@ impl App {
         Ok(())
     }
 
-    /// Retrieves `cite_key` of current item (`book` or `article`); Used to update or delete an item.
-    fn get_item_id(&mut self) {
+    /// Retrieves `cite_key` of current item (`book`, `article` or other item); Used to update or delete an item.
+    fn get_item_id(&mut self) -> DbResult<()> {
         self.update_item_id = String::new();
-        if let MenuItem::ShowBooks = self.active_menu_item {
-            let book_list = read_sqlite_book_table().expect("should fetch book list");
+        if let Some(entry_type) = self.entry_type() {
+            self.update_item_id = self
+                .window()
+                .and_then(|window| window.selected_key())
+                .map(str::to_string)
+                .ok_or_else(|| {
+                    DbError::NotFound(format!("selected {}", entry_type.name().to_lowercase()))
+                })?;
+        } else if let MenuItem::Publishers = self.active_menu_item {
+            self.refresh_table()?;
             let selected = self
-                .book_list_state
+                .publisher_list_state
                 .lock()
                 .expect("should lock list state")
                 .selected();
-            let selected_item = book_list
+            let (selected_item, _) = self
+                .publisher_list
+                .rows()
                 .get(selected.unwrap_or(0))
-                .expect("exists")
-                .clone();
-            self.update_item_id = selected_item.cite_key.clone();
-        } else if let MenuItem::ListArticles = self.active_menu_item {
-            let article_list = read_sqlite_article_table().expect("should fetch book list");
+                .ok_or_else(|| DbError::NotFound("selected publisher".to_string()))?;
+            self.update_item_id = selected_item.publisher_id.clone();
+        } else if let MenuItem::Organizations = self.active_menu_item {
+            self.refresh_table()?;
             let selected = self
-                .article_list_state
+                .organization_list_state
                 .lock()
                 .expect("should lock list state")
                 .selected();
-            let selected_item = article_list
+            let (selected_item, _, _) = self
+                .organization_list
+                .rows()
                 .get(selected.unwrap_or(0))
-                .expect("exists")
-                .clone();
-            self.update_item_id = selected_item.cite_key;
+                .ok_or_else(|| DbError::NotFound("selected organization".to_string()))?;
+            self.update_item_id = selected_item.organization_id.clone();
+        }
+        Ok(())
+    }
+
+    /// Item type listed under `Item Types`
+    fn item_type(&self) -> &'static ItemType {
+        &ITEM_TYPES[self.item_type_index]
+    }
+
+    /// Type of the records listed or edited on the screen shown; `None` on other screens
+    fn entry_type(&self) -> Option<EntryType> {
+        match self.active_menu_item {
+            MenuItem::ShowBooks | MenuItem::NewBook(_) => Some(EntryType::Book),
+            MenuItem::ListArticles | MenuItem::InsertArticle(_) => Some(EntryType::Article),
+            MenuItem::Items | MenuItem::NewItem(_) => Some(self.item_type().entry_type),
+            _ => None,
+        }
+    }
+
+    /// Window onto the list of records shown, if any
+    fn window(&self) -> Option<&ListWindow> {
+        match self.active_menu_item {
+            MenuItem::AllItems => Some(&self.catalog_list),
+            MenuItem::ShowBooks => Some(&self.book_list),
+            MenuItem::ListArticles => Some(&self.article_list),
+            MenuItem::Items => Some(&self.item_list),
+            _ => None,
+        }
+    }
+
+    /// Run `f` on the window onto the list of records shown; `None` if no such list is shown
+    fn with_window<T>(
+        &mut self,
+        f: impl FnOnce(&mut ListWindow, &Catalog) -> DbResult<T>,
+    ) -> Option<DbResult<T>> {
+        let window = match self.active_menu_item {
+            MenuItem::AllItems => &mut self.catalog_list,
+            MenuItem::ShowBooks => &mut self.book_list,
+            MenuItem::ListArticles => &mut self.article_list,
+            MenuItem::Items => &mut self.item_list,
+            _ => return None,
+        };
+        Some(f(window, &self.catalog))
+    }
+
+    /// Change the order or filter of the list shown, keeping the selection on the same record;
+    /// the first record is selected when the filter leaves the selected one out
+    fn change_view(&mut self, change: impl FnOnce(&mut ListView)) -> DbResult<()> {
+        self.with_window(|window, catalog| window.change_view(catalog, change))
+            .unwrap_or(Ok(()))
+    }
+
+    /// Menu entry to underline; the link form belongs to the list it was opened from
+    fn menu_index(&self) -> usize {
+        match self.active_menu_item {
+            MenuItem::EditLink(_) | MenuItem::EditFilter(_) => self.form_source.ordinal(),
+            item => item.ordinal(),
         }
     }
 
-    /// Save the data entered in the textarea to Book or Article table
-    fn save_as_item_type(&mut self, text_area: &TextArea) {
+    /// Check if the list shown has links in its detail pane
+    fn shows_links(&self) -> bool {
+        matches!(
+            self.active_menu_item,
+            MenuItem::ShowBooks | MenuItem::ListArticles | MenuItem::Items
+        )
+    }
+
+    /// Link picked with `Tab` in the detail pane of the selected item
+    fn picked_link(&mut self) -> DbResult<crate::relationship::Link> {
+        self.get_item_id()?;
+        select_links(&self.catalog, &self.update_item_id)?
+            .into_iter()
+            .nth(self.link_index.unwrap_or(0))
+            .ok_or_else(|| DbError::NotFound("picked link".to_string()))
+    }
+
+    /// Show the record the picked link points to, selected in its own list
+    fn follow_link(&mut self) -> DbResult<()> {
+        let link = self.picked_link()?;
+        self.show_record(link.entry_type, &link.cite_key)?;
+        self.link_index = None;
+        Ok(())
+    }
+
+    /// Show the list of records of `entry_type` with the one with `cite_key` selected
+    fn show_record(&mut self, entry_type: EntryType, cite_key: &str) -> DbResult<()> {
+        let menu_item = match entry_type {
+            EntryType::Book => MenuItem::ShowBooks,
+            EntryType::Article => MenuItem::ListArticles,
+            other => {
+                self.item_type_index = ITEM_TYPES
+                    .iter()
+                    .position(|listed| listed.entry_type == other)
+                    .ok_or_else(|| {
+                        DbError::InvalidInput(format!("cannot show a record of type {other}"))
+                    })?;
+                MenuItem::Items
+            }
+        };
+        let previous = self.active_menu_item;
+        self.active_menu_item = menu_item;
+        if let MenuItem::Items = menu_item {
+            self.item_list.set_entry_type(Some(entry_type));
+        }
+        let find = |app: &mut App| {
+            app.with_window(|window, catalog| window.select_key(catalog, cite_key))
+                .unwrap_or(Ok(false))
+        };
+        let mut found = find(self);
+        // a record the list's filter leaves out is shown with the filter off
+        if let Ok(false) = found {
+            let filtered = self
+                .window()
+                .is_some_and(|window| !window.view.filter.is_empty());
+            if filtered {
+                found = self
+                    .change_view(|view| view.filter = ListFilter::default())
+                    .and_then(|_| find(self));
+            }
+        }
+        match found {
+            Ok(true) => Ok(()),
+            Ok(false) => {
+                self.active_menu_item = previous;
+                Err(DbError::NotFound(cite_key.to_string()))
+            }
+            Err(err) => {
+                self.active_menu_item = previous;
+                Err(err)
+            }
+        }
+    }
+
+    /// Search again if the search is shown and its text or the catalog changed since it ran
+    fn refresh_search(&mut self) {
+        if let MenuItem::Search(_) = self.active_menu_item {
+            self.search_results
+                .refresh(&self.catalog, &self.search_text, self.advanced_search);
+        }
+    }
+
+    /// Records to export: every search result, or the marked records in the order they were
+    /// added, or else the whole list shown as it is sorted and filtered
+    fn export_keys(&self) -> DbResult<Vec<String>> {
+        if let MenuItem::Search(_) = self.active_menu_item {
+            let found = match self.advanced_search {
+                true => advanced_search(&self.catalog, &self.search_text, i64::MAX)?,
+                false => search_catalog(&self.catalog, &self.search_text, i64::MAX)?,
+            };
+            return Ok(found.into_iter().map(|entry| entry.cite_key).collect());
+        }
+        if !self.marked.is_empty() {
+            let mut cite_keys = ListView::new(SortKey::Added).cite_keys(&self.catalog, None)?;
+            cite_keys.retain(|cite_key| self.marked.contains(cite_key));
+            return Ok(cite_keys);
+        }
+        match self.window() {
+            Some(window) => window.all_cite_keys(&self.catalog),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Show the record selected under `Catalog` or in the search results in the list of its type
+    fn open_selected_entry(&mut self) -> DbResult<()> {
+        let entry = match self.active_menu_item {
+            MenuItem::AllItems => {
+                let cite_keys: Vec<&str> = self.catalog_list.selected_key().into_iter().collect();
+                read_catalog_entries_with_keys(&self.catalog, &cite_keys)?
+                    .into_iter()
+                    .next()
+            }
+            _ => {
+                let selected = self
+                    .search_list_state
+                    .lock()
+                    .expect("should lock list state")
+                    .selected();
+                self.refresh_search();
+                self.search_results
+                    .entries()
+                    .get(selected.unwrap_or(0))
+                    .cloned()
+            }
+        }
+        .ok_or_else(|| DbError::NotFound("selected record".to_string()))?;
+        self.show_record(entry.entry_type, &entry.cite_key)
+    }
+
+    /// State of the list currently shown, if any
+    fn list_state(&self) -> Option<Arc<Mutex<ListState>>> {
+        match self.active_menu_item {
+            MenuItem::Search(_) => Some(self.search_list_state.clone()),
+            MenuItem::Publishers => Some(self.publisher_list_state.clone()),
+            MenuItem::Organizations => Some(self.organization_list_state.clone()),
+            _ => None,
+        }
+    }
+
+    /// Publishers matching the Publisher line of `text_area` while the cursor is on it
+    fn suggestions(&mut self, text_area: &TextArea, publisher_line: usize) -> Vec<String> {
+        let (row, _) = text_area.cursor();
+        if row != publisher_line {
+            return Vec::new();
+        }
+        let line = text_area.lines()[row].clone();
+        let found = publisher_suggestions(&self.catalog, &line, 5);
+        let found = self.report_error(found).unwrap_or_default();
+        // nothing to suggest once the line already names a publisher exactly
+        if found.len() == 1 && found[0] == line.trim() {
+            return Vec::new();
+        }
+        found
+    }
+
+    /// Number of items in the list currently shown; 0 if the list cannot be read
+    fn item_count(&mut self) -> usize {
+        let count = match self.active_menu_item {
+            MenuItem::AllItems | MenuItem::ShowBooks | MenuItem::ListArticles | MenuItem::Items => {
+                Ok(self.window().map_or(0, ListWindow::count))
+            }
+            MenuItem::Search(_) => {
+                self.refresh_search();
+                Ok(self.search_results.entries().len())
+            }
+            MenuItem::Publishers => self.refresh_table().map(|_| self.publisher_list.count()),
+            MenuItem::Organizations => self.refresh_table().map(|_| self.organization_list.count()),
+            _ => Ok(0),
+        };
+        self.report_error(count).unwrap_or(0)
+    }
+
+    /// Read the publishers or organizations again if they are shown and the catalog changed
+    /// since they were read
+    fn refresh_table(&mut self) -> DbResult<()> {
+        match self.active_menu_item {
+            MenuItem::Publishers => self
+                .publisher_list
+                .refresh(&self.catalog, read_sqlite_publisher_table),
+            MenuItem::Organizations => self
+                .organization_list
+                .refresh(&self.catalog, read_organization_table),
+            _ => Ok(()),
+        }
+    }
+
+    /// Keep the error to show in place of the copyright section; `None` if there was one
+    fn report_error<T>(&mut self, result: DbResult<T>) -> Option<T> {
+        match result {
+            Ok(value) => Some(value),
+            Err(err) => {
+                self.message = Some(err.to_string());
+                None
+            }
+        }
+    }
+
+    /// Import the spreadsheet shown under `ImportCsv` with the mapping in `text_area`, or check
+    /// every row in a dry run, and show the report. The mapping of an import that saved its rows
+    /// is kept in `<path>.map`, for `import csv <path> --map <path>.map` next time.
+    fn import_csv(&mut self, text_area: &TextArea, dry_run: bool) -> DbResult<()> {
+        let Some((path, table)) = &self.csv_import else {
+            return Ok(());
+        };
+        let mapping = ColumnMapping::from_form(text_area.lines().to_vec())?;
+        let report = import_table(&self.catalog, table, &mapping, dry_run)?;
+        let text = report.to_string();
+        let summary = text.lines().last().unwrap_or_default().to_string();
+        self.notice = Some(summary);
+        if !dry_run && !report.rolled_back {
+            let map_path = format!("{path}.map");
+            if let Err(err) = std::fs::write(&map_path, mapping.to_file()) {
+                self.message = Some(format!("cannot write {map_path}: {err}"));
+            }
+        }
+        self.csv_report = Some(text);
+        Ok(())
+    }
+
+    /// Save the data entered in the textarea to Book, Article or other item table.
+    /// Nothing is written unless every row for the item was saved.
+    fn save_as_item_type(&mut self, text_area: &TextArea) -> DbResult<()> {
         let mut text_vec = Vec::new();
         for line in text_area.lines() {
             text_vec.push(line.to_string());
         }
-        if let MenuItem::NewBook(_) = self.active_menu_item {
-            if !self.update_flag {
-                Book::book_transaction(text_vec);
-            } else {
-                Book::book_update(text_vec, self.update_item_id.clone());
+        if let Some(entry_type) = self.entry_type() {
+            let update_item_id = self.update_item_id.clone();
+            match (entry_type, self.update_flag) {
+                (EntryType::Book, false) => {
+                    Book::book_transaction(&self.catalog, text_vec)?;
+                }
+                (EntryType::Book, true) => {
+                    Book::book_update(&self.catalog, text_vec, update_item_id)?;
+                }
+                (EntryType::Article, false) => {
+                    Article::article_transaction(&self.catalog, text_vec)?;
+                }
+                (EntryType::Article, true) => {
+                    Article::article_update(&self.catalog, text_vec, update_item_id)?;
+                }
+                (other, update) => {
+                    let item_type = other.item_type().ok_or_else(|| {
+                        DbError::InvalidInput(format!("cannot save a record of type {other}"))
+                    })?;
+                    if !update {
+                        item_type.item_transaction(&self.catalog, text_vec)?;
+                    } else {
+                        item_type.item_update(&self.catalog, text_vec, update_item_id)?;
+                    }
+                }
             }
-        } else if let MenuItem::InsertArticle(_) = self.active_menu_item {
+        } else if let MenuItem::EditPublisher(_) = self.active_menu_item {
+            Publisher::publisher_update(&self.catalog, text_vec, self.update_item_id.clone())?;
+        } else if let MenuItem::EditOrganization(_) = self.active_menu_item {
             if !self.update_flag {
-                Article::article_transaction(text_vec);
+                Organization::organization_transaction(&self.catalog, text_vec)?;
             } else {
-                Article::article_update(text_vec, self.update_item_id.clone());
+                Organization::organization_update(
+                    &self.catalog,
+                    text_vec,
+                    self.update_item_id.clone(),
+                )?;
             }
+        } else if let MenuItem::EditLink(_) = self.active_menu_item {
+            Relationship::link_from_form(&self.catalog, text_vec, &self.update_item_id)?;
         }
+        Ok(())
     }
 
     /// Change the state of the app from Command mode to Input mode
@@ -451,6 +1386,27 @@ impl App {
         if let MenuItem::InsertArticle(InputMode::Command) = self.active_menu_item {
             self.active_menu_item = MenuItem::InsertArticle(InputMode::Input);
         }
+        if let MenuItem::NewItem(InputMode::Command) = self.active_menu_item {
+            self.active_menu_item = MenuItem::NewItem(InputMode::Input);
+        }
+        if let MenuItem::EditPublisher(InputMode::Command) = self.active_menu_item {
+            self.active_menu_item = MenuItem::EditPublisher(InputMode::Input);
+        }
+        if let MenuItem::EditOrganization(InputMode::Command) = self.active_menu_item {
+            self.active_menu_item = MenuItem::EditOrganization(InputMode::Input);
+        }
+        if let MenuItem::EditLink(InputMode::Command) = self.active_menu_item {
+            self.active_menu_item = MenuItem::EditLink(InputMode::Input);
+        }
+        if let MenuItem::EditFilter(InputMode::Command) = self.active_menu_item {
+            self.active_menu_item = MenuItem::EditFilter(InputMode::Input);
+        }
+        if let MenuItem::ImportCsv(InputMode::Command) = self.active_menu_item {
+            self.active_menu_item = MenuItem::ImportCsv(InputMode::Input);
+        }
+        if let MenuItem::Search(InputMode::Command) = self.active_menu_item {
+            self.active_menu_item = MenuItem::Search(InputMode::Input);
+        }
     }
 
     /// Change the state of the app from Input mode to Command mode
@@ -459,6 +1415,20 @@ impl App {
             self.active_menu_item = MenuItem::NewBook(InputMode::Command);
         } else if let MenuItem::InsertArticle(InputMode::Input) = self.active_menu_item {
             self.active_menu_item = MenuItem::InsertArticle(InputMode::Command);
+        } else if let MenuItem::NewItem(InputMode::Input) = self.active_menu_item {
+            self.active_menu_item = MenuItem::NewItem(InputMode::Command);
+        } else if let MenuItem::EditPublisher(InputMode::Input) = self.active_menu_item {
+            self.active_menu_item = MenuItem::EditPublisher(InputMode::Command);
+        } else if let MenuItem::EditOrganization(InputMode::Input) = self.active_menu_item {
+            self.active_menu_item = MenuItem::EditOrganization(InputMode::Command);
+        } else if let MenuItem::EditLink(InputMode::Input) = self.active_menu_item {
+            self.active_menu_item = MenuItem::EditLink(InputMode::Command);
+        } else if let MenuItem::EditFilter(InputMode::Input) = self.active_menu_item {
+            self.active_menu_item = MenuItem::EditFilter(InputMode::Command);
+        } else if let MenuItem::ImportCsv(InputMode::Input) = self.active_menu_item {
+            self.active_menu_item = MenuItem::ImportCsv(InputMode::Command);
+        } else if let MenuItem::Search(InputMode::Input) = self.active_menu_item {
+            self.active_menu_item = MenuItem::Search(InputMode::Command);
         }
     }
 
@@ -466,7 +1436,22 @@ impl App {
     fn is_command_mode(&self) -> bool {
         !matches!(
             self.active_menu_item,
-            MenuItem::NewBook(InputMode::Input) | MenuItem::InsertArticle(InputMode::Input)
+            MenuItem::NewBook(InputMode::Input)
+                | MenuItem::InsertArticle(InputMode::Input)
+                | MenuItem::NewItem(InputMode::Input)
+                | MenuItem::EditPublisher(InputMode::Input)
+                | MenuItem::EditOrganization(InputMode::Input)
+                | MenuItem::EditLink(InputMode::Input)
+                | MenuItem::EditFilter(InputMode::Input)
+                | MenuItem::ImportCsv(InputMode::Input)
+                | MenuItem::Search(InputMode::Input)
         ) // cool clippy suggestion!
     }
 }
+
+/// Replace the line under the cursor with `text`
+fn complete_line(text_area: &mut TextArea, text: &str) {
+    text_area.move_cursor(CursorMove::Head);
+    text_area.delete_line_by_end();
+    text_area.insert_str(text);
+}
@@ -0,0 +1,238 @@
+use crate::db::{read_text, Catalog, DbError, DbResult, TableInsert};
+use sqlite::{State, Value};
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use uuid::Uuid;
+
+/// What a person contributed to a record
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Role {
+    Author,
+    Editor,
+    Translator,
+    Illustrator,
+}
+
+impl Role {
+    /// Value stored in the `author.role` column
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::Author => "author",
+            Role::Editor => "editor",
+            Role::Translator => "translator",
+            Role::Illustrator => "illustrator",
+        }
+    }
+}
+
+impl FromStr for Role {
+    type Err = DbError;
+
+    fn from_str(s: &str) -> DbResult<Role> {
+        match s.trim().to_lowercase().as_str() {
+            "author" => Ok(Role::Author),
+            "editor" => Ok(Role::Editor),
+            "translator" => Ok(Role::Translator),
+            "illustrator" => Ok(Role::Illustrator),
+            other => Err(DbError::InvalidInput(format!(
+                "unknown author role `{other}`"
+            ))),
+        }
+    }
+}
+
+impl fmt::Display for Role {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// One person named on a `master_entries` record; `position` orders them as printed
+#[derive(Clone, Debug, PartialEq)]
+pub struct Author {
+    pub(crate) author_id: String,
+    pub(crate) cite_key: String,
+    pub(crate) position: i64,
+    pub(crate) role: Role,
+    pub(crate) family: String,
+    pub(crate) given: String,
+}
+
+impl Author {
+    pub fn new(cite_key: &str, position: i64, role: Role, family: &str, given: &str) -> Author {
+        Author {
+            author_id: Uuid::new_v4().to_string(),
+            cite_key: cite_key.to_string(),
+            position,
+            role,
+            family: family.to_string(),
+            given: given.to_string(),
+        }
+    }
+
+    /// `Family, Given`, or just the family name when there is no given name
+    pub fn name(&self) -> String {
+        if self.given.is_empty() {
+            self.family.clone()
+        } else {
+            format!("{}, {}", self.family, self.given)
+        }
+    }
+}
+
+impl TableInsert for Author {
+    fn insert(&self, catalog: &Catalog) -> DbResult<State> {
+        let query = "INSERT INTO author (author_id, cite_key, position, role, family, given) VALUES (:author_id, :cite_key, :position, :role, :family, :given)";
+        catalog.with_statement(query, |statement| {
+            statement.bind_iter::<_, (_, Value)>([
+                (":author_id", self.author_id.clone().into()),
+                (":cite_key", self.cite_key.clone().into()),
+                (":position", self.position.into()),
+                (":role", self.role.as_str().into()),
+                (":family", self.family.clone().into()),
+                (":given", self.given.clone().into()),
+            ])?;
+            Ok(statement.next()?)
+        })
+    }
+}
+
+/// Parse the authors line of a form, e.g. `Knuth, Donald E.; Graham, Ronald (editor)`.
+/// Names are separated by `;`, a role other than author goes in parentheses after the name,
+/// and a name without a comma is read as `Given Family`.
+pub fn parse_authors(cite_key: &str, line: &str) -> DbResult<Vec<Author>> {
+    let mut authors = Vec::new();
+    for entry in line
+        .split(';')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+    {
+        let (name, role) = match entry
+            .strip_suffix(')')
+            .and_then(|rest| rest.rsplit_once('('))
+        {
+            Some((name, role)) => (name.trim(), role.parse()?),
+            None => (entry, Role::Author),
+        };
+        let (family, given) = match name.split_once(',') {
+            Some((family, given)) => (family.trim(), given.trim()),
+            None => match name.rsplit_once(char::is_whitespace) {
+                Some((given, family)) => (family.trim(), given.trim()),
+                None => (name, ""),
+            },
+        };
+        if family.is_empty() {
+            return Err(DbError::InvalidInput(format!(
+                "author `{entry}` has no family name"
+            )));
+        }
+        authors.push(Author::new(
+            cite_key,
+            authors.len() as i64,
+            role,
+            family,
+            given,
+        ));
+    }
+    Ok(authors)
+}
+
+/// Authors as one form line, the inverse of `parse_authors`
+pub fn format_authors(authors: &[Author]) -> String {
+    authors
+        .iter()
+        .map(|author| match author.role {
+            Role::Author => author.name(),
+            role => format!("{} ({role})", author.name()),
+        })
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+/// Read one author row from a statement selecting every `author` column
+fn read_author(statement: &sqlite::Statement) -> DbResult<Author> {
+    Ok(Author {
+        author_id: read_text(statement, "author_id")?,
+        cite_key: read_text(statement, "cite_key")?,
+        position: statement.read::<i64, _>("position")?,
+        role: read_text(statement, "role")?.parse()?,
+        family: read_text(statement, "family")?,
+        given: read_text(statement, "given")?,
+    })
+}
+
+/// Authors of the record with `cite_key`, in order
+pub fn select_authors(catalog: &Catalog, cite_key: &str) -> DbResult<Vec<Author>> {
+    let query = "SELECT author_id, cite_key, position, role, family, given FROM author WHERE cite_key = ? ORDER BY position";
+    catalog.with_statement(query, |statement| {
+        statement.bind((1, cite_key))?;
+        let mut authors = Vec::new();
+        while let State::Row = statement.next()? {
+            authors.push(read_author(statement)?);
+        }
+        Ok(authors)
+    })
+}
+
+/// Every author in the catalog grouped by `cite_key`, each list in order
+pub fn read_author_table(catalog: &Catalog) -> DbResult<HashMap<String, Vec<Author>>> {
+    let query = "SELECT author_id, cite_key, position, role, family, given FROM author ORDER BY cite_key, position";
+    catalog.with_statement(query, |statement| {
+        let mut authors: HashMap<String, Vec<Author>> = HashMap::new();
+        while let State::Row = statement.next()? {
+            let author = read_author(statement)?;
+            authors
+                .entry(author.cite_key.clone())
+                .or_default()
+                .push(author);
+        }
+        Ok(authors)
+    })
+}
+
+/// Replace the authors of the record with `cite_key`
+pub fn replace_authors(catalog: &Catalog, cite_key: &str, authors: &[Author]) -> DbResult<()> {
+    catalog.transaction(|catalog| {
+        catalog.with_statement("DELETE FROM author WHERE cite_key = ?", |statement| {
+            statement.bind((1, cite_key))?;
+            statement.next()?;
+            Ok(())
+        })?;
+        for author in authors {
+            author.insert(catalog)?;
+        }
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_and_format_authors() {
+        let authors = parse_authors(
+            "key",
+            "Knuth, Donald E.; Ronald Graham (Editor); Homer (translator)",
+        )
+        .unwrap();
+        assert_eq!(authors.len(), 3);
+        assert_eq!(authors[0].family, "Knuth");
+        assert_eq!(authors[0].given, "Donald E.");
+        assert_eq!(authors[1].family, "Graham");
+        assert_eq!(authors[1].role, Role::Editor);
+        assert_eq!(authors[2].position, 2);
+        assert_eq!(authors[2].given, "");
+        assert_eq!(
+            format_authors(&authors),
+            "Knuth, Donald E.; Graham, Ronald (editor); Homer (translator)"
+        );
+
+        assert!(parse_authors("key", "  ;  ").unwrap().is_empty());
+        assert!(matches!(
+            parse_authors("key", "Knuth, Donald (publisher)"),
+            Err(DbError::InvalidInput(_))
+        ));
+    }
+}
@@ -0,0 +1,1309 @@
+use crate::author::{Author, Role};
+use crate::db::{
+    read_catalog_entry, read_records_with_keys, Catalog, CatalogEntry, DbResult, EntryType,
+    MonthYear, Record, CATALOG_ENTRY_COLUMNS,
+};
+use crate::import::{import_records, ImportRecord, ImportReport};
+use crate::organization::{OrgRole, RecordOrganization};
+use sqlite::State;
+use std::collections::HashMap;
+use std::fmt;
+
+/// File written by `Ctrl-E` in the TUI, in the working directory
+pub const BIBTEX_EXPORT_FILE: &str = "export.bib";
+
+/// LaTeX accent commands, the letters they go on and the accented letters they make, in order
+pub(crate) const ACCENTS: [(&str, &str, &str); 7] = [
+    ("`", "aeiouAEIOU", "àèìòùÀÈÌÒÙ"),
+    ("'", "aeiouyAEIOUYcnszCNSZ", "áéíóúýÁÉÍÓÚÝćńśźĆŃŚŹ"),
+    ("^", "aeiouAEIOU", "âêîôûÂÊÎÔÛ"),
+    ("~", "anoANO", "ãñõÃÑÕ"),
+    ("\"", "aeiouyAEIOU", "äëïöüÿÄËÏÖÜ"),
+    ("c", "cC", "çÇ"),
+    ("v", "cszrenCSZREN", "čšžřěňČŠŽŘĚŇ"),
+];
+
+/// Letters LaTeX writes with a command of their own
+pub(crate) const LETTERS: [(&str, char); 12] = [
+    ("ss", 'ß'),
+    ("o", 'ø'),
+    ("O", 'Ø'),
+    ("ae", 'æ'),
+    ("AE", 'Æ'),
+    ("oe", 'œ'),
+    ("OE", 'Œ'),
+    ("aa", 'å'),
+    ("AA", 'Å'),
+    ("l", 'ł'),
+    ("L", 'Ł'),
+    ("i", 'ı'),
+];
+
+/// BibTeX month macros, written without braces
+const MONTH_MACROS: [&str; 12] = [
+    "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+];
+
+/// Words left out of cite keys when they start a title
+const KEY_STOP_WORDS: [&str; 8] = ["a", "an", "the", "on", "of", "in", "and", "to"];
+
+/// One BibTeX entry; field values are LaTeX, written between braces as they are
+#[derive(Clone, Debug, PartialEq)]
+pub struct BibEntry {
+    pub(crate) entry_type: String,
+    pub(crate) key: String,
+    pub(crate) fields: Vec<(String, String)>,
+}
+
+impl BibEntry {
+    fn new(entry_type: &str, key: &str) -> BibEntry {
+        BibEntry {
+            entry_type: entry_type.to_string(),
+            key: key.to_string(),
+            fields: Vec::new(),
+        }
+    }
+
+    /// Add `name = {value}` unless the value is empty
+    fn push(&mut self, name: &str, value: String) {
+        if !value.is_empty() {
+            self.fields.push((name.to_string(), value));
+        }
+    }
+
+    /// Add a field holding plain text, escaped for LaTeX
+    fn push_text(&mut self, name: &str, text: &str) {
+        self.push(name, latex(text.trim()));
+    }
+
+    /// Value of the field `name`, if the entry has it
+    pub fn field(&self, name: &str) -> Option<&str> {
+        self.fields
+            .iter()
+            .find(|(field, _)| field == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+impl fmt::Display for BibEntry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "@{}{{{},", self.entry_type, self.key)?;
+        for (name, value) in &self.fields {
+            match name.as_str() == "month" && MONTH_MACROS.contains(&value.as_str()) {
+                true => writeln!(f, "  {name} = {value},")?,
+                false => writeln!(f, "  {name} = {{{value}}},")?,
+            }
+        }
+        writeln!(f, "}}")
+    }
+}
+
+/// `text` with the characters LaTeX treats specially escaped and accented letters written as
+/// accent commands, so the entry also works with 8-bit BibTeX
+pub fn latex(text: &str) -> String {
+    let mut escaped = String::new();
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\textbackslash{}"),
+            '{' => escaped.push_str("\\textbraceleft{}"),
+            '}' => escaped.push_str("\\textbraceright{}"),
+            '~' => escaped.push_str("\\textasciitilde{}"),
+            '^' => escaped.push_str("\\textasciicircum{}"),
+            '$' | '&' | '%' | '#' | '_' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '–' => escaped.push_str("--"),
+            '—' => escaped.push_str("---"),
+            c if c.is_ascii() => escaped.push(c),
+            c => match accent_command(c) {
+                Some(command) => escaped.push_str(&command),
+                None => escaped.push(c),
+            },
+        }
+    }
+    escaped
+}
+
+/// `{\'e}` for `é`, `{\c c}` for `ç`, `{\ss}` for `ß`; `None` for letters without a command
+fn accent_command(c: char) -> Option<String> {
+    for (accent, letters, accented) in ACCENTS {
+        if let Some(index) = accented.chars().position(|letter| letter == c) {
+            let letter = letters.chars().nth(index)?;
+            return Some(match accent.chars().all(char::is_alphabetic) {
+                true => format!("{{\\{accent} {letter}}}"),
+                false => format!("{{\\{accent}{letter}}}"),
+            });
+        }
+    }
+    LETTERS
+        .iter()
+        .find(|(_, letter)| *letter == c)
+        .map(|(command, _)| format!("{{\\{command}}}"))
+}
+
+/// Letter `c` without its accent, for cite keys
+fn unaccented(c: char) -> Option<char> {
+    for (_, letters, accented) in ACCENTS {
+        if let Some(index) = accented.chars().position(|letter| letter == c) {
+            return letters.chars().nth(index);
+        }
+    }
+    LETTERS
+        .iter()
+        .find(|(_, letter)| *letter == c)
+        .and_then(|(command, _)| command.chars().next())
+}
+
+/// Lowercase ASCII letters and digits of `text`, accents dropped
+fn key_part(text: &str) -> String {
+    text.chars()
+        .filter_map(|c| match c.is_ascii() {
+            true => Some(c),
+            false => unaccented(c),
+        })
+        .filter(char::is_ascii_alphanumeric)
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+/// Cite key made from the first author's family name, the year and the first word of the
+/// title that is not an article or preposition, e.g. `knuth1968art`
+fn base_key(entry: &CatalogEntry) -> String {
+    let family = entry
+        .first_author
+        .split(',')
+        .next()
+        .map(key_part)
+        .filter(|family| !family.is_empty())
+        .unwrap_or_else(|| "anon".to_string());
+    let year = entry
+        .year
+        .map(|year| year.to_string())
+        .unwrap_or_else(|| "nd".to_string());
+    let word = entry
+        .title
+        .split_whitespace()
+        .map(key_part)
+        .find(|word| !word.is_empty() && !KEY_STOP_WORDS.contains(&word.as_str()))
+        .unwrap_or_default();
+    format!("{family}{year}{word}")
+}
+
+/// BibTeX cite key of every record in the catalog, by `cite_key`. Records sharing a key get
+/// `b`, `c` and so on in the order they were added, so a record keeps its key whatever is
+/// exported with it and whatever is added after it.
+pub fn citation_keys(catalog: &Catalog) -> DbResult<HashMap<String, String>> {
+    let query = format!(
+        "SELECT {CATALOG_ENTRY_COLUMNS}
+        FROM master_entries m
+        LEFT JOIN catalog_records c ON c.cite_key = m.cite_key
+        LEFT JOIN month_year y ON y.month_year_id = c.month_year_id
+        ORDER BY m.added, m.cite_key"
+    );
+    let entries = catalog.with_statement(&query, |statement| {
+        let mut entries = Vec::new();
+        while let State::Row = statement.next()? {
+            entries.push(read_catalog_entry(statement)?);
+        }
+        Ok(entries)
+    })?;
+    let mut used: HashMap<String, usize> = HashMap::new();
+    let mut keys = HashMap::new();
+    for entry in entries {
+        let base = base_key(&entry);
+        let count = used.entry(base.clone()).or_insert(0);
+        let key = match *count {
+            0 => base,
+            n if n < 26 => format!("{base}{}", char::from(b'a' + n as u8)),
+            n => format!("{base}{}", n + 1),
+        };
+        *count += 1;
+        keys.insert(entry.cite_key, key);
+    }
+    Ok(keys)
+}
+
+/// People with `role` as a BibTeX name list, `Family, Given and Family, Given`
+fn names(authors: &[Author], role: Role) -> String {
+    authors
+        .iter()
+        .filter(|author| author.role == role)
+        .map(|author| latex(&author.name()))
+        .collect::<Vec<_>>()
+        .join(" and ")
+}
+
+/// Organizations with `role`, each in braces so BibTeX does not split the name
+fn organizations(organizations: &[RecordOrganization], role: OrgRole) -> Vec<String> {
+    organizations
+        .iter()
+        .filter(|organization| organization.role == role)
+        .map(|organization| format!("{{{}}}", latex(&organization.organization)))
+        .collect()
+}
+
+/// Authors, editors and other people, with organizations credited as authors after the people
+fn push_names(entry: &mut BibEntry, authors: &[Author], bodies: &[RecordOrganization]) {
+    let mut author = vec![names(authors, Role::Author)];
+    author.extend(organizations(bodies, OrgRole::Author));
+    author.retain(|name| !name.is_empty());
+    entry.push("author", author.join(" and "));
+    entry.push("editor", names(authors, Role::Editor));
+    entry.push("translator", names(authors, Role::Translator));
+    entry.push("illustrator", names(authors, Role::Illustrator));
+}
+
+/// `year` and `month` of a date formatted by `MonthYear`
+fn push_date(entry: &mut BibEntry, date: &str) {
+    let Ok(date) = MonthYear::parse(date) else {
+        return;
+    };
+    entry.push("year", date.year.to_string());
+    if let Some(month) = date.month {
+        entry.push("month", MONTH_MACROS[month as usize - 1].to_string());
+    }
+}
+
+/// A page range with the en dash BibTeX expects, `261--301`
+fn page_range(pages: &str) -> String {
+    match pages.contains("--") {
+        true => latex(pages.trim()),
+        false => latex(&pages.trim().replace('-', "–")),
+    }
+}
+
+/// BibTeX field an item type's own column is written to; columns not listed keep their name
+fn item_field(entry_type: EntryType, column: &str) -> &str {
+    match (entry_type, column) {
+        (EntryType::Thesis | EntryType::Report, "pages") => "pagetotal",
+        (EntryType::Thesis, "degree") | (EntryType::Report, "report_type") => "type",
+        (_, "proceedings" | "book_title") => "booktitle",
+        (_, "location") => "address",
+        (_, "medium") => "howpublished",
+        (_, "site_name") => "organization",
+        (_, "accessed") => "urldate",
+        (_, "catalog_number") => "number",
+        (_, column) => column,
+    }
+}
+
+/// BibTeX entry type for an item; theses are master's theses when the degree starts with `M`
+fn item_entry_type(entry_type: EntryType, fields: &[String]) -> &'static str {
+    match entry_type {
+        EntryType::Thesis if fields[0].trim().to_lowercase().starts_with('m') => "mastersthesis",
+        EntryType::Thesis => "phdthesis",
+        EntryType::ConferencePaper => "inproceedings",
+        EntryType::BookChapter => "incollection",
+        EntryType::Report => "techreport",
+        EntryType::WebResource => "online",
+        EntryType::Software => "software",
+        _ => "misc",
+    }
+}
+
+/// The BibTeX entry for `record`, cited as `key`
+pub fn bibtex_entry(record: &Record, key: &str) -> BibEntry {
+    match record {
+        Record::Book(book) => {
+            let mut entry = BibEntry::new("book", key);
+            push_names(&mut entry, &book.authors, &book.organizations);
+            entry.push_text("title", &book.title);
+            entry.push_text("edition", &book.edition);
+            entry.push_text("volume", &book.volume);
+            entry.push_text("series", &book.series);
+            entry.push_text("publisher", &book.publisher);
+            push_date(&mut entry, &book.year);
+            entry.push_text("pagetotal", &book.pages);
+            entry.push_text("isbn", &book.isbn);
+            let sponsors = organizations(&book.organizations, OrgRole::Sponsor);
+            entry.push("organization", sponsors.join(" and "));
+            entry.push_text("note", &book.note);
+            entry
+        }
+        Record::Article(article) => {
+            let mut entry = BibEntry::new("article", key);
+            push_names(&mut entry, &article.authors, &article.organizations);
+            entry.push_text("title", &article.title);
+            entry.push_text("journal", &article.journal);
+            entry.push_text("volume", &article.volume);
+            entry.push("pages", page_range(&article.pages));
+            entry.push_text("edition", &article.edition);
+            entry.push_text("publisher", &article.publisher);
+            push_date(&mut entry, &article.year);
+            let sponsors = organizations(&article.organizations, OrgRole::Sponsor);
+            entry.push("organization", sponsors.join(" and "));
+            entry.push_text("note", &article.note);
+            entry
+        }
+        Record::Item(item) => {
+            let entry_type = item.item_type.entry_type;
+            let mut entry = BibEntry::new(item_entry_type(entry_type, &item.fields), key);
+            push_names(&mut entry, &item.authors, &item.organizations);
+            entry.push_text("title", &item.title);
+            for (field, value) in item.item_type.fields.iter().zip(&item.fields) {
+                match item_field(entry_type, field.column) {
+                    "pages" => entry.push("pages", page_range(value)),
+                    name => entry.push_text(name, value),
+                }
+            }
+            let schools = organizations(&item.organizations, OrgRole::DegreeGranting);
+            match entry_type {
+                EntryType::Thesis if schools.is_empty() => {
+                    entry.push_text("school", &item.publisher)
+                }
+                EntryType::Thesis => {
+                    entry.push("school", schools.join(" and "));
+                    entry.push_text("publisher", &item.publisher);
+                }
+                EntryType::Report => entry.push_text("institution", &item.publisher),
+                _ => entry.push_text("publisher", &item.publisher),
+            }
+            push_date(&mut entry, &item.year);
+            let sponsors = organizations(&item.organizations, OrgRole::Sponsor);
+            entry.push("organization", sponsors.join(" and "));
+            entry.push_text("note", &item.note);
+            entry
+        }
+    }
+}
+
+/// The records with `cite_keys` as a BibTeX file, in that order; keys not in the catalog are
+/// left out. Returns the text and the number of entries written.
+pub fn export_bibtex(catalog: &Catalog, cite_keys: &[&str]) -> DbResult<(String, usize)> {
+    let keys = citation_keys(catalog)?;
+    let records = read_records_with_keys(catalog, cite_keys)?;
+    let text = records
+        .iter()
+        .map(|record| {
+            let cite_key = record.cite_key();
+            let key = keys.get(cite_key).map_or(cite_key, String::as_str);
+            bibtex_entry(record, key).to_string()
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    Ok((text, records.len()))
+}
+
+/// Month names the predefined month macros stand for
+const MONTH_NAMES: [&str; 12] = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+
+/// Entry types read from a file and the kind of record each becomes
+const IMPORT_TYPES: [(&str, EntryType); 24] = [
+    ("book", EntryType::Book),
+    ("mvbook", EntryType::Book),
+    ("booklet", EntryType::Book),
+    ("manual", EntryType::Book),
+    ("collection", EntryType::Book),
+    ("mvcollection", EntryType::Book),
+    ("proceedings", EntryType::Book),
+    ("mvproceedings", EntryType::Book),
+    ("article", EntryType::Article),
+    ("phdthesis", EntryType::Thesis),
+    ("mastersthesis", EntryType::Thesis),
+    ("thesis", EntryType::Thesis),
+    ("inproceedings", EntryType::ConferencePaper),
+    ("conference", EntryType::ConferencePaper),
+    ("incollection", EntryType::BookChapter),
+    ("inbook", EntryType::BookChapter),
+    ("techreport", EntryType::Report),
+    ("report", EntryType::Report),
+    ("online", EntryType::WebResource),
+    ("www", EntryType::WebResource),
+    ("electronic", EntryType::WebResource),
+    ("software", EntryType::Software),
+    ("audio", EntryType::AudioRecording),
+    ("video", EntryType::Video),
+];
+
+/// Reads BibTeX a character at a time, keeping `@string` macros and the line number
+struct Parser {
+    text: Vec<char>,
+    position: usize,
+    line: usize,
+    macros: HashMap<String, String>,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.text.get(self.position).copied()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.position += 1;
+        if c == '\n' {
+            self.line += 1;
+        }
+        Some(c)
+    }
+
+    /// Skip white space and `%` comments between the parts of an entry
+    fn skip_space(&mut self) {
+        while let Some(c) = self.peek() {
+            match c {
+                '%' => {
+                    while self.peek().is_some_and(|c| c != '\n') {
+                        self.next();
+                    }
+                }
+                c if c.is_whitespace() => {
+                    self.next();
+                }
+                _ => break,
+            }
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), String> {
+        self.skip_space();
+        match self.next() {
+            Some(c) if c == expected => Ok(()),
+            Some(c) => Err(format!("expected `{expected}`, found `{c}`")),
+            None => Err(format!("expected `{expected}`, found the end of the file")),
+        }
+    }
+
+    /// An entry type, key, field or macro name
+    fn name(&mut self) -> String {
+        self.skip_space();
+        let mut name = String::new();
+        while let Some(c) = self.peek() {
+            if c.is_whitespace() || "{}(),=#\"@%".contains(c) {
+                break;
+            }
+            name.push(c);
+            self.next();
+        }
+        name
+    }
+
+    /// Text up to the `close` that balances the opening already read, braces kept inside
+    fn group(&mut self, close: char) -> Result<String, String> {
+        let mut depth = 0;
+        let mut text = String::new();
+        loop {
+            let c = self
+                .next()
+                .ok_or_else(|| format!("`{close}` missing at the end of the file"))?;
+            match c {
+                '{' => depth += 1,
+                '}' if depth > 0 => depth -= 1,
+                c if c == close && depth == 0 => return Ok(text),
+                '}' => return Err("unbalanced `}`".to_string()),
+                _ => {}
+            }
+            text.push(c);
+        }
+    }
+
+    /// A field value: braced or quoted text, a number or a macro, joined by `#`
+    fn value(&mut self) -> Result<String, String> {
+        let mut value = String::new();
+        loop {
+            self.skip_space();
+            match self.peek() {
+                Some('{') => {
+                    self.next();
+                    value.push_str(&self.group('}')?);
+                }
+                Some('"') => {
+                    self.next();
+                    value.push_str(&self.group('"')?);
+                }
+                Some(c) if c.is_ascii_digit() => value.push_str(&self.name()),
+                _ => {
+                    let name = self.name().to_lowercase();
+                    let expansion = match self.macros.get(&name) {
+                        Some(expansion) => expansion.clone(),
+                        None => match MONTH_MACROS.iter().position(|month| *month == name) {
+                            Some(index) => MONTH_NAMES[index].to_string(),
+                            None if name.is_empty() => return Err("value missing".to_string()),
+                            None => return Err(format!("undefined macro `{name}`")),
+                        },
+                    };
+                    value.push_str(&expansion);
+                }
+            }
+            self.skip_space();
+            match self.peek() {
+                Some('#') => {
+                    self.next();
+                }
+                _ => return Ok(value),
+            }
+        }
+    }
+
+    /// The entry after an `@`; `None` for `@string`, `@preamble` and `@comment`
+    fn entry(&mut self) -> Result<Option<BibEntry>, String> {
+        let entry_type = self.name().to_lowercase();
+        self.skip_space();
+        let close = match self.next() {
+            Some('{') => '}',
+            Some('(') => ')',
+            _ => return Err(format!("`{{` missing after @{entry_type}")),
+        };
+        match entry_type.as_str() {
+            "comment" => {
+                self.group(close)?;
+                return Ok(None);
+            }
+            "preamble" => {
+                self.value()?;
+                self.expect(close)?;
+                return Ok(None);
+            }
+            "string" => {
+                let name = self.name().to_lowercase();
+                self.expect('=')?;
+                let value = self.value()?;
+                self.macros.insert(name, value);
+                self.expect(close)?;
+                return Ok(None);
+            }
+            _ => {}
+        }
+        let mut entry = BibEntry::new(&entry_type, &self.name());
+        loop {
+            self.skip_space();
+            match self.peek() {
+                Some(c) if c == close => {
+                    self.next();
+                    return Ok(Some(entry));
+                }
+                Some(',') => {
+                    self.next();
+                }
+                Some(_) => {
+                    let name = self.name().to_lowercase();
+                    if name.is_empty() {
+                        return Err(format!("field name missing in `{}`", entry.key));
+                    }
+                    self.expect('=')?;
+                    let value = self.value()?;
+                    entry.fields.push((name, value));
+                }
+                None => return Err(format!("`{close}` missing at the end of `{}`", entry.key)),
+            }
+        }
+    }
+}
+
+/// An entry and the line of the file it starts on
+pub type LinedEntry = (usize, BibEntry);
+
+/// Entries of a BibTeX or BibLaTeX file with the line each starts on, and the entries that
+/// could not be read with the reason. `@string` macros are expanded and `#` concatenations
+/// joined; values are LaTeX.
+pub fn parse_bibtex(text: &str) -> (Vec<LinedEntry>, Vec<(String, String)>) {
+    let mut parser = Parser {
+        text: text.chars().collect(),
+        position: 0,
+        line: 1,
+        macros: HashMap::new(),
+    };
+    let mut entries = Vec::new();
+    let mut problems = Vec::new();
+    // text outside entries is a comment
+    while let Some(c) = parser.next() {
+        if c != '@' {
+            continue;
+        }
+        let line = parser.line;
+        match parser.entry() {
+            Ok(Some(entry)) => entries.push((line, entry)),
+            Ok(None) => {}
+            Err(reason) => problems.push((format!("entry on line {line}"), reason)),
+        }
+    }
+    (entries, problems)
+}
+
+/// Fill in the fields an entry leaves to the entry it names in `crossref`; the parent's title
+/// becomes the `booktitle` of a chapter or paper
+fn resolve_crossrefs(entries: &mut [LinedEntry]) {
+    let parents: HashMap<String, BibEntry> = entries
+        .iter()
+        .map(|(_, entry)| (entry.key.to_lowercase(), entry.clone()))
+        .collect();
+    for (_, entry) in entries.iter_mut() {
+        let Some(parent) = entry
+            .field("crossref")
+            .and_then(|key| parents.get(&key.trim().to_lowercase()))
+        else {
+            continue;
+        };
+        if entry.field("booktitle").is_none() {
+            if let Some(title) = parent.field("title") {
+                entry
+                    .fields
+                    .push(("booktitle".to_string(), title.to_string()));
+            }
+        }
+        for (name, value) in &parent.fields {
+            if entry.field(name).is_none() {
+                entry.fields.push((name.clone(), value.clone()));
+            }
+        }
+    }
+}
+
+/// Letter `base` with the LaTeX `accent`, or `base` when there is no such letter
+fn accented(accent: &str, base: char) -> char {
+    let base = match base {
+        'ı' => 'i',
+        base => base,
+    };
+    ACCENTS
+        .iter()
+        .find(|(command, _, _)| *command == accent)
+        .and_then(|(_, letters, accented)| {
+            let index = letters.chars().position(|letter| letter == base)?;
+            accented.chars().nth(index)
+        })
+        .unwrap_or(base)
+}
+
+/// Plain text of a LaTeX value: accents and escapes decoded, braces and markup dropped and
+/// white space collapsed
+pub fn plain_text(latex: &str) -> String {
+    let chars: Vec<char> = latex.chars().collect();
+    let mut text = String::new();
+    let mut index = 0;
+    while index < chars.len() {
+        let c = chars[index];
+        index += 1;
+        match c {
+            '\\' => {
+                let Some(&first) = chars.get(index) else {
+                    break;
+                };
+                index += 1;
+                let mut command = first.to_string();
+                if first.is_ascii_alphabetic() {
+                    while chars.get(index).is_some_and(char::is_ascii_alphabetic) {
+                        command.push(chars[index]);
+                        index += 1;
+                    }
+                    while chars.get(index).is_some_and(|c| c.is_whitespace()) {
+                        index += 1;
+                    }
+                }
+                if ACCENTS.iter().any(|(accent, _, _)| *accent == command) {
+                    while chars.get(index).is_some_and(|c| c.is_whitespace()) {
+                        index += 1;
+                    }
+                    let argument = match chars.get(index) {
+                        Some('{') => {
+                            let end = (index..chars.len())
+                                .find(|&end| chars[end] == '}')
+                                .unwrap_or(chars.len());
+                            let argument: String = chars[index + 1..end].iter().collect();
+                            index = end + 1;
+                            plain_text(&argument)
+                        }
+                        Some(&letter) => {
+                            index += 1;
+                            letter.to_string()
+                        }
+                        None => String::new(),
+                    };
+                    let mut letters = argument.chars();
+                    if let Some(base) = letters.next() {
+                        text.push(accented(&command, base));
+                        text.extend(letters);
+                    }
+                    continue;
+                }
+                if let Some((_, letter)) = LETTERS.iter().find(|(name, _)| *name == command) {
+                    text.push(*letter);
+                    continue;
+                }
+                match command.as_str() {
+                    "textbackslash" => text.push('\\'),
+                    "textbraceleft" => text.push('{'),
+                    "textbraceright" => text.push('}'),
+                    "textasciitilde" => text.push('~'),
+                    "textasciicircum" => text.push('^'),
+                    "textendash" => text.push('–'),
+                    "textemdash" => text.push('—'),
+                    "\\" | " " => text.push(' '),
+                    "-" => {}
+                    escaped if !first.is_ascii_alphabetic() => text.push_str(escaped),
+                    // other commands such as \emph keep their argument
+                    _ => {}
+                }
+            }
+            '{' | '}' | '$' => {}
+            '~' => text.push(' '),
+            '-' if chars.get(index) == Some(&'-') => match chars.get(index + 1) == Some(&'-') {
+                true => {
+                    text.push('—');
+                    index += 2;
+                }
+                false => {
+                    text.push('–');
+                    index += 1;
+                }
+            },
+            c if c.is_whitespace() => {
+                if !text.ends_with(' ') {
+                    text.push(' ');
+                }
+            }
+            c => text.push(c),
+        }
+    }
+    text.trim().to_string()
+}
+
+/// Split a name list on the `and`s outside braces
+fn split_names(value: &str) -> Vec<String> {
+    let mut names = Vec::new();
+    let mut name = String::new();
+    let mut depth = 0;
+    let words = value.split_inclusive(char::is_whitespace);
+    for word in words {
+        if depth == 0 && word.trim().eq_ignore_ascii_case("and") {
+            names.push(std::mem::take(&mut name));
+            continue;
+        }
+        for c in word.chars() {
+            match c {
+                '{' => depth += 1,
+                '}' => depth -= 1,
+                _ => {}
+            }
+        }
+        name.push_str(word);
+    }
+    names.push(name);
+    names
+        .into_iter()
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty() && name != "others")
+        .collect()
+}
+
+/// `value` split on `separator` outside braces
+fn split_outside_braces(value: &str, separator: char) -> Vec<String> {
+    let mut parts = vec![String::new()];
+    let mut depth = 0;
+    for c in value.chars() {
+        match c {
+            '{' => depth += 1,
+            '}' => depth -= 1,
+            c if c == separator && depth == 0 => {
+                parts.push(String::new());
+                continue;
+            }
+            _ => {}
+        }
+        if let Some(part) = parts.last_mut() {
+            part.push(c);
+        }
+    }
+    parts
+}
+
+/// A person as `Family, Given`, or a corporate body written in braces, from one BibTeX name
+/// such as `Knuth, Donald E.`, `Ludwig van Beethoven` or `{World Health Organization}`
+enum Name {
+    Person(String),
+    Body(String),
+}
+
+fn read_name(name: &str) -> Name {
+    let whole_group = name.starts_with('{')
+        && name.ends_with('}')
+        && split_outside_braces(&name[1..name.len() - 1], '}').len() == 1;
+    if whole_group {
+        return Name::Body(plain_text(name));
+    }
+    let parts: Vec<String> = split_outside_braces(name, ',')
+        .iter()
+        .map(|part| plain_text(part))
+        .collect();
+    let (family, given) = match parts.as_slice() {
+        [family, given] => (family.clone(), given.clone()),
+        [family, junior, given, ..] => (family.clone(), format!("{given} {junior}")),
+        _ => {
+            let words: Vec<String> = split_outside_braces(name.trim(), ' ')
+                .iter()
+                .map(|word| plain_text(word))
+                .filter(|word| !word.is_empty())
+                .collect();
+            // the family name starts at a lowercase particle such as `van`, or is the last word
+            let last = words.len().saturating_sub(1);
+            let start = words[..last]
+                .iter()
+                .position(|word| word.starts_with(char::is_lowercase))
+                .unwrap_or(last);
+            (words[start..].join(" "), words[..start].join(" "))
+        }
+    };
+    match given.is_empty() {
+        true => Name::Person(family),
+        false => Name::Person(format!("{family}, {given}")),
+    }
+}
+
+/// Authors line and corporate authors of an entry, in the form `parse_authors` reads
+fn people(entry: &BibEntry) -> (Vec<String>, Vec<String>) {
+    let mut people = Vec::new();
+    let mut bodies = Vec::new();
+    for (field, role) in [
+        ("author", ""),
+        ("editor", " (editor)"),
+        ("translator", " (translator)"),
+        ("illustrator", " (illustrator)"),
+    ] {
+        for name in entry.field(field).map(split_names).unwrap_or_default() {
+            match (read_name(&name), role) {
+                (Name::Body(body), "") => bodies.push(body),
+                (Name::Person(person), role) | (Name::Body(person), role) => {
+                    people.push(format!("{person}{role}"))
+                }
+            }
+        }
+    }
+    (people, bodies)
+}
+
+/// Organizations named in `field`, each as it goes on the organizations line with `role`
+fn bodies(entry: &BibEntry, field: &str, role: OrgRole) -> Vec<String> {
+    entry
+        .field(field)
+        .map(split_names)
+        .unwrap_or_default()
+        .iter()
+        .map(|name| match role {
+            OrgRole::Author => plain_text(name),
+            role => format!("{} ({role})", plain_text(name)),
+        })
+        .collect()
+}
+
+/// The date line of an entry: BibLaTeX `date`, the first of a range, or `year` and `month`
+fn date_line(entry: &BibEntry) -> String {
+    let text = |name| entry.field(name).map(plain_text).unwrap_or_default();
+    let date = text("date");
+    if !date.is_empty() {
+        return date.split('/').next().unwrap_or_default().to_string();
+    }
+    let (year, month) = (text("year"), text("month"));
+    match month.parse::<i64>() {
+        _ if month.is_empty() || year.is_empty() => year,
+        Ok(month) => format!("{year}-{month:02}"),
+        Err(_) => format!("{month} {year}"),
+    }
+}
+
+/// The form for an entry, or why it has none
+fn import_record(line: usize, entry: &BibEntry) -> Result<ImportRecord, String> {
+    let source = format!("entry `{}` on line {line}", entry.key);
+    let entry_type = IMPORT_TYPES
+        .iter()
+        .find(|(name, _)| *name == entry.entry_type)
+        .map(|(_, entry_type)| *entry_type)
+        // a @misc with a URL is most often a web page
+        .or_else(|| {
+            (entry.entry_type == "misc" && entry.field("url").is_some())
+                .then_some(EntryType::WebResource)
+        })
+        .ok_or_else(|| format!("@{} has no matching record type", entry.entry_type))?;
+    let text = |name: &str| entry.field(name).map(plain_text).unwrap_or_default();
+    let first = |names: &[&str]| {
+        names
+            .iter()
+            .map(|name| text(name))
+            .find(|value| !value.is_empty())
+            .unwrap_or_default()
+    };
+    let mut record = ImportRecord::new(source, entry_type);
+    let (people, mut organizations) = people(entry);
+    record.set("Authors", &people.join("; "));
+    let title = match text("subtitle").as_str() {
+        "" => text("title"),
+        subtitle => format!("{}: {subtitle}", text("title")),
+    };
+    record.set("Title", &title);
+    record.set("Date", &date_line(entry));
+    record.set("Note", &first(&["note", "addendum"]));
+    let mut publisher = text("publisher");
+    match entry_type {
+        EntryType::Book => {
+            record.set("Pages", &first(&["pagetotal", "pages"]));
+            record.set("Volume", &text("volume"));
+            record.set("Edition", &text("edition"));
+            record.set("Series", &text("series"));
+            record.set("ISBN", &text("isbn"));
+        }
+        EntryType::Article => {
+            record.set("Journal", &first(&["journal", "journaltitle"]));
+            record.set("Volume", &text("volume"));
+            record.set("Pages", &text("pages").replace('–', "-"));
+            record.set("Edition", &text("edition"));
+        }
+        EntryType::Thesis if publisher.is_empty() => publisher = text("school"),
+        EntryType::Thesis => {
+            organizations.extend(bodies(entry, "school", OrgRole::DegreeGranting));
+        }
+        EntryType::Report if publisher.is_empty() => publisher = text("institution"),
+        _ => {}
+    }
+    if let Some(item_type) = entry_type.item_type() {
+        for field in item_type.fields {
+            let value = match (item_field(entry_type, field.column), field.column) {
+                ("pagetotal", _) => first(&["pagetotal", "pages"]),
+                ("pages", _) => text("pages").replace('–', "-"),
+                ("type", "degree") => match (text("type"), entry.entry_type.as_str()) {
+                    (degree, _) if !degree.is_empty() => degree,
+                    (_, "mastersthesis") => "Master's".to_string(),
+                    _ => "PhD".to_string(),
+                },
+                (name, _) => text(name),
+            };
+            record.set(field.label, &value);
+        }
+    }
+    let sponsors_field = !entry_type.item_type().is_some_and(|item_type| {
+        item_type
+            .fields
+            .iter()
+            .any(|field| item_field(entry_type, field.column) == "organization")
+    });
+    if sponsors_field {
+        organizations.extend(bodies(entry, "organization", OrgRole::Sponsor));
+    }
+    record.set("Publisher", &publisher);
+    record.set("Organizations", &organizations.join("; "));
+    Ok(record)
+}
+
+/// Import the entries of a BibTeX or BibLaTeX file through `import_records`. Entries that
+/// cannot be read, or whose type has no matching record type, are reported as skipped.
+pub fn import_bibtex(catalog: &Catalog, text: &str, dry_run: bool) -> DbResult<ImportReport> {
+    let (mut entries, problems) = parse_bibtex(text);
+    resolve_crossrefs(&mut entries);
+    let mut records = Vec::new();
+    let mut skipped = problems;
+    for (line, entry) in &entries {
+        match import_record(*line, entry) {
+            Ok(record) => records.push(record),
+            Err(reason) => skipped.push((format!("entry `{}` on line {line}", entry.key), reason)),
+        }
+    }
+    let mut report = import_records(catalog, &records, dry_run)?;
+    for (source, reason) in skipped {
+        report.skip(source, reason);
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::db::{Article, Book, RowSelect, TestDatabase};
+    use crate::listing::{ListView, SortKey};
+    use serial_test::serial;
+
+    #[test]
+    fn escapes_latex() {
+        for (_, letters, accented) in ACCENTS {
+            assert_eq!(letters.chars().count(), accented.chars().count());
+        }
+        assert_eq!(
+            latex("50% of R&D_1 costs $3 #2 {x} ~ ^ \\"),
+            "50\\% of R\\&D\\_1 costs \\$3 \\#2 \\textbraceleft{}x\\textbraceright{} \\textasciitilde{} \\textasciicircum{} \\textbackslash{}"
+        );
+        assert_eq!(
+            latex("Gödel, Escher, Bach – Çelik Straße"),
+            "G{\\\"o}del, Escher, Bach -- {\\c C}elik Stra{\\ss}e"
+        );
+        assert_eq!(latex("東京"), "東京");
+        assert_eq!(page_range("261-301"), "261--301");
+        assert_eq!(key_part("Erdős-Gödel"), "erdsgodel");
+    }
+
+    #[test]
+    #[serial]
+    fn exports_books_and_articles() {
+        let database = TestDatabase::new();
+        let catalog = database.catalog();
+        let book = |title: &str, note: &str| {
+            Book::book_transaction(
+                &catalog,
+                vec![
+                    "Knuth, Donald; Gödel, Kurt (editor)".to_string(),
+                    title.to_string(),
+                    "650".to_string(),
+                    "1".to_string(),
+                    "2nd".to_string(),
+                    "March 1968".to_string(),
+                    "".to_string(),
+                    "Addison-Wesley".to_string(),
+                    note.to_string(),
+                    "0-201-03801-3".to_string(),
+                    "Stanford University (sponsor)".to_string(),
+                ],
+            )
+            .unwrap()
+        };
+        let first = book("The Art of Computer Programming", "100% of the art & craft");
+        let second = book("The Art of Computer Programming", "");
+        let article = Article::article_transaction(
+            &catalog,
+            vec![
+                "Go to statement considered harmful".to_string(),
+                "Communications of the ACM".to_string(),
+                "11".to_string(),
+                "147-148".to_string(),
+                "".to_string(),
+                "1968".to_string(),
+                "".to_string(),
+                "ACM".to_string(),
+                "Dijkstra, Edsger".to_string(),
+            ],
+        )
+        .unwrap();
+
+        let cite_keys = ListView::new(SortKey::Added)
+            .cite_keys(&catalog, None)
+            .unwrap();
+        let cite_keys: Vec<&str> = cite_keys.iter().map(String::as_str).collect();
+        let (text, count) = export_bibtex(&catalog, &cite_keys).unwrap();
+        assert_eq!(count, 3);
+        assert!(text.starts_with(
+            "@book{knuth1968art,
+  author = {Knuth, Donald},
+  editor = {G{\\\"o}del, Kurt},
+  title = {The Art of Computer Programming},
+  edition = {2nd},
+  volume = {1},
+  publisher = {Addison-Wesley},
+  year = {1968},
+  month = mar,
+  pagetotal = {650},
+  isbn = {0-201-03801-3},
+  organization = {{Stanford University}},
+  note = {100\\% of the art \\& craft},
+}
+"
+        ));
+        assert!(text.contains("@book{knuth1968artb,"));
+        assert!(text.contains(
+            "@article{dijkstra1968go,
+  author = {Dijkstra, Edsger},
+  title = {Go to statement considered harmful},
+  journal = {Communications of the ACM},
+  volume = {11},
+  pages = {147--148},
+  publisher = {ACM},
+  year = {1968},
+}
+"
+        ));
+
+        // a record keeps its key when exported alone or after an earlier one is changed
+        let (text, count) = export_bibtex(&catalog, &[second.as_str(), "missing"]).unwrap();
+        assert_eq!(count, 1);
+        assert!(text.starts_with("@book{knuth1968artb,"));
+        let mut form = Book::select(&catalog, &first).unwrap();
+        form[8] = "Revised".to_string();
+        Book::book_update(&catalog, form, first).unwrap();
+        let keys = citation_keys(&catalog).unwrap();
+        assert_eq!(keys[&second], "knuth1968artb");
+        assert_eq!(keys[&article], "dijkstra1968go");
+    }
+
+    const LIBRARY: &str = r#"
+Exported from a reference manager.
+@string{aw = "Addison-Wesley"}
+@string(cacm = {Communications} # " of the " # {ACM})
+@preamble{"\newcommand{\noop}[1]{}"}
+@comment{@book{ignored, title = {Not an entry}}}
+
+@book{knuth68,
+  author = {Knuth, Donald E. and Gödel, Kurt},
+  editor = "Graham, Ronald",
+  title = {The {Art} of Computer Programming},
+  subtitle = {Fundamental Algorithms},
+  year = 1968, month = mar,
+  publisher = aw,
+  pagetotal = {650},
+  organization = {Stanford University},
+}
+@Article(dijkstra68,
+  author = {Edsger W. Dijkstra},
+  title = {Go to Statement Considered Harmful},
+  journal = cacm, volume = 11, pages = {147--148},
+  date = {1968-03},
+  publisher = {ACM},
+)
+@proceedings{sosp, title = {Symposium on Operating Systems Principles},
+  year = {1967}, publisher = {ACM}}
+@inproceedings{dennis67, author = {Jack B. Dennis and {The MIT Computation Center}},
+  title = {Virtual memory, processes, and sharing in {MULTICS}}, crossref = {sosp}}
+@phdthesis{erdos, author = {Erd{\"o}s, P{\'{a}}l and Ludwig van Beethoven},
+  title = {\emph{Sur} les nombres~premiers}, school = {E{\"o}tv{\"o}s Lor{\'a}nd University},
+  year = {1934}}
+@unpublished{notes, title = {Lecture notes}, year = {2001}}
+@misc{broken, title = {Bad} # nomacro}
+@book{knuth68again, author = {Knuth, D.}, title = {the art of computer programming: fundamental algorithms},
+  year = {1968}, publisher = {Addison-Wesley}, pages = {650}}
+"#;
+
+    #[test]
+    fn reads_bibtex_files() {
+        let (mut entries, problems) = parse_bibtex(LIBRARY);
+        let keys: Vec<&str> = entries
+            .iter()
+            .map(|(_, entry)| entry.key.as_str())
+            .collect();
+        assert_eq!(
+            keys,
+            vec![
+                "knuth68",
+                "dijkstra68",
+                "sosp",
+                "dennis67",
+                "erdos",
+                "notes",
+                "knuth68again"
+            ]
+        );
+        assert_eq!(
+            problems,
+            vec![(
+                "entry on line 33".to_string(),
+                "undefined macro `nomacro`".to_string()
+            )]
+        );
+        let (line, knuth) = &entries[0];
+        assert_eq!(*line, 8);
+        assert_eq!(knuth.field("publisher"), Some("Addison-Wesley"));
+        assert_eq!(knuth.field("month"), Some("March"));
+        assert_eq!(
+            entries[1].1.field("journal"),
+            Some("Communications of the ACM")
+        );
+
+        resolve_crossrefs(&mut entries);
+        let dennis = &entries[3].1;
+        assert_eq!(
+            dennis.field("booktitle"),
+            Some("Symposium on Operating Systems Principles")
+        );
+        assert_eq!(dennis.field("year"), Some("1967"));
+
+        assert_eq!(
+            plain_text("\\emph{Sur} les nombres~premiers -- {\\c C}a\\&{\\ss}  {\\\"O}"),
+            "Sur les nombres premiers – Ça&ß Ö"
+        );
+        assert_eq!(plain_text("Erd\\\"{o}s \\'{\\i} \\v s"), "Erdös í š");
+        assert_eq!(plain_text(&latex("Gödel & 100% {x}")), "Gödel & 100% {x}");
+
+        let record = import_record(entries[4].0, &entries[4].1).unwrap();
+        assert_eq!(record.source, "entry `erdos` on line 29");
+        assert_eq!(record.get("Authors"), "Erdös, Pál; van Beethoven, Ludwig");
+        assert_eq!(record.get("Degree"), "PhD");
+        assert_eq!(record.get("Publisher"), "Eötvös Loránd University");
+        let record = import_record(entries[3].0, &entries[3].1).unwrap();
+        assert_eq!(record.get("Authors"), "Dennis, Jack B.");
+        assert_eq!(record.get("Organizations"), "The MIT Computation Center");
+        assert_eq!(
+            record.get("Proceedings"),
+            "Symposium on Operating Systems Principles"
+        );
+        assert_eq!(
+            import_record(5, &entries[5].1).unwrap_err(),
+            "@unpublished has no matching record type"
+        );
+    }
+
+    #[test]
+    #[serial]
+    fn imports_bibtex() {
+        let database = TestDatabase::new();
+        let catalog = database.catalog();
+        let cite_keys = || {
+            ListView::new(SortKey::Added)
+                .cite_keys(&catalog, None)
+                .unwrap()
+        };
+
+        let report = import_bibtex(&catalog, LIBRARY, true).unwrap();
+        assert_eq!(report.imported(), 5);
+        assert!(cite_keys().is_empty());
+
+        let report = import_bibtex(&catalog, LIBRARY, false).unwrap();
+        assert_eq!(report.imported(), 5);
+        assert_eq!(report.duplicates.len(), 1);
+        assert_eq!(report.duplicates[0].0, "entry `knuth68again` on line 34");
+        assert_eq!(report.duplicates[0].1, report.imported[0].1);
+        // the proceedings has no authors or pages, which a book may leave out
+        assert_eq!(report.imported[2].0, "entry `sosp` on line 25");
+        assert_eq!(report.skipped.len(), 2);
+        assert!(report
+            .to_string()
+            .ends_with("imported 5, 1 duplicates, 2 skipped"));
+        assert_eq!(cite_keys().len(), 5);
+
+        let form = Book::select(&catalog, &report.imported[0].1).unwrap();
+        assert_eq!(
+            form[0],
+            "Knuth, Donald E.; Gödel, Kurt; Graham, Ronald (editor)"
+        );
+        assert_eq!(
+            form[1],
+            "The Art of Computer Programming: Fundamental Algorithms"
+        );
+        assert_eq!(form[5], "1968-03");
+        assert_eq!(form[10], "Stanford University (sponsor)");
+
+        // importing the same file again finds every record already there
+        let report = import_bibtex(&catalog, LIBRARY, false).unwrap();
+        assert_eq!(report.imported(), 0);
+        assert_eq!(report.duplicates.len(), 6);
+        assert_eq!(cite_keys().len(), 5);
+    }
+
+    #[test]
+    #[serial]
+    // Entries as most `.bib` files have them, without every field a form can hold
+    fn imports_sparse_entries() {
+        let database = TestDatabase::new();
+        let catalog = database.catalog();
+        let text = r#"
+@article{hoare69, author = {C. A. R. Hoare}, title = {An axiomatic basis for computer programming},
+  journal = {Communications of the ACM}, year = {1969}}
+@book{notes, title = {Lecture notes on compilers}}
+"#;
+        let report = import_bibtex(&catalog, text, false).unwrap();
+        assert_eq!(report.skipped, vec![]);
+        assert_eq!(report.imported(), 2);
+
+        let article = Article::select(&catalog, &report.imported[0].1).unwrap();
+        assert_eq!(article[1], "Communications of the ACM");
+        assert_eq!(article[5], "1969");
+        assert_eq!(article[7], "");
+        let book = Book::select(&catalog, &report.imported[1].1).unwrap();
+        assert_eq!(book[1], "Lecture notes on compilers");
+        assert_eq!((book[2].as_str(), book[5].as_str()), ("", ""));
+    }
+}
@@ -1,89 +1,12 @@
+use bib_db::config::Config;
+use bib_db::db::{init_db, DbError};
+
 /// Functions for initializing `SQLite` database
 /// If this is the first time using the application you can run this as `cargo run --bin init_db`
 /// It will setup the database and initialize the base tables.
-fn main() {
-    init_db();
-}
-
-const DB_URL: &str = "sqlite://../bibliographic_db/bib_data.db";
-
-/// Initialize the database with tables
-fn init_db() {
-    let connection = sqlite::open(DB_URL).unwrap();
-    let query = "
-    CREATE TABLE IF NOT EXISTS master_entries
-    (
-        cite_key      TEXT PRIMARY KEY UNIQUE NOT NULL,
-        entry_type    VARCHAR NOT NULL
-    );
-
-    CREATE TABLE IF NOT EXISTS book
-    (
-        book_id     TEXT PRIMARY KEY UNIQUE NOT NULL,
-        cite_key    TEXT REFERENCES master_entries(cite_key),
-        publisher_id TEXT REFERENCES publisher(publisher_id),
-        month_year_id TEXT REFERENCES month_year(month_year_id),
-        author      VARCHAR,
-        title       VARCHAR,
-        pages       VARCHAR,
-        volume      VARCHAR,
-        edition     VARCHAR,
-        year        VARCHAR,
-        series      VARCHAR,
-        publisher   VARCHAR,
-        note        VARCHAR
-    );
-
-    CREATE TABLE IF NOT EXISTS relationship
-    (
-        parent_id   TEXT PRIMARY KEY UNIQUE NOT NULL,
-        child_id    INTEGER,
-        cite_key    TEXT REFERENCES master_entries(cite_key)
-    );
-
-    CREATE TABLE IF NOT EXISTS author
-    (
-        cite_key    TEXT REFERENCES master_entries(cite_key),
-        author_id   TEXT PRIMARY KEY UNIQUE NOT NULL,
-        authors     VARCHAR
-    );
-
-    CREATE TABLE IF NOT EXISTS publisher
-    (
-        publisher_id    TEXT PRIMARY KEY UNIQUE NOT NULL,
-        publisher       VARCHAR,
-        address         VARCHAR
-    );
-
-    CREATE TABLE IF NOT EXISTS organizations
-    (
-        organization_id TEXT PRIMARY KEY UNIQUE NOT NULL,
-        organization    VARCHAR,
-        address         VARCHAR
-    );
-
-    CREATE TABLE IF NOT EXISTS month_year
-    (
-        month_year_id   TEXT PRIMARY KEY UNIQUE NOT NULL,
-        month           VARCHAR,
-        year            INTEGER
-    );
-
-    CREATE TABLE IF NOT EXISTS article
-    (
-        cite_key        TEXT REFERENCES master_entries(cite_key),
-        article_id      TEXT PRIMARY KEY UNIQUE NOT NULL,
-        publisher_id    TEXT REFERENCES publisher(publisher_id),
-        month_year_id   TEXT REFERENCES month_year(month_year_id),
-        title           VARCHAR,
-        journal         VARCHAR,
-        volume          VARCHAR,
-        pages           VARCHAR,
-        note            VARCHAR,
-        year            VARCHAR,
-        edition         VARCHAR,
-        publisher       VARCHAR
-    );";
-
-    connection.execute(query).unwrap();
+/// The database location is resolved the same way `bib_db` resolves it: `--db <path>`, `DB_URL`,
+/// `db_url` in `bib_db.conf`, then the default path.
+fn main() -> Result<(), DbError> {
+    let config = Config::load();
+    init_db(&config.db_url)
 }
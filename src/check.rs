@@ -0,0 +1,159 @@
+use crate::db::{Catalog, DbResult};
+use std::fmt;
+
+/// A row that refers to a missing row, or that nothing refers to any more
+#[derive(Clone, Debug, PartialEq)]
+pub struct Orphan {
+    pub table: &'static str,
+    pub id: String,
+    pub problem: &'static str,
+}
+
+impl fmt::Display for Orphan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}: {}", self.table, self.id, self.problem)
+    }
+}
+
+/// Table, problem and the query selecting the id of every row with that problem
+const CHECKS: [(&str, &str, &str); 17] = [
+    (
+        "book",
+        "cite_key not in master_entries",
+        "SELECT book_id FROM book WHERE cite_key IS NULL OR cite_key NOT IN (SELECT cite_key FROM master_entries)",
+    ),
+    (
+        "book",
+        "publisher_id not in publisher",
+        "SELECT book_id FROM book WHERE publisher_id IS NOT NULL AND publisher_id NOT IN (SELECT publisher_id FROM publisher)",
+    ),
+    (
+        "book",
+        "month_year_id not in month_year",
+        "SELECT book_id FROM book WHERE month_year_id IS NOT NULL AND month_year_id NOT IN (SELECT month_year_id FROM month_year)",
+    ),
+    (
+        "article",
+        "cite_key not in master_entries",
+        "SELECT article_id FROM article WHERE cite_key IS NULL OR cite_key NOT IN (SELECT cite_key FROM master_entries)",
+    ),
+    (
+        "article",
+        "publisher_id not in publisher",
+        "SELECT article_id FROM article WHERE publisher_id IS NOT NULL AND publisher_id NOT IN (SELECT publisher_id FROM publisher)",
+    ),
+    (
+        "article",
+        "month_year_id not in month_year",
+        "SELECT article_id FROM article WHERE month_year_id IS NOT NULL AND month_year_id NOT IN (SELECT month_year_id FROM month_year)",
+    ),
+    (
+        "master_entries",
+        "no book, article or other item",
+        "SELECT cite_key FROM master_entries WHERE NOT EXISTS (SELECT 1 FROM catalog_records r WHERE r.cite_key = master_entries.cite_key)",
+    ),
+    (
+        "publisher",
+        "not used by any record",
+        "SELECT publisher_id FROM publisher WHERE NOT EXISTS (SELECT 1 FROM catalog_records r WHERE r.publisher_id = publisher.publisher_id)",
+    ),
+    (
+        "month_year",
+        "not used by any record",
+        "SELECT month_year_id FROM month_year WHERE NOT EXISTS (SELECT 1 FROM catalog_records r WHERE r.month_year_id = month_year.month_year_id)",
+    ),
+    (
+        "item_records",
+        "cite_key not in master_entries",
+        "SELECT cite_key FROM item_records WHERE cite_key NOT IN (SELECT cite_key FROM master_entries)",
+    ),
+    (
+        "item_records",
+        "publisher_id not in publisher",
+        "SELECT cite_key FROM item_records WHERE publisher_id IS NOT NULL AND publisher_id NOT IN (SELECT publisher_id FROM publisher)",
+    ),
+    (
+        "item_records",
+        "month_year_id not in month_year",
+        "SELECT cite_key FROM item_records WHERE month_year_id IS NOT NULL AND month_year_id NOT IN (SELECT month_year_id FROM month_year)",
+    ),
+    (
+        "relationship",
+        "cite_key not in master_entries",
+        "SELECT relationship_id FROM relationship WHERE cite_key NOT IN (SELECT cite_key FROM master_entries)",
+    ),
+    (
+        "relationship",
+        "related_key not in master_entries",
+        "SELECT relationship_id FROM relationship WHERE related_key NOT IN (SELECT cite_key FROM master_entries)",
+    ),
+    (
+        "record_organization",
+        "cite_key not in master_entries",
+        "SELECT cite_key || ' ' || position FROM record_organization WHERE cite_key NOT IN (SELECT cite_key FROM master_entries)",
+    ),
+    (
+        "record_organization",
+        "organization_id not in organizations",
+        "SELECT cite_key || ' ' || position FROM record_organization WHERE organization_id NOT IN (SELECT organization_id FROM organizations)",
+    ),
+    (
+        "catalog_search",
+        "cite_key not in master_entries",
+        "SELECT cite_key FROM catalog_search WHERE cite_key NOT IN (SELECT cite_key FROM master_entries)",
+    ),
+];
+
+/// Every orphaned row in the catalog, grouped by check. Rows written before foreign keys were
+/// enforced are the usual source; an empty list means the catalog is consistent.
+pub fn find_orphans(catalog: &Catalog) -> DbResult<Vec<Orphan>> {
+    let mut orphans = Vec::new();
+    for (table, problem, query) in CHECKS {
+        catalog.with_statement(query, |statement| {
+            while let sqlite::State::Row = statement.next()? {
+                orphans.push(Orphan {
+                    table,
+                    id: statement.read::<String, _>(0)?,
+                    problem,
+                });
+            }
+            Ok(())
+        })?;
+    }
+    Ok(orphans)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::db::{Book, TestDatabase};
+    use serial_test::serial;
+
+    #[test]
+    #[serial]
+    fn reports_rows_left_behind() {
+        let database = TestDatabase::new();
+        let catalog = database.catalog();
+        let book_textarea: Vec<String> = vec![
+            "New Author".to_string(),
+            "New Title".to_string(),
+            "300 pages".to_string(),
+            "Volume 1".to_string(),
+            "1st edition".to_string(),
+            "2023".to_string(),
+            "New Series".to_string(),
+            "New Publisher".to_string(),
+        ];
+        let cite_key = Book::book_transaction(&catalog, book_textarea).unwrap();
+        assert_eq!(find_orphans(&catalog).unwrap(), vec![]);
+
+        // a book row removed behind the catalog's back, as an older version could leave it
+        catalog
+            .execute("PRAGMA foreign_keys = OFF; DELETE FROM book; PRAGMA foreign_keys = ON")
+            .unwrap();
+        let orphans = find_orphans(&catalog).unwrap();
+        let tables: Vec<&str> = orphans.iter().map(|orphan| orphan.table).collect();
+        assert_eq!(tables, vec!["master_entries", "publisher", "month_year"]);
+        assert_eq!(orphans[0].id, cite_key);
+    }
+}
@@ -0,0 +1,171 @@
+use std::env;
+use std::fs;
+
+/// Database used when no flag, environment variable or config file names one
+pub const DEFAULT_DB_URL: &str = "sqlite://../bibliographic_db/bib_data.db";
+
+/// Config file read from the working directory unless `--config` or `BIB_DB_CONFIG` names another
+pub const DEFAULT_CONFIG_FILE: &str = "bib_db.conf";
+
+/// Runtime configuration shared by the `bib_db` and `init_db` binaries
+#[derive(Clone, Debug, PartialEq)]
+pub struct Config {
+    pub db_url: String,
+    /// Subcommand and its arguments, e.g. `check`; empty to start the TUI
+    pub command: Vec<String>,
+    /// `--dry-run`: an import checks and reports but saves nothing
+    pub dry_run: bool,
+    /// `--map <file>`: column mapping for `import csv`; without one the mapping is picked in the TUI
+    pub map: Option<String>,
+    /// `--preview`: `import csv` shows the first rows as they would be saved, and saves nothing
+    pub preview: bool,
+    /// `--rollback-on-failure`: `import csv` saves nothing if any row cannot be saved
+    pub rollback_on_failure: bool,
+}
+
+impl Config {
+    /// Resolve the configuration from the command line, the environment and the config file.
+    /// Precedence: `--db <path>`, then `DB_URL`, then `db_url = <path>` in the config file,
+    /// then `DEFAULT_DB_URL`.
+    pub fn load() -> Config {
+        let args: Vec<String> = env::args().skip(1).collect();
+        let config_file = flag_value(&args, "--config")
+            .or_else(|| env::var("BIB_DB_CONFIG").ok())
+            .unwrap_or_else(|| DEFAULT_CONFIG_FILE.to_string());
+        let file_contents = fs::read_to_string(config_file).ok();
+        Config::from_sources(&args, env::var("DB_URL").ok(), file_contents.as_deref())
+    }
+
+    fn from_sources(args: &[String], env_url: Option<String>, file: Option<&str>) -> Config {
+        let db_url = flag_value(args, "--db")
+            .or(env_url.filter(|url| !url.trim().is_empty()))
+            .or_else(|| file.and_then(|contents| file_value(contents, "db_url")))
+            .unwrap_or_else(|| DEFAULT_DB_URL.to_string());
+        Config {
+            db_url,
+            command: positional_args(args),
+            dry_run: args.iter().any(|arg| arg == "--dry-run"),
+            map: flag_value(args, "--map"),
+            preview: args.iter().any(|arg| arg == "--preview"),
+            rollback_on_failure: args.iter().any(|arg| arg == "--rollback-on-failure"),
+        }
+    }
+}
+
+/// Flags that take a value in the next argument
+const VALUE_FLAGS: [&str; 3] = ["--db", "--config", "--map"];
+
+/// Arguments that are not flags or flag values
+fn positional_args(args: &[String]) -> Vec<String> {
+    let mut positional = Vec::new();
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        if VALUE_FLAGS.contains(&arg.as_str()) {
+            iter.next();
+        } else if !arg.starts_with("--") {
+            positional.push(arg.clone());
+        }
+    }
+    positional
+}
+
+/// Value of `--flag <value>` or `--flag=<value>` in the argument list
+fn flag_value(args: &[String], flag: &str) -> Option<String> {
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        if arg == flag {
+            return iter.next().cloned();
+        }
+        if let Some(value) = arg
+            .strip_prefix(flag)
+            .and_then(|rest| rest.strip_prefix('='))
+        {
+            return Some(value.to_string());
+        }
+    }
+    None
+}
+
+/// Value of `key = value` in a config file; blank lines and `#` comments are ignored
+fn file_value(contents: &str, key: &str) -> Option<String> {
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| line.split_once('='))
+        .find(|(name, _)| name.trim() == key)
+        .map(|(_, value)| value.trim().trim_matches('"').to_string())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn flag_beats_env_and_file() {
+        let config = Config::from_sources(
+            &args(&["--db", "stacks.db"]),
+            Some("env.db".to_string()),
+            Some("db_url = file.db"),
+        );
+        assert_eq!(config.db_url, "stacks.db");
+
+        let config = Config::from_sources(&args(&["--db=special.db"]), None, None);
+        assert_eq!(config.db_url, "special.db");
+        assert!(config.command.is_empty());
+    }
+
+    #[test]
+    fn command_skips_flags() {
+        let config = Config::from_sources(
+            &args(&["--config", "other.conf", "check", "--db", "stacks.db"]),
+            None,
+            None,
+        );
+        assert_eq!(config.db_url, "stacks.db");
+        assert_eq!(config.command, vec!["check".to_string()]);
+        assert!(!config.dry_run);
+
+        let config = Config::from_sources(
+            &args(&["import", "--dry-run", "bibtex", "refs.bib"]),
+            None,
+            None,
+        );
+        assert_eq!(config.command, args(&["import", "bibtex", "refs.bib"]));
+        assert!(config.dry_run);
+
+        let config = Config::from_sources(
+            &args(&[
+                "import",
+                "csv",
+                "--map",
+                "shelf.map",
+                "shelf.csv",
+                "--preview",
+            ]),
+            None,
+            None,
+        );
+        assert_eq!(config.command, args(&["import", "csv", "shelf.csv"]));
+        assert_eq!(config.map.as_deref(), Some("shelf.map"));
+        assert!(config.preview && !config.rollback_on_failure);
+    }
+
+    #[test]
+    fn env_then_file_then_default() {
+        let config =
+            Config::from_sources(&[], Some("env.db".to_string()), Some("db_url = file.db"));
+        assert_eq!(config.db_url, "env.db");
+
+        let file = "# catalog used by the test copy\n\ndb_url = \"test_copy.db\"\n";
+        let config = Config::from_sources(&[], None, Some(file));
+        assert_eq!(config.db_url, "test_copy.db");
+
+        let config = Config::from_sources(&[], None, None);
+        assert_eq!(config.db_url, DEFAULT_DB_URL);
+    }
+}
@@ -0,0 +1,525 @@
+use crate::author::{Author, Role};
+use crate::bibtex::citation_keys;
+use crate::db::{
+    read_doi, read_records_with_keys, Catalog, DbResult, EntryType, MonthYear, Record,
+};
+use crate::import::{import_records, ImportRecord, ImportReport};
+use crate::json::Json;
+use crate::organization::{OrgRole, RecordOrganization};
+
+/// CSL item types written for each kind of record, in `EntryType::ALL` order
+const EXPORT_TYPES: [(EntryType, &str); 11] = [
+    (EntryType::Book, "book"),
+    (EntryType::Article, "article-journal"),
+    (EntryType::Thesis, "thesis"),
+    (EntryType::ConferencePaper, "paper-conference"),
+    (EntryType::BookChapter, "chapter"),
+    (EntryType::Report, "report"),
+    (EntryType::Map, "map"),
+    (EntryType::AudioRecording, "song"),
+    (EntryType::Video, "motion_picture"),
+    (EntryType::WebResource, "webpage"),
+    (EntryType::Software, "software"),
+];
+
+/// Other CSL item types read and the kind of record each becomes
+const IMPORT_TYPES: [(&str, EntryType); 9] = [
+    ("article", EntryType::Article),
+    ("article-magazine", EntryType::Article),
+    ("article-newspaper", EntryType::Article),
+    ("entry-dictionary", EntryType::BookChapter),
+    ("entry-encyclopedia", EntryType::BookChapter),
+    ("broadcast", EntryType::Video),
+    ("post", EntryType::WebResource),
+    ("post-weblog", EntryType::WebResource),
+    ("manuscript", EntryType::Book),
+];
+
+/// Name variable each role is written under
+const ROLE_VARIABLES: [(Role, &str); 4] = [
+    (Role::Author, "author"),
+    (Role::Editor, "editor"),
+    (Role::Translator, "translator"),
+    (Role::Illustrator, "illustrator"),
+];
+
+/// CSL variable an item type's own column is written to. Columns CSL has no variable for,
+/// such as a map's projection, go in the item's `custom` object under the column name.
+fn item_variable(entry_type: EntryType, column: &str) -> Option<&'static str> {
+    match (entry_type, column) {
+        (EntryType::Thesis, "pages") => Some("number-of-pages"),
+        (_, "pages") => Some("page"),
+        (_, "degree" | "report_type") => Some("genre"),
+        (_, "proceedings" | "book_title" | "site_name") => Some("container-title"),
+        (_, "location") => Some("event-place"),
+        (_, "chapter") => Some("chapter-number"),
+        (_, "number" | "catalog_number") => Some("number"),
+        (_, "scale") => Some("scale"),
+        (_, "dimensions" | "duration") => Some("dimensions"),
+        (_, "medium") => Some("medium"),
+        (_, "url") => Some("URL"),
+        (_, "accessed") => Some("accessed"),
+        (_, "version") => Some("version"),
+        _ => None,
+    }
+}
+
+/// Add a string member; empty values are left out
+fn push(item: &mut Vec<(String, Json)>, variable: &str, value: &str) {
+    let value = value.trim();
+    if !value.is_empty() {
+        item.push((variable.to_string(), Json::String(value.to_string())));
+    }
+}
+
+/// A date variable: `date-parts` for a date the catalog can read, otherwise the text as `raw`
+fn push_date(item: &mut Vec<(String, Json)>, variable: &str, date: &str) {
+    let date = date.trim();
+    let value = match MonthYear::parse(date) {
+        Ok(date) => {
+            let parts = [Some(date.year), date.month, date.day]
+                .into_iter()
+                .map_while(|part| part.map(|part| Json::Number(part as f64)))
+                .collect();
+            Json::Object(vec![(
+                "date-parts".to_string(),
+                Json::Array(vec![Json::Array(parts)]),
+            )])
+        }
+        Err(_) if date.is_empty() => return,
+        Err(_) => Json::Object(vec![("raw".to_string(), Json::String(date.to_string()))]),
+    };
+    item.push((variable.to_string(), value));
+}
+
+/// People under their role's name variable, organizations credited as authors as `literal`
+/// names among the authors. Sponsors and degree-granting bodies have no CSL variable.
+fn push_names(item: &mut Vec<(String, Json)>, authors: &[Author], bodies: &[RecordOrganization]) {
+    for (role, variable) in ROLE_VARIABLES {
+        let mut names = Vec::new();
+        for author in authors.iter().filter(|author| author.role == role) {
+            let mut name = Vec::new();
+            push(&mut name, "family", &author.family);
+            push(&mut name, "given", &author.given);
+            names.push(Json::Object(name));
+        }
+        if role == Role::Author {
+            for body in bodies.iter().filter(|body| body.role == OrgRole::Author) {
+                let mut name = Vec::new();
+                push(&mut name, "literal", &body.organization);
+                names.push(Json::Object(name));
+            }
+        }
+        if !names.is_empty() {
+            item.push((variable.to_string(), Json::Array(names)));
+        }
+    }
+}
+
+/// A record and its DOI as a CSL item with `id` set to `key`
+pub fn csl_item(record: &Record, key: &str, doi: &str) -> Json {
+    let entry_type = match record {
+        Record::Book(_) => EntryType::Book,
+        Record::Article(_) => EntryType::Article,
+        Record::Item(item) => item.item_type.entry_type,
+    };
+    let item_type = EXPORT_TYPES
+        .iter()
+        .find(|(export_type, _)| *export_type == entry_type)
+        .map_or("document", |(_, name)| name);
+    let mut item = Vec::new();
+    push(&mut item, "id", key);
+    push(&mut item, "type", item_type);
+    let note = match record {
+        Record::Book(book) => {
+            push_names(&mut item, &book.authors, &book.organizations);
+            push(&mut item, "title", &book.title);
+            push(&mut item, "collection-title", &book.series);
+            push_date(&mut item, "issued", &book.year);
+            push(&mut item, "volume", &book.volume);
+            push(&mut item, "edition", &book.edition);
+            push(&mut item, "number-of-pages", &book.pages);
+            push(&mut item, "publisher", &book.publisher);
+            push(&mut item, "ISBN", &book.isbn);
+            &book.note
+        }
+        Record::Article(article) => {
+            push_names(&mut item, &article.authors, &article.organizations);
+            push(&mut item, "title", &article.title);
+            push(&mut item, "container-title", &article.journal);
+            push_date(&mut item, "issued", &article.year);
+            push(&mut item, "volume", &article.volume);
+            push(&mut item, "page", &article.pages);
+            push(&mut item, "edition", &article.edition);
+            push(&mut item, "publisher", &article.publisher);
+            &article.note
+        }
+        Record::Item(record) => {
+            push_names(&mut item, &record.authors, &record.organizations);
+            push(&mut item, "title", &record.title);
+            push_date(&mut item, "issued", &record.year);
+            push(&mut item, "publisher", &record.publisher);
+            let mut custom = Vec::new();
+            for (field, value) in record.item_type.fields.iter().zip(&record.fields) {
+                match item_variable(entry_type, field.column) {
+                    Some("accessed") => push_date(&mut item, "accessed", value),
+                    Some(variable) => push(&mut item, variable, value),
+                    None => push(&mut custom, field.column, value),
+                }
+            }
+            if !custom.is_empty() {
+                item.push(("custom".to_string(), Json::Object(custom)));
+            }
+            &record.note
+        }
+    };
+    push(&mut item, "note", note);
+    push(&mut item, "DOI", doi);
+    Json::Object(item)
+}
+
+/// Write the records with `cite_keys` as a CSL-JSON array, with the same keys as a BibTeX export
+pub fn export_csl(catalog: &Catalog, cite_keys: &[&str]) -> DbResult<(String, usize)> {
+    let keys = citation_keys(catalog)?;
+    let records = read_records_with_keys(catalog, cite_keys)?;
+    let mut items = Vec::new();
+    for record in &records {
+        let cite_key = record.cite_key();
+        let key = keys.get(cite_key).map_or(cite_key, String::as_str);
+        let doi = read_doi(catalog, cite_key)?;
+        items.push(csl_item(record, key, &doi));
+    }
+    Ok((format!("{}\n", Json::Array(items)), records.len()))
+}
+
+/// A CSL date variable as a date line: `date-parts` as `YYYY-MM-DD`, or its `raw` or
+/// `literal` text
+fn date_line(date: Option<&Json>) -> String {
+    let Some(date) = date else {
+        return String::new();
+    };
+    let parts: Vec<String> = date
+        .get("date-parts")
+        .and_then(|ranges| ranges.elements().first())
+        .map(|parts| parts.elements().iter().map(Json::text).collect())
+        .unwrap_or_default();
+    match parts.split_first() {
+        Some((year, rest)) if !year.is_empty() => {
+            let mut line = year.clone();
+            for part in rest.iter().take(2) {
+                line.push_str(&format!("-{part:0>2}"));
+            }
+            line
+        }
+        _ => match date.text_of("raw") {
+            raw if raw.is_empty() => date.text_of("literal"),
+            raw => raw,
+        },
+    }
+}
+
+/// A CSL name as a line of the authors form: `Family, Given`, with any particles and suffix
+fn person(name: &Json) -> String {
+    let family = [
+        name.text_of("non-dropping-particle"),
+        name.text_of("family"),
+    ]
+    .into_iter()
+    .filter(|part| !part.is_empty())
+    .collect::<Vec<_>>()
+    .join(" ");
+    let given = [
+        name.text_of("given"),
+        name.text_of("dropping-particle"),
+        name.text_of("suffix"),
+    ]
+    .into_iter()
+    .filter(|part| !part.is_empty())
+    .collect::<Vec<_>>()
+    .join(" ");
+    match (family.is_empty(), given.is_empty()) {
+        (true, true) => name.text_of("literal"),
+        (true, false) => given,
+        (false, true) => family,
+        (false, false) => format!("{family}, {given}"),
+    }
+}
+
+/// The form for a CSL item, or why it has none
+fn import_record(number: usize, item: &Json) -> Result<ImportRecord, String> {
+    let source = match item.text_of("id") {
+        id if id.is_empty() => format!("item {number}"),
+        id => format!("item `{id}`"),
+    };
+    let Json::Object(_) = item else {
+        return Err("item is not an object".to_string());
+    };
+    let name = item.text_of("type");
+    let entry_type = EXPORT_TYPES
+        .iter()
+        .find(|(_, export_name)| *export_name == name)
+        .map(|(entry_type, _)| *entry_type)
+        .or_else(|| {
+            IMPORT_TYPES
+                .iter()
+                .find(|(import_name, _)| *import_name == name)
+                .map(|(_, entry_type)| *entry_type)
+        })
+        .ok_or_else(|| format!("type `{name}` has no matching record type"))?;
+    let mut people = Vec::new();
+    let mut organizations = Vec::new();
+    for (role, variable) in ROLE_VARIABLES {
+        for name in item.get(variable).map_or(&[][..], Json::elements) {
+            let line = person(name);
+            match role {
+                _ if line.is_empty() => {}
+                // a literal name among the authors is an organization
+                Role::Author if name.get("family").is_none() && name.get("literal").is_some() => {
+                    organizations.push(line)
+                }
+                Role::Author => people.push(line),
+                role => people.push(format!("{line} ({role})")),
+            }
+        }
+    }
+    let page = match item.text_of("page") {
+        page if page.is_empty() => item.text_of("number-of-pages"),
+        page => page,
+    };
+    let mut record = ImportRecord::new(source, entry_type);
+    record.set("Authors", &people.join("; "));
+    record.set("Title", &item.text_of("title"));
+    record.set("Date", &date_line(item.get("issued")));
+    record.set("Publisher", &item.text_of("publisher"));
+    record.set("Note", &item.text_of("note"));
+    record.set("Organizations", &organizations.join("; "));
+    record.doi = item.text_of("DOI");
+    match entry_type {
+        EntryType::Book => {
+            record.set("Pages", &page);
+            record.set("Volume", &item.text_of("volume"));
+            record.set("Edition", &item.text_of("edition"));
+            record.set("Series", &item.text_of("collection-title"));
+            record.set("ISBN", &item.text_of("ISBN"));
+        }
+        EntryType::Article => {
+            record.set("Journal", &item.text_of("container-title"));
+            record.set("Volume", &item.text_of("volume"));
+            record.set("Pages", &page);
+            record.set("Edition", &item.text_of("edition"));
+        }
+        _ => {}
+    }
+    if let Some(item_type) = entry_type.item_type() {
+        let custom = item.get("custom");
+        for field in item_type.fields {
+            let value = match item_variable(entry_type, field.column) {
+                Some("accessed") => date_line(item.get("accessed")),
+                Some("page" | "number-of-pages") => page.clone(),
+                Some(variable) => item.text_of(variable),
+                None => custom
+                    .map(|custom| custom.text_of(field.column))
+                    .unwrap_or_default(),
+            };
+            record.set(field.label, &value);
+        }
+    }
+    Ok(record)
+}
+
+/// Import the items of a CSL-JSON array, or a single item, through `import_records`. Items that
+/// cannot be read, or whose type has no matching record type, are reported as skipped.
+pub fn import_csl(catalog: &Catalog, text: &str, dry_run: bool) -> DbResult<ImportReport> {
+    let (items, mut skipped) = match Json::parse(text) {
+        Ok(Json::Array(items)) => (items, Vec::new()),
+        Ok(item) => (vec![item], Vec::new()),
+        Err(reason) => (Vec::new(), vec![("file".to_string(), reason)]),
+    };
+    let mut records = Vec::new();
+    for (index, item) in items.iter().enumerate() {
+        match import_record(index + 1, item) {
+            Ok(record) => records.push(record),
+            Err(reason) => skipped.push((format!("item {}", index + 1), reason)),
+        }
+    }
+    let mut report = import_records(catalog, &records, dry_run)?;
+    for (source, reason) in skipped {
+        report.skip(source, reason);
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::db::TestDatabase;
+    use crate::listing::{ListView, SortKey};
+    use serial_test::serial;
+
+    const LIBRARY: &str = r#"[
+  {
+    "id": "knuth1968art",
+    "type": "book",
+    "author": [
+      {"family": "Knuth", "given": "Donald E."},
+      {"literal": "Stanford Computer Science Department"}
+    ],
+    "editor": [{"family": "Gödel", "given": "Kurt"}],
+    "title": "The art of computer programming",
+    "collection-title": "Series in computer science",
+    "issued": {"date-parts": [[1968, 3]]},
+    "volume": 1,
+    "edition": "2",
+    "number-of-pages": "650",
+    "publisher": "Addison-Wesley",
+    "ISBN": "0-201-03801-3"
+  },
+  {
+    "id": "dijkstra1968go",
+    "type": "article-journal",
+    "author": [{"family": "Dijkstra", "given": "Edsger W."}],
+    "title": "Go to statement considered harmful",
+    "container-title": "Communications of the ACM",
+    "issued": {"date-parts": [["1968", "3", "1"]]},
+    "volume": "11",
+    "page": "147-148",
+    "publisher": "ACM",
+    "DOI": "10.1145/362929.362947"
+  },
+  {
+    "id": "os2015lake",
+    "type": "map",
+    "author": [{"literal": "Ordnance Survey"}],
+    "title": "Lake District",
+    "issued": {"raw": "2015"},
+    "publisher": "Ordnance Survey",
+    "scale": "1:25000",
+    "custom": {"projection": "Transverse Mercator"}
+  },
+  {"id": "patent", "type": "patent", "title": "A patent"},
+  "not an item"
+]"#;
+
+    #[test]
+    fn reads_csl_items() {
+        let items = Json::parse(LIBRARY).unwrap();
+        let items = items.elements();
+        let book = import_record(1, &items[0]).unwrap();
+        assert_eq!(book.source, "item `knuth1968art`");
+        assert_eq!(
+            book.lines,
+            vec![
+                "Knuth, Donald E.; Gödel, Kurt (editor)",
+                "The art of computer programming",
+                "650",
+                "1",
+                "2",
+                "1968-03",
+                "Series in computer science",
+                "Addison-Wesley",
+                "",
+                "0-201-03801-3",
+                "Stanford Computer Science Department",
+            ]
+        );
+        let article = import_record(2, &items[1]).unwrap();
+        assert_eq!(article.get("Date"), "1968-03-01");
+        assert_eq!(article.get("Pages"), "147-148");
+        assert_eq!(article.get("Note"), "");
+        assert_eq!(article.doi, "10.1145/362929.362947");
+        let map = import_record(3, &items[2]).unwrap();
+        assert_eq!(map.get("Organizations"), "Ordnance Survey");
+        assert_eq!(map.get("Date"), "2015");
+        assert_eq!(map.get("Scale"), "1:25000");
+        assert_eq!(map.get("Projection"), "Transverse Mercator");
+        assert_eq!(
+            import_record(4, &items[3]).unwrap_err(),
+            "type `patent` has no matching record type"
+        );
+        assert_eq!(
+            import_record(5, &items[4]).unwrap_err(),
+            "item is not an object"
+        );
+    }
+
+    #[test]
+    #[serial]
+    fn round_trips_csl() {
+        let database = TestDatabase::new();
+        let catalog = database.catalog();
+
+        let report = import_csl(&catalog, LIBRARY, false).unwrap();
+        assert_eq!(report.imported(), 3);
+        assert_eq!(report.skipped.len(), 2);
+
+        let cite_keys = ListView::new(SortKey::Added)
+            .cite_keys(&catalog, None)
+            .unwrap();
+        let cite_keys: Vec<&str> = cite_keys.iter().map(String::as_str).collect();
+        let (text, count) = export_csl(&catalog, &cite_keys).unwrap();
+        assert_eq!(count, 3);
+        let items = Json::parse(&text).unwrap();
+        let article = &items.elements()[1];
+        assert_eq!(article.text_of("id"), "dijkstra1968go");
+        assert_eq!(article.text_of("DOI"), "10.1145/362929.362947");
+        assert_eq!(article.get("note"), None);
+        assert_eq!(
+            article.get("issued").unwrap().to_string(),
+            "{\n  \"date-parts\": [\n    [\n      1968,\n      3,\n      1\n    ]\n  ]\n}"
+        );
+
+        // what is written reads back as it was, into another catalog too
+        let other_database = TestDatabase::new();
+        let other_catalog = other_database.catalog();
+        let report = import_csl(&other_catalog, &text, false).unwrap();
+        assert_eq!(report.imported(), 3);
+        let cite_keys = ListView::new(SortKey::Added)
+            .cite_keys(&other_catalog, None)
+            .unwrap();
+        let cite_keys: Vec<&str> = cite_keys.iter().map(String::as_str).collect();
+        assert_eq!(export_csl(&other_catalog, &cite_keys).unwrap().0, text);
+
+        let report = import_csl(&catalog, &text, true).unwrap();
+        assert_eq!(report.duplicates.len(), 3);
+        let report = import_csl(&catalog, "[{\"id\": 1,}]", true).unwrap();
+        assert_eq!(
+            report.skipped,
+            vec![(
+                "file".to_string(),
+                "line 1: expected a member name in quotes".to_string()
+            )]
+        );
+    }
+
+    #[test]
+    #[serial]
+    // Items with no publisher, volume, page or date, as citation tools often write them
+    fn imports_sparse_items() {
+        let database = TestDatabase::new();
+        let catalog = database.catalog();
+        let text = r#"[
+  {"id": "hoare1974", "type": "article-journal", "title": "Monitors",
+   "container-title": "Communications of the ACM"},
+  {"id": "notes", "type": "book", "title": "Notes on structured programming"}
+]"#;
+        let report = import_csl(&catalog, text, false).unwrap();
+        assert_eq!(report.skipped, vec![]);
+        assert_eq!(report.imported(), 2);
+
+        let cite_keys: Vec<&str> = report
+            .imported
+            .iter()
+            .map(|(_, cite_key)| cite_key.as_str())
+            .collect();
+        let (exported, count) = export_csl(&catalog, &cite_keys).unwrap();
+        assert_eq!(count, 2);
+        let items = Json::parse(&exported).unwrap();
+        let article = &items.elements()[0];
+        assert_eq!(
+            article.text_of("container-title"),
+            "Communications of the ACM"
+        );
+        assert_eq!(article.get("publisher"), None);
+        assert_eq!(article.get("issued"), None);
+    }
+}
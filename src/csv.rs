@@ -0,0 +1,559 @@
+use crate::db::{form_fields, Catalog, DbError, DbResult, EntryType};
+use crate::import::{form_labels, import_records, ImportRecord, ImportReport};
+use crate::query::parse_type;
+use std::error::Error;
+
+/// Rows shown by a preview before anything is imported
+pub const PREVIEW_ROWS: usize = 3;
+
+/// Lines of the column mapping form: the record type, the column read for each field of the
+/// book and article forms, then what to do when a row cannot be saved
+pub const MAPPING_FIELDS: [&str; 14] = [
+    "Record type",
+    "Authors",
+    "Title",
+    "Journal",
+    "Pages",
+    "Volume",
+    "Edition",
+    "Date",
+    "Series",
+    "Publisher",
+    "Note",
+    "ISBN",
+    "Organizations",
+    "On failure",
+];
+
+/// Position of `On failure` in `MAPPING_FIELDS`
+const ON_FAILURE_LINE: usize = MAPPING_FIELDS.len() - 1;
+
+/// Labels of the form fields a column can fill
+fn field_labels() -> &'static [&'static str] {
+    &MAPPING_FIELDS[1..ON_FAILURE_LINE]
+}
+
+/// A spreadsheet saved as CSV or TSV: the header row, then the rows below it
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Table {
+    pub(crate) header: Vec<String>,
+    /// Row number as the spreadsheet shows it, counting the header as row 1, and the values;
+    /// blank rows are left out
+    pub(crate) rows: Vec<(usize, Vec<String>)>,
+}
+
+impl Table {
+    /// Read `text`, whose first row is the header. A value may be quoted with `"`, with `""` for
+    /// a quote inside it, and may then hold the separator and line breaks.
+    pub fn parse(text: &str, separator: char) -> Result<Table, String> {
+        let mut records: Vec<Vec<String>> = Vec::new();
+        let mut fields = Vec::new();
+        let mut value = String::new();
+        let mut in_quotes = false;
+        let mut line = 1;
+        let mut quote_line = 1;
+        let mut chars = text.trim_start_matches('\u{feff}').chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '"' if in_quotes => match chars.peek() {
+                    Some('"') => {
+                        chars.next();
+                        value.push('"');
+                    }
+                    _ => in_quotes = false,
+                },
+                '"' if value.trim().is_empty() => {
+                    value.clear();
+                    in_quotes = true;
+                    quote_line = line;
+                }
+                '\r' if chars.peek() == Some(&'\n') => {}
+                '\n' | '\r' if in_quotes => {
+                    line += 1;
+                    value.push('\n');
+                }
+                c if in_quotes => value.push(c),
+                c if c == separator => fields.push(std::mem::take(&mut value)),
+                '\n' | '\r' => {
+                    line += 1;
+                    fields.push(std::mem::take(&mut value));
+                    records.push(std::mem::take(&mut fields));
+                }
+                c => value.push(c),
+            }
+        }
+        if in_quotes {
+            return Err(format!("line {quote_line}: quoted value is not closed"));
+        }
+        if !value.is_empty() || !fields.is_empty() {
+            fields.push(value);
+            records.push(fields);
+        }
+        let mut records = records.into_iter().enumerate();
+        let header = match records.next() {
+            Some((_, header)) if header.iter().any(|name| !name.trim().is_empty()) => header,
+            _ => return Err("the file has no header row".to_string()),
+        };
+        Ok(Table {
+            header: header.iter().map(|name| name.trim().to_string()).collect(),
+            rows: records
+                .filter(|(_, values)| values.iter().any(|value| !value.trim().is_empty()))
+                .map(|(index, values)| (index + 1, values))
+                .collect(),
+        })
+    }
+
+    /// The header and the first `count` rows
+    pub fn head(&self, count: usize) -> Table {
+        Table {
+            header: self.header.clone(),
+            rows: self.rows.iter().take(count).cloned().collect(),
+        }
+    }
+
+    /// Column `column` names, by its header ignoring case or by its number counting from 1
+    fn column(&self, column: &str) -> Result<usize, String> {
+        let column = column.trim();
+        if let Some(index) = self
+            .header
+            .iter()
+            .position(|name| name.eq_ignore_ascii_case(column))
+        {
+            return Ok(index);
+        }
+        match column.parse::<usize>() {
+            Ok(number) if (1..=self.header.len()).contains(&number) => Ok(number - 1),
+            Ok(number) => Err(format!(
+                "there is no column {number}, the file has {}",
+                self.header.len()
+            )),
+            Err(_) => Err(format!("no column is headed `{column}`")),
+        }
+    }
+}
+
+/// Separator of a file: a tab for `.tsv` and `.tab` files, otherwise whichever of comma, tab
+/// and semicolon is found most often in the header row
+pub fn separator(file_name: &str, text: &str) -> char {
+    let file_name = file_name.to_lowercase();
+    if file_name.ends_with(".tsv") || file_name.ends_with(".tab") {
+        return '\t';
+    }
+    let header = text.lines().next().unwrap_or_default();
+    // on a tie the comma wins
+    [';', '\t', ',']
+        .into_iter()
+        .max_by_key(|&c| header.matches(c).count())
+        .unwrap_or(',')
+}
+
+/// Read the CSV or TSV file at `path`
+pub fn read_table(path: &str) -> Result<Table, Box<dyn Error>> {
+    let text = std::fs::read_to_string(path).map_err(|err| format!("{path}: {err}"))?;
+    Table::parse(&text, separator(path, &text)).map_err(|err| format!("{path}: {err}").into())
+}
+
+/// Which column fills each form field. A column is named by its header, ignoring case, or by
+/// its number counting from 1.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ColumnMapping {
+    /// `book` or `article` for every row, or the column that says which each row is
+    pub(crate) record_type: String,
+    /// Label of each form field read from a column, and the column, in `MAPPING_FIELDS` order
+    pub(crate) columns: Vec<(&'static str, String)>,
+    /// Roll the whole import back if any row cannot be saved, rather than skipping the row
+    pub(crate) rollback_on_failure: bool,
+}
+
+impl ColumnMapping {
+    /// Read the lines of the mapping form, one per label of `MAPPING_FIELDS`
+    pub fn from_form(textarea: Vec<String>) -> DbResult<ColumnMapping> {
+        let lines = form_fields(textarea, &MAPPING_FIELDS, &[0])?;
+        let rollback_on_failure = match lines[ON_FAILURE_LINE].trim().to_lowercase().as_str() {
+            "" | "skip" => false,
+            "rollback" | "roll back" => true,
+            other => {
+                return Err(DbError::InvalidInput(format!(
+                    "On failure should be skip or rollback, not `{other}`"
+                )))
+            }
+        };
+        Ok(ColumnMapping {
+            record_type: lines[0].trim().to_string(),
+            columns: field_labels()
+                .iter()
+                .zip(&lines[1..ON_FAILURE_LINE])
+                .filter(|(_, column)| !column.trim().is_empty())
+                .map(|(label, column)| (*label, column.trim().to_string()))
+                .collect(),
+            rollback_on_failure,
+        })
+    }
+
+    /// Roll back on failure whatever the mapping says, for `--rollback-on-failure`
+    pub fn roll_back_on_failure(&mut self) {
+        self.rollback_on_failure = true;
+    }
+
+    /// Lines of the mapping form showing this mapping
+    pub fn form_lines(&self) -> Vec<String> {
+        let mut lines = vec![self.record_type.clone()];
+        lines.extend(field_labels().iter().map(|label| {
+            self.columns
+                .iter()
+                .find(|(mapped, _)| mapped == label)
+                .map(|(_, column)| column.clone())
+                .unwrap_or_default()
+        }));
+        lines.push(match self.rollback_on_failure {
+            true => "rollback".to_string(),
+            false => "skip".to_string(),
+        });
+        lines
+    }
+
+    /// Read a mapping file of `Label = column` lines, labelled as in `MAPPING_FIELDS` ignoring
+    /// case; blank lines and `#` comments are ignored
+    pub fn from_file(contents: &str) -> DbResult<ColumnMapping> {
+        let mut lines = vec![String::new(); MAPPING_FIELDS.len()];
+        for (number, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (label, value) = line.split_once('=').ok_or_else(|| {
+                DbError::InvalidInput(format!("line {}: expected `Label = column`", number + 1))
+            })?;
+            let index = MAPPING_FIELDS
+                .iter()
+                .position(|field| field.eq_ignore_ascii_case(label.trim()))
+                .ok_or_else(|| {
+                    DbError::InvalidInput(format!(
+                        "line {}: unknown field `{}`",
+                        number + 1,
+                        label.trim()
+                    ))
+                })?;
+            lines[index] = value.trim().trim_matches('"').to_string();
+        }
+        ColumnMapping::from_form(lines)
+    }
+
+    /// The mapping as a mapping file, to be read back with `from_file`
+    pub fn to_file(&self) -> String {
+        MAPPING_FIELDS
+            .iter()
+            .zip(self.form_lines())
+            .filter(|(_, value)| !value.is_empty())
+            .map(|(label, value)| format!("{label} = {value}\n"))
+            .collect()
+    }
+
+    /// A first guess from the header: each field is read from the column headed with its label,
+    /// singular or plural, and the date from a `Year` column. Rows are books unless a column is
+    /// headed `Type` or `Record type`.
+    pub fn guess(table: &Table) -> ColumnMapping {
+        let header = &table.header;
+        let normal = |text: &str| {
+            text.to_lowercase()
+                .chars()
+                .filter(|c| c.is_alphanumeric())
+                .collect::<String>()
+        };
+        let headed = |names: &[&str]| {
+            header
+                .iter()
+                .find(|column| names.iter().any(|name| normal(column) == normal(name)))
+                .cloned()
+        };
+        let columns = field_labels()
+            .iter()
+            .filter_map(|label| {
+                let singular = label.strip_suffix('s').unwrap_or(label);
+                let names: &[&str] = match *label {
+                    "Date" => &["Date", "Year"],
+                    _ => &[label, singular],
+                };
+                headed(names).map(|column| (*label, column))
+            })
+            .collect();
+        ColumnMapping {
+            record_type: headed(&["Type", "Record type"]).unwrap_or_else(|| "book".to_string()),
+            columns,
+            rollback_on_failure: false,
+        }
+    }
+}
+
+/// Book or article, as named in a mapping or a type column
+fn record_type(value: &str) -> Option<EntryType> {
+    parse_type(value)
+        .filter(|entry_type| matches!(entry_type, EntryType::Book | EntryType::Article))
+}
+
+/// A row read as a form, or its row number and why it cannot be read
+pub type RowRecord = Result<ImportRecord, (String, String)>;
+
+/// Each row of `table` as the form for its record type. Fails if the mapping names a column the
+/// file does not have.
+pub fn table_records(table: &Table, mapping: &ColumnMapping) -> Result<Vec<RowRecord>, String> {
+    let fixed_type = record_type(&mapping.record_type);
+    let type_column =
+        match fixed_type {
+            Some(_) => None,
+            None if mapping.record_type.trim().is_empty() => {
+                return Err("Record type is required".to_string())
+            }
+            None => Some(table.column(&mapping.record_type).map_err(|err| {
+                format!("Record type should be book, article or a column: {err}")
+            })?),
+        };
+    let mut columns = Vec::new();
+    for (label, column) in &mapping.columns {
+        columns.push((
+            *label,
+            table
+                .column(column)
+                .map_err(|err| format!("{label}: {err}"))?,
+        ));
+    }
+    let records = table.rows.iter().map(|(number, values)| {
+        let source = format!("row {number}");
+        let value = |index: usize| values.get(index).map_or("", |value| value.trim());
+        if values[table.header.len().min(values.len())..]
+            .iter()
+            .any(|value| !value.trim().is_empty())
+        {
+            return Err((
+                source,
+                format!("{} values for {} columns", values.len(), table.header.len()),
+            ));
+        }
+        let entry_type = match (fixed_type, type_column) {
+            (Some(entry_type), _) => entry_type,
+            (None, Some(index)) => record_type(value(index)).ok_or_else(|| {
+                (
+                    source.clone(),
+                    format!("record type `{}` should be book or article", value(index)),
+                )
+            })?,
+            (None, None) => unreachable!("a mapping without a record type is refused above"),
+        };
+        let mut record = ImportRecord::new(source, entry_type);
+        for (label, index) in &columns {
+            record.set(label, value(*index));
+        }
+        Ok(record)
+    });
+    Ok(records.collect())
+}
+
+/// The first `count` rows as they would be saved, one filled-in field per line
+pub fn preview(records: &[RowRecord], count: usize) -> String {
+    let mut text = String::new();
+    for record in records.iter().take(count) {
+        match record {
+            Ok(record) => {
+                text.push_str(&format!(
+                    "{} as {}\n",
+                    record.source,
+                    record.entry_type.name().to_lowercase()
+                ));
+                for (label, line) in form_labels(record.entry_type)
+                    .iter()
+                    .zip(&record.lines)
+                    .filter(|(_, line)| !line.is_empty())
+                {
+                    text.push_str(&format!("  {label}: {line}\n"));
+                }
+            }
+            Err((source, reason)) => text.push_str(&format!("{source}: {reason}\n")),
+        }
+    }
+    text
+}
+
+/// Save the rows of `table` through `import_records`, in one transaction. A row that cannot be
+/// read or saved is skipped with its row number, unless the mapping rolls back on failure; then
+/// nothing is saved and the report says what would have been.
+pub fn import_table(
+    catalog: &Catalog,
+    table: &Table,
+    mapping: &ColumnMapping,
+    dry_run: bool,
+) -> DbResult<ImportReport> {
+    let rows = table_records(table, mapping).map_err(DbError::InvalidInput)?;
+    let mut records = Vec::new();
+    let mut unread = Vec::new();
+    for row in rows {
+        match row {
+            Ok(record) => records.push(record),
+            Err(problem) => unread.push(problem),
+        }
+    }
+    // the report of a rolled back import is kept here, as the transaction only returns the error
+    let mut checked = None;
+    let import = |catalog: &Catalog| {
+        let mut report = import_records(catalog, &records, false)?;
+        for (source, reason) in unread {
+            report.skip(source, reason);
+        }
+        report.dry_run = dry_run;
+        report.rolled_back = mapping.rollback_on_failure && !report.skipped.is_empty();
+        let rolled_back = report.rolled_back;
+        checked = Some(report);
+        match rolled_back {
+            true => Err(DbError::InvalidInput("rows could not be saved".to_string())),
+            false => Ok(()),
+        }
+    };
+    let saved = match dry_run {
+        true => catalog.dry_run(import),
+        false => catalog.transaction(import),
+    };
+    // a rolled back import fails on purpose; any other error, such as a commit that failed,
+    // means the report cannot be trusted
+    match (saved, checked) {
+        (Err(_), Some(report)) if report.rolled_back => Ok(report),
+        (Err(err), _) => Err(err),
+        (Ok(()), report) => report.ok_or_else(|| {
+            DbError::InvalidInput("the import stopped before any row was read".to_string())
+        }),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::db::TestDatabase;
+    use serial_test::serial;
+
+    const INVENTORY: &str = "\u{feff}Type,Author,Title,Journal,Volume,Year,Publisher,Pages,Shelf\r
+book,\"Knuth, Donald E.\",The art of computer programming,,1,1968,Addison-Wesley,634,QA76\r
+article,\"Dijkstra, Edsger W.\",\"Go to statement \"\"considered\"\" harmful\",Communications of the ACM,11,1968-03,ACM,147-148,\r
+,,,,,,,,\r
+book,\"Kernighan, Brian W.\",,,,1978,Prentice Hall,228,QA76\r
+map,,Atlas,,,1990,,,\r
+";
+
+    #[test]
+    fn reads_tables_and_mappings() {
+        let table = Table::parse(INVENTORY, separator("inventory.csv", INVENTORY)).unwrap();
+        assert_eq!(table.header[0], "Type");
+        assert_eq!(table.rows.len(), 4);
+        assert_eq!(table.rows[1].0, 3);
+        assert_eq!(table.rows[1].1[2], "Go to statement \"considered\" harmful");
+        assert_eq!(table.rows[2].0, 5);
+
+        let tsv = "Title\tNote\n\"Two\nlines\"\tx;y\n";
+        assert_eq!(separator("notes.txt", tsv), '\t');
+        let table = Table::parse(tsv, '\t').unwrap();
+        assert_eq!(
+            table.rows,
+            vec![(2, vec!["Two\nlines".to_string(), "x;y".to_string()])]
+        );
+        assert_eq!(
+            Table::parse("Title\n\"open", ',').unwrap_err(),
+            "line 2: quoted value is not closed"
+        );
+
+        let table = Table::parse(INVENTORY, ',').unwrap();
+        let mapping = ColumnMapping::guess(&table);
+        assert_eq!(mapping.record_type, "Type");
+        assert_eq!(mapping.columns[0], ("Authors", "Author".to_string()));
+        assert!(mapping.columns.contains(&("Date", "Year".to_string())));
+        let file = format!(
+            "# inventory of the reading room\n{}on failure = rollback\n",
+            mapping.to_file()
+        );
+        let read = ColumnMapping::from_file(&file).unwrap();
+        assert_eq!(read.columns, mapping.columns);
+        assert!(read.rollback_on_failure);
+        assert_eq!(ColumnMapping::from_form(read.form_lines()).unwrap(), read);
+        assert!(ColumnMapping::from_file("Shelf = 8").is_err());
+
+        let records = table_records(&table, &mapping).unwrap();
+        let book = records[0].as_ref().unwrap();
+        assert_eq!(book.get("Authors"), "Knuth, Donald E.");
+        assert_eq!(book.get("Date"), "1968");
+        assert_eq!(
+            records[1].as_ref().unwrap().get("Journal"),
+            "Communications of the ACM"
+        );
+        assert_eq!(
+            records[3],
+            Err((
+                "row 6".to_string(),
+                "record type `map` should be book or article".to_string()
+            ))
+        );
+        let shown = preview(&records, 2);
+        assert!(shown.starts_with("row 2 as book\n  Authors: Knuth, Donald E.\n"));
+        assert!(shown.contains("row 3 as article\n"));
+
+        let mapping = ColumnMapping {
+            columns: vec![("Title", "10".to_string())],
+            ..mapping
+        };
+        assert_eq!(
+            table_records(&table, &mapping).unwrap_err(),
+            "Title: there is no column 10, the file has 9"
+        );
+    }
+
+    #[test]
+    #[serial]
+    fn imports_rows_or_rolls_back() {
+        let database = TestDatabase::new();
+        let catalog = database.catalog();
+        let table = Table::parse(INVENTORY, ',').unwrap();
+        let mut mapping = ColumnMapping::guess(&table);
+        mapping.rollback_on_failure = true;
+
+        let report = import_table(&catalog, &table, &mapping, false).unwrap();
+        assert!(report.rolled_back);
+        assert_eq!(report.imported(), 2);
+        assert_eq!(
+            report.skipped,
+            vec![
+                ("row 5".to_string(), "Title is required".to_string()),
+                (
+                    "row 6".to_string(),
+                    "record type `map` should be book or article".to_string()
+                ),
+            ]
+        );
+        assert!(report.to_string().contains("rolled back"));
+        let report = import_table(&catalog, &table, &mapping, true).unwrap();
+        assert!(report.duplicates.is_empty());
+
+        mapping.rollback_on_failure = false;
+        let report = import_table(&catalog, &table, &mapping, false).unwrap();
+        assert!(!report.rolled_back);
+        assert_eq!(report.imported(), 2);
+        assert_eq!(report.skipped.len(), 2);
+        let report = import_table(&catalog, &table, &mapping, true).unwrap();
+        assert_eq!(report.duplicates.len(), 2);
+    }
+
+    #[test]
+    #[serial]
+    // Empty cells leave optional fields blank; they do not roll the import back
+    fn imports_sparse_rows() {
+        let database = TestDatabase::new();
+        let catalog = database.catalog();
+        let sparse = "Type,Author,Title,Journal,Volume,Year,Publisher,Pages\n\
+            book,,Notes on structured programming,,,,,\n\
+            article,\"Hoare, C. A. R.\",Monitors,Communications of the ACM,,,,\n";
+        let table = Table::parse(sparse, ',').unwrap();
+        let mut mapping = ColumnMapping::guess(&table);
+        mapping.roll_back_on_failure();
+
+        let report = import_table(&catalog, &table, &mapping, false).unwrap();
+        assert!(!report.rolled_back);
+        assert_eq!(report.skipped, vec![]);
+        assert_eq!(report.imported(), 2);
+        let report = import_table(&catalog, &table, &mapping, true).unwrap();
+        assert_eq!(report.duplicates.len(), 2);
+    }
+}
@@ -1,15 +1,232 @@
-use crate::DB_URL;
-use sqlite::{State, Value};
-use std::io::Error;
+use crate::author::{
+    format_authors, parse_authors, read_author_table, replace_authors, select_authors, Author,
+};
+use crate::item::{Item, ItemType, ITEM_TYPES};
+use crate::migrations::migrate;
+use crate::organization::{
+    format_organizations, parse_organizations, read_record_organization_table,
+    replace_organizations, select_record_organizations, RecordOrganization,
+};
+use crate::search::{index_publisher_records, index_record, unindex_record};
+use sqlite::{Connection, State, Statement, Value};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
 use std::string::String;
 use uuid::Uuid;
 
+/// Labels of the lines in the `book` form, in order; required fields are listed in `BOOK_REQUIRED`
+pub const BOOK_FIELDS: [&str; 11] = [
+    "Authors",
+    "Title",
+    "Pages",
+    "Volume",
+    "Edition",
+    "Date",
+    "Series",
+    "Publisher",
+    "Note",
+    "ISBN",
+    "Organizations",
+];
+const BOOK_REQUIRED: [usize; 1] = [1];
+
+/// Labels of the lines in the `article` form, in order; required fields are listed in
+/// `ARTICLE_REQUIRED`
+pub const ARTICLE_FIELDS: [&str; 10] = [
+    "Title",
+    "Journal",
+    "Volume",
+    "Pages",
+    "Note",
+    "Date",
+    "Edition",
+    "Publisher",
+    "Authors",
+    "Organizations",
+];
+const ARTICLE_REQUIRED: [usize; 2] = [0, 1];
+
+/// Line of the Publisher field in both the `book` and `article` forms
+pub const PUBLISHER_LINE: usize = 7;
+
+/// Labels of the lines in the `publisher` form, in order
+pub const PUBLISHER_FIELDS: [&str; 3] = ["Name", "Address", "Place"];
+const PUBLISHER_REQUIRED: [usize; 1] = [0];
+
+/// Errors returned by every public function in the `db` module
+#[derive(Debug)]
+pub enum DbError {
+    /// The database could not be opened, is locked, or is not an `SQLite` file
+    Connection(sqlite::Error),
+    /// A write broke a `UNIQUE`, `NOT NULL` or foreign key constraint
+    Constraint(String),
+    /// No record with this `cite_key`
+    NotFound(String),
+    /// The tables do not have the columns this version of the program expects
+    Schema(String),
+    /// The data entered cannot be stored
+    InvalidInput(String),
+    /// Any other error reported by `SQLite`
+    Sqlite(sqlite::Error),
+}
+
+pub type DbResult<T> = Result<T, DbError>;
+
+// `SQLite` primary result codes, see https://www.sqlite.org/rescode.html
+const SQLITE_ERROR: i32 = 1;
+const SQLITE_BUSY: i32 = 5;
+const SQLITE_LOCKED: i32 = 6;
+const SQLITE_IOERR: i32 = 10;
+const SQLITE_CORRUPT: i32 = 11;
+const SQLITE_CANTOPEN: i32 = 14;
+const SQLITE_CONSTRAINT: i32 = 19;
+const SQLITE_MISMATCH: i32 = 20;
+const SQLITE_NOTADB: i32 = 26;
+
+impl From<sqlite::Error> for DbError {
+    fn from(err: sqlite::Error) -> DbError {
+        let message = err.message.clone().unwrap_or_default();
+        match err.code.map(|code| code as i32) {
+            Some(SQLITE_CONSTRAINT) => DbError::Constraint(message),
+            Some(
+                SQLITE_BUSY | SQLITE_LOCKED | SQLITE_IOERR | SQLITE_CORRUPT | SQLITE_CANTOPEN
+                | SQLITE_NOTADB,
+            ) => DbError::Connection(err),
+            Some(SQLITE_MISMATCH) => DbError::Schema(message),
+            Some(SQLITE_ERROR)
+                if message.starts_with("no such")
+                    || message.contains("has no column")
+                    || message.contains("values were supplied") =>
+            {
+                DbError::Schema(message)
+            }
+            None if message.starts_with("the index is out of range") => DbError::Schema(message),
+            _ => DbError::Sqlite(err),
+        }
+    }
+}
+
+impl fmt::Display for DbError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DbError::Connection(err) => write!(f, "cannot use the database: {err}"),
+            DbError::Constraint(message) => write!(f, "not saved, constraint failed: {message}"),
+            DbError::NotFound(cite_key) => write!(f, "no record with cite_key {cite_key}"),
+            DbError::Schema(message) => write!(f, "database schema does not match: {message}"),
+            DbError::InvalidInput(message) => write!(f, "invalid input: {message}"),
+            DbError::Sqlite(err) => write!(f, "database error: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for DbError {}
+
+/// How long a write waits on a database locked by another process before giving up
+const BUSY_TIMEOUT_MS: usize = 5_000;
+
+/// Initialize the database at `db_url` with tables, or bring an existing one up to date
+pub fn init_db(db_url: &str) -> DbResult<()> {
+    Database::open(db_url).map(|_| ())
+}
+
 /// Database Structs and implementations for `SQLite` data tables
-// todo! Implement remaining relational databases
 #[derive(Clone, Debug)]
 pub struct MasterEntries {
     pub(crate) cite_key: String,
-    pub(crate) entry_type: String,
+    pub(crate) entry_type: EntryType,
+}
+
+/// Kind of record a `master_entries` row belongs to, stored in its `entry_type` column
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum EntryType {
+    Book,
+    Article,
+    Thesis,
+    ConferencePaper,
+    BookChapter,
+    Report,
+    Map,
+    AudioRecording,
+    Video,
+    WebResource,
+    Software,
+}
+
+impl EntryType {
+    pub const ALL: [EntryType; 11] = [
+        EntryType::Book,
+        EntryType::Article,
+        EntryType::Thesis,
+        EntryType::ConferencePaper,
+        EntryType::BookChapter,
+        EntryType::Report,
+        EntryType::Map,
+        EntryType::AudioRecording,
+        EntryType::Video,
+        EntryType::WebResource,
+        EntryType::Software,
+    ];
+
+    /// Value stored in the `master_entries.entry_type` column
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EntryType::Book => "BOOK",
+            EntryType::Article => "ARTICLE",
+            EntryType::Thesis => "THESIS",
+            EntryType::ConferencePaper => "CONFERENCE_PAPER",
+            EntryType::BookChapter => "BOOK_CHAPTER",
+            EntryType::Report => "REPORT",
+            EntryType::Map => "MAP",
+            EntryType::AudioRecording => "AUDIO_RECORDING",
+            EntryType::Video => "VIDEO",
+            EntryType::WebResource => "WEB_RESOURCE",
+            EntryType::Software => "SOFTWARE",
+        }
+    }
+
+    /// Name shown in the TUI
+    pub fn name(&self) -> &'static str {
+        match self {
+            EntryType::Book => "Book",
+            EntryType::Article => "Article",
+            EntryType::Thesis => "Thesis",
+            EntryType::ConferencePaper => "Conference Paper",
+            EntryType::BookChapter => "Book Chapter",
+            EntryType::Report => "Report",
+            EntryType::Map => "Map",
+            EntryType::AudioRecording => "Audio Recording",
+            EntryType::Video => "Video",
+            EntryType::WebResource => "Web Resource",
+            EntryType::Software => "Software",
+        }
+    }
+
+    /// Table and fields of the type, `None` for books and articles
+    pub fn item_type(&self) -> Option<&'static ItemType> {
+        ITEM_TYPES
+            .iter()
+            .find(|item_type| item_type.entry_type == *self)
+    }
+}
+
+impl FromStr for EntryType {
+    type Err = DbError;
+
+    /// Only the exact column values are accepted, so a row written by something else is refused
+    fn from_str(s: &str) -> DbResult<EntryType> {
+        EntryType::ALL
+            .into_iter()
+            .find(|entry_type| entry_type.as_str() == s)
+            .ok_or_else(|| DbError::Schema(format!("unknown entry_type `{s}`")))
+    }
+}
+
+impl fmt::Display for EntryType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -18,29 +235,38 @@ pub struct Book {
     pub(crate) cite_key: String,
     pub(crate) publisher_id: String,
     pub(crate) month_year_id: String,
-    pub(crate) author: String,
+    /// Rows of the `author` table for this record, in order
+    pub(crate) authors: Vec<Author>,
     pub(crate) title: String,
     pub(crate) pages: String,
     pub(crate) volume: String,
     pub(crate) edition: String,
+    /// Publication date from the `month_year` table, formatted by `MonthYear`
     pub(crate) year: String,
     pub(crate) series: String,
     pub(crate) publisher: String,
     pub(crate) note: String,
+    pub(crate) isbn: String,
+    /// Rows of the `record_organization` table for this record, in order
+    pub(crate) organizations: Vec<RecordOrganization>,
 }
 
+/// Publisher authority record, shared by every record from the same press
 #[derive(Clone, Debug)]
 pub struct Publisher {
     pub(crate) publisher_id: String,
     pub(crate) publisher: String,
     pub(crate) address: String,
+    pub(crate) place: String,
 }
 
-#[derive(Clone, Debug)]
+/// Publication date of a book or article; month and day are optional
+#[derive(Clone, Debug, PartialEq)]
 pub struct MonthYear {
     pub(crate) month_year_id: String,
-    pub(crate) month: String,
-    pub(crate) year: String,
+    pub(crate) year: i64,
+    pub(crate) month: Option<i64>,
+    pub(crate) day: Option<i64>,
 }
 
 #[derive(Clone, Debug)]
@@ -54,443 +280,1283 @@ pub struct Article {
     pub(crate) volume: String,
     pub(crate) pages: String,
     pub(crate) note: String,
+    /// Publication date from the `month_year` table, formatted by `MonthYear`
     pub(crate) year: String,
     pub(crate) edition: String,
     pub(crate) publisher: String,
+    /// Rows of the `author` table for this record, in order
+    pub(crate) authors: Vec<Author>,
+    /// Rows of the `record_organization` table for this record, in order
+    pub(crate) organizations: Vec<RecordOrganization>,
 }
 
 /// Struct Traits and Implementations
 pub trait TableInsert {
-    fn insert(&self) -> sqlite::Result<State>;
+    fn insert(&self, catalog: &Catalog) -> DbResult<State>;
 }
 
 pub trait RowDelete {
-    fn delete(item_id: String) -> sqlite::Result<State>;
+    fn delete(catalog: &Catalog, item_id: String) -> DbResult<State>;
 }
 
 pub trait RowUpdate {
-    fn update(&self, item_id: String) -> sqlite::Result<State>;
+    fn update(&self, catalog: &Catalog, item_id: String) -> DbResult<State>;
 }
 
 pub trait RowSelect {
-    fn select(item_id: &str) -> Vec<String>;
+    fn select(catalog: &Catalog, item_id: &str) -> DbResult<Vec<String>>;
+}
+
+/// Owns the one `SQLite` connection the application uses for the catalog
+pub struct Database {
+    connection: Connection,
+}
+
+impl Database {
+    /// Open the database at `db_url`, apply any pending migrations and switch on foreign key
+    /// enforcement; waits on a locked database instead of failing right away.
+    /// Refuses a database newer than this program.
+    pub fn open(db_url: &str) -> DbResult<Database> {
+        let mut connection = sqlite::open(db_url).map_err(DbError::Connection)?;
+        connection.set_busy_timeout(BUSY_TIMEOUT_MS)?;
+        let database = Database { connection };
+        migrate(&database.catalog())?;
+        database.connection.execute("PRAGMA foreign_keys = ON")?;
+        Ok(database)
+    }
+
+    /// Handle that every `db` function goes through
+    pub fn catalog(&self) -> Catalog<'_> {
+        Catalog {
+            connection: &self.connection,
+            statements: RefCell::new(HashMap::new()),
+        }
+    }
+}
+
+/// A database file of its own for one test, removed with its journal when dropped
+#[cfg(test)]
+pub(crate) struct TestDbFile {
+    pub(crate) db_url: String,
+}
+
+#[cfg(test)]
+impl TestDbFile {
+    /// A new path in the temp directory; nothing is created until it is opened
+    pub(crate) fn new() -> TestDbFile {
+        let path = std::env::temp_dir().join(format!("bib_db_test_{}.db", Uuid::new_v4()));
+        TestDbFile {
+            db_url: path
+                .to_str()
+                .expect("temp path should be utf-8")
+                .to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+impl Drop for TestDbFile {
+    fn drop(&mut self) {
+        for suffix in ["", "-journal", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{suffix}", self.db_url));
+        }
+    }
+}
+
+/// A migrated database for one test; the file is removed when it is dropped
+#[cfg(test)]
+pub(crate) struct TestDatabase {
+    // closed before the file is removed, as fields are dropped in order
+    database: Database,
+    file: TestDbFile,
+}
+
+#[cfg(test)]
+impl TestDatabase {
+    pub(crate) fn new() -> TestDatabase {
+        let file = TestDbFile::new();
+        TestDatabase {
+            database: Database::open(&file.db_url).expect("should open test database"),
+            file,
+        }
+    }
+
+    pub(crate) fn catalog(&self) -> Catalog<'_> {
+        self.database.catalog()
+    }
+
+    /// Location of the file, for opening a second connection to it
+    pub(crate) fn db_url(&self) -> &str {
+        &self.file.db_url
+    }
+}
+
+/// Shared handle to the catalog connection. Prepared statements are cached by query text and
+/// reused for as long as the handle lives, so repeated reads do not re-parse SQL.
+pub struct Catalog<'c> {
+    connection: &'c Connection,
+    statements: RefCell<HashMap<String, Statement<'c>>>,
+}
+
+impl<'c> Catalog<'c> {
+    /// Run `f` with the prepared statement for `query`, preparing and caching it on first use.
+    /// The statement is reset before and after `f` so no read is left open between calls.
+    pub(crate) fn with_statement<T, F>(&self, query: &str, f: F) -> DbResult<T>
+    where
+        F: FnOnce(&mut Statement<'c>) -> DbResult<T>,
+    {
+        let cached = self.statements.borrow_mut().remove(query);
+        let mut statement = match cached {
+            Some(statement) => statement,
+            None => self.connection.prepare(query)?,
+        };
+        statement.reset()?;
+        let result = f(&mut statement);
+        let _ = statement.reset();
+        self.statements
+            .borrow_mut()
+            .insert(query.to_string(), statement);
+        result
+    }
+
+    /// Like `with_statement`, for a query built from user input that is not worth caching
+    pub(crate) fn with_uncached_statement<T, F>(&self, query: &str, f: F) -> DbResult<T>
+    where
+        F: FnOnce(&mut Statement<'c>) -> DbResult<T>,
+    {
+        let mut statement = self.connection.prepare(query)?;
+        f(&mut statement)
+    }
+
+    /// Rows inserted, updated or deleted through the connection so far. It only moves when the
+    /// catalog is written to, so a read can be kept until it changes.
+    pub(crate) fn total_changes(&self) -> usize {
+        self.connection.total_change_count()
+    }
+
+    /// `PRAGMA data_version`, which moves when another connection commits a write, with
+    /// `total_changes`, which moves when this one writes. A read can be kept until either does.
+    pub(crate) fn version(&self) -> DbResult<(i64, usize)> {
+        let data_version = self.with_statement("PRAGMA data_version", |statement| {
+            statement.next()?;
+            Ok(statement.read::<i64, _>(0)?)
+        })?;
+        Ok((data_version, self.total_changes()))
+    }
+
+    /// Run one or more statements that take no parameters
+    pub(crate) fn execute(&self, query: &str) -> DbResult<()> {
+        Ok(self.connection.execute(query)?)
+    }
+
+    /// `DbError::NotFound` if the last `UPDATE` or `DELETE` matched no rows
+    pub(crate) fn expect_changed(&self, item_id: &str) -> DbResult<()> {
+        match self.connection.change_count() {
+            0 => Err(DbError::NotFound(item_id.to_string())),
+            _ => Ok(()),
+        }
+    }
+
+    /// Run `f` inside a transaction: everything it wrote is committed if it returns `Ok`, and
+    /// rolled back if it returns `Err`. Uses a savepoint, so transactions can be nested.
+    pub fn transaction<T, F>(&self, f: F) -> DbResult<T>
+    where
+        F: FnOnce(&Catalog<'c>) -> DbResult<T>,
+    {
+        self.connection.execute("SAVEPOINT catalog_write")?;
+        match f(self) {
+            Ok(value) => {
+                self.connection.execute("RELEASE catalog_write")?;
+                Ok(value)
+            }
+            Err(err) => {
+                self.connection
+                    .execute("ROLLBACK TO catalog_write; RELEASE catalog_write")?;
+                Err(err)
+            }
+        }
+    }
+
+    /// Run `f` inside a transaction that is always rolled back, to see what it would do
+    pub fn dry_run<T, F>(&self, f: F) -> DbResult<T>
+    where
+        F: FnOnce(&Catalog<'c>) -> DbResult<T>,
+    {
+        self.connection.execute("SAVEPOINT catalog_dry_run")?;
+        let result = f(self);
+        self.connection
+            .execute("ROLLBACK TO catalog_dry_run; RELEASE catalog_dry_run")?;
+        result
+    }
+}
+
+/// Read a text column, treating `NULL` as an empty string
+pub(crate) fn read_text(statement: &Statement, column: &str) -> DbResult<String> {
+    Ok(statement
+        .read::<Option<String>, _>(column)?
+        .unwrap_or_default())
+}
+
+/// `publisher_id` and `month_year_id` read by `query` for the record with `cite_key`
+pub(crate) fn linked_ids(
+    catalog: &Catalog,
+    query: &str,
+    cite_key: &str,
+) -> DbResult<(String, String)> {
+    catalog.with_statement(query, |statement| {
+        statement.bind((1, cite_key))?;
+        match statement.next()? {
+            State::Row => Ok((
+                read_text(statement, "publisher_id")?,
+                read_text(statement, "month_year_id")?,
+            )),
+            State::Done => Err(DbError::NotFound(cite_key.to_string())),
+        }
+    })
+}
+
+/// `NULL` for an empty id, so a record can have no publisher or date
+pub(crate) fn optional_id(id: &str) -> Value {
+    match id {
+        "" => Value::Null,
+        id => id.into(),
+    }
+}
+
+/// `publisher_id` of the publisher named on a form, if one was
+pub(crate) fn named_publisher_id(catalog: &Catalog, publisher: &str) -> DbResult<String> {
+    match publisher.trim() {
+        "" => Ok(String::new()),
+        name => Ok(Publisher::find_or_create(catalog, name)?.publisher_id),
+    }
+}
+
+/// The date typed on a form, if one was
+pub(crate) fn optional_date(text: &str) -> DbResult<Option<MonthYear>> {
+    match text.trim() {
+        "" => Ok(None),
+        text => MonthYear::parse(text).map(Some),
+    }
+}
+
+/// Write the date of a record that had the `month_year` row `old_month_year_id`, if any;
+/// returns its `month_year_id` now, empty when it has no date
+pub(crate) fn save_date(
+    catalog: &Catalog,
+    date: Option<MonthYear>,
+    old_month_year_id: &str,
+) -> DbResult<String> {
+    match date {
+        Some(date) if !old_month_year_id.is_empty() => {
+            date.update(catalog, old_month_year_id.to_string())?;
+            Ok(old_month_year_id.to_string())
+        }
+        Some(date) => {
+            date.insert(catalog)?;
+            Ok(date.month_year_id)
+        }
+        None => Ok(String::new()),
+    }
+}
+
+/// Delete the `publisher` and `month_year` rows with these ids if no record uses them
+pub(crate) fn remove_unused_links(
+    catalog: &Catalog,
+    publisher_id: &str,
+    month_year_id: &str,
+) -> DbResult<()> {
+    let query = "DELETE FROM publisher WHERE publisher_id = :id \
+        AND NOT EXISTS (SELECT 1 FROM catalog_records WHERE publisher_id = :id)";
+    catalog.with_statement(query, |statement| {
+        statement.bind((":id", publisher_id))?;
+        statement.next()?;
+        Ok(())
+    })?;
+    let query = "DELETE FROM month_year WHERE month_year_id = :id \
+        AND NOT EXISTS (SELECT 1 FROM catalog_records WHERE month_year_id = :id)";
+    catalog.with_statement(query, |statement| {
+        statement.bind((":id", month_year_id))?;
+        statement.next()?;
+        Ok(())
+    })
+}
+
+/// Pad the lines entered in a form to one per field and check that required fields are filled in
+pub(crate) fn form_fields(
+    textarea: Vec<String>,
+    fields: &[&str],
+    required: &[usize],
+) -> DbResult<Vec<String>> {
+    let mut lines = textarea;
+    while lines.len() > fields.len() && lines.last().is_some_and(|l| l.trim().is_empty()) {
+        lines.pop();
+    }
+    if lines.len() > fields.len() {
+        return Err(DbError::InvalidInput(format!(
+            "expected at most {} lines, got {}",
+            fields.len(),
+            lines.len()
+        )));
+    }
+    lines.resize(fields.len(), String::new());
+    match required
+        .iter()
+        .find(|&&index| lines[index].trim().is_empty())
+    {
+        Some(&index) => Err(DbError::InvalidInput(format!(
+            "{} is required",
+            fields[index]
+        ))),
+        None => Ok(lines),
+    }
 }
 
 impl MasterEntries {
-    pub fn new_book() -> MasterEntries {
-        let key = Uuid::new_v4().to_string();
+    /// Entry with a new `cite_key` for a record of `entry_type`
+    pub fn new(entry_type: EntryType) -> MasterEntries {
         MasterEntries {
-            cite_key: key,
-            entry_type: "BOOK".parse().unwrap(),
+            cite_key: Uuid::new_v4().to_string(),
+            entry_type,
         }
     }
 
+    pub fn new_book() -> MasterEntries {
+        MasterEntries::new(EntryType::Book)
+    }
+
     pub fn new_article() -> MasterEntries {
-        let key = Uuid::new_v4().to_string();
-        MasterEntries {
-            cite_key: key,
-            entry_type: "ARTICLE".parse().unwrap(),
-        }
+        MasterEntries::new(EntryType::Article)
     }
 }
 
 impl TableInsert for MasterEntries {
-    fn insert(&self) -> sqlite::Result<State> {
-        let connection = sqlite::open(DB_URL).unwrap();
-        let query = "INSERT INTO master_entries VALUES (:cite_key, :entry_type)";
-        let mut statement = connection.prepare(query).unwrap();
-        statement
-            .bind_iter::<_, (_, Value)>([
+    fn insert(&self, catalog: &Catalog) -> DbResult<State> {
+        let query = "INSERT INTO master_entries (cite_key, entry_type, added)
+            VALUES (:cite_key, :entry_type, (SELECT coalesce(max(added), 0) + 1 FROM master_entries))";
+        catalog.with_statement(query, |statement| {
+            statement.bind_iter::<_, (_, Value)>([
                 (":cite_key", self.cite_key.clone().into()),
-                (":entry_type", self.entry_type.clone().into()),
-            ])
-            .expect("should bind_iter");
-        statement.next()
+                (":entry_type", self.entry_type.as_str().into()),
+            ])?;
+            Ok(statement.next()?)
+        })
     }
 }
 
 impl RowDelete for MasterEntries {
-    fn delete(item_id: String) -> sqlite::Result<State> {
-        let connection = sqlite::open(DB_URL).unwrap();
+    /// Also drops the record's search text, which has no foreign key to cascade from
+    fn delete(catalog: &Catalog, item_id: String) -> DbResult<State> {
+        unindex_record(catalog, &item_id)?;
         let query = "DELETE FROM master_entries WHERE cite_key = ?";
-        let mut statement = connection.prepare(query).unwrap();
-        statement
-            .bind_iter::<_, (_, Value)>([(1, item_id.into())])
-            .unwrap();
-        statement.next()
+        catalog.with_statement(query, |statement| {
+            statement.bind_iter::<_, (_, Value)>([(1, item_id.clone().into())])?;
+            let state = statement.next()?;
+            catalog.expect_changed(&item_id)?;
+            Ok(state)
+        })
     }
 }
 
+/// DOI of the record with `cite_key`; empty if it has none
+pub fn read_doi(catalog: &Catalog, cite_key: &str) -> DbResult<String> {
+    let query = "SELECT doi FROM master_entries WHERE cite_key = ?";
+    catalog.with_statement(query, |statement| {
+        statement.bind((1, cite_key))?;
+        match statement.next()? {
+            State::Row => Ok(statement.read::<String, _>("doi")?),
+            State::Done => Ok(String::new()),
+        }
+    })
+}
+
+/// Keep `doi` as the DOI of the record with `cite_key`
+pub fn set_doi(catalog: &Catalog, cite_key: &str, doi: &str) -> DbResult<()> {
+    let query = "UPDATE master_entries SET doi = ? WHERE cite_key = ?";
+    catalog.with_statement(query, |statement| {
+        statement.bind_iter::<_, (_, Value)>([(1, doi.trim().into()), (2, cite_key.into())])?;
+        statement.next()?;
+        catalog.expect_changed(cite_key)?;
+        Ok(())
+    })
+}
+
+/// One row of the catalog across every entry type, as listed under `Catalog`
+#[derive(Clone, Debug, PartialEq)]
+pub struct CatalogEntry {
+    pub(crate) cite_key: String,
+    pub(crate) entry_type: EntryType,
+    /// Empty for a `master_entries` row with no record
+    pub(crate) title: String,
+    /// `Family, Given` of the first person named, empty when there is none
+    pub(crate) first_author: String,
+    pub(crate) year: Option<i64>,
+}
+
+/// Columns read by `read_catalog_entry`, selected from `master_entries m` joined to
+/// `catalog_records c` and `month_year y`
+pub(crate) const CATALOG_ENTRY_COLUMNS: &str = "m.cite_key, m.entry_type, coalesce(c.title, '') AS title,
+    coalesce((SELECT a.family || CASE a.given WHEN '' THEN '' ELSE ', ' || a.given END
+        FROM author a WHERE a.cite_key = m.cite_key ORDER BY a.position LIMIT 1), '') AS first_author,
+    y.year";
+
+pub(crate) fn read_catalog_entry(statement: &Statement) -> DbResult<CatalogEntry> {
+    Ok(CatalogEntry {
+        cite_key: read_text(statement, "cite_key")?,
+        entry_type: read_text(statement, "entry_type")?.parse()?,
+        title: read_text(statement, "title")?,
+        first_author: read_text(statement, "first_author")?,
+        year: statement.read::<Option<i64>, _>("year")?,
+    })
+}
+
+/// Every `master_entries` row with its title, first author and year, ordered by title
+pub fn read_catalog_entries(catalog: &Catalog) -> DbResult<Vec<CatalogEntry>> {
+    let query = format!(
+        "SELECT {CATALOG_ENTRY_COLUMNS}
+        FROM master_entries m
+        LEFT JOIN catalog_records c ON c.cite_key = m.cite_key
+        LEFT JOIN month_year y ON y.month_year_id = c.month_year_id
+        ORDER BY lower(title), m.cite_key"
+    );
+    catalog.with_statement(&query, |statement| {
+        let mut entries = Vec::new();
+        while let State::Row = statement.next()? {
+            entries.push(read_catalog_entry(statement)?);
+        }
+        Ok(entries)
+    })
+}
+
+/// The `master_entries` rows with `cite_keys` as catalog entries, in that order
+pub fn read_catalog_entries_with_keys(
+    catalog: &Catalog,
+    cite_keys: &[&str],
+) -> DbResult<Vec<CatalogEntry>> {
+    let query = format!(
+        "SELECT {CATALOG_ENTRY_COLUMNS}
+        FROM master_entries m
+        LEFT JOIN catalog_records c ON c.cite_key = m.cite_key
+        LEFT JOIN month_year y ON y.month_year_id = c.month_year_id
+        WHERE m.cite_key = ?"
+    );
+    let mut entries = Vec::new();
+    for &cite_key in cite_keys {
+        let entry = catalog.with_statement(&query, |statement| {
+            statement.bind((1, cite_key))?;
+            match statement.next()? {
+                State::Row => Ok(Some(read_catalog_entry(statement)?)),
+                State::Done => Ok(None),
+            }
+        })?;
+        entries.extend(entry);
+    }
+    Ok(entries)
+}
+
+/// A whole record of any type, with its people and organizations
+#[derive(Clone, Debug)]
+pub enum Record {
+    Book(Book),
+    Article(Article),
+    Item(Item),
+}
+
+impl Record {
+    pub fn cite_key(&self) -> &str {
+        match self {
+            Record::Book(book) => &book.cite_key,
+            Record::Article(article) => &article.cite_key,
+            Record::Item(item) => &item.cite_key,
+        }
+    }
+}
+
+/// The records with `cite_keys`, in that order, skipping keys that are not in the catalog
+pub fn read_records_with_keys(catalog: &Catalog, cite_keys: &[&str]) -> DbResult<Vec<Record>> {
+    let mut records = Vec::new();
+    for entry in read_catalog_entries_with_keys(catalog, cite_keys)? {
+        let cite_key = [entry.cite_key.as_str()];
+        match entry.entry_type {
+            EntryType::Book => records.extend(
+                read_books_with_keys(catalog, &cite_key)?
+                    .into_iter()
+                    .map(Record::Book),
+            ),
+            EntryType::Article => records.extend(
+                read_articles_with_keys(catalog, &cite_key)?
+                    .into_iter()
+                    .map(Record::Article),
+            ),
+            other => {
+                if let Some(item_type) = other.item_type() {
+                    records.extend(
+                        item_type
+                            .read_items_with_keys(catalog, &cite_key)?
+                            .into_iter()
+                            .map(Record::Item),
+                    );
+                }
+            }
+        }
+    }
+    Ok(records)
+}
+
 impl Book {
-    /// Create and add `book` to `SQLite` database
-    pub fn book_transaction(textarea: Vec<String>) {
+    /// Create and add `book` to `SQLite` database in one transaction; returns the new `cite_key`
+    pub fn book_transaction(catalog: &Catalog, textarea: Vec<String>) -> DbResult<String> {
+        let textarea = form_fields(textarea, &BOOK_FIELDS, &BOOK_REQUIRED)?;
         let master = MasterEntries::new_book();
-        let publisher = Publisher::new(textarea[7].clone());
-        let year = textarea[5].clone();
-        let m_y = MonthYear::new(year);
+        let date = optional_date(&textarea[5])?;
         let book_id = Uuid::new_v4().to_string();
-        let book = Book {
+        let mut book = Book {
             book_id,
             cite_key: master.cite_key.clone(),
-            publisher_id: publisher.publisher_id.clone(),
-            month_year_id: m_y.month_year_id.clone(),
-            author: textarea[0].clone(),
+            publisher_id: String::new(),
+            month_year_id: String::new(),
+            authors: parse_authors(&master.cite_key, &textarea[0])?,
             title: textarea[1].clone(),
             pages: textarea[2].clone(),
             volume: textarea[3].clone(),
             edition: textarea[4].clone(),
-            year: textarea[5].clone(),
+            year: date.as_ref().map(ToString::to_string).unwrap_or_default(),
             series: textarea[6].clone(),
             publisher: textarea[7].clone(),
             note: textarea[8].clone(),
+            isbn: textarea[9].clone(),
+            organizations: parse_organizations(&master.cite_key, &textarea[10])?,
         };
 
-        // todo! make these a transaction so that if one of the insert()'s fail it will rollback; probably change to use rusqlite crate instead of sqlite crate and refactor
-        let _ = master.insert();
-        let _ = book.insert();
-        let _ = publisher.insert();
-        let _ = m_y.insert();
+        catalog.transaction(|catalog| {
+            master.insert(catalog)?;
+            book.publisher_id = named_publisher_id(catalog, &book.publisher)?;
+            book.month_year_id = save_date(catalog, date, "")?;
+            book.insert(catalog)?;
+            for author in &book.authors {
+                author.insert(catalog)?;
+            }
+            replace_organizations(catalog, &master.cite_key, &book.organizations)?;
+            index_record(catalog, &master.cite_key)?;
+            Ok(master.cite_key.clone())
+        })
     }
 
-    /// Remove item from `book` and `master_entries` tables
-    pub fn delete_book(item_id: String) {
-        let _ = MasterEntries::delete(item_id.clone());
-        let _ = Book::delete(item_id.clone());
+    /// Remove item from `book` and `master_entries` tables in one transaction, along with
+    /// its `publisher` and `month_year` rows once no other record uses them
+    pub fn delete_book(catalog: &Catalog, item_id: String) -> DbResult<()> {
+        let query = "SELECT publisher_id, month_year_id FROM book WHERE cite_key = ?";
+        catalog.transaction(|catalog| {
+            let (publisher_id, month_year_id) = linked_ids(catalog, query, &item_id)?;
+            Book::delete(catalog, item_id.clone())?;
+            MasterEntries::delete(catalog, item_id.clone())?;
+            remove_unused_links(catalog, &publisher_id, &month_year_id)
+        })
     }
 
     /// Update the data in the `book` table
-    pub fn book_update(textarea: Vec<String>, item_id: String) {
-        let book = Book {
+    pub fn book_update(catalog: &Catalog, textarea: Vec<String>, item_id: String) -> DbResult<()> {
+        let textarea = form_fields(textarea, &BOOK_FIELDS, &BOOK_REQUIRED)?;
+        let query = "SELECT publisher_id, month_year_id FROM book WHERE cite_key = ?";
+        let date = optional_date(&textarea[5])?;
+        let mut book = Book {
             book_id: item_id.clone(),
             cite_key: "n/a".to_string(),
-            publisher_id: "n/a".to_string(),
+            publisher_id: String::new(),
             month_year_id: "n/a".to_string(),
-            author: textarea[0].clone(),
+            authors: parse_authors(&item_id, &textarea[0])?,
             title: textarea[1].clone(),
             pages: textarea[2].clone(),
             volume: textarea[3].clone(),
             edition: textarea[4].clone(),
-            year: textarea[5].clone(),
+            year: String::new(),
             series: textarea[6].clone(),
             publisher: textarea[7].clone(),
             note: textarea[8].clone(),
+            isbn: textarea[9].clone(),
+            organizations: parse_organizations(&item_id, &textarea[10])?,
         };
-        let _ = Book::update(&book, item_id.clone());
+        catalog.transaction(|catalog| {
+            let (old_publisher_id, month_year_id) = linked_ids(catalog, query, &item_id)?;
+            book.month_year_id = save_date(catalog, date, &month_year_id)?;
+            book.publisher_id = named_publisher_id(catalog, &book.publisher)?;
+            Book::update(&book, catalog, item_id.clone())?;
+            replace_authors(catalog, &item_id, &book.authors)?;
+            replace_organizations(catalog, &item_id, &book.organizations)?;
+            index_record(catalog, &item_id)?;
+            remove_unused_links(catalog, &old_publisher_id, &month_year_id)
+        })
     }
 }
 
 impl TableInsert for Book {
-    fn insert(&self) -> sqlite::Result<State> {
-        let connection = sqlite::open(DB_URL).unwrap();
-        let query = "INSERT INTO book VALUES (:book_id, :cite_key, :publisher_id, :month_year_id, :author, :title, :pages, :volume, :edition, :year, :series, :publisher, :note)";
-        let mut statement = connection.prepare(query).unwrap();
-        statement
-            .bind_iter::<_, (_, Value)>([
+    fn insert(&self, catalog: &Catalog) -> DbResult<State> {
+        let query = "INSERT INTO book (book_id, cite_key, publisher_id, month_year_id, title, pages, volume, edition, series, note, isbn) VALUES (:book_id, :cite_key, :publisher_id, :month_year_id, :title, :pages, :volume, :edition, :series, :note, :isbn)";
+        catalog.with_statement(query, |statement| {
+            statement.bind_iter::<_, (_, Value)>([
                 (":book_id", self.book_id.clone().into()),
                 (":cite_key", self.cite_key.clone().into()),
-                (":publisher_id", self.publisher_id.clone().into()),
-                (":month_year_id", self.month_year_id.clone().into()),
-                (":author", self.author.clone().into()),
+                (":publisher_id", optional_id(&self.publisher_id)),
+                (":month_year_id", optional_id(&self.month_year_id)),
                 (":title", self.title.clone().into()),
                 (":pages", self.pages.clone().into()),
                 (":volume", self.volume.clone().into()),
                 (":edition", self.edition.clone().into()),
-                (":year", self.year.clone().into()),
                 (":series", self.series.clone().into()),
-                (":publisher", self.publisher.clone().into()),
                 (":note", self.note.clone().into()),
-            ])
-            .unwrap();
-        statement.next()
+                (":isbn", self.isbn.clone().into()),
+            ])?;
+            Ok(statement.next()?)
+        })
     }
 }
 
 impl RowUpdate for Book {
-    fn update(&self, item_id: String) -> sqlite::Result<State> {
-        let connection = sqlite::open(DB_URL).unwrap();
-        let query = "UPDATE book SET author = :author, title = :title, pages = :pages, volume = :volume, edition = :edition, year = :year, series = :series, publisher = :publisher, note = :note WHERE cite_key = :cite_key";
-        let mut statement = connection.prepare(query).unwrap();
-        statement
-            .bind_iter::<_, (_, Value)>([
-                (":author", self.author.clone().into()),
+    fn update(&self, catalog: &Catalog, item_id: String) -> DbResult<State> {
+        let query = "UPDATE book SET publisher_id = :publisher_id, month_year_id = :month_year_id, title = :title, pages = :pages, volume = :volume, edition = :edition, series = :series, note = :note, isbn = :isbn WHERE cite_key = :cite_key";
+        catalog.with_statement(query, |statement| {
+            statement.bind_iter::<_, (_, Value)>([
+                (":publisher_id", optional_id(&self.publisher_id)),
+                (":month_year_id", optional_id(&self.month_year_id)),
                 (":title", self.title.clone().into()),
                 (":pages", self.pages.clone().into()),
                 (":volume", self.volume.clone().into()),
                 (":edition", self.edition.clone().into()),
-                (":year", self.year.clone().into()),
                 (":series", self.series.clone().into()),
-                (":publisher", self.publisher.clone().into()),
                 (":note", self.note.clone().into()),
-                (":cite_key", item_id.into()),
-            ])
-            .unwrap();
-        statement.next()
+                (":isbn", self.isbn.clone().into()),
+                (":cite_key", item_id.clone().into()),
+            ])?;
+            let state = statement.next()?;
+            catalog.expect_changed(&item_id)?;
+            Ok(state)
+        })
     }
 }
 
 impl RowDelete for Book {
-    fn delete(item_id: String) -> sqlite::Result<State> {
-        let connection = sqlite::open(DB_URL).unwrap();
+    fn delete(catalog: &Catalog, item_id: String) -> DbResult<State> {
         let query = "DELETE FROM book WHERE cite_key = ?";
-        let mut statement = connection.prepare(query).unwrap();
-        statement
-            .bind_iter::<_, (_, Value)>([(1, item_id.into())])
-            .unwrap();
-        statement.next()
+        catalog.with_statement(query, |statement| {
+            statement.bind_iter::<_, (_, Value)>([(1, item_id.clone().into())])?;
+            let state = statement.next()?;
+            catalog.expect_changed(&item_id)?;
+            Ok(state)
+        })
     }
 }
 
 impl RowSelect for Book {
-    fn select(item_id: &str) -> Vec<String> {
-        let connection = sqlite::open(DB_URL).unwrap();
-        let query = "SELECT * FROM book WHERE cite_key = :cite_key";
-        let mut statement = connection.prepare(query).unwrap();
-        statement
-            .bind((":cite_key", item_id))
-            .expect("should bind id");
-        let mut text_vec = Vec::new();
-
-        while let Ok(State::Row) = statement.next() {
-            text_vec = vec![
-                "author",
-                "title",
-                "pages",
-                "volume",
-                "edition",
-                "year",
-                "series",
-                "publisher",
-                "note",
-            ]
-            .into_iter()
-            .map(|index| statement.read::<String, _>(index).unwrap())
-            .collect();
-        }
-        text_vec
+    fn select(catalog: &Catalog, item_id: &str) -> DbResult<Vec<String>> {
+        let query = "SELECT b.*, p.publisher, m.year, m.month, m.day FROM book b LEFT JOIN publisher p ON p.publisher_id = b.publisher_id LEFT JOIN month_year m ON m.month_year_id = b.month_year_id WHERE b.cite_key = :cite_key";
+        let mut text_vec = catalog.with_statement(query, |statement| {
+            statement.bind((":cite_key", item_id))?;
+            let mut text_vec = Vec::new();
+
+            while let State::Row = statement.next()? {
+                text_vec = vec![
+                    "title",
+                    "pages",
+                    "volume",
+                    "edition",
+                    "year",
+                    "series",
+                    "publisher",
+                    "note",
+                    "isbn",
+                ]
+                .into_iter()
+                .map(|index| match index {
+                    "year" => read_date(statement),
+                    _ => read_text(statement, index),
+                })
+                .collect::<DbResult<_>>()?;
+            }
+            if text_vec.is_empty() {
+                return Err(DbError::NotFound(item_id.to_string()));
+            }
+            Ok(text_vec)
+        })?;
+        text_vec.insert(0, format_authors(&select_authors(catalog, item_id)?));
+        text_vec.push(format_organizations(&select_record_organizations(
+            catalog, item_id,
+        )?));
+        Ok(text_vec)
     }
 }
 
-// todo! test? Other tests test this logic
+/// Every `book` column read by `read_book`, with its publisher and date
+const BOOK_QUERY: &str = "SELECT b.book_id, b.cite_key, b.publisher_id, b.month_year_id, b.title, b.pages, b.volume, b.edition, m.year, m.month, m.day, b.series, p.publisher, b.note, b.isbn FROM book b LEFT JOIN publisher p ON p.publisher_id = b.publisher_id LEFT JOIN month_year m ON m.month_year_id = b.month_year_id";
+
+fn read_book(
+    statement: &Statement,
+    authors: Vec<Author>,
+    organizations: Vec<RecordOrganization>,
+) -> DbResult<Book> {
+    Ok(Book {
+        book_id: read_text(statement, "book_id")?,
+        cite_key: read_text(statement, "cite_key")?,
+        authors,
+        organizations,
+        publisher_id: read_text(statement, "publisher_id")?,
+        month_year_id: read_text(statement, "month_year_id")?,
+        title: read_text(statement, "title")?,
+        pages: read_text(statement, "pages")?,
+        volume: read_text(statement, "volume")?,
+        edition: read_text(statement, "edition")?,
+        year: read_date(statement)?,
+        series: read_text(statement, "series")?,
+        publisher: read_text(statement, "publisher")?,
+        note: read_text(statement, "note")?,
+        isbn: read_text(statement, "isbn")?,
+    })
+}
+
 /// Read the `SQLite` database `book` table and returns a vector of `book` objects
-pub fn read_sqlite_book_table() -> Result<Vec<Book>, Error> {
-    let connection = sqlite::open(DB_URL).unwrap();
-    let query = "SELECT book_id, cite_key, publisher_id, month_year_id, author, title, pages, volume, edition, year, series, publisher, note FROM book";
-    let mut statement = connection.prepare(query).unwrap();
-    let mut parsed = Vec::new();
-
-    while let Ok(State::Row) = statement.next() {
-        parsed.push(Book {
-            book_id: statement.read::<String, _>("book_id").unwrap(),
-            cite_key: statement.read::<String, _>("cite_key").unwrap(),
-            publisher_id: statement.read::<String, _>("publisher_id").unwrap(),
-            month_year_id: statement.read::<String, _>("month_year_id").unwrap(),
-            author: statement.read::<String, _>("author").unwrap(),
-            title: statement.read::<String, _>("title").unwrap(),
-            pages: statement.read::<String, _>("pages").unwrap(),
-            volume: statement.read::<String, _>("volume").unwrap(),
-            edition: statement.read::<String, _>("edition").unwrap(),
-            year: statement.read::<String, _>("year").unwrap(),
-            series: statement.read::<String, _>("series").unwrap(),
-            publisher: statement.read::<String, _>("publisher").unwrap(),
-            note: statement.read::<String, _>("note").unwrap(),
-        });
+pub fn read_sqlite_book_table(catalog: &Catalog) -> DbResult<Vec<Book>> {
+    let mut authors = read_author_table(catalog)?;
+    let mut organizations = read_record_organization_table(catalog)?;
+    catalog.with_statement(BOOK_QUERY, |statement| {
+        let mut parsed = Vec::new();
+        while let State::Row = statement.next()? {
+            let cite_key = read_text(statement, "cite_key")?;
+            parsed.push(read_book(
+                statement,
+                authors.remove(&cite_key).unwrap_or_default(),
+                organizations.remove(&cite_key).unwrap_or_default(),
+            )?);
+        }
+        Ok(parsed)
+    })
+}
+
+/// The books with `cite_keys`, in that order, skipping keys that are not books; reads only
+/// those rows, for the part of a list that is on screen
+pub fn read_books_with_keys(catalog: &Catalog, cite_keys: &[&str]) -> DbResult<Vec<Book>> {
+    let query = format!("{BOOK_QUERY} WHERE b.cite_key = ?");
+    let mut books = Vec::new();
+    for &cite_key in cite_keys {
+        let book = catalog.with_statement(&query, |statement| {
+            statement.bind((1, cite_key))?;
+            match statement.next()? {
+                State::Row => Ok(Some(read_book(statement, Vec::new(), Vec::new())?)),
+                State::Done => Ok(None),
+            }
+        })?;
+        if let Some(mut book) = book {
+            book.authors = select_authors(catalog, cite_key)?;
+            book.organizations = select_record_organizations(catalog, cite_key)?;
+            books.push(book);
+        }
     }
-    Ok(parsed)
+    Ok(books)
 }
 
+/// English month names and abbreviations accepted in dates, with their month number
+const MONTHS: [(&str, i64); 24] = [
+    ("january", 1),
+    ("february", 2),
+    ("march", 3),
+    ("april", 4),
+    ("may", 5),
+    ("june", 6),
+    ("july", 7),
+    ("august", 8),
+    ("september", 9),
+    ("october", 10),
+    ("november", 11),
+    ("december", 12),
+    ("jan", 1),
+    ("feb", 2),
+    ("mar", 3),
+    ("apr", 4),
+    ("jun", 6),
+    ("jul", 7),
+    ("aug", 8),
+    ("sep", 9),
+    ("sept", 9),
+    ("oct", 10),
+    ("nov", 11),
+    ("dec", 12),
+];
+
+/// Markers catalogers put before a year, such as `c1998` for a copyright date
+const YEAR_PREFIXES: [&str; 7] = ["circa", "ca.", "ca", "c.", "c", "©", "p"];
+
 impl MonthYear {
-    pub fn new(year: String) -> MonthYear {
-        let month_year_id = Uuid::new_v4().to_string();
-        MonthYear {
-            month_year_id,
-            month: String::from("01"),
+    /// Parse a publication date such as `2023`, `2023-05`, `2023-05-17`, `May 2023`,
+    /// `17 May 2023`, `May 17, 2023`, `c1998` or `[1998]`
+    pub fn parse(input: &str) -> DbResult<MonthYear> {
+        let invalid = || {
+            DbError::InvalidInput(format!(
+                "cannot read date `{}`; use 2023, 2023-05, May 2023 or c1998",
+                input.trim()
+            ))
+        };
+        let mut text = input
+            .trim()
+            .trim_matches(|c| c == '[' || c == ']')
+            .to_lowercase();
+        if let Some(rest) = YEAR_PREFIXES.iter().find_map(|prefix| {
+            text.strip_prefix(prefix)
+                .map(str::trim_start)
+                .filter(|rest| rest.starts_with(|c: char| c.is_ascii_digit()))
+        }) {
+            text = rest.to_string();
+        }
+        let tokens: Vec<&str> = text
+            .split(|c: char| c.is_whitespace() || matches!(c, ',' | '-' | '/' | '.'))
+            .filter(|token| !token.is_empty())
+            .collect();
+        let number = |token: &str| -> DbResult<i64> {
+            match token.chars().all(|c| c.is_ascii_digit()) {
+                true => token.parse().map_err(|_| invalid()),
+                false => Err(invalid()),
+            }
+        };
+        let is_year = |token: &&str| token.len() == 4 && number(token).is_ok();
+        let month_name = |token: &&str| {
+            MONTHS
+                .iter()
+                .find(|(name, _)| name == token)
+                .map(|(_, month)| *month)
+        };
+
+        let (year, month, day) = match tokens.as_slice() {
+            // ISO style: 2023, 2023-05, 2023-05-17
+            [year] if is_year(year) => (number(year)?, None, None),
+            [year, month] if is_year(year) && month_name(month).is_none() => {
+                (number(year)?, Some(number(month)?), None)
+            }
+            [year, month, day] if is_year(year) && month_name(month).is_none() => {
+                (number(year)?, Some(number(month)?), Some(number(day)?))
+            }
+            // with a month name: May 2023, 17 May 2023, May 17, 2023
+            _ => {
+                let month = tokens.iter().find_map(month_name).ok_or_else(invalid)?;
+                let year = tokens
+                    .iter()
+                    .find(|token| is_year(token))
+                    .ok_or_else(invalid)?;
+                let rest: Vec<&&str> = tokens
+                    .iter()
+                    .filter(|token| month_name(token).is_none() && *token != year)
+                    .collect();
+                let day = match rest.as_slice() {
+                    [] => None,
+                    [day] if day.len() <= 2 => Some(number(day)?),
+                    _ => return Err(invalid()),
+                };
+                (number(year)?, Some(month), day)
+            }
+        };
+        if let Some(month) = month {
+            if !(1..=12).contains(&month) {
+                return Err(invalid());
+            }
+            if day.is_some_and(|day| day < 1 || day > days_in_month(year, month)) {
+                return Err(invalid());
+            }
+        }
+        Ok(MonthYear {
+            month_year_id: Uuid::new_v4().to_string(),
             year,
+            month,
+            day,
+        })
+    }
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if (year % 4 == 0 && year % 100 != 0) || year % 400 == 0 => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// `2023`, `2023-05` or `2023-05-17`, depending on how much of the date is known
+impl fmt::Display for MonthYear {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:04}", self.year)?;
+        if let Some(month) = self.month {
+            write!(f, "-{month:02}")?;
+            if let Some(day) = self.day {
+                write!(f, "-{day:02}")?;
+            }
         }
+        Ok(())
+    }
+}
+
+/// Read the `year`, `month` and `day` columns of a joined `month_year` row as a date;
+/// empty if the year is not known
+pub(crate) fn read_date(statement: &Statement) -> DbResult<String> {
+    match statement.read::<Option<i64>, _>("year")? {
+        Some(year) => Ok(MonthYear {
+            month_year_id: String::new(),
+            year,
+            month: statement.read::<Option<i64>, _>("month")?,
+            day: statement.read::<Option<i64>, _>("day")?,
+        }
+        .to_string()),
+        None => Ok(String::new()),
     }
 }
 
 impl TableInsert for MonthYear {
-    fn insert(&self) -> sqlite::Result<State> {
-        let connection = sqlite::open(DB_URL).unwrap();
-        let query = "INSERT INTO month_year VALUES (:month_year_id, :month, :year)";
-        let mut statement = connection.prepare(query).unwrap();
-        statement
-            .bind_iter::<_, (_, Value)>([
+    fn insert(&self, catalog: &Catalog) -> DbResult<State> {
+        let query = "INSERT OR REPLACE INTO month_year (month_year_id, year, month, day) VALUES (:month_year_id, :year, :month, :day)";
+        catalog.with_statement(query, |statement| {
+            statement.bind_iter::<_, (_, Value)>([
                 (":month_year_id", self.month_year_id.clone().into()),
-                (":month", self.month.clone().into()),
-                (":year", self.year.clone().into()),
-            ])
-            .unwrap();
-        statement.next()
+                (":year", self.year.into()),
+                (":month", self.month.map_or(Value::Null, Value::Integer)),
+                (":day", self.day.map_or(Value::Null, Value::Integer)),
+            ])?;
+            Ok(statement.next()?)
+        })
+    }
+}
+
+impl RowUpdate for MonthYear {
+    fn update(&self, catalog: &Catalog, item_id: String) -> DbResult<State> {
+        let query = "UPDATE month_year SET year = :year, month = :month, day = :day WHERE month_year_id = :month_year_id";
+        catalog.with_statement(query, |statement| {
+            statement.bind_iter::<_, (_, Value)>([
+                (":year", self.year.into()),
+                (":month", self.month.map_or(Value::Null, Value::Integer)),
+                (":day", self.day.map_or(Value::Null, Value::Integer)),
+                (":month_year_id", item_id.clone().into()),
+            ])?;
+            let state = statement.next()?;
+            catalog.expect_changed(&item_id)?;
+            Ok(state)
+        })
+    }
+}
+
+/// Parse the free-text `year` of every book and article into its `month_year` row. A date that
+/// cannot be read is kept at the end of the record's note. Run once by migration 6.
+pub(crate) fn parse_publication_dates(catalog: &Catalog) -> DbResult<()> {
+    for (select, keep_in_note) in [
+        (
+            "SELECT month_year_id, year FROM book WHERE month_year_id IS NOT NULL",
+            "UPDATE book SET note = trim(coalesce(note, '') || ' Date: ' || :text) WHERE month_year_id = :id",
+        ),
+        (
+            "SELECT month_year_id, year FROM article WHERE month_year_id IS NOT NULL",
+            "UPDATE article SET note = trim(coalesce(note, '') || ' Date: ' || :text) WHERE month_year_id = :id",
+        ),
+    ] {
+        let dates = catalog.with_statement(select, |statement| {
+            let mut dates = Vec::new();
+            while let State::Row = statement.next()? {
+                dates.push((
+                    read_text(statement, "month_year_id")?,
+                    read_text(statement, "year")?,
+                ));
+            }
+            Ok(dates)
+        })?;
+        for (month_year_id, text) in dates {
+            match MonthYear::parse(&text) {
+                // also restores a `month_year` row that had gone missing
+                Ok(date) => MonthYear {
+                    month_year_id,
+                    ..date
+                }
+                .insert(catalog)
+                .map(|_| ())?,
+                Err(_) if text.trim().is_empty() => {}
+                Err(_) => catalog.with_statement(keep_in_note, |statement| {
+                    statement.bind((":text", text.trim()))?;
+                    statement.bind((":id", month_year_id.as_str()))?;
+                    statement.next()?;
+                    Ok(())
+                })?,
+            }
+        }
     }
+    Ok(())
 }
 
 impl Article {
-    /// Create and add `article` to `SQLite` database
-    pub(crate) fn article_transaction(textarea: Vec<String>) {
+    /// Create and add `article` to `SQLite` database in one transaction; returns the new `cite_key`
+    pub(crate) fn article_transaction(
+        catalog: &Catalog,
+        textarea: Vec<String>,
+    ) -> DbResult<String> {
+        let textarea = form_fields(textarea, &ARTICLE_FIELDS, &ARTICLE_REQUIRED)?;
         let master = MasterEntries::new_article();
-        let publisher = Publisher::new(textarea[7].clone());
-        let year = textarea[5].clone();
-        let m_y = MonthYear::new(year);
+        let date = optional_date(&textarea[5])?;
         let article_id = Uuid::new_v4().to_string();
-        let article = Article {
+        let mut article = Article {
             cite_key: master.cite_key.clone(),
             article_id,
-            publisher_id: publisher.publisher_id.clone(),
-            month_year_id: m_y.month_year_id.clone(),
+            publisher_id: String::new(),
+            month_year_id: String::new(),
             title: textarea[0].clone(),
             journal: textarea[1].clone(),
             volume: textarea[2].clone(),
             pages: textarea[3].clone(),
             note: textarea[4].clone(),
-            year: textarea[5].clone(),
+            year: date.as_ref().map(ToString::to_string).unwrap_or_default(),
             edition: textarea[6].clone(),
             publisher: textarea[7].clone(),
+            authors: parse_authors(&master.cite_key, &textarea[8])?,
+            organizations: parse_organizations(&master.cite_key, &textarea[9])?,
         };
 
-        let _ = master.insert();
-        let _ = article.insert();
-        let _ = publisher.insert();
-        let _ = m_y.insert();
+        catalog.transaction(|catalog| {
+            master.insert(catalog)?;
+            article.publisher_id = named_publisher_id(catalog, &article.publisher)?;
+            article.month_year_id = save_date(catalog, date, "")?;
+            article.insert(catalog)?;
+            for author in &article.authors {
+                author.insert(catalog)?;
+            }
+            replace_organizations(catalog, &master.cite_key, &article.organizations)?;
+            index_record(catalog, &master.cite_key)?;
+            Ok(master.cite_key.clone())
+        })
     }
 
-    pub fn delete_article(item_id: String) {
-        let _ = MasterEntries::delete(item_id.clone());
-        let _ = Article::delete(item_id.clone());
+    /// Remove item from `article` and `master_entries` tables in one transaction, along with
+    /// its `publisher` and `month_year` rows once no other record uses them
+    pub fn delete_article(catalog: &Catalog, item_id: String) -> DbResult<()> {
+        let query = "SELECT publisher_id, month_year_id FROM article WHERE cite_key = ?";
+        catalog.transaction(|catalog| {
+            let (publisher_id, month_year_id) = linked_ids(catalog, query, &item_id)?;
+            Article::delete(catalog, item_id.clone())?;
+            MasterEntries::delete(catalog, item_id.clone())?;
+            remove_unused_links(catalog, &publisher_id, &month_year_id)
+        })
     }
 
-    pub fn article_update(textarea: Vec<String>, item_id: String) {
-        let article = Article {
+    /// Update the data in the `article` table
+    pub fn article_update(
+        catalog: &Catalog,
+        textarea: Vec<String>,
+        item_id: String,
+    ) -> DbResult<()> {
+        let textarea = form_fields(textarea, &ARTICLE_FIELDS, &ARTICLE_REQUIRED)?;
+        let query = "SELECT publisher_id, month_year_id FROM article WHERE cite_key = ?";
+        let date = optional_date(&textarea[5])?;
+        let mut article = Article {
             cite_key: "n/a".to_string(),
             article_id: item_id.clone(),
-            publisher_id: "n/a".to_string(),
+            publisher_id: String::new(),
             month_year_id: "n/a".to_string(),
             title: textarea[0].clone(),
             journal: textarea[1].clone(),
             volume: textarea[2].clone(),
             pages: textarea[3].clone(),
             note: textarea[4].clone(),
-            year: textarea[5].clone(),
+            year: String::new(),
             edition: textarea[6].clone(),
             publisher: textarea[7].clone(),
+            authors: parse_authors(&item_id, &textarea[8])?,
+            organizations: parse_organizations(&item_id, &textarea[9])?,
         };
-        let _ = Article::update(&article, item_id.clone());
+        catalog.transaction(|catalog| {
+            let (old_publisher_id, month_year_id) = linked_ids(catalog, query, &item_id)?;
+            article.month_year_id = save_date(catalog, date, &month_year_id)?;
+            article.publisher_id = named_publisher_id(catalog, &article.publisher)?;
+            Article::update(&article, catalog, item_id.clone())?;
+            replace_authors(catalog, &item_id, &article.authors)?;
+            replace_organizations(catalog, &item_id, &article.organizations)?;
+            index_record(catalog, &item_id)?;
+            remove_unused_links(catalog, &old_publisher_id, &month_year_id)
+        })
     }
 }
 
 impl TableInsert for Article {
-    fn insert(&self) -> sqlite::Result<State> {
-        let connection = sqlite::open(DB_URL).unwrap();
-        let query = "INSERT INTO article VALUES (:cite_key, :article_id, :publisher_id, :month_year_id, :title, :journal, :volume, :pages, :note, :year, :edition, :publisher)";
-        let mut statement = connection.prepare(query).unwrap();
-        statement
-            .bind_iter::<_, (_, Value)>([
+    fn insert(&self, catalog: &Catalog) -> DbResult<State> {
+        let query = "INSERT INTO article (cite_key, article_id, publisher_id, month_year_id, title, journal, volume, pages, note, edition) VALUES (:cite_key, :article_id, :publisher_id, :month_year_id, :title, :journal, :volume, :pages, :note, :edition)";
+        catalog.with_statement(query, |statement| {
+            statement.bind_iter::<_, (_, Value)>([
                 (":cite_key", self.cite_key.clone().into()),
                 (":article_id", self.article_id.clone().into()),
-                (":publisher_id", self.publisher_id.clone().into()),
-                (":month_year_id", self.month_year_id.clone().into()),
+                (":publisher_id", optional_id(&self.publisher_id)),
+                (":month_year_id", optional_id(&self.month_year_id)),
                 (":title", self.title.clone().into()),
                 (":journal", self.journal.clone().into()),
                 (":volume", self.volume.clone().into()),
                 (":pages", self.pages.clone().into()),
                 (":note", self.note.clone().into()),
-                (":year", self.year.clone().into()),
                 (":edition", self.edition.clone().into()),
-                (":publisher", self.publisher.clone().into()),
-            ])
-            .unwrap();
-        statement.next()
+            ])?;
+            Ok(statement.next()?)
+        })
     }
 }
 
 impl RowDelete for Article {
-    fn delete(item_id: String) -> sqlite::Result<State> {
-        let connection = sqlite::open(DB_URL).unwrap();
+    fn delete(catalog: &Catalog, item_id: String) -> DbResult<State> {
         let query = "DELETE FROM article WHERE cite_key = ?";
-        let mut statement = connection.prepare(query).unwrap();
-        statement
-            .bind_iter::<_, (_, Value)>([(1, item_id.into())])
-            .unwrap();
-        statement.next()
+        catalog.with_statement(query, |statement| {
+            statement.bind_iter::<_, (_, Value)>([(1, item_id.clone().into())])?;
+            let state = statement.next()?;
+            catalog.expect_changed(&item_id)?;
+            Ok(state)
+        })
     }
 }
 
 impl RowUpdate for Article {
-    fn update(&self, item_id: String) -> sqlite::Result<State> {
-        let connection = sqlite::open(DB_URL).unwrap();
-        let query = "UPDATE article SET title = :title, journal = :journal, volume = :volume, pages = :pages, note = :note, year = :year, edition = :edition, publisher = :publisher WHERE cite_key = :cite_key";
-        let mut statement = connection.prepare(query).unwrap();
-        statement
-            .bind_iter::<_, (_, Value)>([
+    fn update(&self, catalog: &Catalog, item_id: String) -> DbResult<State> {
+        let query = "UPDATE article SET publisher_id = :publisher_id, month_year_id = :month_year_id, title = :title, journal = :journal, volume = :volume, pages = :pages, note = :note, edition = :edition WHERE cite_key = :cite_key";
+        catalog.with_statement(query, |statement| {
+            statement.bind_iter::<_, (_, Value)>([
+                (":publisher_id", optional_id(&self.publisher_id)),
+                (":month_year_id", optional_id(&self.month_year_id)),
                 (":title", self.title.clone().into()),
                 (":journal", self.journal.clone().into()),
                 (":volume", self.volume.clone().into()),
                 (":pages", self.pages.clone().into()),
                 (":note", self.note.clone().into()),
-                (":year", self.year.clone().into()),
                 (":edition", self.edition.clone().into()),
-                (":publisher", self.publisher.clone().into()),
-                (":cite_key", item_id.into()),
-            ])
-            .unwrap();
-        statement.next()
+                (":cite_key", item_id.clone().into()),
+            ])?;
+            let state = statement.next()?;
+            catalog.expect_changed(&item_id)?;
+            Ok(state)
+        })
     }
 }
 
 impl RowSelect for Article {
-    fn select(item_id: &str) -> Vec<String> {
-        let connection = sqlite::open(DB_URL).unwrap();
-        let query = "SELECT * FROM article WHERE cite_key = ?";
-        let mut statement = connection.prepare(query).unwrap();
-        statement
-            .bind_iter::<_, (_, Value)>([(1, item_id.into())])
-            .expect("should bind id");
-        let mut text_vec = Vec::new();
-
-        while let Ok(State::Row) = statement.next() {
-            text_vec = vec![
-                "title",
-                "journal",
-                "volume",
-                "pages",
-                "note",
-                "year",
-                "edition",
-                "publisher",
-            ]
-            .into_iter()
-            .map(|index| statement.read::<String, _>(index).unwrap())
-            .collect();
-        }
-        text_vec
+    fn select(catalog: &Catalog, item_id: &str) -> DbResult<Vec<String>> {
+        let query = "SELECT a.*, p.publisher, m.year, m.month, m.day FROM article a LEFT JOIN publisher p ON p.publisher_id = a.publisher_id LEFT JOIN month_year m ON m.month_year_id = a.month_year_id WHERE a.cite_key = ?";
+        let mut text_vec = catalog.with_statement(query, |statement| {
+            statement.bind_iter::<_, (_, Value)>([(1, item_id.into())])?;
+            let mut text_vec = Vec::new();
+
+            while let State::Row = statement.next()? {
+                text_vec = vec![
+                    "title",
+                    "journal",
+                    "volume",
+                    "pages",
+                    "note",
+                    "year",
+                    "edition",
+                    "publisher",
+                ]
+                .into_iter()
+                .map(|index| match index {
+                    "year" => read_date(statement),
+                    _ => read_text(statement, index),
+                })
+                .collect::<DbResult<_>>()?;
+            }
+            if text_vec.is_empty() {
+                return Err(DbError::NotFound(item_id.to_string()));
+            }
+            Ok(text_vec)
+        })?;
+        text_vec.push(format_authors(&select_authors(catalog, item_id)?));
+        text_vec.push(format_organizations(&select_record_organizations(
+            catalog, item_id,
+        )?));
+        Ok(text_vec)
     }
 }
 
+/// Every `article` column read by `read_article`, with its publisher and date
+const ARTICLE_QUERY: &str = "SELECT a.cite_key, a.article_id, a.publisher_id, a.month_year_id, a.title, a.journal, a.volume, a.pages, a.note, m.year, m.month, m.day, a.edition, p.publisher FROM article a LEFT JOIN publisher p ON p.publisher_id = a.publisher_id LEFT JOIN month_year m ON m.month_year_id = a.month_year_id";
+
+fn read_article(
+    statement: &Statement,
+    authors: Vec<Author>,
+    organizations: Vec<RecordOrganization>,
+) -> DbResult<Article> {
+    Ok(Article {
+        cite_key: read_text(statement, "cite_key")?,
+        authors,
+        organizations,
+        article_id: read_text(statement, "article_id")?,
+        publisher_id: read_text(statement, "publisher_id")?,
+        month_year_id: read_text(statement, "month_year_id")?,
+        title: read_text(statement, "title")?,
+        journal: read_text(statement, "journal")?,
+        pages: read_text(statement, "pages")?,
+        volume: read_text(statement, "volume")?,
+        note: read_text(statement, "note")?,
+        year: read_date(statement)?,
+        edition: read_text(statement, "edition")?,
+        publisher: read_text(statement, "publisher")?,
+    })
+}
+
 /// Read the `SQLite` database `article` table and returns a vector of `article` objects
-// todo! test? Other tests test this logic
-pub fn read_sqlite_article_table() -> Result<Vec<Article>, Error> {
-    let connection = sqlite::open(DB_URL).unwrap();
-    let query = "SELECT cite_key, article_id, publisher_id, month_year_id, title, journal, volume, pages, note, year, edition, publisher FROM article";
-    let mut statement = connection.prepare(query).unwrap();
-    let mut parsed = Vec::new();
-
-    while let Ok(State::Row) = statement.next() {
-        parsed.push(Article {
-            cite_key: statement.read::<String, _>("cite_key").unwrap(),
-            article_id: statement.read::<String, _>("article_id").unwrap(),
-            publisher_id: statement.read::<String, _>("publisher_id").unwrap(),
-            month_year_id: statement.read::<String, _>("month_year_id").unwrap(),
-            title: statement.read::<String, _>("title").unwrap(),
-            journal: statement.read::<String, _>("journal").unwrap(),
-            pages: statement.read::<String, _>("pages").unwrap(),
-            volume: statement.read::<String, _>("volume").unwrap(),
-            note: statement.read::<String, _>("note").unwrap(),
-            year: statement.read::<String, _>("year").unwrap(),
-            edition: statement.read::<String, _>("edition").unwrap(),
-            publisher: statement.read::<String, _>("publisher").unwrap(),
-        });
+pub fn read_sqlite_article_table(catalog: &Catalog) -> DbResult<Vec<Article>> {
+    let mut authors = read_author_table(catalog)?;
+    let mut organizations = read_record_organization_table(catalog)?;
+    catalog.with_statement(ARTICLE_QUERY, |statement| {
+        let mut parsed = Vec::new();
+        while let State::Row = statement.next()? {
+            let cite_key = read_text(statement, "cite_key")?;
+            parsed.push(read_article(
+                statement,
+                authors.remove(&cite_key).unwrap_or_default(),
+                organizations.remove(&cite_key).unwrap_or_default(),
+            )?);
+        }
+        Ok(parsed)
+    })
+}
+
+/// The articles with `cite_keys`, in that order, skipping keys that are not articles
+pub fn read_articles_with_keys(catalog: &Catalog, cite_keys: &[&str]) -> DbResult<Vec<Article>> {
+    let query = format!("{ARTICLE_QUERY} WHERE a.cite_key = ?");
+    let mut articles = Vec::new();
+    for &cite_key in cite_keys {
+        let article = catalog.with_statement(&query, |statement| {
+            statement.bind((1, cite_key))?;
+            match statement.next()? {
+                State::Row => Ok(Some(read_article(statement, Vec::new(), Vec::new())?)),
+                State::Done => Ok(None),
+            }
+        })?;
+        if let Some(mut article) = article {
+            article.authors = select_authors(catalog, cite_key)?;
+            article.organizations = select_record_organizations(catalog, cite_key)?;
+            articles.push(article);
+        }
     }
-    Ok(parsed)
+    Ok(articles)
+}
+
+/// Key publishers are matched on: lower case, punctuation dropped, `&` read as `and`, and a
+/// leading "The" or a trailing "Inc"/"Ltd"/"LLC"/"Co" ignored
+pub fn normalize_publisher(name: &str) -> String {
+    let cleaned: String = name
+        .to_lowercase()
+        .replace('&', " and ")
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { ' ' })
+        .collect();
+    let mut words: Vec<&str> = cleaned.split_whitespace().collect();
+    if words.len() > 1 && words[0] == "the" {
+        words.remove(0);
+    }
+    if words.len() > 1 && matches!(words[words.len() - 1], "inc" | "ltd" | "llc" | "co") {
+        words.pop();
+    }
+    words.join(" ")
+}
+
+/// Read one publisher row from a statement selecting every `publisher` column
+fn read_publisher(statement: &Statement) -> DbResult<Publisher> {
+    Ok(Publisher {
+        publisher_id: read_text(statement, "publisher_id")?,
+        publisher: read_text(statement, "publisher")?,
+        address: read_text(statement, "address")?,
+        place: read_text(statement, "place")?,
+    })
 }
 
 impl Publisher {
@@ -498,44 +1564,220 @@ impl Publisher {
         let publisher_id = Uuid::new_v4().to_string();
         Publisher {
             publisher_id,
-            publisher: vec,
-            address: String::from("n/a"), // This would be a lookup based on the publisher name
+            publisher: vec.trim().to_string(),
+            address: String::new(),
+            place: String::new(),
+        }
+    }
+
+    /// The publisher whose name normalizes to the same key as `name`, if there is one
+    pub fn find(catalog: &Catalog, name: &str) -> DbResult<Option<Publisher>> {
+        let query =
+            "SELECT publisher_id, publisher, address, place FROM publisher WHERE normalized = ?";
+        catalog.with_statement(query, |statement| {
+            statement.bind((1, normalize_publisher(name).as_str()))?;
+            match statement.next()? {
+                State::Row => Ok(Some(read_publisher(statement)?)),
+                State::Done => Ok(None),
+            }
+        })
+    }
+
+    /// The publisher found by `Publisher::find`, added if there is none
+    pub fn find_or_create(catalog: &Catalog, name: &str) -> DbResult<Publisher> {
+        match Publisher::find(catalog, name)? {
+            Some(publisher) => Ok(publisher),
+            None => {
+                let publisher = Publisher::new(name.to_string());
+                publisher.insert(catalog)?;
+                Ok(publisher)
+            }
         }
     }
+
+    /// Update name, address and place of the publisher from the lines of the publisher form.
+    /// Renaming a publisher to the name of another one is refused.
+    pub fn publisher_update(
+        catalog: &Catalog,
+        textarea: Vec<String>,
+        publisher_id: String,
+    ) -> DbResult<()> {
+        let textarea = form_fields(textarea, &PUBLISHER_FIELDS, &PUBLISHER_REQUIRED)?;
+        let publisher = Publisher {
+            publisher_id: publisher_id.clone(),
+            publisher: textarea[0].trim().to_string(),
+            address: textarea[1].trim().to_string(),
+            place: textarea[2].trim().to_string(),
+        };
+        catalog.transaction(|catalog| {
+            if let Some(existing) = Publisher::find(catalog, &publisher.publisher)? {
+                if existing.publisher_id != publisher_id {
+                    return Err(DbError::InvalidInput(format!(
+                        "publisher {} already exists",
+                        existing.publisher
+                    )));
+                }
+            }
+            Publisher::update(&publisher, catalog, publisher_id.clone())?;
+            index_publisher_records(catalog, &publisher_id)
+        })
+    }
 }
 
 impl TableInsert for Publisher {
-    fn insert(&self) -> sqlite::Result<State> {
-        let connection = sqlite::open(DB_URL).unwrap();
-        let query = "INSERT INTO publisher VALUES (:publisher_id, :publisher, :address)";
-        let mut statement = connection.prepare(query).unwrap();
-        statement
-            .bind_iter::<_, (_, Value)>([
+    fn insert(&self, catalog: &Catalog) -> DbResult<State> {
+        let query = "INSERT INTO publisher (publisher_id, publisher, address, place, normalized) VALUES (:publisher_id, :publisher, :address, :place, :normalized)";
+        catalog.with_statement(query, |statement| {
+            statement.bind_iter::<_, (_, Value)>([
                 (":publisher_id", self.publisher_id.clone().into()),
                 (":publisher", self.publisher.clone().into()),
                 (":address", self.address.clone().into()),
-            ])
-            .as_ref()
-            .unwrap();
-        statement.next()
+                (":place", self.place.clone().into()),
+                (":normalized", normalize_publisher(&self.publisher).into()),
+            ])?;
+            Ok(statement.next()?)
+        })
+    }
+}
+
+impl RowUpdate for Publisher {
+    fn update(&self, catalog: &Catalog, item_id: String) -> DbResult<State> {
+        let query = "UPDATE publisher SET publisher = :publisher, address = :address, place = :place, normalized = :normalized WHERE publisher_id = :publisher_id";
+        catalog.with_statement(query, |statement| {
+            statement.bind_iter::<_, (_, Value)>([
+                (":publisher", self.publisher.clone().into()),
+                (":address", self.address.clone().into()),
+                (":place", self.place.clone().into()),
+                (":normalized", normalize_publisher(&self.publisher).into()),
+                (":publisher_id", item_id.clone().into()),
+            ])?;
+            let state = statement.next()?;
+            catalog.expect_changed(&item_id)?;
+            Ok(state)
+        })
+    }
+}
+
+impl RowSelect for Publisher {
+    fn select(catalog: &Catalog, item_id: &str) -> DbResult<Vec<String>> {
+        let query =
+            "SELECT publisher_id, publisher, address, place FROM publisher WHERE publisher_id = ?";
+        catalog.with_statement(query, |statement| {
+            statement.bind((1, item_id))?;
+            match statement.next()? {
+                State::Row => {
+                    let publisher = read_publisher(statement)?;
+                    Ok(vec![
+                        publisher.publisher,
+                        publisher.address,
+                        publisher.place,
+                    ])
+                }
+                State::Done => Err(DbError::NotFound(item_id.to_string())),
+            }
+        })
+    }
+}
+
+/// Names of up to `limit` publishers whose normalized name starts with that of `prefix`
+pub fn publisher_suggestions(catalog: &Catalog, prefix: &str, limit: i64) -> DbResult<Vec<String>> {
+    let prefix = normalize_publisher(prefix);
+    if prefix.is_empty() {
+        return Ok(Vec::new());
+    }
+    // normalized names hold only letters, digits and spaces, so `prefix` needs no LIKE escaping
+    let query = "SELECT publisher FROM publisher WHERE normalized LIKE :prefix || '%' ORDER BY normalized LIMIT :limit";
+    catalog.with_statement(query, |statement| {
+        statement.bind((":prefix", prefix.as_str()))?;
+        statement.bind((":limit", limit))?;
+        let mut names = Vec::new();
+        while let State::Row = statement.next()? {
+            names.push(read_text(statement, "publisher")?);
+        }
+        Ok(names)
+    })
+}
+
+/// Read the `SQLite` database `publisher` table, sorted by name, with the number of records that
+/// use each publisher
+pub fn read_sqlite_publisher_table(catalog: &Catalog) -> DbResult<Vec<(Publisher, i64)>> {
+    let query = "SELECT publisher_id, publisher, address, place, (SELECT COUNT(*) FROM catalog_records r WHERE r.publisher_id = publisher.publisher_id) AS records FROM publisher ORDER BY normalized";
+    catalog.with_statement(query, |statement| {
+        let mut parsed = Vec::new();
+        while let State::Row = statement.next()? {
+            parsed.push((
+                read_publisher(statement)?,
+                statement.read::<i64, _>("records")?,
+            ));
+        }
+        Ok(parsed)
+    })
+}
+
+/// Fill in `publisher.normalized` and merge publishers that share a normalized name into the
+/// oldest one, repointing their books and articles. Run once by migration 5.
+pub(crate) fn merge_duplicate_publishers(catalog: &Catalog) -> DbResult<()> {
+    let query = "SELECT publisher_id, publisher FROM publisher ORDER BY rowid";
+    let publishers = catalog.with_statement(query, |statement| {
+        let mut publishers = Vec::new();
+        while let State::Row = statement.next()? {
+            publishers.push((
+                read_text(statement, "publisher_id")?,
+                read_text(statement, "publisher")?,
+            ));
+        }
+        Ok(publishers)
+    })?;
+
+    let mut kept: HashMap<String, String> = HashMap::new();
+    for (publisher_id, name) in publishers {
+        let normalized = normalize_publisher(&name);
+        match kept.get(&normalized) {
+            Some(kept_id) => {
+                for query in [
+                    "UPDATE book SET publisher_id = :kept WHERE publisher_id = :id",
+                    "UPDATE article SET publisher_id = :kept WHERE publisher_id = :id",
+                ] {
+                    catalog.with_statement(query, |statement| {
+                        statement.bind((":kept", kept_id.as_str()))?;
+                        statement.bind((":id", publisher_id.as_str()))?;
+                        statement.next()?;
+                        Ok(())
+                    })?;
+                }
+                let query = "DELETE FROM publisher WHERE publisher_id = ?";
+                catalog.with_statement(query, |statement| {
+                    statement.bind((1, publisher_id.as_str()))?;
+                    statement.next()?;
+                    Ok(())
+                })?;
+            }
+            None => {
+                let query =
+                    "UPDATE publisher SET normalized = :normalized WHERE publisher_id = :id";
+                catalog.with_statement(query, |statement| {
+                    statement.bind((":normalized", normalized.as_str()))?;
+                    statement.bind((":id", publisher_id.as_str()))?;
+                    statement.next()?;
+                    Ok(())
+                })?;
+                kept.insert(normalized, publisher_id);
+            }
+        }
     }
+    catalog.execute("CREATE UNIQUE INDEX publisher_normalized ON publisher(normalized)")
 }
 
 #[cfg(test)]
 mod test {
-    /// To test on a blank database Initialize a different database by changing the DB_URL path
-    /// and running `cargo run --bin init_db`
-    ///
-    /// A better way to do this may have been to have the init_db split into other files. Having
-    /// the query str available from db.rs, and then passing in the desired database path passed
-    /// into the init_db() function; then using a once_cell Lazy<Arc<sqlite::Connection>> to pass
-    /// around the database connection.
+    /// Each test runs against its own blank database in the system temp directory, initialized
+    /// with the same `init_db()` used by the `init_db` binary.
     use super::*;
     use serial_test::serial;
     use sqlite::State::{Done, Row};
 
-    fn exists(table: String, cite_key: String) -> bool {
-        let connection = sqlite::open(DB_URL).unwrap();
+    fn exists(db_url: &str, table: String, cite_key: String) -> bool {
+        let connection = sqlite::open(db_url).unwrap();
         let query = format!("SELECT * FROM {} WHERE cite_key = ?", table);
         let mut statement = connection.prepare(query).unwrap();
         statement
@@ -548,11 +1790,27 @@ mod test {
             _ => false,
         }
     }
+
+    /// Insert the `master_entries`, `publisher` and `month_year` rows a book or article refers to
+    fn parent_rows(catalog: &Catalog, entry_type: EntryType) -> (String, String, String) {
+        let master = MasterEntries::new(entry_type);
+        let publisher = Publisher::new("New Publisher".to_string());
+        let month_year = MonthYear::parse("2023").unwrap();
+        master.insert(catalog).unwrap();
+        publisher.insert(catalog).unwrap();
+        month_year.insert(catalog).unwrap();
+        (
+            master.cite_key,
+            publisher.publisher_id,
+            month_year.month_year_id,
+        )
+    }
+
     #[test]
     #[serial]
     fn new_book_master_entries() {
         let x = MasterEntries::new_book();
-        assert_eq!(x.entry_type, "BOOK".to_string());
+        assert_eq!(x.entry_type, EntryType::Book);
         let y = MasterEntries::new_book();
         assert_ne!(x.cite_key, y.cite_key);
     }
@@ -561,33 +1819,234 @@ mod test {
     #[serial]
     fn new_article_master_entries() {
         let x = MasterEntries::new_article();
-        assert_eq!(x.entry_type, "ARTICLE".to_string());
+        assert_eq!(x.entry_type, EntryType::Article);
         let y = MasterEntries::new_article();
         assert_ne!(x.cite_key, y.cite_key);
     }
 
+    #[test]
+    #[serial]
+    fn catalog_entries_cover_every_type() {
+        let database = TestDatabase::new();
+        let catalog = database.catalog();
+        let book = Book::book_transaction(
+            &catalog,
+            vec![
+                "Knuth, Donald; Graham, Ronald".to_string(),
+                "Concrete Mathematics".to_string(),
+                "657".to_string(),
+                "".to_string(),
+                "".to_string(),
+                "1989".to_string(),
+                "".to_string(),
+                "Addison-Wesley".to_string(),
+            ],
+        )
+        .unwrap();
+        let thesis = EntryType::Thesis
+            .item_type()
+            .unwrap()
+            .item_transaction(
+                &catalog,
+                vec![
+                    "Shannon".to_string(),
+                    "A Symbolic Analysis of Relay Circuits".to_string(),
+                    "".to_string(),
+                    "".to_string(),
+                    "".to_string(),
+                    "".to_string(),
+                    "MS".to_string(),
+                ],
+            )
+            .unwrap();
+        let orphan = MasterEntries::new_article();
+        orphan.insert(&catalog).unwrap();
+
+        let entries = read_catalog_entries(&catalog).unwrap();
+        assert_eq!(entries.len(), 3);
+        // a master entry with no record sorts first, with nothing but its type
+        assert_eq!(entries[0].cite_key, orphan.cite_key);
+        assert_eq!(entries[0].entry_type, EntryType::Article);
+        assert_eq!(entries[0].title, "");
+        assert_eq!(entries[1].cite_key, thesis);
+        assert_eq!(entries[1].first_author, "Shannon");
+        assert_eq!(entries[1].year, None);
+        assert_eq!(
+            entries[2],
+            CatalogEntry {
+                cite_key: book,
+                entry_type: EntryType::Book,
+                title: "Concrete Mathematics".to_string(),
+                first_author: "Knuth, Donald".to_string(),
+                year: Some(1989),
+            }
+        );
+    }
+
+    #[test]
+    fn entry_types_round_trip() {
+        for entry_type in EntryType::ALL {
+            assert_eq!(
+                entry_type.as_str().parse::<EntryType>().unwrap(),
+                entry_type
+            );
+        }
+        assert!(matches!(
+            "Book".parse::<EntryType>(),
+            Err(DbError::Schema(_))
+        ));
+        assert!(matches!("".parse::<EntryType>(), Err(DbError::Schema(_))));
+    }
+
     #[test]
     #[serial]
     fn insert_and_delete_master_entries() {
+        let database = TestDatabase::new();
+        let db_url = database.db_url();
+        let catalog = database.catalog();
         let x = MasterEntries::new_book();
-        let actual = x.insert();
+        let actual = x.insert(&catalog);
         // assert_eq!(false, actual.is_ok());
         assert_eq!(Done, actual.unwrap());
-        let result = exists("master_entries".to_string(), x.cite_key.clone());
-        assert_eq!(result, true, "you are here");
-        MasterEntries::delete(x.cite_key.clone()).expect("TODO: panic message");
-        let result = exists("master_entries".to_string(), x.cite_key.clone());
-        assert_eq!(result, false, "you are not here");
+        let result = exists(db_url, "master_entries".to_string(), x.cite_key.clone());
+        assert!(result, "you are here");
+        MasterEntries::delete(&catalog, x.cite_key.clone()).expect("TODO: panic message");
+        let result = exists(db_url, "master_entries".to_string(), x.cite_key.clone());
+        assert!(!result, "you are not here");
     }
 
-    // Note: Book::book_transaction() and Article::article_transaction() are not tested, but the
-    // individual elements of the functions are
+    #[test]
+    #[serial]
+    // Tests Book transaction book_transaction() and delete_book()
+    fn book_transaction_and_delete_book() {
+        let database = TestDatabase::new();
+        let db_url = database.db_url();
+        let catalog = database.catalog();
+        let book_textarea: Vec<String> = vec![
+            "New Author".to_string(),
+            "New Title".to_string(),
+            "300 pages".to_string(),
+            "Volume 1".to_string(),
+            "1st edition".to_string(),
+            "2023".to_string(),
+            "New Series".to_string(),
+            "New Publisher".to_string(),
+            "New Note".to_string(),
+        ];
+        let cite_key = Book::book_transaction(&catalog, book_textarea).unwrap();
+        assert!(exists(
+            db_url,
+            "master_entries".to_string(),
+            cite_key.clone()
+        ));
+        assert!(exists(db_url, "book".to_string(), cite_key.clone()));
+        assert!(exists(db_url, "author".to_string(), cite_key.clone()));
+
+        Book::delete_book(&catalog, cite_key.clone()).unwrap();
+        assert!(!exists(db_url, "author".to_string(), cite_key.clone()));
+        assert!(!exists(
+            db_url,
+            "master_entries".to_string(),
+            cite_key.clone()
+        ));
+        assert!(!exists(db_url, "book".to_string(), cite_key));
+
+        // the publisher and month_year rows created with the book go with it
+        let connection = sqlite::open(db_url).unwrap();
+        let mut statement = connection
+            .prepare("SELECT (SELECT COUNT(*) FROM publisher) + (SELECT COUNT(*) FROM month_year)")
+            .unwrap();
+        statement.next().unwrap();
+        assert_eq!(statement.read::<i64, _>(0).unwrap(), 0);
+    }
+
+    #[test]
+    #[serial]
+    // Tests that a failed write inside a transaction rolls back the writes before it
+    fn transaction_rolls_back_on_error() {
+        let database = TestDatabase::new();
+        let db_url = database.db_url();
+        let catalog = database.catalog();
+        let x = MasterEntries::new_book();
+        let result = catalog.transaction(|catalog| {
+            x.insert(catalog)?;
+            // same primary key again violates the UNIQUE constraint
+            x.insert(catalog)
+        });
+        assert!(result.is_err());
+        assert!(!exists(
+            db_url,
+            "master_entries".to_string(),
+            x.cite_key.clone()
+        ));
+
+        // the connection is usable again after the rollback
+        assert_eq!(Done, x.insert(&catalog).unwrap());
+    }
+
+    #[test]
+    #[serial]
+    // Tests that db functions return DbError variants instead of panicking
+    fn db_errors() {
+        let database = TestDatabase::new();
+        let db_url = database.db_url();
+        let catalog = database.catalog();
+
+        // required fields left blank
+        let short_form = vec!["New Author".to_string(), " ".to_string()];
+        let result = Book::book_transaction(&catalog, short_form);
+        assert!(matches!(result, Err(DbError::InvalidInput(_))));
+
+        // missing record
+        let result = Book::select(&catalog, "no such cite_key");
+        assert!(matches!(result, Err(DbError::NotFound(_))));
+        let result = Article::delete_article(&catalog, "no such cite_key".to_string());
+        assert!(matches!(result, Err(DbError::NotFound(_))));
+
+        // constraint violation
+        let x = MasterEntries::new_book();
+        x.insert(&catalog).unwrap();
+        assert!(matches!(x.insert(&catalog), Err(DbError::Constraint(_))));
+
+        // foreign key violation: a book whose cite_key is not in master_entries
+        let (_, publisher_id, month_year_id) = parent_rows(&catalog, EntryType::Book);
+        let orphan = Book {
+            book_id: Uuid::new_v4().to_string(),
+            cite_key: Uuid::new_v4().to_string(),
+            publisher_id,
+            month_year_id,
+            authors: Vec::new(),
+            title: String::new(),
+            pages: String::new(),
+            volume: String::new(),
+            edition: String::new(),
+            year: String::new(),
+            series: String::new(),
+            publisher: String::new(),
+            note: String::new(),
+            isbn: String::new(),
+            organizations: Vec::new(),
+        };
+        assert!(matches!(
+            orphan.insert(&catalog),
+            Err(DbError::Constraint(_))
+        ));
+
+        // schema mismatch
+        let connection = sqlite::open(db_url).unwrap();
+        connection.execute("DROP TABLE article").unwrap();
+        let result = read_sqlite_article_table(&catalog);
+        assert!(matches!(result, Err(DbError::Schema(_))));
+    }
 
     #[test]
     #[serial]
     // Tests two trait functions BookInsert insert() and RowDelete delete() for book
     // Tests Book transaction delete_book()
     fn insert_and_delete_new_book() {
+        let database = TestDatabase::new();
+        let db_url = database.db_url();
+        let catalog = database.catalog();
         let book_textarea: Vec<String> = vec![
             "New Author".to_string(),
             "New Title".to_string(),
@@ -599,13 +2058,13 @@ mod test {
             "New Publisher".to_string(),
             "New Note".to_string(),
         ];
-        // Note: Book::book_transaction() is not tested, but the individual elements of the function are
+        let (cite_key, publisher_id, month_year_id) = parent_rows(&catalog, EntryType::Book);
         let b = Book {
             book_id: Uuid::new_v4().to_string(),
-            cite_key: Uuid::new_v4().to_string(),
-            publisher_id: Uuid::new_v4().to_string(),
-            month_year_id: Uuid::new_v4().to_string(),
-            author: book_textarea[0].clone(),
+            cite_key: cite_key.clone(),
+            publisher_id,
+            month_year_id,
+            authors: parse_authors(&cite_key, &book_textarea[0]).unwrap(),
             title: book_textarea[1].clone(),
             pages: book_textarea[2].clone(),
             volume: book_textarea[3].clone(),
@@ -614,27 +2073,31 @@ mod test {
             series: book_textarea[6].clone(),
             publisher: book_textarea[7].clone(),
             note: book_textarea[8].clone(),
+            isbn: String::new(),
+            organizations: Vec::new(),
         };
 
         // Test that the the result == State<Done>
-        let actual = b.insert();
-        assert_eq!(true, actual.is_ok());
+        let actual = b.insert(&catalog);
+        assert!(actual.is_ok());
         assert_eq!(Done, actual.unwrap());
 
         // Test that the item is inserted into the `book` table
-        let result = exists("book".to_string(), b.cite_key.clone());
-        assert_eq!(result, true, "you are here");
+        let result = exists(db_url, "book".to_string(), b.cite_key.clone());
+        assert!(result, "you are here");
 
         // Test that the item is removed from the `book` table
-        let _ = Book::delete(b.cite_key.clone());
-        let result = exists("book".to_string(), b.cite_key.clone());
-        assert_eq!(result, false, "you are not here");
+        let _ = Book::delete(&catalog, b.cite_key.clone());
+        let result = exists(db_url, "book".to_string(), b.cite_key.clone());
+        assert!(!result, "you are not here");
     }
 
     #[test]
     #[serial]
     // Tests two trait functions RowSelect select() and RowUpdate update() for book
     fn select_and_update_book() {
+        let database = TestDatabase::new();
+        let catalog = database.catalog();
         // Instantiate book object and insert to database
         let book_textarea: Vec<String> = vec![
             "New Author".to_string(),
@@ -648,12 +2111,13 @@ mod test {
             "New Note".to_string(),
         ];
 
+        let (cite_key, publisher_id, month_year_id) = parent_rows(&catalog, EntryType::Book);
         let b = Book {
             book_id: Uuid::new_v4().to_string(),
-            cite_key: Uuid::new_v4().to_string(),
-            publisher_id: Uuid::new_v4().to_string(),
-            month_year_id: Uuid::new_v4().to_string(),
-            author: book_textarea[0].clone(),
+            cite_key: cite_key.clone(),
+            publisher_id,
+            month_year_id,
+            authors: parse_authors(&cite_key, &book_textarea[0]).unwrap(),
             title: book_textarea[1].clone(),
             pages: book_textarea[2].clone(),
             volume: book_textarea[3].clone(),
@@ -662,12 +2126,14 @@ mod test {
             series: book_textarea[6].clone(),
             publisher: book_textarea[7].clone(),
             note: book_textarea[8].clone(),
+            isbn: String::new(),
+            organizations: Vec::new(),
         };
-        let _ = b.insert();
+        let _ = b.insert(&catalog);
 
         // New vec of strings to update
         let new_book_textarea: Vec<String> = vec![
-            "NewNew Author".to_string(),
+            "Author, NewNew; Editor, New (editor)".to_string(),
             "NewNew Title".to_string(),
             "300 pages".to_string(),
             "Volume 1".to_string(),
@@ -678,11 +2144,11 @@ mod test {
             "NewNew Note".to_string(),
         ];
         // Update original book with new vec of strings
-        let _ = Book::book_update(new_book_textarea, b.cite_key.clone());
+        Book::book_update(&catalog, new_book_textarea, b.cite_key.clone()).unwrap();
 
         // Find book with original cite_key and verify that the data is updated
-        let found = Book::select(b.cite_key.as_str());
-        assert_eq!(found[0], "NewNew Author".to_string());
+        let found = Book::select(&catalog, b.cite_key.as_str()).unwrap();
+        assert_eq!(found[0], "Author, NewNew; Editor, New (editor)".to_string());
         assert_eq!(found[1], "NewNew Title".to_string());
         assert_eq!(found[2], "300 pages".to_string());
         assert_eq!(found[3], "Volume 1".to_string());
@@ -693,11 +2159,61 @@ mod test {
         assert_eq!(found[8], "NewNew Note".to_string());
     }
 
+    #[test]
+    #[serial]
+    // A book needs only a title; pages, date and publisher may be left out and added later
+    fn save_book_with_authors_and_title() {
+        let database = TestDatabase::new();
+        let catalog = database.catalog();
+        let rows = |table: &str| {
+            let query = format!("SELECT count(*) FROM {table}");
+            catalog
+                .with_statement(&query, |statement| {
+                    statement.next()?;
+                    Ok(statement.read::<i64, _>(0)?)
+                })
+                .unwrap()
+        };
+        let form = vec![
+            "Knuth, Donald".to_string(),
+            "The Art of Computer Programming".to_string(),
+        ];
+        let cite_key = Book::book_transaction(&catalog, form.clone()).unwrap();
+        let found = Book::select(&catalog, &cite_key).unwrap();
+        assert_eq!(found[0], "Knuth, Donald");
+        assert_eq!(found[1], "The Art of Computer Programming");
+        assert_eq!(found[2], "");
+        assert_eq!(found[5], "");
+        assert_eq!(found[7], "");
+        assert_eq!((rows("publisher"), rows("month_year")), (0, 0));
+
+        let mut dated = found.clone();
+        dated[5] = "1968".to_string();
+        dated[7] = "Addison-Wesley".to_string();
+        Book::book_update(&catalog, dated, cite_key.clone()).unwrap();
+        let found = Book::select(&catalog, &cite_key).unwrap();
+        assert_eq!(
+            (found[5].as_str(), found[7].as_str()),
+            ("1968", "Addison-Wesley")
+        );
+        assert_eq!((rows("publisher"), rows("month_year")), (1, 1));
+
+        // clearing them again leaves no unused rows behind
+        Book::book_update(&catalog, form, cite_key.clone()).unwrap();
+        let found = Book::select(&catalog, &cite_key).unwrap();
+        assert_eq!((found[5].as_str(), found[7].as_str()), ("", ""));
+        assert_eq!((rows("publisher"), rows("month_year")), (0, 0));
+        assert_eq!(crate::check::find_orphans(&catalog).unwrap(), vec![]);
+    }
+
     #[test]
     #[serial]
     // Tests two trait functions ArticleInsert insert() and RowDelete delete() for article
     // Tests Article transaction delete_book()
     fn insert_and_delete_new_article() {
+        let database = TestDatabase::new();
+        let db_url = database.db_url();
+        let catalog = database.catalog();
         let article_textarea: Vec<String> = vec![
             "New Title".to_string(),
             "New Journal".to_string(),
@@ -708,11 +2224,12 @@ mod test {
             "New Edition".to_string(),
             "New Publisher".to_string(),
         ];
+        let (cite_key, publisher_id, month_year_id) = parent_rows(&catalog, EntryType::Article);
         let a = Article {
             article_id: Uuid::new_v4().to_string(),
-            cite_key: Uuid::new_v4().to_string(),
-            publisher_id: Uuid::new_v4().to_string(),
-            month_year_id: Uuid::new_v4().to_string(),
+            cite_key,
+            publisher_id,
+            month_year_id,
             title: article_textarea[0].clone(),
             journal: article_textarea[1].clone(),
             volume: article_textarea[2].clone(),
@@ -721,27 +2238,31 @@ mod test {
             year: article_textarea[5].clone(),
             edition: article_textarea[6].clone(),
             publisher: article_textarea[7].clone(),
+            authors: Vec::new(),
+            organizations: Vec::new(),
         };
 
         // Test that the the result == State<Done>
-        let actual = a.insert();
-        assert_eq!(true, actual.is_ok());
+        let actual = a.insert(&catalog);
+        assert!(actual.is_ok());
         assert_eq!(Done, actual.unwrap());
 
         // Test that the item is inserted into the `article` table
-        let result = exists("article".to_string(), a.cite_key.clone());
-        assert_eq!(result, true, "you are here");
+        let result = exists(db_url, "article".to_string(), a.cite_key.clone());
+        assert!(result, "you are here");
 
         // Test that the item is removed from the `article` table
-        let _ = Article::delete(a.cite_key.clone());
-        let result = exists("article".to_string(), a.cite_key.clone());
-        assert_eq!(result, false, "you are not here");
+        let _ = Article::delete(&catalog, a.cite_key.clone());
+        let result = exists(db_url, "article".to_string(), a.cite_key.clone());
+        assert!(!result, "you are not here");
     }
 
     #[test]
     #[serial]
     // Tests two trait functions RowSelect select() and RowUpdate update() for article
     fn select_and_update_article() {
+        let database = TestDatabase::new();
+        let catalog = database.catalog();
         // Instantiate article object and insert to database
         let article_textarea: Vec<String> = vec![
             "New Title".to_string(),
@@ -754,11 +2275,12 @@ mod test {
             "New Publisher".to_string(),
         ];
 
+        let (cite_key, publisher_id, month_year_id) = parent_rows(&catalog, EntryType::Article);
         let a = Article {
             article_id: Uuid::new_v4().to_string(),
-            cite_key: Uuid::new_v4().to_string(),
-            publisher_id: Uuid::new_v4().to_string(),
-            month_year_id: Uuid::new_v4().to_string(),
+            cite_key,
+            publisher_id,
+            month_year_id,
             title: article_textarea[0].clone(),
             journal: article_textarea[1].clone(),
             volume: article_textarea[2].clone(),
@@ -767,8 +2289,10 @@ mod test {
             year: article_textarea[5].clone(),
             edition: article_textarea[6].clone(),
             publisher: article_textarea[7].clone(),
+            authors: Vec::new(),
+            organizations: Vec::new(),
         };
-        let _ = a.insert();
+        let _ = a.insert(&catalog);
 
         // New vec of strings to update
         let new_article_textarea: Vec<String> = vec![
@@ -782,10 +2306,10 @@ mod test {
             "NewNew Publisher".to_string(),
         ];
         // Update original article with new vec of strings
-        let _ = Article::article_update(new_article_textarea, a.cite_key.clone());
+        Article::article_update(&catalog, new_article_textarea, a.cite_key.clone()).unwrap();
 
         // Find article with original cite_key and verify that the data is updated
-        let found = Article::select(a.cite_key.as_str());
+        let found = Article::select(&catalog, a.cite_key.as_str()).unwrap();
         assert_eq!(found[0], "NewNew Title".to_string());
         assert_eq!(found[1], "NewNew Journal".to_string());
         assert_eq!(found[2], "Volume 1".to_string());
@@ -798,20 +2322,131 @@ mod test {
 
     #[test]
     #[serial]
-    fn new_month_year() {
-        let x = MonthYear::new("1923".to_string());
-        let y = MonthYear::new("2023".to_string());
-        assert_eq!(x.year, "1923".to_string());
-        assert_eq!(y.year, "2023".to_string());
-        assert_ne!(x.month, y.month_year_id)
+    // Tests that books from the same press share one publisher row, matched by normalized name
+    fn publishers_are_shared_by_name() {
+        let database = TestDatabase::new();
+        let catalog = database.catalog();
+        assert_eq!(normalize_publisher(" The  MIT Press, Inc. "), "mit press");
+        assert_eq!(
+            normalize_publisher("Simon & Schuster"),
+            "simon and schuster"
+        );
+
+        let mut cite_keys = Vec::new();
+        for publisher in ["The MIT Press", "MIT press."] {
+            let book_textarea: Vec<String> = vec![
+                "Abelson, Harold".to_string(),
+                "Structure and Interpretation".to_string(),
+                "657 pages".to_string(),
+                "".to_string(),
+                "".to_string(),
+                "1985".to_string(),
+                "".to_string(),
+                publisher.to_string(),
+            ];
+            cite_keys.push(Book::book_transaction(&catalog, book_textarea).unwrap());
+        }
+        let publishers = read_sqlite_publisher_table(&catalog).unwrap();
+        assert_eq!(publishers.len(), 1);
+        let (publisher, records) = publishers[0].clone();
+        assert_eq!(publisher.publisher, "The MIT Press");
+        assert_eq!(records, 2);
+        assert_eq!(
+            publisher_suggestions(&catalog, "mi", 5).unwrap(),
+            vec!["The MIT Press".to_string()]
+        );
+        assert!(publisher_suggestions(&catalog, "oxford", 5)
+            .unwrap()
+            .is_empty());
+
+        // editing the authority record changes every book that uses it
+        let form = vec![
+            "MIT Press".to_string(),
+            "55 Hayward Street".to_string(),
+            "Cambridge, MA".to_string(),
+        ];
+        Publisher::publisher_update(&catalog, form, publisher.publisher_id.clone()).unwrap();
+        let books = read_sqlite_book_table(&catalog).unwrap();
+        assert!(books.iter().all(|book| book.publisher == "MIT Press"));
+        assert_eq!(books[0].publisher_id, publisher.publisher_id);
+        assert_eq!(
+            Publisher::select(&catalog, &publisher.publisher_id).unwrap()[2],
+            "Cambridge, MA"
+        );
+        let form = vec!["Knopf".to_string()];
+        Publisher::publisher_update(&catalog, form, publisher.publisher_id.clone()).unwrap();
+        let other = Publisher::find_or_create(&catalog, "Penguin").unwrap();
+        let form = vec!["knopf".to_string()];
+        let result = Publisher::publisher_update(&catalog, form, other.publisher_id);
+        assert!(matches!(result, Err(DbError::InvalidInput(_))));
+
+        // the publisher row goes once its last book does
+        Book::delete_book(&catalog, cite_keys[0].clone()).unwrap();
+        assert_eq!(read_sqlite_publisher_table(&catalog).unwrap().len(), 2);
+        Book::delete_book(&catalog, cite_keys[1].clone()).unwrap();
+        assert_eq!(read_sqlite_publisher_table(&catalog).unwrap().len(), 1);
+    }
+
+    #[test]
+    #[serial]
+    // Tests that the catalog keeps one prepared statement per query and reuses it
+    fn catalog_caches_statements() {
+        let database = TestDatabase::new();
+        let catalog = database.catalog();
+        for _ in 0..3 {
+            let _ = MasterEntries::new_book().insert(&catalog);
+            let _ = read_sqlite_book_table(&catalog);
+        }
+        // read_sqlite_book_table also caches the queries for the author and organization tables
+        assert_eq!(catalog.statements.borrow().len(), 4);
+
+        let found = catalog
+            .with_statement("SELECT COUNT(*) FROM master_entries", |statement| {
+                statement.next()?;
+                Ok(statement.read::<i64, _>(0)?)
+            })
+            .unwrap();
+        assert_eq!(found, 3);
+    }
+
+    #[test]
+    #[serial]
+    fn parse_month_year() {
+        for (input, expected) in [
+            ("2023", "2023"),
+            ("2023-05", "2023-05"),
+            ("2023-05-17", "2023-05-17"),
+            ("May 2023", "2023-05"),
+            ("17 May 2023", "2023-05-17"),
+            ("May 17, 2023", "2023-05-17"),
+            ("Sept. 1999", "1999-09"),
+            ("c1998", "1998"),
+            ("[1998]", "1998"),
+            ("2024-02-29", "2024-02-29"),
+        ] {
+            assert_eq!(MonthYear::parse(input).unwrap().to_string(), expected);
+        }
+        for input in ["2023-13", "Feb 30 2023", "2023-02-29", "soon", "May", ""] {
+            assert!(matches!(
+                MonthYear::parse(input),
+                Err(DbError::InvalidInput(_))
+            ));
+        }
+        let x = MonthYear::parse("1923").unwrap();
+        let y = MonthYear::parse("1923").unwrap();
+        assert_eq!(x.month, None);
+        assert_ne!(x.month_year_id, y.month_year_id);
     }
 
     #[test]
     #[serial]
     fn month_year_insert() {
-        let x = MonthYear::new("2023".to_string());
-        let _ = x.insert();
-        let connection = sqlite::open(DB_URL).unwrap();
+        let database = TestDatabase::new();
+        let db_url = database.db_url();
+        let catalog = database.catalog();
+        let x = MonthYear::parse("2023").unwrap();
+        let _ = x.insert(&catalog);
+        let connection = sqlite::open(db_url).unwrap();
         let query = "SELECT * FROM month_year WHERE month_year_id = :month_year_id";
         let mut statement = connection.prepare(query).unwrap();
         statement
@@ -819,7 +2454,7 @@ mod test {
             .unwrap();
 
         let result = statement.next();
-        assert_eq!(true, result.is_ok());
+        assert!(result.is_ok());
         assert_eq!(Row, result.unwrap());
     }
 
@@ -836,9 +2471,12 @@ mod test {
     #[test]
     #[serial]
     fn publisher_insert() {
+        let database = TestDatabase::new();
+        let db_url = database.db_url();
+        let catalog = database.catalog();
         let x = Publisher::new("New Publisher".to_string());
-        let _ = x.insert();
-        let connection = sqlite::open(DB_URL).unwrap();
+        let _ = x.insert(&catalog);
+        let connection = sqlite::open(db_url).unwrap();
         let query = "SELECT * FROM publisher WHERE publisher_id = :publisher_id";
         let mut statement = connection.prepare(query).unwrap();
         statement
@@ -846,117 +2484,7 @@ mod test {
             .unwrap();
 
         let result = statement.next();
-        assert_eq!(true, result.is_ok());
+        assert!(result.is_ok());
         assert_eq!(Row, result.unwrap());
     }
 }
-
-// Implement Later
-// #[derive(Clone, Debug)]
-// pub struct Relationship {
-//     pub(crate) parent_id: String,
-//     pub(crate) child_id: String,
-//     pub(crate) cite_key: String,
-// }
-
-// Implement Later
-// #[derive(Clone, Debug)]
-// pub struct Author {
-//     pub(crate) cite_key: String,
-//     pub(crate) author_id: String,
-//     pub(crate) authors: String,
-// }
-
-// Implement Later
-// #[derive(Clone, Debug)]
-// pub struct Organizations {
-//     pub(crate) organization_id: String,
-//     pub(crate) organization: String,
-//     pub(crate) address: String,
-// }
-
-// impl Relationship {
-//     pub fn new(master_key: String) -> Relationship {
-//         let parent_id = Uuid::new_v4().to_string();
-//         let child_id = Uuid::new_v4().to_string();
-//         Relationship {
-//             parent_id,
-//             child_id,
-//             cite_key: master_key,
-//         }
-//     }
-// }
-//
-// impl TableInsert for Relationship {
-//     fn insert(&self) {
-//         let db = Arc::new(SqlitePool::connect(DB_URL).await.unwrap());
-//         let result = sqlx::query("INSERT INTO relationship (parent_id, child_id, cite_key) VALUES (?,?,?,)")
-//             .bind(&self.parent_id)
-//             .bind(&self.child_id)
-//             .bind(&self.cite_key)
-//             .execute(&*db)
-//             .await;
-//
-//         match result {
-//             Ok(rs) => eprintln!("Row inserted: {:?}", rs),
-//             Err(e) => eprintln!("Error inserting row: {}", e),
-//         };
-//     }
-// }
-
-// // Implement later
-// impl Author {
-//     pub fn new(master_key: String) -> Author {
-//         let author_id = Uuid::new_v4().to_string();
-//         Author {
-//             cite_key: master_key,
-//             author_id,
-//             authors: String::new(),
-//         }
-//     }
-// }
-//
-// impl TableInsert for Author {
-//     fn insert(&self) {
-//         let db = Arc::new(SqlitePool::connect(DB_URL).await.unwrap());
-//         let result = sqlx::query("INSERT INTO author (cite_key, author_id, authors) VALUES (?,?,?,)")
-//             .bind(&self.cite_key)
-//             .bind(&self.author_id)
-//             .bind(&self.authors)
-//             .execute(&*db)
-//             .await;
-//
-//         match result {
-//             Ok(rs) => eprintln!("Row inserted: {:?}", rs),
-//             Err(e) => eprintln!("Error inserting row: {}", e),
-//         };
-//     }
-// }
-// // Implement later
-// impl Organizations {
-//     pub fn new() -> Organizations {
-//         let organization_id = Uuid::new_v4().to_string();
-//         Organizations {
-//             organization_id,
-//             organization: String::new(),
-//             address: String::new(),
-//         }
-//     }
-// }
-//
-// impl TableInsert for Organizations {
-//     fn insert(&self) {
-//         let db = Arc::new(SqlitePool::connect(DB_URL).await.unwrap());
-//         let result = sqlx::query("INSERT INTO organizations (organization_id, organization, address) VALUES (?,?,?,)")
-//             .bind(&self.organization_id)
-//             .bind(&self.organization)
-//             .bind(&self.address)
-//             .execute(&*db)
-//             .await;
-//
-//         match result {
-//             Ok(rs) => eprintln!("Row inserted: {:?}", rs),
-//             Err(e) => eprintln!("Error inserting row: {}", e),
-//         };
-//     }
-// }
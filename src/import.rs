@@ -0,0 +1,259 @@
+use crate::db::{
+    read_doi, set_doi, Article, Book, Catalog, DbError, DbResult, EntryType, MonthYear, Record,
+    RowSelect, ARTICLE_FIELDS, BOOK_FIELDS,
+};
+use sqlite::Value;
+use std::fmt;
+
+/// Records read and saved together by `import_stream`, so a large file is never all in memory
+pub(crate) const IMPORT_BATCH: usize = 500;
+
+/// A record read from a file, as the lines of the form for its type
+#[derive(Clone, Debug, PartialEq)]
+pub struct ImportRecord {
+    /// Where the record came from, e.g. ``entry `knuth1968` on line 12``
+    pub(crate) source: String,
+    pub(crate) entry_type: EntryType,
+    /// Lines of the form for `entry_type`, as they would be typed in the TUI
+    pub(crate) lines: Vec<String>,
+    /// Kept in `master_entries.doi`, which has no line in the form
+    pub(crate) doi: String,
+}
+
+impl ImportRecord {
+    /// An empty form for `entry_type`, to be filled in with `set`
+    pub fn new(source: String, entry_type: EntryType) -> ImportRecord {
+        let lines = vec![String::new(); form_labels(entry_type).len()];
+        ImportRecord {
+            source,
+            entry_type,
+            lines,
+            doi: String::new(),
+        }
+    }
+
+    /// Fill in the form line labelled `label`; labels the form does not have are ignored
+    pub fn set(&mut self, label: &str, value: &str) {
+        if let Some(index) = form_labels(self.entry_type)
+            .iter()
+            .position(|form_label| *form_label == label)
+        {
+            self.lines[index] = value.trim().to_string();
+        }
+    }
+
+    /// The form line labelled `label`, empty if the form does not have it
+    pub fn get(&self, label: &str) -> &str {
+        form_labels(self.entry_type)
+            .iter()
+            .position(|form_label| *form_label == label)
+            .map_or("", |index| self.lines[index].as_str())
+    }
+}
+
+/// Labels of the lines of the form for `entry_type`, in order
+pub fn form_labels(entry_type: EntryType) -> Vec<&'static str> {
+    match entry_type {
+        EntryType::Book => BOOK_FIELDS.to_vec(),
+        EntryType::Article => ARTICLE_FIELDS.to_vec(),
+        other => other
+            .item_type()
+            .map(|item_type| item_type.form_labels())
+            .unwrap_or_default(),
+    }
+}
+
+/// What an import did, or would do in a dry run, with each record by its source
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ImportReport {
+    pub(crate) dry_run: bool,
+    /// Source and new `cite_key` of each record saved
+    pub(crate) imported: Vec<(String, String)>,
+    /// Source and `cite_key` of the record already in the catalog
+    pub(crate) duplicates: Vec<(String, String)>,
+    /// Source and why the record was not saved
+    pub(crate) skipped: Vec<(String, String)>,
+    /// Nothing was saved because a record was skipped, see `csv::import_table`
+    pub(crate) rolled_back: bool,
+}
+
+impl ImportReport {
+    pub fn skip(&mut self, source: String, reason: String) {
+        self.skipped.push((source, reason));
+    }
+
+    pub fn imported(&self) -> usize {
+        self.imported.len()
+    }
+
+    /// Add what a later batch of the same import did
+    pub(crate) fn append(&mut self, other: ImportReport) {
+        self.imported.extend(other.imported);
+        self.duplicates.extend(other.duplicates);
+        self.skipped.extend(other.skipped);
+    }
+}
+
+impl fmt::Display for ImportReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (source, cite_key) in &self.duplicates {
+            writeln!(
+                f,
+                "duplicate {source}: already in the catalog as {cite_key}"
+            )?;
+        }
+        for (source, reason) in &self.skipped {
+            writeln!(f, "skipped {source}: {reason}")?;
+        }
+        if self.rolled_back {
+            writeln!(
+                f,
+                "rolled back: nothing was saved because {} records were skipped",
+                self.skipped.len()
+            )?;
+        }
+        let verb = match self.dry_run || self.rolled_back {
+            true => "would import",
+            false => "imported",
+        };
+        write!(
+            f,
+            "{verb} {}, {} duplicates, {} skipped",
+            self.imported.len(),
+            self.duplicates.len(),
+            self.skipped.len()
+        )
+    }
+}
+
+/// `cite_key` of a record of the same type with the same title, ignoring case, and the same year
+fn find_duplicate(catalog: &Catalog, record: &ImportRecord) -> DbResult<Option<String>> {
+    let year = MonthYear::parse(record.get("Date"))
+        .ok()
+        .map(|date| date.year);
+    let query = "SELECT c.cite_key FROM catalog_records c
+        LEFT JOIN month_year y ON y.month_year_id = c.month_year_id
+        WHERE c.entry_type = ? AND lower(trim(c.title)) = lower(trim(?)) AND y.year IS ?
+        LIMIT 1";
+    catalog.with_statement(query, |statement| {
+        statement.bind_iter::<_, (_, Value)>([
+            (1, record.entry_type.as_str().into()),
+            (2, record.get("Title").into()),
+            (3, year.map_or(Value::Null, Value::Integer)),
+        ])?;
+        match statement.next()? {
+            sqlite::State::Row => Ok(Some(statement.read::<String, _>(0)?)),
+            sqlite::State::Done => Ok(None),
+        }
+    })
+}
+
+/// Save `record` the way its form is saved in the TUI, then its DOI; returns the new `cite_key`
+fn save(catalog: &Catalog, record: &ImportRecord) -> DbResult<String> {
+    let lines = record.lines.clone();
+    let cite_key = match record.entry_type {
+        EntryType::Book => Book::book_transaction(catalog, lines),
+        EntryType::Article => Article::article_transaction(catalog, lines),
+        other => other
+            .item_type()
+            .ok_or_else(|| DbError::InvalidInput(format!("cannot save a record of type {other}")))?
+            .item_transaction(catalog, lines),
+    }?;
+    if !record.doi.is_empty() {
+        set_doi(catalog, &cite_key, &record.doi)?;
+    }
+    Ok(cite_key)
+}
+
+/// Save every record that is not already in the catalog, in one transaction. A record that
+/// cannot be saved, or that repeats one saved before, is reported and the rest are saved; a
+/// database error rolls the whole import back. A dry run checks everything the same way and
+/// then rolls back.
+pub fn import_records(
+    catalog: &Catalog,
+    records: &[ImportRecord],
+    dry_run: bool,
+) -> DbResult<ImportReport> {
+    let import = |catalog: &Catalog| {
+        let mut report = ImportReport {
+            dry_run,
+            ..ImportReport::default()
+        };
+        for record in records {
+            if let Some(cite_key) = find_duplicate(catalog, record)? {
+                report.duplicates.push((record.source.clone(), cite_key));
+                continue;
+            }
+            match save(catalog, record) {
+                Ok(cite_key) => report.imported.push((record.source.clone(), cite_key)),
+                Err(DbError::InvalidInput(reason) | DbError::Constraint(reason)) => {
+                    report.skip(record.source.clone(), reason)
+                }
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(report)
+    };
+    match dry_run {
+        true => catalog.dry_run(import),
+        false => catalog.transaction(import),
+    }
+}
+
+/// Save records as they are read, `IMPORT_BATCH` at a time, in one transaction, as
+/// `import_records` does. A record that could not be read comes as its source and the reason.
+pub fn import_stream<I>(catalog: &Catalog, records: I, dry_run: bool) -> DbResult<ImportReport>
+where
+    I: IntoIterator<Item = Result<ImportRecord, (String, String)>>,
+{
+    let import = |catalog: &Catalog| {
+        let mut report = ImportReport {
+            dry_run,
+            ..ImportReport::default()
+        };
+        let mut batch = Vec::new();
+        let mut records = records.into_iter().peekable();
+        while let Some(record) = records.next() {
+            let unread = match record {
+                Ok(record) => {
+                    batch.push(record);
+                    None
+                }
+                Err(skipped) => Some(skipped),
+            };
+            // the records read before one that could not be are saved first, so the report
+            // follows the input
+            if batch.len() == IMPORT_BATCH || unread.is_some() || records.peek().is_none() {
+                report.append(import_records(catalog, &batch, false)?);
+                batch.clear();
+            }
+            if let Some((source, reason)) = unread {
+                report.skip(source, reason);
+            }
+        }
+        Ok(report)
+    };
+    match dry_run {
+        true => catalog.dry_run(import),
+        false => catalog.transaction(import),
+    }
+}
+
+/// The form of a book or article as it is now in the catalog, with its `cite_key` as source;
+/// `None` for other item types
+pub(crate) fn record_form(catalog: &Catalog, record: &Record) -> DbResult<Option<ImportRecord>> {
+    let (entry_type, lines) = match record {
+        Record::Book(book) => (EntryType::Book, Book::select(catalog, &book.cite_key)?),
+        Record::Article(article) => (
+            EntryType::Article,
+            Article::select(catalog, &article.cite_key)?,
+        ),
+        Record::Item(_) => return Ok(None),
+    };
+    Ok(Some(ImportRecord {
+        source: record.cite_key().to_string(),
+        entry_type,
+        lines,
+        doi: read_doi(catalog, record.cite_key())?,
+    }))
+}
@@ -0,0 +1,508 @@
+use crate::author::{
+    format_authors, parse_authors, read_author_table, replace_authors, select_authors, Author,
+};
+use crate::db::{
+    form_fields, linked_ids, named_publisher_id, optional_date, optional_id, read_date, read_text,
+    remove_unused_links, save_date, Catalog, DbError, DbResult, EntryType, MasterEntries,
+    MonthYear, RowDelete, RowUpdate, TableInsert,
+};
+use crate::organization::{
+    format_organizations, parse_organizations, read_record_organization_table,
+    replace_organizations, select_record_organizations, RecordOrganization,
+};
+use crate::search::index_record;
+use sqlite::{State, Statement, Value};
+
+/// Labels of the lines every item form starts with; the item type's own fields follow
+pub const ITEM_COMMON_FIELDS: [&str; 6] = [
+    "Authors",
+    "Title",
+    "Date",
+    "Publisher",
+    "Note",
+    "Organizations",
+];
+
+/// Line of the Publisher field in every item form
+pub const ITEM_PUBLISHER_LINE: usize = 3;
+
+/// A column of an item type's own table, with its form label
+#[derive(Debug, PartialEq)]
+pub struct ItemField {
+    pub label: &'static str,
+    pub column: &'static str,
+    pub required: bool,
+}
+
+const fn field(label: &'static str, column: &'static str, required: bool) -> ItemField {
+    ItemField {
+        label,
+        column,
+        required,
+    }
+}
+
+/// A kind of record other than book and article. Each has its own table holding the columns every
+/// item shares (`cite_key`, `title`, `publisher_id`, `month_year_id`, `note`) and its own `fields`.
+/// Adding a type takes an entry in `ITEM_TYPES` and a migration creating its table and adding it
+/// to the `item_records` view.
+#[derive(Debug, PartialEq)]
+pub struct ItemType {
+    pub entry_type: EntryType,
+    pub table: &'static str,
+    pub fields: &'static [ItemField],
+}
+
+pub static ITEM_TYPES: [ItemType; 9] = [
+    ItemType {
+        entry_type: EntryType::Thesis,
+        table: "thesis",
+        fields: &[
+            field("Degree", "degree", true),
+            field("Pages", "pages", false),
+        ],
+    },
+    ItemType {
+        entry_type: EntryType::ConferencePaper,
+        table: "conference_paper",
+        fields: &[
+            field("Proceedings", "proceedings", true),
+            field("Location", "location", false),
+            field("Pages", "pages", false),
+        ],
+    },
+    ItemType {
+        entry_type: EntryType::BookChapter,
+        table: "book_chapter",
+        fields: &[
+            field("Book title", "book_title", true),
+            field("Chapter", "chapter", false),
+            field("Pages", "pages", false),
+        ],
+    },
+    ItemType {
+        entry_type: EntryType::Report,
+        table: "report",
+        fields: &[
+            field("Report type", "report_type", false),
+            field("Number", "number", false),
+            field("Pages", "pages", false),
+        ],
+    },
+    ItemType {
+        entry_type: EntryType::Map,
+        table: "map",
+        fields: &[
+            field("Scale", "scale", false),
+            field("Projection", "projection", false),
+            field("Dimensions", "dimensions", false),
+        ],
+    },
+    ItemType {
+        entry_type: EntryType::AudioRecording,
+        table: "audio_recording",
+        fields: &[
+            field("Medium", "medium", false),
+            field("Duration", "duration", false),
+            field("Catalog number", "catalog_number", false),
+        ],
+    },
+    ItemType {
+        entry_type: EntryType::Video,
+        table: "video",
+        fields: &[
+            field("Medium", "medium", false),
+            field("Duration", "duration", false),
+        ],
+    },
+    ItemType {
+        entry_type: EntryType::WebResource,
+        table: "web_resource",
+        fields: &[
+            field("URL", "url", true),
+            field("Site name", "site_name", false),
+            field("Accessed", "accessed", false),
+        ],
+    },
+    ItemType {
+        entry_type: EntryType::Software,
+        table: "software",
+        fields: &[
+            field("Version", "version", false),
+            field("Platform", "platform", false),
+            field("URL", "url", false),
+        ],
+    },
+];
+
+/// A record of one of the `ITEM_TYPES`
+#[derive(Clone, Debug)]
+pub struct Item {
+    pub(crate) cite_key: String,
+    pub(crate) item_type: &'static ItemType,
+    /// Empty when no publisher was entered
+    pub(crate) publisher_id: String,
+    /// Empty when no date was entered
+    pub(crate) month_year_id: String,
+    /// Rows of the `author` table for this record, in order
+    pub(crate) authors: Vec<Author>,
+    pub(crate) title: String,
+    /// Publication date from the `month_year` table, formatted by `MonthYear`
+    pub(crate) year: String,
+    pub(crate) publisher: String,
+    pub(crate) note: String,
+    /// Rows of the `record_organization` table for this record, in order
+    pub(crate) organizations: Vec<RecordOrganization>,
+    /// Values of the item type's own `fields`, in order
+    pub(crate) fields: Vec<String>,
+}
+
+impl Item {
+    /// Lines of the item form, in the order of `ItemType::form_labels`
+    pub fn form_lines(&self) -> Vec<String> {
+        let mut lines = vec![
+            format_authors(&self.authors),
+            self.title.clone(),
+            self.year.clone(),
+            self.publisher.clone(),
+            self.note.clone(),
+            format_organizations(&self.organizations),
+        ];
+        lines.extend(self.fields.iter().cloned());
+        lines
+    }
+}
+
+impl ItemType {
+    /// Labels of the lines in this type's form, in order
+    pub fn form_labels(&self) -> Vec<&'static str> {
+        ITEM_COMMON_FIELDS
+            .iter()
+            .copied()
+            .chain(self.fields.iter().map(|field| field.label))
+            .collect()
+    }
+
+    /// Lines of the form that must be filled in: the title and the type's required fields
+    fn required_lines(&self) -> Vec<usize> {
+        let own = self
+            .fields
+            .iter()
+            .enumerate()
+            .filter(|(_, field)| field.required)
+            .map(|(index, _)| ITEM_COMMON_FIELDS.len() + index);
+        std::iter::once(1).chain(own).collect()
+    }
+
+    /// Read the form lines into an item; the date is parsed but not written anywhere yet
+    fn parse_form(
+        &'static self,
+        textarea: Vec<String>,
+        cite_key: &str,
+    ) -> DbResult<(Item, Option<MonthYear>)> {
+        let textarea = form_fields(textarea, &self.form_labels(), &self.required_lines())?;
+        let date = optional_date(&textarea[2])?;
+        let item = Item {
+            cite_key: cite_key.to_string(),
+            item_type: self,
+            publisher_id: String::new(),
+            month_year_id: String::new(),
+            authors: parse_authors(cite_key, &textarea[0])?,
+            title: textarea[1].trim().to_string(),
+            year: date.as_ref().map(ToString::to_string).unwrap_or_default(),
+            publisher: textarea[3].trim().to_string(),
+            note: textarea[4].clone(),
+            organizations: parse_organizations(cite_key, &textarea[5])?,
+            fields: textarea[ITEM_COMMON_FIELDS.len()..].to_vec(),
+        };
+        Ok((item, date))
+    }
+
+    /// Create and add an item of this type from its form in one transaction; returns the new
+    /// `cite_key`
+    pub fn item_transaction(
+        &'static self,
+        catalog: &Catalog,
+        textarea: Vec<String>,
+    ) -> DbResult<String> {
+        let master = MasterEntries::new(self.entry_type);
+        let (mut item, date) = self.parse_form(textarea, &master.cite_key)?;
+        catalog.transaction(|catalog| {
+            master.insert(catalog)?;
+            item.publisher_id = named_publisher_id(catalog, &item.publisher)?;
+            if let Some(date) = &date {
+                date.insert(catalog)?;
+                item.month_year_id = date.month_year_id.clone();
+            }
+            item.insert(catalog)?;
+            replace_authors(catalog, &item.cite_key, &item.authors)?;
+            replace_organizations(catalog, &item.cite_key, &item.organizations)?;
+            index_record(catalog, &item.cite_key)?;
+            Ok(master.cite_key.clone())
+        })
+    }
+
+    /// Update the item with `cite_key` from its form
+    pub fn item_update(
+        &'static self,
+        catalog: &Catalog,
+        textarea: Vec<String>,
+        cite_key: String,
+    ) -> DbResult<()> {
+        let (mut item, date) = self.parse_form(textarea, &cite_key)?;
+        let query = format!(
+            "SELECT publisher_id, month_year_id FROM {} WHERE cite_key = ?",
+            self.table
+        );
+        catalog.transaction(|catalog| {
+            let (old_publisher_id, old_month_year_id) = linked_ids(catalog, &query, &cite_key)?;
+            item.publisher_id = named_publisher_id(catalog, &item.publisher)?;
+            item.month_year_id = save_date(catalog, date, &old_month_year_id)?;
+            item.update(catalog, cite_key.clone())?;
+            replace_authors(catalog, &cite_key, &item.authors)?;
+            replace_organizations(catalog, &cite_key, &item.organizations)?;
+            index_record(catalog, &cite_key)?;
+            remove_unused_links(catalog, &old_publisher_id, &old_month_year_id)
+        })
+    }
+
+    /// Remove the item with `cite_key` and its `master_entries` row in one transaction, along
+    /// with its `publisher` and `month_year` rows once no other record uses them
+    pub fn delete_item(&self, catalog: &Catalog, cite_key: String) -> DbResult<()> {
+        let query = format!(
+            "SELECT publisher_id, month_year_id FROM {} WHERE cite_key = ?",
+            self.table
+        );
+        catalog.transaction(|catalog| {
+            let (publisher_id, month_year_id) = linked_ids(catalog, &query, &cite_key)?;
+            // the item row goes with its master entry
+            MasterEntries::delete(catalog, cite_key.clone())?;
+            remove_unused_links(catalog, &publisher_id, &month_year_id)
+        })
+    }
+
+    /// Lines of the form for the item with `cite_key`
+    pub fn select(&'static self, catalog: &Catalog, cite_key: &str) -> DbResult<Vec<String>> {
+        self.read_items_with_keys(catalog, &[cite_key])?
+            .into_iter()
+            .next()
+            .map(|item| item.form_lines())
+            .ok_or_else(|| DbError::NotFound(cite_key.to_string()))
+    }
+
+    /// Every column of this type's table, with the item's publisher and date
+    fn item_query(&self) -> String {
+        format!(
+            "SELECT t.*, p.publisher, m.year, m.month, m.day FROM {} t LEFT JOIN publisher p ON p.publisher_id = t.publisher_id LEFT JOIN month_year m ON m.month_year_id = t.month_year_id",
+            self.table
+        )
+    }
+
+    fn read_item(
+        &'static self,
+        statement: &Statement,
+        authors: Vec<Author>,
+        organizations: Vec<RecordOrganization>,
+    ) -> DbResult<Item> {
+        Ok(Item {
+            cite_key: read_text(statement, "cite_key")?,
+            authors,
+            organizations,
+            item_type: self,
+            publisher_id: read_text(statement, "publisher_id")?,
+            month_year_id: read_text(statement, "month_year_id")?,
+            title: read_text(statement, "title")?,
+            year: read_date(statement)?,
+            publisher: read_text(statement, "publisher")?,
+            note: read_text(statement, "note")?,
+            fields: self
+                .fields
+                .iter()
+                .map(|field| read_text(statement, field.column))
+                .collect::<DbResult<_>>()?,
+        })
+    }
+
+    /// Every item of this type, in the order they were added
+    pub fn read_items(&'static self, catalog: &Catalog) -> DbResult<Vec<Item>> {
+        let query = format!("{} ORDER BY t.rowid", self.item_query());
+        let mut authors = read_author_table(catalog)?;
+        let mut organizations = read_record_organization_table(catalog)?;
+        catalog.with_statement(&query, |statement| {
+            let mut parsed = Vec::new();
+            while let State::Row = statement.next()? {
+                let cite_key = read_text(statement, "cite_key")?;
+                parsed.push(self.read_item(
+                    statement,
+                    authors.remove(&cite_key).unwrap_or_default(),
+                    organizations.remove(&cite_key).unwrap_or_default(),
+                )?);
+            }
+            Ok(parsed)
+        })
+    }
+
+    /// The items of this type with `cite_keys`, in that order, skipping keys of other records
+    pub fn read_items_with_keys(
+        &'static self,
+        catalog: &Catalog,
+        cite_keys: &[&str],
+    ) -> DbResult<Vec<Item>> {
+        let query = format!("{} WHERE t.cite_key = ?", self.item_query());
+        let mut items = Vec::new();
+        for &cite_key in cite_keys {
+            let item = catalog.with_statement(&query, |statement| {
+                statement.bind((1, cite_key))?;
+                match statement.next()? {
+                    State::Row => Ok(Some(self.read_item(statement, Vec::new(), Vec::new())?)),
+                    State::Done => Ok(None),
+                }
+            })?;
+            if let Some(mut item) = item {
+                item.authors = select_authors(catalog, cite_key)?;
+                item.organizations = select_record_organizations(catalog, cite_key)?;
+                items.push(item);
+            }
+        }
+        Ok(items)
+    }
+}
+
+impl TableInsert for Item {
+    fn insert(&self, catalog: &Catalog) -> DbResult<State> {
+        let columns: Vec<&str> = self.item_type.fields.iter().map(|f| f.column).collect();
+        let query = format!(
+            "INSERT INTO {} (cite_key, title, publisher_id, month_year_id, note{}) VALUES (?, ?, ?, ?, ?{})",
+            self.item_type.table,
+            columns.iter().map(|c| format!(", {c}")).collect::<String>(),
+            ", ?".repeat(columns.len()),
+        );
+        catalog.with_statement(&query, |statement| {
+            let mut values: Vec<Value> = vec![
+                self.cite_key.clone().into(),
+                self.title.clone().into(),
+                optional_id(&self.publisher_id),
+                optional_id(&self.month_year_id),
+                self.note.clone().into(),
+            ];
+            values.extend(self.fields.iter().map(|value| value.clone().into()));
+            statement.bind_iter(values.into_iter().enumerate().map(|(i, v)| (i + 1, v)))?;
+            Ok(statement.next()?)
+        })
+    }
+}
+
+impl RowUpdate for Item {
+    fn update(&self, catalog: &Catalog, item_id: String) -> DbResult<State> {
+        let query = format!(
+            "UPDATE {} SET title = ?, publisher_id = ?, month_year_id = ?, note = ?{} WHERE cite_key = ?",
+            self.item_type.table,
+            self.item_type
+                .fields
+                .iter()
+                .map(|field| format!(", {} = ?", field.column))
+                .collect::<String>(),
+        );
+        catalog.with_statement(&query, |statement| {
+            let mut values: Vec<Value> = vec![
+                self.title.clone().into(),
+                optional_id(&self.publisher_id),
+                optional_id(&self.month_year_id),
+                self.note.clone().into(),
+            ];
+            values.extend(self.fields.iter().map(|value| value.clone().into()));
+            values.push(item_id.clone().into());
+            statement.bind_iter(values.into_iter().enumerate().map(|(i, v)| (i + 1, v)))?;
+            let state = statement.next()?;
+            catalog.expect_changed(&item_id)?;
+            Ok(state)
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::db::{read_sqlite_publisher_table, TestDatabase};
+    use serial_test::serial;
+
+    #[test]
+    fn item_types_are_distinct() {
+        for (index, item_type) in ITEM_TYPES.iter().enumerate() {
+            assert_eq!(item_type.entry_type.item_type(), Some(item_type));
+            assert!(ITEM_TYPES[index + 1..]
+                .iter()
+                .all(|other| other.table != item_type.table
+                    && other.entry_type != item_type.entry_type));
+        }
+        assert_eq!(EntryType::Book.item_type(), None);
+    }
+
+    #[test]
+    #[serial]
+    fn add_update_and_delete_items() {
+        let database = TestDatabase::new();
+        let catalog = database.catalog();
+
+        // every type can be added with just its required lines
+        for item_type in &ITEM_TYPES {
+            let mut lines = vec![String::new(); item_type.form_labels().len()];
+            lines[1] = format!("A {}", item_type.entry_type.name());
+            for index in item_type.required_lines() {
+                if lines[index].is_empty() {
+                    lines[index] = "required".to_string();
+                }
+            }
+            item_type.item_transaction(&catalog, lines).unwrap();
+            assert_eq!(item_type.read_items(&catalog).unwrap().len(), 1);
+        }
+
+        let thesis = EntryType::Thesis.item_type().unwrap();
+        let lines = vec![
+            "Knuth, Donald".to_string(),
+            "Finite Semifields and Projective Planes".to_string(),
+            "1963".to_string(),
+            "Caltech".to_string(),
+            "".to_string(),
+            "California Institute of Technology (degree-granting)".to_string(),
+            "PhD".to_string(),
+            "".to_string(),
+        ];
+        let cite_key = thesis.item_transaction(&catalog, lines.clone()).unwrap();
+        let mut selected = thesis.select(&catalog, &cite_key).unwrap();
+        assert_eq!(selected, lines);
+
+        // the date and publisher can be cleared, and their rows go once unused
+        selected[2] = String::new();
+        selected[3] = String::new();
+        selected[7] = "xii, 120".to_string();
+        thesis
+            .item_update(&catalog, selected.clone(), cite_key.clone())
+            .unwrap();
+        let item = thesis
+            .read_items(&catalog)
+            .unwrap()
+            .into_iter()
+            .find(|item| item.cite_key == cite_key)
+            .unwrap();
+        assert_eq!(item.month_year_id, "");
+        assert_eq!(item.fields, vec!["PhD", "xii, 120"]);
+        assert!(read_sqlite_publisher_table(&catalog)
+            .unwrap()
+            .iter()
+            .all(|(publisher, _)| publisher.publisher != "Caltech"));
+
+        // a required field of the type must be filled in
+        selected[6] = String::new();
+        assert!(matches!(
+            thesis.item_update(&catalog, selected, cite_key.clone()),
+            Err(DbError::InvalidInput(_))
+        ));
+
+        thesis.delete_item(&catalog, cite_key.clone()).unwrap();
+        assert!(matches!(
+            thesis.select(&catalog, &cite_key),
+            Err(DbError::NotFound(_))
+        ));
+    }
+}
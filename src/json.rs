@@ -0,0 +1,352 @@
+use std::fmt;
+
+/// A JSON value. Object members keep the order they were read or pushed in.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    /// The member `key` of an object
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(members) => members
+                .iter()
+                .find(|(name, _)| name == key)
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    /// Elements of an array; none for any other value
+    pub fn elements(&self) -> &[Json] {
+        match self {
+            Json::Array(elements) => elements,
+            _ => &[],
+        }
+    }
+
+    /// A string, or a number written as text; empty for any other value. Numeric variables
+    /// such as `volume` may be either.
+    pub fn text(&self) -> String {
+        match self {
+            Json::String(text) => text.trim().to_string(),
+            Json::Number(number) => format_number(*number),
+            _ => String::new(),
+        }
+    }
+
+    /// Text of the member `key`, empty if there is none
+    pub fn text_of(&self, key: &str) -> String {
+        self.get(key).map(Json::text).unwrap_or_default()
+    }
+
+    /// Read a JSON document
+    pub fn parse(text: &str) -> Result<Json, String> {
+        let mut parser = Parser {
+            text: text.trim_start_matches('\u{feff}'),
+            position: 0,
+        };
+        let value = parser.value()?;
+        parser.skip_space();
+        match parser.peek() {
+            None => Ok(value),
+            Some(_) => Err(parser.error("text after the end of the document")),
+        }
+    }
+
+    fn write(&self, f: &mut fmt::Formatter, depth: usize) -> fmt::Result {
+        let indent = "  ".repeat(depth + 1);
+        match self {
+            Json::Null => f.write_str("null"),
+            Json::Bool(value) => write!(f, "{value}"),
+            Json::Number(number) => f.write_str(&format_number(*number)),
+            Json::String(text) => write_string(f, text),
+            Json::Array(elements) if elements.is_empty() => f.write_str("[]"),
+            Json::Object(members) if members.is_empty() => f.write_str("{}"),
+            Json::Array(elements) => {
+                f.write_str("[")?;
+                for (index, element) in elements.iter().enumerate() {
+                    let separator = if index == 0 { "" } else { "," };
+                    write!(f, "{separator}\n{indent}")?;
+                    element.write(f, depth + 1)?;
+                }
+                write!(f, "\n{}]", "  ".repeat(depth))
+            }
+            Json::Object(members) => {
+                f.write_str("{")?;
+                for (index, (name, value)) in members.iter().enumerate() {
+                    let separator = if index == 0 { "" } else { "," };
+                    write!(f, "{separator}\n{indent}")?;
+                    write_string(f, name)?;
+                    f.write_str(": ")?;
+                    value.write(f, depth + 1)?;
+                }
+                write!(f, "\n{}}}", "  ".repeat(depth))
+            }
+        }
+    }
+}
+
+/// Pretty-printed with two-space indents, as `JSON.stringify(value, null, 2)` writes it
+impl fmt::Display for Json {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.write(f, 0)
+    }
+}
+
+/// A whole number without a fraction, e.g. `1968` rather than `1968.0`
+fn format_number(number: f64) -> String {
+    if number.fract() == 0.0 && number.abs() < 1e15 {
+        format!("{}", number as i64)
+    } else {
+        format!("{number}")
+    }
+}
+
+fn write_string(f: &mut fmt::Formatter, text: &str) -> fmt::Result {
+    f.write_str("\"")?;
+    for c in text.chars() {
+        match c {
+            '"' => f.write_str("\\\"")?,
+            '\\' => f.write_str("\\\\")?,
+            '\n' => f.write_str("\\n")?,
+            '\r' => f.write_str("\\r")?,
+            '\t' => f.write_str("\\t")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{c}")?,
+        }
+    }
+    f.write_str("\"")
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    position: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<char> {
+        self.text[self.position..].chars().next()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.position += c.len_utf8();
+        Some(c)
+    }
+
+    fn skip_space(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.next();
+        }
+    }
+
+    /// `message` with the line it applies to
+    fn error(&self, message: &str) -> String {
+        let line = self.text[..self.position].matches('\n').count() + 1;
+        format!("line {line}: {message}")
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), String> {
+        self.skip_space();
+        match self.next() {
+            Some(c) if c == expected => Ok(()),
+            Some(c) => Err(self.error(&format!("expected `{expected}`, found `{c}`"))),
+            None => Err(self.error(&format!("expected `{expected}` at the end of the file"))),
+        }
+    }
+
+    fn value(&mut self) -> Result<Json, String> {
+        self.skip_space();
+        match self.peek() {
+            Some('{') => self.object(),
+            Some('[') => self.array(),
+            Some('"') => Ok(Json::String(self.string()?)),
+            Some('-' | '0'..='9') => self.number(),
+            Some(_) => {
+                let rest = &self.text[self.position..];
+                for (word, value) in [
+                    ("null", Json::Null),
+                    ("true", Json::Bool(true)),
+                    ("false", Json::Bool(false)),
+                ] {
+                    if rest.starts_with(word) {
+                        self.position += word.len();
+                        return Ok(value);
+                    }
+                }
+                Err(self.error("expected a value"))
+            }
+            None => Err(self.error("expected a value at the end of the file")),
+        }
+    }
+
+    fn object(&mut self) -> Result<Json, String> {
+        self.expect('{')?;
+        let mut members = Vec::new();
+        self.skip_space();
+        if self.peek() == Some('}') {
+            self.next();
+            return Ok(Json::Object(members));
+        }
+        loop {
+            self.skip_space();
+            if self.peek() != Some('"') {
+                return Err(self.error("expected a member name in quotes"));
+            }
+            let name = self.string()?;
+            self.expect(':')?;
+            members.push((name, self.value()?));
+            self.skip_space();
+            match self.next() {
+                Some(',') => continue,
+                Some('}') => return Ok(Json::Object(members)),
+                _ => return Err(self.error("expected `,` or `}` after a member")),
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<Json, String> {
+        self.expect('[')?;
+        let mut elements = Vec::new();
+        self.skip_space();
+        if self.peek() == Some(']') {
+            self.next();
+            return Ok(Json::Array(elements));
+        }
+        loop {
+            elements.push(self.value()?);
+            self.skip_space();
+            match self.next() {
+                Some(',') => continue,
+                Some(']') => return Ok(Json::Array(elements)),
+                _ => return Err(self.error("expected `,` or `]` after an element")),
+            }
+        }
+    }
+
+    fn number(&mut self) -> Result<Json, String> {
+        let start = self.position;
+        while self
+            .peek()
+            .is_some_and(|c| c.is_ascii_digit() || "+-.eE".contains(c))
+        {
+            self.next();
+        }
+        let text = &self.text[start..self.position];
+        text.parse()
+            .map(Json::Number)
+            .map_err(|_| self.error(&format!("`{text}` is not a number")))
+    }
+
+    /// Four hex digits of a `\u` escape
+    fn code_unit(&mut self) -> Result<u32, String> {
+        let digits = self
+            .text
+            .get(self.position..self.position + 4)
+            .unwrap_or("");
+        let unit = u32::from_str_radix(digits, 16)
+            .map_err(|_| self.error(&format!("`\\u{digits}` is not a character")))?;
+        self.position += 4;
+        Ok(unit)
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        self.expect('"')?;
+        let mut text = String::new();
+        loop {
+            match self.next() {
+                Some('"') => return Ok(text),
+                Some('\\') => {
+                    let c = match self.next() {
+                        Some('n') => '\n',
+                        Some('t') => '\t',
+                        Some('r') => '\r',
+                        Some('b') => '\u{8}',
+                        Some('f') => '\u{c}',
+                        Some('u') => {
+                            let mut unit = self.code_unit()?;
+                            // a character outside the Basic Multilingual Plane is a surrogate
+                            // pair; a high surrogate without a low one is left as U+FFFD and the
+                            // escape after it is read on its own
+                            if (0xd800..0xdc00).contains(&unit)
+                                && self.text[self.position..].starts_with("\\u")
+                            {
+                                let after_high = self.position;
+                                self.position += 2;
+                                let low = self.code_unit()?;
+                                match low {
+                                    0xdc00..=0xdfff => {
+                                        unit = 0x10000 + ((unit - 0xd800) << 10) + (low - 0xdc00)
+                                    }
+                                    _ => self.position = after_high,
+                                }
+                            }
+                            char::from_u32(unit).unwrap_or(char::REPLACEMENT_CHARACTER)
+                        }
+                        Some(c @ ('"' | '\\' | '/')) => c,
+                        _ => return Err(self.error("unknown escape in a string")),
+                    };
+                    text.push(c);
+                }
+                Some(c) => text.push(c),
+                None => return Err(self.error("string is not closed")),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn reads_and_writes_json() {
+        let text = "\u{feff}[{\"id\": \"knuth1968\", \"issued\": {\"date-parts\": [[1968, \"3\"]]},
+            \"title\": \"G\\u00f6del \\\"&\\\" \\ud83d\\ude00\\n\", \"volume\": 1.5, \"empty\": {},
+            \"flags\": [true, false, null, -2e3]}]";
+        let value = Json::parse(text).unwrap();
+        let item = &value.elements()[0];
+        assert_eq!(item.text_of("id"), "knuth1968");
+        let parts = item.get("issued").unwrap().get("date-parts").unwrap();
+        let parts: Vec<String> = parts.elements()[0]
+            .elements()
+            .iter()
+            .map(Json::text)
+            .collect();
+        assert_eq!(parts, vec!["1968", "3"]);
+        assert_eq!(
+            item.get("title"),
+            Some(&Json::String("Gödel \"&\" 😀\n".to_string()))
+        );
+        assert_eq!(item.text_of("volume"), "1.5");
+        assert_eq!(item.text_of("missing"), "");
+
+        let written = value.to_string();
+        assert!(written.starts_with("[\n  {\n    \"id\": \"knuth1968\",\n"));
+        assert!(written.contains("\"title\": \"Gödel \\\"&\\\" 😀\\n\""));
+        assert!(written.contains("\"empty\": {},"));
+        assert!(written.contains("      -2000\n"));
+        assert_eq!(Json::parse(&written).unwrap(), value);
+
+        assert_eq!(
+            Json::parse("[\n{\"id\": 1,}\n]").unwrap_err(),
+            "line 2: expected a member name in quotes"
+        );
+        assert_eq!(
+            Json::parse("{\"id\": \"x\"} x").unwrap_err(),
+            "line 1: text after the end of the document"
+        );
+        assert!(Json::parse("[\"unclosed]").is_err());
+        assert_eq!(
+            Json::parse("\"\\ud800\\u0041\"").unwrap(),
+            Json::String("\u{fffd}A".to_string())
+        );
+    }
+}
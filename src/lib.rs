@@ -0,0 +1,24 @@
+//! Library DB: a bibliographic catalog stored in `SQLite` with a terminal user interface
+pub mod app;
+pub mod author;
+pub mod bibtex;
+pub mod check;
+pub mod config;
+pub mod csl;
+pub mod csv;
+pub mod db;
+pub mod import;
+pub mod item;
+pub mod json;
+pub mod listing;
+pub mod marc;
+pub mod marcxml;
+pub mod migrations;
+pub mod mods;
+pub mod organization;
+pub mod query;
+pub mod relationship;
+pub mod ris;
+pub mod search;
+pub mod ui;
+pub mod xml;
@@ -0,0 +1,1059 @@
+use crate::db::{
+    form_fields, read_articles_with_keys, read_books_with_keys, read_catalog_entries_with_keys,
+    Article, Book, Catalog, CatalogEntry, DbError, DbResult, EntryType,
+};
+use crate::item::Item;
+use crate::query::{like_pattern, parse_type, parse_years};
+use crate::relationship::{select_links, Link};
+use sqlite::{State, Statement, Value};
+
+/// Labels of the lines in the filter form, in order
+pub const FILTER_FIELDS: [&str; 3] = ["Years", "Publisher", "Type"];
+
+/// What the records of a list screen are ordered by
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SortKey {
+    Added,
+    Title,
+    Author,
+    Year,
+    Publisher,
+}
+
+impl SortKey {
+    pub const ALL: [SortKey; 5] = [
+        SortKey::Added,
+        SortKey::Title,
+        SortKey::Author,
+        SortKey::Year,
+        SortKey::Publisher,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            SortKey::Added => "date added",
+            SortKey::Title => "title",
+            SortKey::Author => "author",
+            SortKey::Year => "year",
+            SortKey::Publisher => "publisher",
+        }
+    }
+
+    /// Key after this one in `ALL`, back to the first after the last
+    pub fn next(&self) -> SortKey {
+        let position = SortKey::ALL.iter().position(|key| key == self).unwrap_or(0);
+        SortKey::ALL[(position + 1) % SortKey::ALL.len()]
+    }
+
+    /// `ORDER BY` terms over `master_entries m`, `catalog_records c`, `month_year y` and `publisher p`
+    fn terms(&self) -> &'static [&'static str] {
+        match self {
+            SortKey::Added => &["m.added"],
+            SortKey::Title => &["lower(coalesce(c.title, ''))"],
+            SortKey::Author => &["lower(coalesce((SELECT a.family || ' ' || a.given FROM author a WHERE a.cite_key = m.cite_key ORDER BY a.position LIMIT 1), ''))"],
+            SortKey::Year => &[
+                "coalesce(y.year, -1)",
+                "coalesce(y.month, 0)",
+                "coalesce(y.day, 0)",
+            ],
+            SortKey::Publisher => &["lower(coalesce(p.publisher, ''))"],
+        }
+    }
+}
+
+/// Records a list screen leaves out; the default lets every record through
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ListFilter {
+    /// First and last year shown, either end open; records without a date are left out
+    pub(crate) years: Option<(Option<i64>, Option<i64>)>,
+    /// Part of the publisher's name, any case; empty for any publisher
+    pub(crate) publisher: String,
+    pub(crate) entry_type: Option<EntryType>,
+}
+
+impl ListFilter {
+    /// Filter from the lines of the filter form; a blank line turns that filter off
+    pub fn from_form(textarea: Vec<String>) -> DbResult<ListFilter> {
+        let textarea = form_fields(textarea, &FILTER_FIELDS, &[])?;
+        let years = match textarea[0].trim() {
+            "" => None,
+            value => Some(parse_years(value).ok_or_else(|| {
+                DbError::InvalidInput(format!(
+                    "Years `{value}` should be a year or a range like 1968..1975"
+                ))
+            })?),
+        };
+        let entry_type = match textarea[2].trim() {
+            "" => None,
+            value => Some(
+                parse_type(value)
+                    .ok_or_else(|| DbError::InvalidInput(format!("unknown type `{value}`")))?,
+            ),
+        };
+        Ok(ListFilter {
+            years,
+            publisher: textarea[1].trim().to_string(),
+            entry_type,
+        })
+    }
+
+    /// Lines of the filter form showing this filter
+    pub fn form_lines(&self) -> Vec<String> {
+        vec![
+            self.years.map(format_years).unwrap_or_default(),
+            self.publisher.clone(),
+            self.entry_type
+                .map(|entry_type| entry_type.name().to_string())
+                .unwrap_or_default(),
+        ]
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self == &ListFilter::default()
+    }
+}
+
+/// `1968`, `1968..1975`, `1968..` or `..1975`, as `query::parse_years` reads them
+fn format_years((from, to): (Option<i64>, Option<i64>)) -> String {
+    let year = |year: Option<i64>| year.map(|year| year.to_string()).unwrap_or_default();
+    match (from, to) {
+        (Some(from), Some(to)) if from == to => from.to_string(),
+        _ => format!("{}..{}", year(from), year(to)),
+    }
+}
+
+/// Records listed, with the columns their sort terms refer to
+const LISTED_RECORDS: &str = "FROM master_entries m
+    LEFT JOIN catalog_records c ON c.cite_key = m.cite_key
+    LEFT JOIN month_year y ON y.month_year_id = c.month_year_id
+    LEFT JOIN publisher p ON p.publisher_id = c.publisher_id";
+
+/// A row of a sorted list: its `cite_key` and the values it is sorted by, enough to read the
+/// rows next to it without counting from the top of the list
+#[derive(Clone, Debug, PartialEq)]
+pub struct ListedKey {
+    pub(crate) cite_key: String,
+    values: Vec<Value>,
+}
+
+/// Where a page of a list starts, in the order the list is shown
+#[derive(Copy, Clone, Debug)]
+enum Bound<'k> {
+    First,
+    /// The page that ends the list
+    Last,
+    /// The row at the key and the rows after it
+    From(&'k ListedKey),
+    After(&'k ListedKey),
+    /// The page that ends just before the key
+    Before(&'k ListedKey),
+    /// Only the row of the record with this `cite_key`
+    Record(&'k str),
+}
+
+fn bind_values(statement: &mut Statement, values: Vec<Value>) -> DbResult<()> {
+    statement.bind_iter(
+        values
+            .into_iter()
+            .enumerate()
+            .map(|(i, value)| (i + 1, value)),
+    )?;
+    Ok(())
+}
+
+/// Order and filter of a list screen
+#[derive(Clone, Debug, PartialEq)]
+pub struct ListView {
+    pub(crate) sort: SortKey,
+    pub(crate) descending: bool,
+    pub(crate) filter: ListFilter,
+}
+
+impl ListView {
+    /// Every record, ascending by `sort`
+    pub fn new(sort: SortKey) -> ListView {
+        ListView {
+            sort,
+            descending: false,
+            filter: ListFilter::default(),
+        }
+    }
+
+    /// Condition keeping the records of `entry_type` (of any type when `None`) that the filter
+    /// lets through, with its parameters added to `values`
+    fn condition(&self, entry_type: Option<EntryType>, values: &mut Vec<Value>) -> String {
+        let mut conditions = vec!["1"];
+        for entry_type in [entry_type, self.filter.entry_type].into_iter().flatten() {
+            conditions.push("m.entry_type = ?");
+            values.push(entry_type.as_str().into());
+        }
+        if let Some((from, to)) = self.filter.years {
+            if let Some(from) = from {
+                conditions.push("y.year >= ?");
+                values.push(from.into());
+            }
+            if let Some(to) = to {
+                conditions.push("y.year <= ?");
+                values.push(to.into());
+            }
+            conditions.push("y.year IS NOT NULL");
+        }
+        if !self.filter.publisher.is_empty() {
+            conditions.push("coalesce(p.publisher, '') LIKE ? ESCAPE '\\'");
+            values.push(like_pattern(&self.filter.publisher).into());
+        }
+        conditions.join(" AND ")
+    }
+
+    /// Sort terms, then `added` and `cite_key` so that every row has a place of its own
+    fn order_terms(&self) -> Vec<&'static str> {
+        let mut terms = self.sort.terms().to_vec();
+        terms.extend(["m.added", "m.cite_key"]);
+        terms
+    }
+
+    /// Condition keeping the rows `operator` the row at `key` in the order shown, e.g. `>` for
+    /// the rows after it, with its parameters added to `values`
+    fn compare(&self, operator: &str, key: &ListedKey, values: &mut Vec<Value>) -> String {
+        let operator = match (self.descending, operator) {
+            (false, operator) => operator,
+            (true, ">") => "<",
+            (true, ">=") => "<=",
+            (true, _) => ">",
+        };
+        let terms = self.order_terms();
+        values.extend(key.values.iter().cloned());
+        format!(
+            "({}) {operator} ({})",
+            terms.join(", "),
+            vec!["?"; terms.len()].join(", ")
+        )
+    }
+
+    /// Up to `limit` rows of records of `entry_type`, starting at `bound`, in the order shown.
+    /// Pages are read from the sort values of the row they start next to, so no rows are
+    /// skipped over on the way.
+    fn page(
+        &self,
+        catalog: &Catalog,
+        entry_type: Option<EntryType>,
+        bound: Bound,
+        limit: Option<usize>,
+    ) -> DbResult<Vec<ListedKey>> {
+        let terms = self.order_terms();
+        let mut values = Vec::new();
+        let mut condition = self.condition(entry_type, &mut values);
+        let comparison = match bound {
+            Bound::First | Bound::Last => None,
+            Bound::Record(cite_key) => {
+                values.push(cite_key.into());
+                Some("m.cite_key = ?".to_string())
+            }
+            Bound::From(key) => Some(self.compare(">=", key, &mut values)),
+            Bound::After(key) => Some(self.compare(">", key, &mut values)),
+            Bound::Before(key) => Some(self.compare("<", key, &mut values)),
+        };
+        if let Some(comparison) = comparison {
+            condition = format!("{condition} AND {comparison}");
+        }
+        // rows before a bound are read backwards from it, then turned around
+        let backwards = matches!(bound, Bound::Last | Bound::Before(_));
+        let direction = match self.descending != backwards {
+            true => " DESC",
+            false => "",
+        };
+        let order: Vec<String> = terms
+            .iter()
+            .map(|term| format!("{term}{direction}"))
+            .collect();
+        let limit = limit.map_or(String::new(), |limit| format!(" LIMIT {limit}"));
+        let query = format!(
+            "SELECT m.cite_key, {} {LISTED_RECORDS} WHERE {condition} ORDER BY {}{limit}",
+            terms.join(", "),
+            order.join(", ")
+        );
+        let mut rows = catalog.with_uncached_statement(&query, |statement| {
+            bind_values(statement, values)?;
+            let mut rows = Vec::new();
+            while let State::Row = statement.next()? {
+                rows.push(ListedKey {
+                    cite_key: statement.read::<String, _>(0)?,
+                    values: (1..=terms.len())
+                        .map(|column| statement.read::<Value, _>(column))
+                        .collect::<Result<_, _>>()?,
+                });
+            }
+            Ok(rows)
+        })?;
+        if backwards {
+            rows.reverse();
+        }
+        Ok(rows)
+    }
+
+    /// Number of records of `entry_type` the filter lets through, before the row at `key` if
+    /// one is given
+    fn count(
+        &self,
+        catalog: &Catalog,
+        entry_type: Option<EntryType>,
+        before: Option<&ListedKey>,
+    ) -> DbResult<usize> {
+        let mut values = Vec::new();
+        let mut condition = self.condition(entry_type, &mut values);
+        if let Some(key) = before {
+            condition = format!("{condition} AND {}", self.compare("<", key, &mut values));
+        }
+        let query = format!("SELECT count(*) {LISTED_RECORDS} WHERE {condition}");
+        catalog.with_uncached_statement(&query, |statement| {
+            bind_values(statement, values)?;
+            statement.next()?;
+            Ok(statement.read::<i64, _>(0)? as usize)
+        })
+    }
+
+    /// Row of the record with `cite_key`; `None` if it is not a record of `entry_type` the
+    /// filter lets through
+    fn locate(
+        &self,
+        catalog: &Catalog,
+        entry_type: Option<EntryType>,
+        cite_key: &str,
+    ) -> DbResult<Option<ListedKey>> {
+        let rows = self.page(catalog, entry_type, Bound::Record(cite_key), Some(1))?;
+        Ok(rows.into_iter().next())
+    }
+
+    /// `cite_key` of every record of `entry_type` (of any type when `None`) that the filter
+    /// lets through, in order; records that sort the same are in the order they were added,
+    /// or the reverse of it when descending
+    pub fn cite_keys(
+        &self,
+        catalog: &Catalog,
+        entry_type: Option<EntryType>,
+    ) -> DbResult<Vec<String>> {
+        Ok(self
+            .page(catalog, entry_type, Bound::First, None)?
+            .into_iter()
+            .map(|row| row.cite_key)
+            .collect())
+    }
+
+    /// `by year, descending; years 1968..1975, publisher acm` for the title of the list
+    pub fn describe(&self) -> String {
+        let mut text = format!("by {}", self.sort.label());
+        if self.descending {
+            text.push_str(", descending");
+        }
+        let mut filters = Vec::new();
+        if let Some(years) = self.filter.years {
+            filters.push(format!("years {}", format_years(years)));
+        }
+        if !self.filter.publisher.is_empty() {
+            filters.push(format!("publisher {}", self.filter.publisher));
+        }
+        if let Some(entry_type) = self.filter.entry_type {
+            filters.push(format!("type {}", entry_type.name()));
+        }
+        if !filters.is_empty() {
+            text.push_str("; ");
+            text.push_str(&filters.join(", "));
+        }
+        text
+    }
+}
+
+/// Every row of a short table, such as the publishers, read again only when the catalog changes,
+/// through this connection or another
+#[derive(Clone, Debug)]
+pub struct CachedRows<T> {
+    rows: Vec<T>,
+    /// `Catalog::version` when `rows` were read; `None` until they are
+    read_at: Option<(i64, usize)>,
+}
+
+impl<T> CachedRows<T> {
+    pub fn new() -> CachedRows<T> {
+        CachedRows {
+            rows: Vec::new(),
+            read_at: None,
+        }
+    }
+
+    pub fn rows(&self) -> &[T] {
+        &self.rows
+    }
+
+    /// Rows as of the last `refresh`
+    pub fn count(&self) -> usize {
+        self.rows.len()
+    }
+
+    /// Read the rows with `read` if the catalog changed since they were read
+    pub fn refresh(
+        &mut self,
+        catalog: &Catalog,
+        read: impl FnOnce(&Catalog) -> DbResult<Vec<T>>,
+    ) -> DbResult<()> {
+        let version = catalog.version()?;
+        if self.read_at != Some(version) {
+            self.rows = read(catalog)?;
+            self.read_at = Some(version);
+        }
+        Ok(())
+    }
+}
+
+impl<T> Default for CachedRows<T> {
+    fn default() -> CachedRows<T> {
+        CachedRows::new()
+    }
+}
+
+/// Records of the rows on screen, read whole for the list's type
+#[derive(Clone, Debug)]
+enum ShownRecords {
+    Entries(Vec<CatalogEntry>),
+    Books(Vec<Book>),
+    Articles(Vec<Article>),
+    Items(Vec<Item>),
+}
+
+/// The rows of a list of records that fit on screen, read a page at a time as the selection
+/// moves, so a list costs the same to show however many records it holds
+#[derive(Clone, Debug)]
+pub struct ListWindow {
+    pub(crate) view: ListView,
+    /// Type of the records listed; every type when `None`
+    entry_type: Option<EntryType>,
+    /// Rows that fit on screen
+    height: usize,
+    /// Position in the whole list of the first row in `rows`
+    offset: usize,
+    rows: Vec<ListedKey>,
+    /// Position in the whole list of the selected row
+    selected: usize,
+    /// Rows in the whole list
+    count: usize,
+    /// `Catalog::version` when `rows` and `count` were read; `None` until they are
+    read_at: Option<(i64, usize)>,
+    /// Records of `rows`, in the same order
+    records: ShownRecords,
+    /// `Catalog::version` when `records` were read, with the `cite_key` of each
+    records_at: Option<((i64, usize), Vec<String>)>,
+    /// Links of the selected record; none for a list of every type
+    links: Vec<Link>,
+    /// `Catalog::version` when `links` were read, with the `cite_key` they belong to
+    links_at: Option<((i64, usize), String)>,
+}
+
+impl ListWindow {
+    pub fn new(entry_type: Option<EntryType>, view: ListView) -> ListWindow {
+        ListWindow {
+            view,
+            entry_type,
+            height: 1,
+            offset: 0,
+            rows: Vec::new(),
+            selected: 0,
+            count: 0,
+            read_at: None,
+            records: ShownRecords::Entries(Vec::new()),
+            records_at: None,
+            links: Vec::new(),
+            links_at: None,
+        }
+    }
+
+    /// Rows in the whole list, as of the last `refresh`
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    /// `cite_key` of each row on screen, in order
+    pub fn cite_keys(&self) -> Vec<&str> {
+        self.rows.iter().map(|row| row.cite_key.as_str()).collect()
+    }
+
+    /// `cite_key` of every row in the list, on screen or not, in order
+    pub fn all_cite_keys(&self, catalog: &Catalog) -> DbResult<Vec<String>> {
+        self.view.cite_keys(catalog, self.entry_type)
+    }
+
+    /// Records on screen of a list of every type, as of the last `refresh`
+    pub fn entries(&self) -> &[CatalogEntry] {
+        match &self.records {
+            ShownRecords::Entries(entries) => entries,
+            _ => &[],
+        }
+    }
+
+    /// Books on screen, as of the last `refresh`
+    pub fn books(&self) -> &[Book] {
+        match &self.records {
+            ShownRecords::Books(books) => books,
+            _ => &[],
+        }
+    }
+
+    /// Articles on screen, as of the last `refresh`
+    pub fn articles(&self) -> &[Article] {
+        match &self.records {
+            ShownRecords::Articles(articles) => articles,
+            _ => &[],
+        }
+    }
+
+    /// Items on screen, as of the last `refresh`
+    pub fn items(&self) -> &[Item] {
+        match &self.records {
+            ShownRecords::Items(items) => items,
+            _ => &[],
+        }
+    }
+
+    /// Links of the selected record, as of the last `refresh`
+    pub fn links(&self) -> &[Link] {
+        &self.links
+    }
+
+    pub fn selected_key(&self) -> Option<&str> {
+        self.rows
+            .get(self.selected.checked_sub(self.offset)?)
+            .map(|row| row.cite_key.as_str())
+    }
+
+    /// Selected row among the rows on screen
+    pub fn selected_row(&self) -> Option<usize> {
+        self.selected_key().map(|_| self.selected - self.offset)
+    }
+
+    /// List the records of another type, from the top
+    pub fn set_entry_type(&mut self, entry_type: Option<EntryType>) {
+        if self.entry_type != entry_type {
+            *self = ListWindow::new(entry_type, self.view.clone());
+        }
+    }
+
+    /// Read the rows again if the catalog changed since they were read or the screen was
+    /// resized. The selected record stays selected; if it is gone, the record now in its
+    /// place is selected. The records on screen and the links of the selected one are read
+    /// again only when they or the catalog changed.
+    pub fn refresh(&mut self, catalog: &Catalog, height: usize) -> DbResult<()> {
+        let height = height.max(1);
+        if self.read_at != Some(catalog.version()?) || self.height != height {
+            self.read_rows(catalog, height)?;
+        }
+        self.read_records(catalog)?;
+        self.read_links(catalog)
+    }
+
+    fn read_rows(&mut self, catalog: &Catalog, height: usize) -> DbResult<()> {
+        let version = catalog.version()?;
+        let selected_key = self.selected_key().map(str::to_string);
+        self.height = height;
+        self.count = self.view.count(catalog, self.entry_type, None)?;
+        // the first row shown is found again by its sort values, even if it is gone
+        match self.rows.first().cloned() {
+            Some(first) => {
+                self.offset = self.view.count(catalog, self.entry_type, Some(&first))?;
+                self.rows = self.page(catalog, Bound::From(&first))?;
+            }
+            None => {
+                self.offset = 0;
+                self.rows = self.page(catalog, Bound::First)?;
+            }
+        }
+        self.fill(catalog)?;
+        self.read_at = Some(version);
+        let position = selected_key
+            .and_then(|cite_key| self.rows.iter().position(|row| row.cite_key == cite_key));
+        let selected = match position {
+            Some(position) => self.offset + position,
+            None => self.selected.min(self.count.saturating_sub(1)),
+        };
+        self.scroll_to(catalog, selected)
+    }
+
+    /// Select the next row, or the first after the last
+    pub fn select_next(&mut self, catalog: &Catalog) -> DbResult<()> {
+        match self.selected + 1 < self.count {
+            true => self.scroll_to(catalog, self.selected + 1),
+            false => self.select_first(catalog),
+        }
+    }
+
+    /// Select the row before, or the last before the first
+    pub fn select_previous(&mut self, catalog: &Catalog) -> DbResult<()> {
+        match self.selected {
+            0 => self.select_last(catalog),
+            selected => self.scroll_to(catalog, selected - 1),
+        }
+    }
+
+    pub fn select_first(&mut self, catalog: &Catalog) -> DbResult<()> {
+        self.rows = self.page(catalog, Bound::First)?;
+        self.offset = 0;
+        self.selected = 0;
+        Ok(())
+    }
+
+    pub fn select_last(&mut self, catalog: &Catalog) -> DbResult<()> {
+        self.rows = self.page(catalog, Bound::Last)?;
+        self.offset = self.count.saturating_sub(self.rows.len());
+        self.selected = self.count.saturating_sub(1);
+        Ok(())
+    }
+
+    /// Select the record with `cite_key`; `false` if it is not in the list
+    pub fn select_key(&mut self, catalog: &Catalog, cite_key: &str) -> DbResult<bool> {
+        let Some(key) = self.view.locate(catalog, self.entry_type, cite_key)? else {
+            return Ok(false);
+        };
+        self.offset = self.view.count(catalog, self.entry_type, Some(&key))?;
+        self.selected = self.offset;
+        self.rows = self.page(catalog, Bound::From(&key))?;
+        self.fill(catalog)?;
+        Ok(true)
+    }
+
+    /// Change the order or filter of the list, keeping the same record selected; the first
+    /// record is selected when the filter leaves the selected one out
+    pub fn change_view(
+        &mut self,
+        catalog: &Catalog,
+        change: impl FnOnce(&mut ListView),
+    ) -> DbResult<()> {
+        let selected_key = self.selected_key().map(str::to_string);
+        change(&mut self.view);
+        self.count = self.view.count(catalog, self.entry_type, None)?;
+        self.read_at = Some(catalog.version()?);
+        let found = match selected_key {
+            Some(cite_key) => self.select_key(catalog, &cite_key)?,
+            None => false,
+        };
+        match found {
+            true => Ok(()),
+            false => self.select_first(catalog),
+        }
+    }
+
+    /// Read the records of the rows on screen unless they were read for the same rows
+    fn read_records(&mut self, catalog: &Catalog) -> DbResult<()> {
+        let read_at = (
+            catalog.version()?,
+            self.rows.iter().map(|row| row.cite_key.clone()).collect(),
+        );
+        if self.records_at.as_ref() == Some(&read_at) {
+            return Ok(());
+        }
+        let cite_keys = self.cite_keys();
+        self.records = match self.entry_type {
+            None => ShownRecords::Entries(read_catalog_entries_with_keys(catalog, &cite_keys)?),
+            Some(EntryType::Book) => {
+                ShownRecords::Books(read_books_with_keys(catalog, &cite_keys)?)
+            }
+            Some(EntryType::Article) => {
+                ShownRecords::Articles(read_articles_with_keys(catalog, &cite_keys)?)
+            }
+            Some(entry_type) => ShownRecords::Items(match entry_type.item_type() {
+                Some(item_type) => item_type.read_items_with_keys(catalog, &cite_keys)?,
+                None => Vec::new(),
+            }),
+        };
+        self.records_at = Some(read_at);
+        Ok(())
+    }
+
+    /// Read the links of the selected record unless they were read for it already
+    fn read_links(&mut self, catalog: &Catalog) -> DbResult<()> {
+        let cite_key = match self.entry_type {
+            Some(_) => self.selected_key().unwrap_or_default().to_string(),
+            None => String::new(),
+        };
+        let read_at = (catalog.version()?, cite_key);
+        if self.links_at.as_ref() == Some(&read_at) {
+            return Ok(());
+        }
+        self.links = match read_at.1.as_str() {
+            "" => Vec::new(),
+            cite_key => select_links(catalog, cite_key)?,
+        };
+        self.links_at = Some(read_at);
+        Ok(())
+    }
+
+    /// A screenful of rows starting at `bound`
+    fn page(&self, catalog: &Catalog, bound: Bound) -> DbResult<Vec<ListedKey>> {
+        self.view
+            .page(catalog, self.entry_type, bound, Some(self.height))
+    }
+
+    /// Fill the screen with the rows before the first one shown when the list ends early
+    fn fill(&mut self, catalog: &Catalog) -> DbResult<()> {
+        let missing = self.height.saturating_sub(self.rows.len()).min(self.offset);
+        if missing == 0 {
+            return Ok(());
+        }
+        let before = match self.rows.first() {
+            Some(first) => self.view.page(
+                catalog,
+                self.entry_type,
+                Bound::Before(first),
+                Some(missing),
+            )?,
+            None => self
+                .view
+                .page(catalog, self.entry_type, Bound::Last, Some(missing))?,
+        };
+        self.offset -= before.len();
+        self.rows.splice(0..0, before);
+        Ok(())
+    }
+
+    /// Select the row at `selected`, one row away from the rows on screen at most, and
+    /// scroll it into view
+    fn scroll_to(&mut self, catalog: &Catalog, selected: usize) -> DbResult<()> {
+        self.selected = selected;
+        if selected < self.offset {
+            let before = match self.rows.first() {
+                Some(first) => self.view.page(
+                    catalog,
+                    self.entry_type,
+                    Bound::Before(first),
+                    Some(self.offset - selected),
+                )?,
+                None => Vec::new(),
+            };
+            self.offset -= before.len();
+            self.rows.splice(0..0, before);
+            self.rows.truncate(self.height);
+        } else if selected >= self.offset + self.rows.len() {
+            let after = match self.rows.last() {
+                Some(last) => self.view.page(
+                    catalog,
+                    self.entry_type,
+                    Bound::After(last),
+                    Some(selected + 1 - self.offset - self.rows.len()),
+                )?,
+                None => self.page(catalog, Bound::First)?,
+            };
+            self.rows.extend(after);
+            let extra = self.rows.len().saturating_sub(self.height);
+            self.rows.drain(..extra);
+            self.offset += extra;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::db::{read_sqlite_publisher_table, Article, Book, Database, TestDatabase};
+    use crate::relationship::Relationship;
+    use serial_test::serial;
+
+    #[test]
+    fn filter_form_round_trips() {
+        let filter = ListFilter::from_form(vec![
+            " 1968..1975".to_string(),
+            "Addison".to_string(),
+            "conference paper".to_string(),
+        ])
+        .unwrap();
+        assert_eq!(filter.years, Some((Some(1968), Some(1975))));
+        assert_eq!(filter.entry_type, Some(EntryType::ConferencePaper));
+        assert_eq!(ListFilter::from_form(filter.form_lines()).unwrap(), filter);
+        assert!(ListFilter::from_form(vec![]).unwrap().is_empty());
+        assert!(matches!(
+            ListFilter::from_form(vec!["1975..1968".to_string()]),
+            Err(DbError::InvalidInput(_))
+        ));
+        assert!(matches!(
+            ListFilter::from_form(vec!["".to_string(), "".to_string(), "poem".to_string()]),
+            Err(DbError::InvalidInput(_))
+        ));
+    }
+
+    #[test]
+    #[serial]
+    fn sorts_and_filters_records() {
+        let database = TestDatabase::new();
+        let catalog = database.catalog();
+        let book = |author: &str, title: &str, year: &str, publisher: &str| {
+            Book::book_transaction(
+                &catalog,
+                vec![
+                    author.to_string(),
+                    title.to_string(),
+                    "1".to_string(),
+                    "".to_string(),
+                    "".to_string(),
+                    year.to_string(),
+                    "".to_string(),
+                    publisher.to_string(),
+                ],
+            )
+            .unwrap()
+        };
+        let taocp = book(
+            "Knuth, Donald",
+            "The Art of Computer Programming",
+            "1968",
+            "Addison-Wesley",
+        );
+        let sicp = book(
+            "Abelson, Harold",
+            "Structure and Interpretation of Computer Programs",
+            "1985",
+            "MIT Press",
+        );
+        let wirth = book(
+            "Wirth, Niklaus",
+            "Algorithms + Data Structures = Programs",
+            "1976",
+            "Prentice Hall",
+        );
+        let article = Article::article_transaction(
+            &catalog,
+            vec![
+                "Go to statement considered harmful".to_string(),
+                "CACM".to_string(),
+                "11".to_string(),
+                "".to_string(),
+                "".to_string(),
+                "1968".to_string(),
+                "".to_string(),
+                "ACM".to_string(),
+            ],
+        )
+        .unwrap();
+
+        let mut view = ListView::new(SortKey::Added);
+        let keys = |view: &ListView| view.cite_keys(&catalog, Some(EntryType::Book)).unwrap();
+        assert_eq!(
+            keys(&view),
+            vec![taocp.clone(), sicp.clone(), wirth.clone()]
+        );
+        view.sort = SortKey::Title;
+        assert_eq!(
+            keys(&view),
+            vec![wirth.clone(), sicp.clone(), taocp.clone()]
+        );
+        view.sort = SortKey::Author;
+        view.descending = true;
+        assert_eq!(
+            keys(&view),
+            vec![wirth.clone(), taocp.clone(), sicp.clone()]
+        );
+        view.sort = SortKey::Year;
+        assert_eq!(
+            keys(&view),
+            vec![sicp.clone(), wirth.clone(), taocp.clone()]
+        );
+        view.sort = SortKey::Publisher;
+        view.descending = false;
+        assert_eq!(
+            keys(&view),
+            vec![taocp.clone(), sicp.clone(), wirth.clone()]
+        );
+
+        view.filter.years = Some((None, Some(1970)));
+        assert_eq!(keys(&view), vec![taocp.clone()]);
+        assert_eq!(
+            view.cite_keys(&catalog, None).unwrap(),
+            vec![article.clone(), taocp.clone()]
+        );
+        view.filter.entry_type = Some(EntryType::Article);
+        assert_eq!(view.cite_keys(&catalog, None).unwrap(), vec![article]);
+        assert!(keys(&view).is_empty());
+
+        view.filter = ListFilter::default();
+        view.filter.publisher = "mit".to_string();
+        assert_eq!(keys(&view), vec![sicp]);
+        assert_eq!(view.describe(), "by publisher; publisher mit");
+    }
+
+    #[test]
+    #[serial]
+    fn window_reads_one_screen_at_a_time() {
+        let database = TestDatabase::new();
+        let catalog = database.catalog();
+        let cite_keys: Vec<String> = (1..=7)
+            .map(|number| {
+                Book::book_transaction(
+                    &catalog,
+                    vec![
+                        "Knuth, Donald".to_string(),
+                        format!("Volume {number}"),
+                        "1".to_string(),
+                        "".to_string(),
+                        "".to_string(),
+                        "1968".to_string(),
+                        "".to_string(),
+                        "Addison-Wesley".to_string(),
+                    ],
+                )
+                .unwrap()
+            })
+            .collect();
+
+        let mut window = ListWindow::new(Some(EntryType::Book), ListView::new(SortKey::Added));
+        window.refresh(&catalog, 3).unwrap();
+        assert_eq!(window.count(), 7);
+        assert_eq!(
+            window.cite_keys(),
+            vec![&cite_keys[0], &cite_keys[1], &cite_keys[2]]
+        );
+        for _ in 0..4 {
+            window.select_next(&catalog).unwrap();
+        }
+        assert_eq!(window.selected_key(), Some(cite_keys[4].as_str()));
+        assert_eq!(
+            window.cite_keys(),
+            vec![&cite_keys[2], &cite_keys[3], &cite_keys[4]]
+        );
+        assert_eq!(window.selected_row(), Some(2));
+        window.select_previous(&catalog).unwrap();
+        window.select_previous(&catalog).unwrap();
+        window.select_previous(&catalog).unwrap();
+        assert_eq!(window.selected_key(), Some(cite_keys[1].as_str()));
+        assert_eq!(
+            window.cite_keys(),
+            vec![&cite_keys[1], &cite_keys[2], &cite_keys[3]]
+        );
+
+        // both ends wrap around
+        window.select_previous(&catalog).unwrap();
+        window.select_previous(&catalog).unwrap();
+        assert_eq!(window.selected_key(), Some(cite_keys[6].as_str()));
+        assert_eq!(
+            window.cite_keys(),
+            vec![&cite_keys[4], &cite_keys[5], &cite_keys[6]]
+        );
+        window.select_next(&catalog).unwrap();
+        assert_eq!(window.selected_key(), Some(cite_keys[0].as_str()));
+
+        // nothing is read again until the catalog changes
+        window.select_key(&catalog, &cite_keys[3]).unwrap();
+        Book::delete_book(&catalog, cite_keys[3].clone()).unwrap();
+        assert_eq!(window.count(), 7);
+        window.refresh(&catalog, 3).unwrap();
+        assert_eq!(window.count(), 6);
+        assert_eq!(window.selected_key(), Some(cite_keys[4].as_str()));
+
+        window
+            .change_view(&catalog, |view| view.descending = true)
+            .unwrap();
+        assert_eq!(window.selected_key(), Some(cite_keys[4].as_str()));
+        assert!(!window.select_key(&catalog, &cite_keys[3]).unwrap());
+        window.select_last(&catalog).unwrap();
+        assert_eq!(
+            window.cite_keys(),
+            vec![&cite_keys[2], &cite_keys[1], &cite_keys[0]]
+        );
+
+        // a whole table is cached the same way
+        let mut publishers = CachedRows::new();
+        publishers
+            .refresh(&catalog, read_sqlite_publisher_table)
+            .unwrap();
+        assert_eq!(publishers.count(), 1);
+        publishers
+            .refresh(&catalog, |_| panic!("should not read an unchanged catalog"))
+            .unwrap();
+        Book::delete_book(&catalog, cite_keys[4].clone()).unwrap();
+        publishers
+            .refresh(&catalog, read_sqlite_publisher_table)
+            .unwrap();
+        assert_eq!(publishers.rows()[0].1, 5);
+
+        // so is a write from a second connection, which leaves this one's `total_changes` alone
+        let other = Database::open(database.db_url()).unwrap();
+        Book::delete_book(&other.catalog(), cite_keys[5].clone()).unwrap();
+        publishers
+            .refresh(&catalog, read_sqlite_publisher_table)
+            .unwrap();
+        assert_eq!(publishers.rows()[0].1, 4);
+        window.refresh(&catalog, 3).unwrap();
+        assert_eq!(window.count(), 4);
+    }
+
+    #[test]
+    #[serial]
+    fn window_keeps_records_and_links_until_they_change() {
+        let database = TestDatabase::new();
+        let catalog = database.catalog();
+        let book = |title: &str| {
+            vec![
+                "Knuth, Donald".to_string(),
+                title.to_string(),
+                "1".to_string(),
+                "".to_string(),
+                "".to_string(),
+                "1968".to_string(),
+                "".to_string(),
+                "Addison-Wesley".to_string(),
+            ]
+        };
+        let cite_keys: Vec<String> = ["Volume 1", "Volume 2", "Volume 3"]
+            .into_iter()
+            .map(|title| Book::book_transaction(&catalog, book(title)).unwrap())
+            .collect();
+        let titles = |window: &ListWindow| -> Vec<String> {
+            window
+                .books()
+                .iter()
+                .map(|book| book.title.clone())
+                .collect()
+        };
+
+        let mut window = ListWindow::new(Some(EntryType::Book), ListView::new(SortKey::Added));
+        window.refresh(&catalog, 2).unwrap();
+        assert_eq!(titles(&window), vec!["Volume 1", "Volume 2"]);
+        assert!(window.links().is_empty());
+        assert!(window.entries().is_empty());
+
+        Relationship::link_from_form(
+            &catalog,
+            vec!["reviews".to_string(), cite_keys[2].clone()],
+            &cite_keys[0],
+        )
+        .unwrap();
+        assert!(window.links().is_empty());
+        window.refresh(&catalog, 2).unwrap();
+        assert_eq!(window.links().len(), 1);
+        assert_eq!(window.links()[0].cite_key, cite_keys[2]);
+
+        // moving the selection reads the links of the record selected
+        window.select_next(&catalog).unwrap();
+        window.refresh(&catalog, 2).unwrap();
+        assert!(window.links().is_empty());
+
+        Book::book_update(
+            &catalog,
+            book("Seminumerical Algorithms"),
+            cite_keys[1].clone(),
+        )
+        .unwrap();
+        assert_eq!(titles(&window), vec!["Volume 1", "Volume 2"]);
+        window.refresh(&catalog, 2).unwrap();
+        assert_eq!(
+            titles(&window),
+            vec!["Volume 1", "Seminumerical Algorithms"]
+        );
+
+        window.select_next(&catalog).unwrap();
+        window.refresh(&catalog, 2).unwrap();
+        assert_eq!(
+            titles(&window),
+            vec!["Seminumerical Algorithms", "Volume 3"]
+        );
+
+        let mut entry_list = ListWindow::new(None, ListView::new(SortKey::Added));
+        entry_list.refresh(&catalog, 5).unwrap();
+        assert_eq!(entry_list.entries().len(), 3);
+        assert!(entry_list.books().is_empty());
+    }
+}
@@ -1,8 +1,16 @@
-mod app;
-mod db;
-mod ui;
-
-use crate::app::App;
+use bib_db::app::App;
+use bib_db::bibtex::{export_bibtex, import_bibtex};
+use bib_db::check::find_orphans;
+use bib_db::config::Config;
+use bib_db::csl::{export_csl, import_csl};
+use bib_db::csv::{import_table, preview, read_table, table_records, ColumnMapping, PREVIEW_ROWS};
+use bib_db::db::{Catalog, Database};
+use bib_db::import::ImportReport;
+use bib_db::marc::{export_marc, import_marc, MarcReader};
+use bib_db::marcxml::{import_marcxml, write_marcxml};
+use bib_db::mods::{import_mods, write_mods};
+use bib_db::query::matching_keys;
+use bib_db::ris::{export_ris, import_ris};
 use crossterm::execute;
 use crossterm::terminal::{
     disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen,
@@ -10,12 +18,36 @@ use crossterm::terminal::{
 use ratatui::prelude::CrosstermBackend;
 use ratatui::Terminal;
 use std::error::Error;
+use std::fs::File;
+use std::io::{BufReader, Write};
 
-/// If database is not already created, initialize it by running `init_db` binary crate.
-/// Update const `DB_URL` to match what you have named it in `init_db`
-const DB_URL: &str = "sqlite://../bibliographic_db/bib_data.db";
+const EXPORT_USAGE: &str = "usage: export bibtex|ris|csl|marc|marcxml|mods [query]";
+const IMPORT_USAGE: &str = "usage: import bibtex|ris|csl|marc|marcxml|mods <file> [--dry-run]
+       import csv|tsv <file> [--map <mapping>] [--preview] [--rollback-on-failure] [--dry-run]";
 
+/// If database is not already created, initialize it by running `init_db` binary crate.
+/// Both binaries read the database location from `--db <path>`, `DB_URL` or `bib_db.conf`.
+/// A subcommand such as `check`, `export bibtex` or `import ris` runs without starting the TUI,
+/// except `import csv <file>` without a mapping, which starts it at the column mapping.
 fn main() -> Result<(), Box<dyn Error>> {
+    let config = Config::load();
+    let database = Database::open(&config.db_url)?;
+    let catalog = database.catalog();
+    let csv_import = match config.command.as_slice() {
+        [import, format, file]
+            if import == "import"
+                && matches!(format.as_str(), "csv" | "tsv")
+                && config.map.is_none()
+                && !config.preview =>
+        {
+            Some((file.clone(), read_table(file)?))
+        }
+        _ => None,
+    };
+    if !config.command.is_empty() && csv_import.is_none() {
+        return run_command(&catalog, &config);
+    }
+
     // setup terminal
     enable_raw_mode()?;
     let mut stdout = std::io::stdout();
@@ -25,7 +57,10 @@ fn main() -> Result<(), Box<dyn Error>> {
     terminal.clear()?;
 
     // create app and run it
-    let mut app = App::new();
+    let mut app = App::new(catalog);
+    if let Some((path, table)) = csv_import {
+        app.open_csv_import(path, table);
+    }
     let res = app.run(&mut terminal);
 
     // restore terminal
@@ -39,3 +74,98 @@ fn main() -> Result<(), Box<dyn Error>> {
 
     Ok(())
 }
+
+/// Run a subcommand and print its result to stdout
+fn run_command(catalog: &Catalog, config: &Config) -> Result<(), Box<dyn Error>> {
+    let command = &config.command;
+    match command[0].as_str() {
+        "check" => {
+            let orphans = find_orphans(catalog)?;
+            for orphan in &orphans {
+                println!("{orphan}");
+            }
+            println!("{} orphaned rows", orphans.len());
+            Ok(())
+        }
+        // export <format> [query]: the records matching the query, or the whole catalog
+        "export" => {
+            let query = command[2.min(command.len())..].join(" ");
+            let cite_keys = matching_keys(catalog, &query)?;
+            let cite_keys: Vec<&str> = cite_keys.iter().map(String::as_str).collect();
+            let text = |(text, count): (String, usize)| (text.into_bytes(), count);
+            // records that could not be mapped go with a whole-catalog export
+            let with_unmapped = query.trim().is_empty();
+            let mut stdout = std::io::stdout().lock();
+            let count = match command.get(1).map(String::as_str) {
+                // the XML formats are written a record at a time
+                Some("marcxml") => write_marcxml(catalog, &cite_keys, with_unmapped, &mut stdout)?,
+                Some("mods") => write_mods(catalog, &cite_keys, &mut stdout)?,
+                format => {
+                    let (bytes, count) = match format {
+                        Some("bibtex") => text(export_bibtex(catalog, &cite_keys)?),
+                        Some("ris") => text(export_ris(catalog, &cite_keys)?),
+                        Some("csl") => text(export_csl(catalog, &cite_keys)?),
+                        Some("marc") => export_marc(catalog, &cite_keys, with_unmapped)?,
+                        _ => return Err(EXPORT_USAGE.into()),
+                    };
+                    stdout.write_all(&bytes)?;
+                    count
+                }
+            };
+            eprintln!("{count} records exported");
+            Ok(())
+        }
+        // import <format> <file> [--dry-run], and for csv the flags in `IMPORT_USAGE`
+        "import" => {
+            let (Some(format), Some(file)) = (command.get(1), command.get(2)) else {
+                return Err(IMPORT_USAGE.into());
+            };
+            let text = || std::fs::read_to_string(file);
+            let reader = || File::open(file).map(BufReader::new);
+            let report = match format.as_str() {
+                "bibtex" => import_bibtex(catalog, &text()?, config.dry_run)?,
+                "ris" => import_ris(catalog, &text()?, config.dry_run)?,
+                "csl" => import_csl(catalog, &text()?, config.dry_run)?,
+                "marc" => import_marc(catalog, MarcReader::new(reader()?), config.dry_run)?,
+                "marcxml" => import_marcxml(catalog, reader()?, config.dry_run)?,
+                "mods" => import_mods(catalog, reader()?, config.dry_run)?,
+                "csv" | "tsv" => import_csv(catalog, config, file)?,
+                _ => return Err(IMPORT_USAGE.into()),
+            };
+            println!("{report}");
+            Ok(())
+        }
+        other => Err(format!("unknown command `{other}`").into()),
+    }
+}
+
+/// `import csv <file> --map <mapping>`, or with the mapping guessed from the header and printed
+/// for `--preview` without one
+fn import_csv(
+    catalog: &Catalog,
+    config: &Config,
+    file: &str,
+) -> Result<ImportReport, Box<dyn Error>> {
+    let table = read_table(file)?;
+    let mut mapping = match &config.map {
+        Some(map) => {
+            let contents = std::fs::read_to_string(map).map_err(|err| format!("{map}: {err}"))?;
+            ColumnMapping::from_file(&contents)?
+        }
+        None => {
+            let mapping = ColumnMapping::guess(&table);
+            println!("# mapping guessed from the header; save it and pass it with --map");
+            print!("{}", mapping.to_file());
+            mapping
+        }
+    };
+    if config.rollback_on_failure {
+        mapping.roll_back_on_failure();
+    }
+    if config.preview {
+        let records = table_records(&table.head(PREVIEW_ROWS), &mapping)?;
+        print!("{}", preview(&records, PREVIEW_ROWS));
+    }
+    let dry_run = config.dry_run || config.preview;
+    Ok(import_table(catalog, &table, &mapping, dry_run)?)
+}
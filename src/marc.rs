@@ -0,0 +1,1111 @@
+use crate::author::{parse_authors, Role};
+use crate::db::{read_records_with_keys, Catalog, DbError, DbResult, EntryType, Record};
+use crate::import::{import_records, record_form, ImportRecord, ImportReport, IMPORT_BATCH};
+use crate::organization::{parse_organizations, OrgRole};
+use sqlite::State;
+use std::collections::HashMap;
+use std::io::BufRead;
+use uuid::Uuid;
+
+/// Ends the directory and every field of an ISO 2709 record
+pub const FIELD_TERMINATOR: u8 = 0x1e;
+
+/// Ends an ISO 2709 record
+pub const RECORD_TERMINATOR: u8 = 0x1d;
+
+/// Starts each subfield of a data field, followed by the subfield code
+pub const SUBFIELD_DELIMITER: u8 = 0x1f;
+
+/// Leader of a record written from scratch: a UTF-8 book without ISBD punctuation; the
+/// lengths, base address and bibliographic level are filled in when it is written
+const NEW_LEADER: &str = "00000nam a2200000 c 4500";
+
+/// Data fields read onto a book or article and written from it; the others pass through
+const MAPPED_TAGS: [&str; 13] = [
+    "020", "100", "110", "245", "250", "260", "264", "300", "490", "500", "700", "710", "773",
+];
+
+/// One field of a MARC record
+#[derive(Clone, Debug, PartialEq)]
+pub enum MarcField {
+    /// Tags `001` to `009`: a single value without indicators or subfields
+    Control { tag: String, value: String },
+    Data {
+        tag: String,
+        indicators: [char; 2],
+        subfields: Vec<(char, String)>,
+    },
+}
+
+impl MarcField {
+    /// A data field with the non-empty `subfields`; `indicators` is two characters, blank as space
+    pub fn data(tag: &str, indicators: &str, subfields: &[(char, &str)]) -> MarcField {
+        let mut chars = indicators.chars();
+        MarcField::Data {
+            tag: tag.to_string(),
+            indicators: [chars.next().unwrap_or(' '), chars.next().unwrap_or(' ')],
+            subfields: subfields
+                .iter()
+                .filter(|(_, value)| !value.trim().is_empty())
+                .map(|(code, value)| (*code, value.trim().to_string()))
+                .collect(),
+        }
+    }
+
+    pub fn tag(&self) -> &str {
+        match self {
+            MarcField::Control { tag, .. } | MarcField::Data { tag, .. } => tag,
+        }
+    }
+
+    /// Values of the subfields with `code`, in order; none for a control field
+    pub fn subfields(&self, code: char) -> impl Iterator<Item = &str> {
+        let subfields = match self {
+            MarcField::Control { .. } => &[][..],
+            MarcField::Data { subfields, .. } => subfields.as_slice(),
+        };
+        subfields
+            .iter()
+            .filter(move |(subfield, _)| *subfield == code)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// The first subfield with `code`
+    pub fn subfield(&self, code: char) -> Option<&str> {
+        self.subfields(code).next()
+    }
+
+    fn indicator(&self, position: usize) -> char {
+        match self {
+            MarcField::Control { .. } => ' ',
+            MarcField::Data { indicators, .. } => indicators[position],
+        }
+    }
+
+    /// The field's bytes in a record, without the field terminator
+    fn to_bytes(&self) -> Vec<u8> {
+        match self {
+            MarcField::Control { value, .. } => value.as_bytes().to_vec(),
+            MarcField::Data {
+                indicators,
+                subfields,
+                ..
+            } => {
+                let mut bytes = indicators.iter().collect::<String>().into_bytes();
+                for (code, value) in subfields {
+                    bytes.push(SUBFIELD_DELIMITER);
+                    bytes.extend(code.to_string().as_bytes());
+                    bytes.extend(value.as_bytes());
+                }
+                bytes
+            }
+        }
+    }
+}
+
+/// A MARC 21 bibliographic record: the 24-character leader and the fields in order
+#[derive(Clone, Debug, PartialEq)]
+pub struct MarcRecord {
+    pub(crate) leader: String,
+    pub(crate) fields: Vec<MarcField>,
+}
+
+impl MarcRecord {
+    /// Fields with `tag`, in order
+    pub fn fields<'a>(&'a self, tag: &'a str) -> impl Iterator<Item = &'a MarcField> + 'a {
+        self.fields.iter().filter(move |field| field.tag() == tag)
+    }
+
+    /// The first field with `tag`
+    pub fn field(&self, tag: &str) -> Option<&MarcField> {
+        self.fields.iter().find(|field| field.tag() == tag)
+    }
+
+    /// Value of the control field with `tag`
+    pub fn control(&self, tag: &str) -> Option<&str> {
+        self.fields.iter().find_map(|field| match field {
+            MarcField::Control { tag: name, value } if name == tag => Some(value.as_str()),
+            _ => None,
+        })
+    }
+
+    /// Read one ISO 2709 record. Records must be UTF-8; MARC-8 is only read when it is plain ASCII.
+    pub fn from_bytes(bytes: &[u8]) -> Result<MarcRecord, String> {
+        let text = |bytes: &[u8]| {
+            String::from_utf8(bytes.to_vec())
+                .map_err(|_| "record is not UTF-8; convert MARC-8 records first".to_string())
+        };
+        let number = |bytes: &[u8], what: &str| {
+            std::str::from_utf8(bytes)
+                .ok()
+                .and_then(|digits| digits.parse::<usize>().ok())
+                .ok_or_else(|| format!("{what} is not a number"))
+        };
+        if bytes.len() < 24 {
+            return Err("record is shorter than its leader".to_string());
+        }
+        let leader = text(&bytes[..24])?;
+        let base = number(&bytes[12..17], "base address")?;
+        if base < 25 || base > bytes.len() || bytes[base - 1] != FIELD_TERMINATOR {
+            return Err("directory does not end at the base address".to_string());
+        }
+        let mut fields = Vec::new();
+        for entry in bytes[24..base - 1].chunks(12) {
+            if entry.len() < 12 {
+                return Err("directory entry is cut short".to_string());
+            }
+            let tag = text(&entry[..3])?;
+            let length = number(&entry[3..7], "field length")?;
+            let start = base + number(&entry[7..12], "field start")?;
+            let field = bytes
+                .get(start..start + length)
+                .ok_or_else(|| format!("field {tag} runs past the end of the record"))?;
+            let field = field.strip_suffix(&[FIELD_TERMINATOR]).unwrap_or(field);
+            if tag.starts_with("00") {
+                fields.push(MarcField::Control {
+                    tag,
+                    value: text(field)?,
+                });
+                continue;
+            }
+            if field.len() < 2 {
+                return Err(format!("field {tag} has no indicators"));
+            }
+            let indicators = text(&field[..2])?;
+            let mut subfields = Vec::new();
+            for subfield in field[2..].split(|byte| *byte == SUBFIELD_DELIMITER).skip(1) {
+                let subfield = text(subfield)?;
+                let mut chars = subfield.chars();
+                if let Some(code) = chars.next() {
+                    subfields.push((code, chars.as_str().to_string()));
+                }
+            }
+            let mut chars = indicators.chars();
+            fields.push(MarcField::Data {
+                tag,
+                indicators: [chars.next().unwrap_or(' '), chars.next().unwrap_or(' ')],
+                subfields,
+            });
+        }
+        Ok(MarcRecord { leader, fields })
+    }
+
+    /// The record as ISO 2709, with the leader's lengths and base address worked out
+    pub fn to_bytes(&self) -> Result<Vec<u8>, String> {
+        let mut directory = Vec::new();
+        let mut data = Vec::new();
+        for field in &self.fields {
+            let mut bytes = field.to_bytes();
+            bytes.push(FIELD_TERMINATOR);
+            if bytes.len() > 9999 || data.len() > 99999 {
+                return Err(format!("field {} is too long for ISO 2709", field.tag()));
+            }
+            directory
+                .extend(format!("{:3.3}{:04}{:05}", field.tag(), bytes.len(), data.len()).bytes());
+            data.extend(bytes);
+        }
+        let base = 24 + directory.len() + 1;
+        let length = base + data.len() + 1;
+        if length > 99999 {
+            return Err("record is too long for ISO 2709".to_string());
+        }
+        let mut leader: Vec<char> = format!("{:24.24}", self.leader).chars().collect();
+        leader.splice(0..5, format!("{length:05}").chars());
+        leader.splice(12..17, format!("{base:05}").chars());
+        leader.splice(10..12, ['2', '2']);
+        leader.splice(20..24, ['4', '5', '0', '0']);
+        if !data.is_ascii() {
+            leader[9] = 'a';
+        }
+        let mut bytes = leader.into_iter().collect::<String>().into_bytes();
+        bytes.extend(directory);
+        bytes.push(FIELD_TERMINATOR);
+        bytes.extend(data);
+        bytes.push(RECORD_TERMINATOR);
+        Ok(bytes)
+    }
+}
+
+/// Reads the records of an ISO 2709 file one at a time
+pub struct MarcReader<R: BufRead> {
+    reader: R,
+}
+
+impl<R: BufRead> MarcReader<R> {
+    pub fn new(reader: R) -> MarcReader<R> {
+        MarcReader { reader }
+    }
+}
+
+impl<R: BufRead> Iterator for MarcReader<R> {
+    type Item = Result<MarcRecord, String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut bytes = Vec::new();
+        match self.reader.read_until(RECORD_TERMINATOR, &mut bytes) {
+            Err(err) => return Some(Err(err.to_string())),
+            Ok(0) => return None,
+            Ok(_) => {}
+        }
+        // some files put a line break between records
+        let start = bytes.iter().position(|byte| !byte.is_ascii_whitespace())?;
+        Some(MarcRecord::from_bytes(&bytes[start..]))
+    }
+}
+
+/// A value without the ISBD punctuation that ends it, e.g. `Knuth, Donald E.,` or `Title /`.
+/// A final full stop after an initial or short abbreviation such as `E.`, `ed.` or `p.` is kept.
+fn trim_punctuation(value: &str) -> String {
+    let value = value
+        .trim()
+        .trim_end_matches([' ', '/', ':', ';', ',', '=', '+']);
+    let Some(rest) = value.strip_suffix('.') else {
+        return value.to_string();
+    };
+    let last_word = rest.rsplit(' ').next().unwrap_or(rest);
+    let abbreviation = last_word.chars().count() <= 3 && !last_word.contains(char::is_uppercase);
+    match last_word.chars().count() <= 1 || abbreviation {
+        true => value.to_string(),
+        false => rest.to_string(),
+    }
+}
+
+/// Role of a person from the relator term in `$e` or code in `$4`
+fn person_role(field: &MarcField) -> Role {
+    let relator = field
+        .subfield('e')
+        .or(field.subfield('4'))
+        .unwrap_or_default()
+        .to_lowercase();
+    match relator.trim() {
+        relator if relator.starts_with("ed") => Role::Editor,
+        relator if relator.starts_with("tr") => Role::Translator,
+        relator if relator.starts_with("il") => Role::Illustrator,
+        _ => Role::Author,
+    }
+}
+
+/// Role of an organization from the relator term in `$e` or code in `$4`
+fn organization_role(field: &MarcField) -> OrgRole {
+    let relator = field
+        .subfield('e')
+        .or(field.subfield('4'))
+        .unwrap_or_default()
+        .to_lowercase();
+    match relator.trim() {
+        relator if relator.starts_with("spon") || relator == "spn" => OrgRole::Sponsor,
+        relator if relator.starts_with("degree") || relator == "dgg" => OrgRole::DegreeGranting,
+        _ => OrgRole::Author,
+    }
+}
+
+/// Volume and pages from the related parts of a 773, e.g. `Vol. 11, no. 3 (Mar. 1968), p. 147-148`
+fn host_parts(related: &str) -> (String, String) {
+    let mut volume = String::new();
+    let mut pages = String::new();
+    for part in related.split(',').map(str::trim) {
+        let lower = part.to_lowercase();
+        if let Some(rest) = ["vol.", "v."]
+            .iter()
+            .find_map(|prefix| lower.strip_prefix(prefix))
+        {
+            volume = rest
+                .split_whitespace()
+                .next()
+                .unwrap_or_default()
+                .to_string();
+        } else if let Some(rest) = ["pp.", "p."]
+            .iter()
+            .find_map(|prefix| lower.strip_prefix(prefix))
+        {
+            pages = trim_punctuation(rest).trim_end_matches('.').to_string();
+        }
+    }
+    (volume, pages)
+}
+
+/// Where a record read from a file came from, e.g. `record 3 (001 ocm01234567)`
+fn source(number: usize, record: &MarcRecord) -> String {
+    match record.control("001") {
+        Some(control_number) => format!("record {number} ({control_number})"),
+        None => format!("record {number}"),
+    }
+}
+
+/// The form for a MARC record: a book for a monograph, an article for a component part
+/// with a 773 host item. Other records have no form and are kept as they are.
+pub fn import_record(source: String, record: &MarcRecord) -> Result<ImportRecord, String> {
+    let leader: Vec<char> = record.leader.chars().collect();
+    let (record_type, level) = (leader.get(6).copied(), leader.get(7).copied());
+    let entry_type = match (record_type, level, record.field("773").is_some()) {
+        (Some('a' | 't'), Some('a' | 'b'), true) => EntryType::Article,
+        (Some('a' | 't'), Some('m'), _) => EntryType::Book,
+        _ => {
+            let kind = leader.get(6..8).map(String::from_iter).unwrap_or_default();
+            return Err(format!("leader type `{kind}` is not a book or article"));
+        }
+    };
+    let first = |tag: &str, code: char| {
+        record
+            .fields(tag)
+            .find_map(|field| field.subfield(code))
+            .map(trim_punctuation)
+            .unwrap_or_default()
+    };
+    let mut people = Vec::new();
+    for field in record.fields("100").chain(record.fields("700")) {
+        if let Some(name) = field.subfield('a') {
+            match person_role(field) {
+                Role::Author => people.push(trim_punctuation(name)),
+                role => people.push(format!("{} ({role})", trim_punctuation(name))),
+            }
+        }
+    }
+    let mut organizations = Vec::new();
+    for field in record.fields("110").chain(record.fields("710")) {
+        let name = field
+            .subfields('a')
+            .chain(field.subfields('b'))
+            .map(trim_punctuation)
+            .collect::<Vec<_>>()
+            .join(". ");
+        match organization_role(field) {
+            _ if name.is_empty() => {}
+            OrgRole::Author => organizations.push(name),
+            role => organizations.push(format!("{name} ({role})")),
+        }
+    }
+    // 264 second indicator 1 is the publication statement; 260 is its older form
+    let publication = record
+        .fields("264")
+        .find(|field| field.indicator(1) == '1')
+        .or(record.field("260"));
+    let published = |code| {
+        publication
+            .and_then(|field| field.subfield(code))
+            .map(|value| trim_punctuation(value).replace(['[', ']'], ""))
+            .unwrap_or_default()
+    };
+    let date = match published('c') {
+        date if date.is_empty() => record
+            .control("008")
+            .and_then(|fixed| fixed.get(7..11))
+            .filter(|year| year.chars().all(|c| c.is_ascii_digit()))
+            .unwrap_or_default()
+            .to_string(),
+        date => date.trim_end_matches('.').to_string(),
+    };
+    let title = match (first("245", 'a'), first("245", 'b')) {
+        (title, subtitle) if subtitle.is_empty() => title,
+        (title, subtitle) => format!("{title}: {subtitle}"),
+    };
+    let note = record
+        .fields("500")
+        .filter_map(|field| field.subfield('a'))
+        .map(str::trim)
+        .collect::<Vec<_>>()
+        .join(" ");
+    let mut form = ImportRecord::new(source, entry_type);
+    form.set("Authors", &people.join("; "));
+    form.set("Title", &title);
+    form.set("Edition", &first("250", 'a'));
+    form.set("Date", &date);
+    form.set("Note", &note);
+    form.set("Organizations", &organizations.join("; "));
+    match entry_type {
+        EntryType::Article => {
+            let host = record.field("773");
+            let related = host
+                .and_then(|field| field.subfield('g'))
+                .unwrap_or_default();
+            let (volume, pages) = host_parts(related);
+            form.set("Journal", &first("773", 't'));
+            form.set("Volume", &volume);
+            form.set("Pages", &pages);
+            // a host item's `$d` reads `Place : Publisher, Year`
+            let host_publisher = first("773", 'd');
+            let host_publisher = host_publisher
+                .rsplit(':')
+                .next()
+                .and_then(|rest| rest.split(',').next())
+                .unwrap_or_default();
+            match published('b') {
+                publisher if publisher.is_empty() => form.set("Publisher", host_publisher),
+                publisher => form.set("Publisher", &publisher),
+            }
+        }
+        _ => {
+            let isbn = first("020", 'a');
+            form.set("ISBN", isbn.split_whitespace().next().unwrap_or_default());
+            form.set("Pages", &first("300", 'a'));
+            form.set("Series", &first("490", 'a'));
+            match first("490", 'v') {
+                volume if volume.is_empty() => form.set("Volume", &first("245", 'n')),
+                volume => form.set("Volume", &volume),
+            }
+            form.set("Publisher", &published('b'));
+        }
+    }
+    Ok(form)
+}
+
+/// The mapped fields for the form of a book or article, in tag order
+fn mapped_fields(form: &ImportRecord, cite_key: &str) -> DbResult<Vec<MarcField>> {
+    let mut fields = Vec::new();
+    let isbn = form.get("ISBN");
+    fields.push(MarcField::data("020", "  ", &[('a', isbn)]));
+
+    let authors = parse_authors(cite_key, form.get("Authors"))?;
+    let main_author = authors
+        .iter()
+        .position(|author| author.role == Role::Author);
+    let organizations = parse_organizations(cite_key, form.get("Organizations"))?;
+    let main_body = match main_author {
+        Some(_) => None,
+        None => organizations
+            .iter()
+            .position(|organization| organization.role == OrgRole::Author),
+    };
+    if let Some(index) = main_author {
+        fields.push(MarcField::data(
+            "100",
+            "1 ",
+            &[('a', &authors[index].name())],
+        ));
+    }
+    if let Some(index) = main_body {
+        let name = &organizations[index].organization;
+        fields.push(MarcField::data("110", "2 ", &[('a', name)]));
+    }
+
+    let (title, subtitle) = form
+        .get("Title")
+        .split_once(": ")
+        .unwrap_or((form.get("Title"), ""));
+    let indicator = match main_author.or(main_body) {
+        Some(_) => "10",
+        None => "00",
+    };
+    let series = form.get("Series");
+    let volume = match (form.entry_type, series.is_empty()) {
+        (EntryType::Book, true) => form.get("Volume"),
+        _ => "",
+    };
+    fields.push(MarcField::data(
+        "245",
+        indicator,
+        &[('a', title), ('b', subtitle), ('n', volume)],
+    ));
+    fields.push(MarcField::data("250", "  ", &[('a', form.get("Edition"))]));
+    fields.push(MarcField::data(
+        "264",
+        " 1",
+        &[('b', form.get("Publisher")), ('c', form.get("Date"))],
+    ));
+    if form.entry_type == EntryType::Book {
+        fields.push(MarcField::data("300", "  ", &[('a', form.get("Pages"))]));
+        fields.push(MarcField::data(
+            "490",
+            "0 ",
+            &[('a', series), ('v', form.get("Volume"))],
+        ));
+    }
+    fields.push(MarcField::data("500", "  ", &[('a', form.get("Note"))]));
+
+    for (index, author) in authors.iter().enumerate() {
+        if Some(index) != main_author {
+            let role = match author.role {
+                Role::Author => String::new(),
+                role => role.to_string(),
+            };
+            fields.push(MarcField::data(
+                "700",
+                "1 ",
+                &[('a', &author.name()), ('e', &role)],
+            ));
+        }
+    }
+    for (index, organization) in organizations.iter().enumerate() {
+        if Some(index) != main_body {
+            let role = match organization.role {
+                OrgRole::Author => String::new(),
+                role => role.to_string().replace('-', " "),
+            };
+            let name = &organization.organization;
+            fields.push(MarcField::data("710", "2 ", &[('a', name), ('e', &role)]));
+        }
+    }
+    if form.entry_type == EntryType::Article {
+        let related = [("Vol. ", form.get("Volume")), ("p. ", form.get("Pages"))]
+            .iter()
+            .filter(|(_, value)| !value.is_empty())
+            .map(|(prefix, value)| format!("{prefix}{value}"))
+            .collect::<Vec<_>>()
+            .join(", ");
+        fields.push(MarcField::data(
+            "773",
+            "0 ",
+            &[('t', form.get("Journal")), ('g', &related)],
+        ));
+    }
+    fields.retain(|field| match field {
+        MarcField::Control { .. } => true,
+        MarcField::Data { subfields, .. } => !subfields.is_empty(),
+    });
+    Ok(fields)
+}
+
+/// The MARC record for a book or article. A record imported from MARC is written back as it was
+/// read unless the book or article has been changed since; then its mapped fields are replaced
+/// and the others kept. A record not imported from MARC is written from scratch.
+pub fn marc_record(catalog: &Catalog, record: &Record) -> DbResult<Option<MarcRecord>> {
+    let Some(form) = record_form(catalog, record)? else {
+        return Ok(None);
+    };
+    let (cite_key, entry_type) = (record.cite_key(), form.entry_type);
+    let kept = kept_record(catalog, cite_key)?;
+    if let Some(kept) = &kept {
+        if import_record(cite_key.to_string(), kept).is_ok_and(|read| read.lines == form.lines) {
+            return Ok(Some(kept.clone()));
+        }
+    }
+    let mut marc = kept.unwrap_or_else(|| {
+        let mut leader = NEW_LEADER.to_string();
+        if entry_type == EntryType::Article {
+            leader.replace_range(7..8, "a");
+        }
+        MarcRecord {
+            leader,
+            fields: vec![MarcField::Control {
+                tag: "001".to_string(),
+                value: cite_key.to_string(),
+            }],
+        }
+    });
+    marc.fields
+        .retain(|field| !MAPPED_TAGS.contains(&field.tag()));
+    for field in mapped_fields(&form, cite_key)? {
+        let index = marc
+            .fields
+            .iter()
+            .position(|kept| kept.tag() > field.tag())
+            .unwrap_or(marc.fields.len());
+        marc.fields.insert(index, field);
+    }
+    Ok(Some(marc))
+}
+
+/// Keep an imported record for re-export, with the `cite_key` it was saved as or `None` when
+/// it could not be mapped
+fn keep_record(catalog: &Catalog, cite_key: Option<&str>, record: &MarcRecord) -> DbResult<()> {
+    let bytes = record.to_bytes().map_err(DbError::InvalidInput)?;
+    let query = "INSERT INTO marc_record (marc_record_id, cite_key, added, record)
+        VALUES (?, ?, (SELECT COALESCE(MAX(added), 0) + 1 FROM marc_record), ?)";
+    catalog.with_statement(query, |statement| {
+        statement.bind((1, Uuid::new_v4().to_string().as_str()))?;
+        statement.bind((2, cite_key))?;
+        statement.bind((3, &bytes[..]))?;
+        statement.next()?;
+        Ok(())
+    })
+}
+
+/// Read a kept record back, reporting a damaged one as a schema error
+fn read_kept(statement: &sqlite::Statement) -> DbResult<MarcRecord> {
+    let bytes = statement.read::<Vec<u8>, _>("record")?;
+    MarcRecord::from_bytes(&bytes)
+        .map_err(|reason| DbError::Schema(format!("marc_record: {reason}")))
+}
+
+/// The MARC record `cite_key` was imported from, if any
+pub fn kept_record(catalog: &Catalog, cite_key: &str) -> DbResult<Option<MarcRecord>> {
+    let query = "SELECT record FROM marc_record WHERE cite_key = ?";
+    catalog.with_statement(query, |statement| {
+        statement.bind((1, cite_key))?;
+        match statement.next()? {
+            State::Row => Ok(Some(read_kept(statement)?)),
+            State::Done => Ok(None),
+        }
+    })
+}
+
+/// Up to `limit` imported records that could not be mapped onto a book or article, in the order
+/// imported, starting after the one added at `after`; each comes with its `added`
+fn unmapped_records(
+    catalog: &Catalog,
+    after: i64,
+    limit: usize,
+) -> DbResult<Vec<(i64, MarcRecord)>> {
+    let query = "SELECT added, record FROM marc_record WHERE cite_key IS NULL AND added > ?
+        ORDER BY added LIMIT ?";
+    catalog.with_statement(query, |statement| {
+        statement.bind((1, after))?;
+        statement.bind((2, limit as i64))?;
+        let mut records = Vec::new();
+        while let State::Row = statement.next()? {
+            records.push((statement.read::<i64, _>("added")?, read_kept(statement)?));
+        }
+        Ok(records)
+    })
+}
+
+/// Save one batch of records through `import_records` and keep each MARC record that was
+/// saved, or that could not be mapped or saved, for re-export
+fn import_batch(
+    catalog: &Catalog,
+    batch: &[(String, Result<MarcRecord, String>)],
+    report: &mut ImportReport,
+) -> DbResult<()> {
+    let mut forms = Vec::new();
+    let mut records = HashMap::new();
+    let mut unmapped = Vec::new();
+    for (source, record) in batch {
+        let record = match record {
+            Ok(record) => record,
+            Err(reason) => {
+                report.skip(source.clone(), reason.clone());
+                continue;
+            }
+        };
+        records.insert(source.as_str(), record);
+        match import_record(source.clone(), record) {
+            Ok(form) => forms.push(form),
+            Err(reason) => unmapped.push((source.clone(), reason)),
+        }
+    }
+    let saved = import_records(catalog, &forms, false)?;
+    for (source, cite_key) in &saved.imported {
+        keep_record(catalog, Some(cite_key), records[source.as_str()])?;
+    }
+    for (source, reason) in saved.skipped.into_iter().chain(unmapped) {
+        keep_record(catalog, None, records[source.as_str()])?;
+        report.skip(source, format!("{reason}; kept for MARC export"));
+    }
+    report.append(ImportReport {
+        imported: saved.imported,
+        duplicates: saved.duplicates,
+        ..ImportReport::default()
+    });
+    Ok(())
+}
+
+/// Import MARC records as they are read, a batch at a time, in one transaction. Books and
+/// articles are saved through `import_records`; every record read is kept so that fields the
+/// catalog has no place for, and records it cannot map at all, survive re-export.
+pub fn import_marc<I>(catalog: &Catalog, records: I, dry_run: bool) -> DbResult<ImportReport>
+where
+    I: IntoIterator<Item = Result<MarcRecord, String>>,
+{
+    let import = |catalog: &Catalog| {
+        let mut report = ImportReport {
+            dry_run,
+            ..ImportReport::default()
+        };
+        let mut batch = Vec::new();
+        for (index, record) in records.into_iter().enumerate() {
+            let source = match &record {
+                Ok(record) => source(index + 1, record),
+                Err(_) => format!("record {}", index + 1),
+            };
+            batch.push((source, record));
+            if batch.len() == IMPORT_BATCH {
+                import_batch(catalog, &batch, &mut report)?;
+                batch.clear();
+            }
+        }
+        import_batch(catalog, &batch, &mut report)?;
+        Ok(report)
+    };
+    match dry_run {
+        true => catalog.dry_run(import),
+        false => catalog.transaction(import),
+    }
+}
+
+/// Pass the books and articles with `cite_keys` to `write` as MARC records, in that order,
+/// followed by the imported records that could not be mapped when `with_unmapped` is set.
+/// Other item types are left out. Records are read a batch at a time, so an export of any size
+/// can be written as it goes. Returns the number of records passed.
+pub fn for_each_marc_record<E, F>(
+    catalog: &Catalog,
+    cite_keys: &[&str],
+    with_unmapped: bool,
+    mut write: F,
+) -> Result<usize, E>
+where
+    E: From<DbError>,
+    F: FnMut(&MarcRecord) -> Result<(), E>,
+{
+    let mut count = 0;
+    for batch in cite_keys.chunks(IMPORT_BATCH) {
+        for record in read_records_with_keys(catalog, batch)? {
+            if let Some(marc) = marc_record(catalog, &record)? {
+                write(&marc)?;
+                count += 1;
+            }
+        }
+    }
+    let (mut after, mut more) = (0, with_unmapped);
+    while more {
+        let batch = unmapped_records(catalog, after, IMPORT_BATCH)?;
+        for (added, marc) in &batch {
+            write(marc)?;
+            after = *added;
+            count += 1;
+        }
+        more = batch.len() == IMPORT_BATCH;
+    }
+    Ok(count)
+}
+
+/// The books and articles with `cite_keys` as MARC records, followed by the unmapped records
+/// when `with_unmapped` is set, as `for_each_marc_record` passes them
+pub fn marc_records(
+    catalog: &Catalog,
+    cite_keys: &[&str],
+    with_unmapped: bool,
+) -> DbResult<Vec<MarcRecord>> {
+    let mut records = Vec::new();
+    for_each_marc_record(catalog, cite_keys, with_unmapped, |record| {
+        records.push(record.clone());
+        Ok::<_, DbError>(())
+    })?;
+    Ok(records)
+}
+
+/// The books and articles with `cite_keys`, and the unmapped records when `with_unmapped` is
+/// set, as an ISO 2709 file. Returns the bytes and the number of records written.
+pub fn export_marc(
+    catalog: &Catalog,
+    cite_keys: &[&str],
+    with_unmapped: bool,
+) -> DbResult<(Vec<u8>, usize)> {
+    let mut bytes = Vec::new();
+    let count = for_each_marc_record(catalog, cite_keys, with_unmapped, |record| {
+        bytes.extend(record.to_bytes().map_err(DbError::InvalidInput)?);
+        Ok::<_, DbError>(())
+    })?;
+    Ok((bytes, count))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::db::{Book, RowSelect, TestDatabase};
+    use crate::listing::{ListView, SortKey};
+    use serial_test::serial;
+
+    fn control(tag: &str, value: &str) -> MarcField {
+        MarcField::Control {
+            tag: tag.to_string(),
+            value: value.to_string(),
+        }
+    }
+
+    /// A cataloged book with a subject heading the catalog has no field for
+    fn book_record() -> MarcRecord {
+        MarcRecord {
+            leader: "00000cam a2200000 i 4500".to_string(),
+            fields: vec![
+                control("001", "ocm00012345"),
+                control("008", "680301s1968    mau           001 0 eng  "),
+                MarcField::data("020", "  ", &[('a', "0201038013 (v. 1)")]),
+                MarcField::data("100", "1 ", &[('a', "Knuth, Donald E.,"), ('e', "author.")]),
+                MarcField::data(
+                    "245",
+                    "14",
+                    &[
+                        ('a', "The art of computer programming /"),
+                        ('c', "Donald E. Knuth."),
+                    ],
+                ),
+                MarcField::data("250", "  ", &[('a', "2nd ed.")]),
+                MarcField::data(
+                    "264",
+                    " 1",
+                    &[
+                        ('a', "Reading, Mass. :"),
+                        ('b', "Addison-Wesley,"),
+                        ('c', "[1968]"),
+                    ],
+                ),
+                MarcField::data("300", "  ", &[('a', "xxi, 634 p. ;"), ('c', "25 cm.")]),
+                MarcField::data(
+                    "490",
+                    "1 ",
+                    &[
+                        ('a', "Addison-Wesley series in computer science ;"),
+                        ('v', "1"),
+                    ],
+                ),
+                MarcField::data("500", "  ", &[('a', "Includes index.")]),
+                MarcField::data(
+                    "650",
+                    " 0",
+                    &[('a', "Electronic digital computers"), ('x', "Programming.")],
+                ),
+                MarcField::data("700", "1 ", &[('a', "Gödel, Kurt,"), ('e', "editor.")]),
+            ],
+        }
+    }
+
+    fn article_record() -> MarcRecord {
+        MarcRecord {
+            leader: "00000nab a2200000 a 4500".to_string(),
+            fields: vec![
+                MarcField::data("100", "1 ", &[('a', "Dijkstra, Edsger W.")]),
+                MarcField::data("245", "10", &[('a', "Go to statement considered harmful.")]),
+                MarcField::data("264", " 1", &[('c', "1968-03")]),
+                MarcField::data(
+                    "773",
+                    "0 ",
+                    &[
+                        ('t', "Communications of the ACM."),
+                        ('d', "New York : ACM, 1958-"),
+                        ('g', "Vol. 11, no. 3 (Mar. 1968), p. 147-148"),
+                    ],
+                ),
+            ],
+        }
+    }
+
+    /// A map, which is neither a book nor an article
+    fn map_record() -> MarcRecord {
+        MarcRecord {
+            leader: "00000nem a2200000 a 4500".to_string(),
+            fields: vec![
+                MarcField::data("034", "1 ", &[('a', "a"), ('b', "25000")]),
+                MarcField::data("245", "00", &[('a', "Lake District.")]),
+            ],
+        }
+    }
+
+    #[test]
+    fn reads_and_writes_iso_2709() {
+        let record = book_record();
+        let bytes = record.to_bytes().unwrap();
+        assert_eq!(&bytes[..5], format!("{:05}", bytes.len()).as_bytes());
+        assert_eq!(bytes.last(), Some(&RECORD_TERMINATOR));
+        // 12 directory entries of 12 bytes after the leader, then the field terminator
+        assert_eq!(&bytes[12..17], b"00169");
+        assert_eq!(&bytes[24..36], b"001001200000");
+        assert_eq!(bytes[168], FIELD_TERMINATOR);
+        assert_eq!(&bytes[9..10], b"a");
+
+        let read = MarcRecord::from_bytes(&bytes).unwrap();
+        assert_eq!(read.fields, record.fields);
+        assert_eq!(read.to_bytes().unwrap(), bytes);
+        assert_eq!(read.control("001"), Some("ocm00012345"));
+        assert_eq!(
+            read.field("650").and_then(|field| field.subfield('x')),
+            Some("Programming.")
+        );
+
+        // records one after another, with or without a line break between them
+        let mut file = bytes.clone();
+        file.push(b'\n');
+        file.extend(article_record().to_bytes().unwrap());
+        file.extend(&bytes[..100]);
+        let records: Vec<_> = MarcReader::new(&file[..]).collect();
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[0].as_ref().unwrap(), &read);
+        assert_eq!(records[1].as_ref().unwrap().fields, article_record().fields);
+        assert!(records[2].is_err());
+
+        assert_eq!(trim_punctuation("Knuth, Donald E.,"), "Knuth, Donald E.");
+        assert_eq!(
+            trim_punctuation("The art of programming /"),
+            "The art of programming"
+        );
+        assert_eq!(trim_punctuation("Includes index."), "Includes index");
+        assert_eq!(trim_punctuation("2nd ed."), "2nd ed.");
+        assert_eq!(
+            trim_punctuation("Communications of the ACM."),
+            "Communications of the ACM"
+        );
+        assert_eq!(trim_punctuation("xxi, 634 p. ;"), "xxi, 634 p.");
+        assert_eq!(
+            host_parts("Vol. 11, no. 3 (Mar. 1968), p. 147-148"),
+            ("11".to_string(), "147-148".to_string())
+        );
+    }
+
+    #[test]
+    fn maps_books_and_articles() {
+        let book = import_record("record 1".to_string(), &book_record()).unwrap();
+        assert_eq!(book.entry_type, EntryType::Book);
+        assert_eq!(
+            book.get("Authors"),
+            "Knuth, Donald E.; Gödel, Kurt (editor)"
+        );
+        assert_eq!(book.get("Title"), "The art of computer programming");
+        assert_eq!(book.get("Pages"), "xxi, 634 p.");
+        assert_eq!(book.get("Volume"), "1");
+        assert_eq!(book.get("Edition"), "2nd ed.");
+        assert_eq!(book.get("Date"), "1968");
+        assert_eq!(
+            book.get("Series"),
+            "Addison-Wesley series in computer science"
+        );
+        assert_eq!(book.get("Publisher"), "Addison-Wesley");
+        assert_eq!(book.get("ISBN"), "0201038013");
+
+        let article = import_record("record 2".to_string(), &article_record()).unwrap();
+        assert_eq!(article.entry_type, EntryType::Article);
+        assert_eq!(article.get("Journal"), "Communications of the ACM");
+        assert_eq!(article.get("Volume"), "11");
+        assert_eq!(article.get("Pages"), "147-148");
+        assert_eq!(article.get("Date"), "1968-03");
+        assert_eq!(article.get("Publisher"), "ACM");
+
+        assert_eq!(
+            import_record("record 3".to_string(), &map_record()).unwrap_err(),
+            "leader type `em` is not a book or article"
+        );
+    }
+
+    #[test]
+    #[serial]
+    fn keeps_marc_records_for_export() {
+        let database = TestDatabase::new();
+        let catalog = database.catalog();
+        let records = || {
+            [book_record(), article_record(), map_record()]
+                .into_iter()
+                .map(Ok)
+                .chain([Err("record is shorter than its leader".to_string())])
+        };
+
+        let report = import_marc(&catalog, records(), true).unwrap();
+        assert_eq!(report.imported(), 2);
+        assert!(unmapped_records(&catalog, 0, 10).unwrap().is_empty());
+
+        let report = import_marc(&catalog, records(), false).unwrap();
+        assert_eq!(report.imported(), 2);
+        assert_eq!(report.imported[0].0, "record 1 (ocm00012345)");
+        assert_eq!(
+            report.skipped,
+            vec![
+                (
+                    "record 4".to_string(),
+                    "record is shorter than its leader".to_string()
+                ),
+                (
+                    "record 3".to_string(),
+                    "leader type `em` is not a book or article; kept for MARC export".to_string()
+                ),
+            ]
+        );
+
+        // unchanged records go out as they came in, the unmapped one after them
+        let cite_keys = ListView::new(SortKey::Added)
+            .cite_keys(&catalog, None)
+            .unwrap();
+        let cite_keys: Vec<&str> = cite_keys.iter().map(String::as_str).collect();
+        let exported = marc_records(&catalog, &cite_keys, true).unwrap();
+        let originals = [book_record(), article_record(), map_record()];
+        assert_eq!(exported.len(), 3);
+        for (exported, original) in exported.iter().zip(&originals) {
+            assert_eq!(exported.to_bytes(), original.to_bytes());
+        }
+        let (bytes, count) = export_marc(&catalog, &cite_keys, false).unwrap();
+        assert_eq!(count, 2);
+        assert_eq!(MarcReader::new(&bytes[..]).count(), 2);
+
+        // a changed book is written with its new values and keeps the fields it had no place for
+        let book = &report.imported[0].1;
+        let mut form = Book::select(&catalog, book).unwrap();
+        form[8] = "Revised printing".to_string();
+        Book::book_update(&catalog, form, book.clone()).unwrap();
+        let exported = marc_records(&catalog, &cite_keys[..1], false).unwrap();
+        let changed = &exported[0];
+        assert_eq!(changed.control("008"), book_record().control("008"));
+        assert!(changed.field("650").is_some());
+        assert_eq!(
+            changed.field("500").and_then(|field| field.subfield('a')),
+            Some("Revised printing")
+        );
+        let tags: Vec<&str> = changed.fields.iter().map(MarcField::tag).collect();
+        assert_eq!(
+            tags,
+            vec![
+                "001", "008", "020", "100", "245", "250", "264", "300", "490", "500", "650", "700"
+            ]
+        );
+        let read = import_record("changed".to_string(), changed).unwrap();
+        assert_eq!(read.lines, Book::select(&catalog, book).unwrap());
+
+        // importing the same file again finds the books and articles already there
+        let report = import_marc(&catalog, records(), false).unwrap();
+        assert_eq!(report.duplicates.len(), 2);
+
+        // a record added in the TUI is written from scratch
+        let lines = [
+            "Dijkstra, Edsger; Hoare, C. A. R. (editor)",
+            "Structured programming: a primer",
+            "220",
+            "",
+            "",
+            "1972",
+            "A.P.I.C. studies in data processing",
+            "Academic Press",
+            "",
+            "",
+            "",
+        ];
+        let cite_key = Book::book_transaction(
+            &catalog,
+            lines.iter().map(|line| line.to_string()).collect(),
+        )
+        .unwrap();
+        let records = read_records_with_keys(&catalog, &[cite_key.as_str()]).unwrap();
+        let written = marc_record(&catalog, &records[0]).unwrap().unwrap();
+        assert_eq!(written.leader, NEW_LEADER);
+        assert_eq!(written.control("001"), Some(cite_key.as_str()));
+        assert_eq!(
+            written.field("245"),
+            Some(&MarcField::data(
+                "245",
+                "10",
+                &[('a', "Structured programming"), ('b', "a primer")]
+            ))
+        );
+        assert_eq!(
+            written.field("700"),
+            Some(&MarcField::data(
+                "700",
+                "1 ",
+                &[('a', "Hoare, C. A. R."), ('e', "editor")]
+            ))
+        );
+        let read = import_record(cite_key.clone(), &written).unwrap();
+        assert_eq!(read.lines, Book::select(&catalog, &cite_key).unwrap());
+    }
+
+    #[test]
+    #[serial]
+    // A brief record with only a title, as a minimal-level cataloging record has it
+    fn imports_record_without_physical_description() {
+        let database = TestDatabase::new();
+        let catalog = database.catalog();
+        let brief = MarcRecord {
+            leader: "00000nam a2200000 7 4500".to_string(),
+            fields: vec![MarcField::data(
+                "245",
+                "00",
+                &[('a', "Notes on structured programming.")],
+            )],
+        };
+        let report = import_marc(&catalog, [Ok(brief.clone())], false).unwrap();
+        assert_eq!(report.skipped, vec![]);
+        assert_eq!(report.imported(), 1);
+        assert!(unmapped_records(&catalog, 0, 10).unwrap().is_empty());
+
+        let cite_key = report.imported[0].1.as_str();
+        let form = Book::select(&catalog, cite_key).unwrap();
+        assert_eq!(form[1], "Notes on structured programming");
+        assert_eq!((form[2].as_str(), form[5].as_str()), ("", ""));
+        let exported = marc_records(&catalog, &[cite_key], false).unwrap();
+        assert_eq!(exported[0].to_bytes(), brief.to_bytes());
+    }
+}
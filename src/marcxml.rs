@@ -0,0 +1,322 @@
+use crate::db::{Catalog, DbResult};
+use crate::import::ImportReport;
+use crate::marc::{for_each_marc_record, import_marc, MarcField, MarcRecord};
+use crate::xml::{escape, Elements, XmlElement};
+use std::error::Error;
+use std::io::{BufRead, Write};
+
+pub const MARCXML_NAMESPACE: &str = "http://www.loc.gov/MARC21/slim";
+
+/// Text written before the first record of a collection
+pub fn collection_start() -> String {
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<collection xmlns=\"{MARCXML_NAMESPACE}\">\n"
+    )
+}
+
+pub const COLLECTION_END: &str = "</collection>\n";
+
+/// One record as a MARCXML `<record>` element, indented to sit inside a collection
+pub fn record_xml(record: &MarcRecord) -> String {
+    let mut xml = String::from("  <record>\n");
+    xml.push_str(&format!(
+        "    <leader>{}</leader>\n",
+        escape(&record.leader)
+    ));
+    for field in &record.fields {
+        match field {
+            MarcField::Control { tag, value } => xml.push_str(&format!(
+                "    <controlfield tag=\"{}\">{}</controlfield>\n",
+                escape(tag),
+                escape(value)
+            )),
+            MarcField::Data {
+                tag,
+                indicators,
+                subfields,
+            } => {
+                xml.push_str(&format!(
+                    "    <datafield tag=\"{}\" ind1=\"{}\" ind2=\"{}\">\n",
+                    escape(tag),
+                    escape(&indicators[0].to_string()),
+                    escape(&indicators[1].to_string())
+                ));
+                for (code, value) in subfields {
+                    xml.push_str(&format!(
+                        "      <subfield code=\"{}\">{}</subfield>\n",
+                        escape(&code.to_string()),
+                        escape(value)
+                    ));
+                }
+                xml.push_str("    </datafield>\n");
+            }
+        }
+    }
+    xml.push_str("  </record>\n");
+    xml
+}
+
+/// Indicator `position` of a `<datafield>`, blank when missing
+fn indicator(element: &XmlElement, position: usize) -> char {
+    let name = ["ind1", "ind2"][position];
+    element
+        .attribute(name)
+        .and_then(|value| value.chars().next())
+        .unwrap_or(' ')
+}
+
+/// Read one `<record>` element. The lengths in the leader are not checked; they are worked out
+/// again whenever the record is written as ISO 2709.
+pub fn read_record(element: &XmlElement) -> Result<MarcRecord, String> {
+    let leader = element
+        .child("leader")
+        .map(|leader| leader.text.clone())
+        .unwrap_or_default();
+    if leader.chars().count() != 24 {
+        return Err(format!(
+            "leader `{}` is not 24 characters long",
+            leader.trim()
+        ));
+    }
+    let mut fields = Vec::new();
+    for child in &element.children {
+        let tag = child.attribute("tag").unwrap_or_default().to_string();
+        if child.name.ends_with("field") && tag.len() != 3 {
+            return Err(format!(
+                "{} tag `{tag}` is not three characters",
+                child.name
+            ));
+        }
+        match child.name.as_str() {
+            "controlfield" => fields.push(MarcField::Control {
+                tag,
+                value: child.text.clone(),
+            }),
+            "datafield" => {
+                let mut subfields = Vec::new();
+                for subfield in child.children("subfield") {
+                    let code = subfield
+                        .attribute("code")
+                        .and_then(|code| code.chars().next())
+                        .ok_or_else(|| format!("subfield in field {tag} has no code"))?;
+                    subfields.push((code, subfield.text.clone()));
+                }
+                fields.push(MarcField::Data {
+                    tag,
+                    indicators: [indicator(child, 0), indicator(child, 1)],
+                    subfields,
+                });
+            }
+            _ => {}
+        }
+    }
+    Ok(MarcRecord { leader, fields })
+}
+
+/// Reads the records of a MARCXML file one at a time, whether it holds a `<collection>` or a
+/// single `<record>`
+pub struct MarcXmlReader<R: BufRead> {
+    elements: Elements<R>,
+}
+
+impl<R: BufRead> MarcXmlReader<R> {
+    pub fn new(reader: R) -> MarcXmlReader<R> {
+        MarcXmlReader {
+            elements: Elements::new(reader, "record"),
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for MarcXmlReader<R> {
+    type Item = Result<MarcRecord, String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let element = self.elements.next()?;
+        Some(element.and_then(|element| read_record(&element)))
+    }
+}
+
+/// Import a MARCXML file the way an ISO 2709 file is imported, keeping each record for re-export
+pub fn import_marcxml<R: BufRead>(
+    catalog: &Catalog,
+    reader: R,
+    dry_run: bool,
+) -> DbResult<ImportReport> {
+    import_marc(catalog, MarcXmlReader::new(reader), dry_run)
+}
+
+/// Write the records with `cite_keys` as a MARCXML collection, one record at a time, with the
+/// imported records that are not books or articles at the end when `with_unmapped`
+pub fn write_marcxml<W: Write>(
+    catalog: &Catalog,
+    cite_keys: &[&str],
+    with_unmapped: bool,
+    out: &mut W,
+) -> Result<usize, Box<dyn Error>> {
+    out.write_all(collection_start().as_bytes())?;
+    let count = for_each_marc_record(catalog, cite_keys, with_unmapped, |record| {
+        out.write_all(record_xml(record).as_bytes())
+            .map_err(Box::<dyn Error>::from)
+    })?;
+    out.write_all(COLLECTION_END.as_bytes())?;
+    Ok(count)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::db::TestDatabase;
+    use crate::listing::{ListView, SortKey};
+    use crate::marc::kept_record;
+    use serial_test::serial;
+
+    const COLLECTION: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<marc:collection xmlns:marc="http://www.loc.gov/MARC21/slim">
+  <marc:record>
+    <marc:leader>00000cam a2200000 i 4500</marc:leader>
+    <marc:controlfield tag="001">ocm00012345</marc:controlfield>
+    <marc:datafield tag="100" ind1="1" ind2=" ">
+      <marc:subfield code="a">Knuth, Donald E.,</marc:subfield>
+      <marc:subfield code="e">author.</marc:subfield>
+    </marc:datafield>
+    <marc:datafield tag="245" ind1="1" ind2="4">
+      <marc:subfield code="a">The art of computer programming /</marc:subfield>
+      <marc:subfield code="c">Donald E. Knuth.</marc:subfield>
+    </marc:datafield>
+    <marc:datafield tag="264" ind1=" " ind2="1">
+      <marc:subfield code="a">Reading, Mass. :</marc:subfield>
+      <marc:subfield code="b">Addison-Wesley,</marc:subfield>
+      <marc:subfield code="c">[1968]</marc:subfield>
+    </marc:datafield>
+    <marc:datafield tag="300" ind1=" " ind2=" ">
+      <marc:subfield code="a">xxi, 634 p. ;</marc:subfield>
+    </marc:datafield>
+    <marc:datafield tag="650" ind1=" " ind2="0">
+      <marc:subfield code="a">Computer programming &amp; algorithms.</marc:subfield>
+    </marc:datafield>
+  </marc:record>
+  <marc:record>
+    <marc:leader>00000nab a2200000 a 4500</marc:leader>
+    <marc:datafield tag="100" ind1="1" ind2=" ">
+      <marc:subfield code="a">Dijkstra, Edsger W.</marc:subfield>
+    </marc:datafield>
+    <marc:datafield tag="245" ind1="1" ind2="0">
+      <marc:subfield code="a">Go to statement considered harmful.</marc:subfield>
+    </marc:datafield>
+    <marc:datafield tag="264" ind1=" " ind2="1">
+      <marc:subfield code="b">ACM,</marc:subfield>
+      <marc:subfield code="c">1968-03</marc:subfield>
+    </marc:datafield>
+    <marc:datafield tag="773" ind1="0" ind2=" ">
+      <marc:subfield code="t">Communications of the ACM.</marc:subfield>
+      <marc:subfield code="g">Vol. 11, no. 3 (Mar. 1968), p. 147-148</marc:subfield>
+    </marc:datafield>
+  </marc:record>
+  <marc:record>
+    <marc:leader>short</marc:leader>
+  </marc:record>
+</marc:collection>
+"#;
+
+    #[test]
+    fn reads_marcxml_collections() {
+        let records: Vec<Result<MarcRecord, String>> =
+            MarcXmlReader::new(COLLECTION.as_bytes()).collect();
+        assert_eq!(records.len(), 3);
+        let book = records[0].as_ref().unwrap();
+        assert_eq!(book.control("001"), Some("ocm00012345"));
+        let subject = book.field("650").unwrap();
+        assert_eq!(
+            subject.subfield('a'),
+            Some("Computer programming & algorithms.")
+        );
+        assert_eq!(
+            records[2].as_ref().unwrap_err(),
+            "leader `short` is not 24 characters long"
+        );
+
+        // writing and reading again gives the same record, and the same ISO 2709 bytes
+        let xml = format!(
+            "{}{}{}",
+            collection_start(),
+            record_xml(book),
+            COLLECTION_END
+        );
+        let read: Vec<MarcRecord> = MarcXmlReader::new(xml.as_bytes())
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(&read[0], book);
+        assert_eq!(read[0].to_bytes(), book.to_bytes());
+    }
+
+    #[test]
+    #[serial]
+    fn round_trips_marcxml() {
+        let database = TestDatabase::new();
+        let catalog = database.catalog();
+
+        let report = import_marcxml(&catalog, COLLECTION.as_bytes(), false).unwrap();
+        assert_eq!(report.imported(), 2);
+        assert_eq!(report.skipped.len(), 1);
+        let book = &report.imported[0].1;
+        assert!(kept_record(&catalog, book).unwrap().is_some());
+
+        let cite_keys = ListView::new(SortKey::Added)
+            .cite_keys(&catalog, None)
+            .unwrap();
+        let cite_keys: Vec<&str> = cite_keys.iter().map(String::as_str).collect();
+        let mut out = Vec::new();
+        let count = write_marcxml(&catalog, &cite_keys, true, &mut out).unwrap();
+        assert_eq!(count, 2);
+        let exported = String::from_utf8(out).unwrap();
+        assert!(exported.starts_with(&collection_start()));
+        assert!(exported.contains("Computer programming &amp; algorithms."));
+        let originals: Vec<MarcRecord> = MarcXmlReader::new(COLLECTION.as_bytes())
+            .take(2)
+            .map(Result::unwrap)
+            .collect();
+        let read: Vec<MarcRecord> = MarcXmlReader::new(exported.as_bytes())
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(read.len(), 2);
+        for (read, original) in read.iter().zip(&originals) {
+            assert_eq!(read.to_bytes(), original.to_bytes());
+        }
+
+        let report = import_marcxml(&catalog, exported.as_bytes(), true).unwrap();
+        assert_eq!(report.duplicates.len(), 2);
+
+        // the file ends after a `>` inside a quoted attribute value
+        let truncated = format!(
+            "{}>",
+            &COLLECTION[..COLLECTION.find("ind2=\"4").unwrap() + 7]
+        );
+        let report = import_marcxml(&catalog, truncated.as_bytes(), true).unwrap();
+        assert_eq!(report.imported(), 0);
+        assert_eq!(report.skipped.len(), 1);
+    }
+
+    #[test]
+    #[serial]
+    // A brief record with a title and nothing else, not even a 300 physical description
+    fn imports_brief_records() {
+        let database = TestDatabase::new();
+        let catalog = database.catalog();
+        let brief = format!(
+            "{}<record>
+  <leader>00000nam a2200000 7 4500</leader>
+  <datafield tag=\"245\" ind1=\"0\" ind2=\"0\">
+    <subfield code=\"a\">Notes on structured programming.</subfield>
+  </datafield>
+</record>
+{COLLECTION_END}",
+            collection_start()
+        );
+        let report = import_marcxml(&catalog, brief.as_bytes(), false).unwrap();
+        assert_eq!(report.skipped, vec![]);
+        assert_eq!(report.imported(), 1);
+        assert!(kept_record(&catalog, &report.imported[0].1)
+            .unwrap()
+            .is_some());
+    }
+}
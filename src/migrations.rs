@@ -0,0 +1,670 @@
+use crate::db::{merge_duplicate_publishers, parse_publication_dates, Catalog, DbError, DbResult};
+use crate::search::rebuild_search_index;
+use sqlite::State;
+
+/// Schema migrations, embedded in the binary and applied in order by `Database::open`.
+/// Never edit a migration that has been released; add a new one with the next version instead.
+/// Migrations run before foreign keys are switched on, so tables can be rebuilt with
+/// `CREATE`/`INSERT`/`DROP`/`RENAME` without cascading.
+pub struct Migration {
+    pub version: i64,
+    pub description: &'static str,
+    pub sql: &'static str,
+    /// Data step that SQL cannot express, run after `sql` in the same transaction
+    pub data: Option<fn(&Catalog) -> DbResult<()>>,
+}
+
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "initial tables from init_db",
+        // `IF NOT EXISTS` so catalogs created before migrations existed are adopted as version 1
+        sql: "
+CREATE TABLE IF NOT EXISTS master_entries
+(
+    cite_key      TEXT PRIMARY KEY UNIQUE NOT NULL,
+    entry_type    VARCHAR NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS book
+(
+    book_id     TEXT PRIMARY KEY UNIQUE NOT NULL,
+    cite_key    TEXT REFERENCES master_entries(cite_key),
+    publisher_id TEXT REFERENCES publisher(publisher_id),
+    month_year_id TEXT REFERENCES month_year(month_year_id),
+    author      VARCHAR,
+    title       VARCHAR,
+    pages       VARCHAR,
+    volume      VARCHAR,
+    edition     VARCHAR,
+    year        VARCHAR,
+    series      VARCHAR,
+    publisher   VARCHAR,
+    note        VARCHAR
+);
+
+CREATE TABLE IF NOT EXISTS relationship
+(
+    parent_id   TEXT PRIMARY KEY UNIQUE NOT NULL,
+    child_id    INTEGER,
+    cite_key    TEXT REFERENCES master_entries(cite_key)
+);
+
+CREATE TABLE IF NOT EXISTS author
+(
+    cite_key    TEXT REFERENCES master_entries(cite_key),
+    author_id   TEXT PRIMARY KEY UNIQUE NOT NULL,
+    authors     VARCHAR
+);
+
+CREATE TABLE IF NOT EXISTS publisher
+(
+    publisher_id    TEXT PRIMARY KEY UNIQUE NOT NULL,
+    publisher       VARCHAR,
+    address         VARCHAR
+);
+
+CREATE TABLE IF NOT EXISTS organizations
+(
+    organization_id TEXT PRIMARY KEY UNIQUE NOT NULL,
+    organization    VARCHAR,
+    address         VARCHAR
+);
+
+CREATE TABLE IF NOT EXISTS month_year
+(
+    month_year_id   TEXT PRIMARY KEY UNIQUE NOT NULL,
+    month           VARCHAR,
+    year            INTEGER
+);
+
+CREATE TABLE IF NOT EXISTS article
+(
+    cite_key        TEXT REFERENCES master_entries(cite_key),
+    article_id      TEXT PRIMARY KEY UNIQUE NOT NULL,
+    publisher_id    TEXT REFERENCES publisher(publisher_id),
+    month_year_id   TEXT REFERENCES month_year(month_year_id),
+    title           VARCHAR,
+    journal         VARCHAR,
+    volume          VARCHAR,
+    pages           VARCHAR,
+    note            VARCHAR,
+    year            VARCHAR,
+    edition         VARCHAR,
+    publisher       VARCHAR
+);
+
+CREATE INDEX IF NOT EXISTS book_cite_key ON book(cite_key);
+CREATE INDEX IF NOT EXISTS article_cite_key ON article(cite_key);",
+        data: None,
+    },
+    Migration {
+        version: 2,
+        description: "add isbn to book",
+        sql: "ALTER TABLE book ADD COLUMN isbn VARCHAR NOT NULL DEFAULT '';",
+        data: None,
+    },
+    Migration {
+        version: 3,
+        description: "cascade deletes from master_entries to book and article",
+        // SQLite cannot change a constraint in place, so the tables are rebuilt
+        sql: "
+CREATE TABLE book_new
+(
+    book_id       TEXT PRIMARY KEY UNIQUE NOT NULL,
+    cite_key      TEXT REFERENCES master_entries(cite_key) ON DELETE CASCADE,
+    publisher_id  TEXT REFERENCES publisher(publisher_id),
+    month_year_id TEXT REFERENCES month_year(month_year_id),
+    author        VARCHAR,
+    title         VARCHAR,
+    pages         VARCHAR,
+    volume        VARCHAR,
+    edition       VARCHAR,
+    year          VARCHAR,
+    series        VARCHAR,
+    publisher     VARCHAR,
+    note          VARCHAR,
+    isbn          VARCHAR NOT NULL DEFAULT ''
+);
+INSERT INTO book_new SELECT book_id, cite_key, publisher_id, month_year_id, author, title, pages, volume, edition, year, series, publisher, note, isbn FROM book;
+DROP TABLE book;
+ALTER TABLE book_new RENAME TO book;
+CREATE INDEX book_cite_key ON book(cite_key);
+CREATE INDEX book_publisher_id ON book(publisher_id);
+CREATE INDEX book_month_year_id ON book(month_year_id);
+
+CREATE TABLE article_new
+(
+    cite_key        TEXT REFERENCES master_entries(cite_key) ON DELETE CASCADE,
+    article_id      TEXT PRIMARY KEY UNIQUE NOT NULL,
+    publisher_id    TEXT REFERENCES publisher(publisher_id),
+    month_year_id   TEXT REFERENCES month_year(month_year_id),
+    title           VARCHAR,
+    journal         VARCHAR,
+    volume          VARCHAR,
+    pages           VARCHAR,
+    note            VARCHAR,
+    year            VARCHAR,
+    edition         VARCHAR,
+    publisher       VARCHAR
+);
+INSERT INTO article_new SELECT cite_key, article_id, publisher_id, month_year_id, title, journal, volume, pages, note, year, edition, publisher FROM article;
+DROP TABLE article;
+ALTER TABLE article_new RENAME TO article;
+CREATE INDEX article_cite_key ON article(cite_key);
+CREATE INDEX article_publisher_id ON article(publisher_id);
+CREATE INDEX article_month_year_id ON article(month_year_id);",
+        data: None,
+    },
+    Migration {
+        version: 4,
+        description: "ordered authors with roles; moves book.author into the author table",
+        // the old `author` table was never written to, so it is replaced rather than copied
+        sql: "
+DROP TABLE author;
+CREATE TABLE author
+(
+    author_id   TEXT PRIMARY KEY UNIQUE NOT NULL,
+    cite_key    TEXT NOT NULL REFERENCES master_entries(cite_key) ON DELETE CASCADE,
+    position    INTEGER NOT NULL,
+    role        VARCHAR NOT NULL DEFAULT 'author'
+                CHECK (role IN ('author', 'editor', 'translator', 'illustrator')),
+    family      VARCHAR NOT NULL,
+    given       VARCHAR NOT NULL DEFAULT '',
+    UNIQUE (cite_key, position)
+);
+CREATE INDEX author_cite_key ON author(cite_key);
+
+INSERT INTO author (author_id, cite_key, position, role, family, given)
+SELECT lower(hex(randomblob(16))), cite_key, 0, 'author',
+       CASE WHEN instr(author, ',') > 0 THEN trim(substr(author, 1, instr(author, ',') - 1))
+            ELSE trim(author) END,
+       CASE WHEN instr(author, ',') > 0 THEN trim(substr(author, instr(author, ',') + 1))
+            ELSE '' END
+FROM book
+WHERE trim(coalesce(author, '')) <> '' AND cite_key IN (SELECT cite_key FROM master_entries);
+ALTER TABLE book DROP COLUMN author;",
+        data: None,
+    },
+    Migration {
+        version: 5,
+        description: "publisher authority records shared by name; drops book.publisher and article.publisher",
+        sql: "
+ALTER TABLE publisher ADD COLUMN place VARCHAR NOT NULL DEFAULT '';
+ALTER TABLE publisher ADD COLUMN normalized VARCHAR NOT NULL DEFAULT '';
+UPDATE publisher SET address = '' WHERE address = 'n/a';
+
+INSERT OR IGNORE INTO publisher (publisher_id, publisher, address)
+SELECT publisher_id, publisher, '' FROM book
+WHERE publisher_id IS NOT NULL AND publisher_id NOT IN (SELECT publisher_id FROM publisher);
+INSERT OR IGNORE INTO publisher (publisher_id, publisher, address)
+SELECT publisher_id, publisher, '' FROM article
+WHERE publisher_id IS NOT NULL AND publisher_id NOT IN (SELECT publisher_id FROM publisher);
+
+ALTER TABLE book DROP COLUMN publisher;
+ALTER TABLE article DROP COLUMN publisher;",
+        data: Some(merge_duplicate_publishers),
+    },
+    Migration {
+        version: 6,
+        description: "month_year holds a parsed year with optional month and day",
+        sql: "
+CREATE TABLE month_year_new
+(
+    month_year_id   TEXT PRIMARY KEY UNIQUE NOT NULL,
+    year            INTEGER,
+    month           INTEGER CHECK (month BETWEEN 1 AND 12),
+    day             INTEGER CHECK (day BETWEEN 1 AND 31)
+);
+INSERT INTO month_year_new (month_year_id) SELECT month_year_id FROM month_year;
+DROP TABLE month_year;
+ALTER TABLE month_year_new RENAME TO month_year;",
+        data: Some(parse_publication_dates),
+    },
+    Migration {
+        version: 7,
+        description: "drops book.year and article.year; the date lives in month_year",
+        sql: "
+ALTER TABLE book DROP COLUMN year;
+ALTER TABLE article DROP COLUMN year;",
+        data: None,
+    },
+    Migration {
+        version: 8,
+        description: "typed links between master_entries records in relationship",
+        // the old `relationship` table was never written to, so it is replaced rather than copied
+        sql: "
+DROP TABLE relationship;
+CREATE TABLE relationship
+(
+    relationship_id TEXT PRIMARY KEY UNIQUE NOT NULL,
+    cite_key        TEXT NOT NULL REFERENCES master_entries(cite_key) ON DELETE CASCADE,
+    related_key     TEXT NOT NULL REFERENCES master_entries(cite_key) ON DELETE CASCADE,
+    relation        VARCHAR NOT NULL
+                    CHECK (relation IN ('part-of', 'edition-of', 'translation-of', 'reviews', 'continues')),
+    CHECK (cite_key <> related_key),
+    UNIQUE (cite_key, related_key, relation)
+);
+CREATE INDEX relationship_cite_key ON relationship(cite_key);
+CREATE INDEX relationship_related_key ON relationship(related_key);",
+        data: None,
+    },
+    Migration {
+        version: 9,
+        description: "organizations with a parent body, linked to records by role in record_organization",
+        sql: "
+CREATE TABLE organizations_new
+(
+    organization_id TEXT PRIMARY KEY UNIQUE NOT NULL,
+    organization    VARCHAR NOT NULL,
+    address         VARCHAR NOT NULL DEFAULT '',
+    parent_id       TEXT REFERENCES organizations(organization_id) ON DELETE SET NULL,
+    CHECK (parent_id <> organization_id)
+);
+INSERT INTO organizations_new (organization_id, organization, address)
+SELECT organization_id, coalesce(organization, ''), coalesce(address, '') FROM organizations;
+DROP TABLE organizations;
+ALTER TABLE organizations_new RENAME TO organizations;
+CREATE INDEX organizations_parent_id ON organizations(parent_id);
+
+CREATE TABLE record_organization
+(
+    cite_key        TEXT NOT NULL REFERENCES master_entries(cite_key) ON DELETE CASCADE,
+    organization_id TEXT NOT NULL REFERENCES organizations(organization_id) ON DELETE CASCADE,
+    position        INTEGER NOT NULL,
+    role            VARCHAR NOT NULL DEFAULT 'author'
+                    CHECK (role IN ('author', 'sponsor', 'degree-granting')),
+    PRIMARY KEY (cite_key, position)
+);
+CREATE INDEX record_organization_organization_id ON record_organization(organization_id);",
+        data: None,
+    },
+    Migration {
+        version: 10,
+        description: "a table for each item type in item::ITEM_TYPES, and views over every record",
+        // `item_records` lists the item tables; `catalog_records` adds books and articles
+        sql: "
+CREATE TABLE thesis
+(
+    cite_key        TEXT PRIMARY KEY NOT NULL REFERENCES master_entries(cite_key) ON DELETE CASCADE,
+    title           VARCHAR NOT NULL,
+    publisher_id    TEXT REFERENCES publisher(publisher_id),
+    month_year_id   TEXT REFERENCES month_year(month_year_id),
+    note            VARCHAR NOT NULL DEFAULT '',
+    degree          VARCHAR NOT NULL DEFAULT '',
+    pages           VARCHAR NOT NULL DEFAULT ''
+);
+CREATE INDEX thesis_publisher_id ON thesis(publisher_id);
+CREATE INDEX thesis_month_year_id ON thesis(month_year_id);
+
+CREATE TABLE conference_paper
+(
+    cite_key        TEXT PRIMARY KEY NOT NULL REFERENCES master_entries(cite_key) ON DELETE CASCADE,
+    title           VARCHAR NOT NULL,
+    publisher_id    TEXT REFERENCES publisher(publisher_id),
+    month_year_id   TEXT REFERENCES month_year(month_year_id),
+    note            VARCHAR NOT NULL DEFAULT '',
+    proceedings     VARCHAR NOT NULL DEFAULT '',
+    location        VARCHAR NOT NULL DEFAULT '',
+    pages           VARCHAR NOT NULL DEFAULT ''
+);
+CREATE INDEX conference_paper_publisher_id ON conference_paper(publisher_id);
+CREATE INDEX conference_paper_month_year_id ON conference_paper(month_year_id);
+
+CREATE TABLE book_chapter
+(
+    cite_key        TEXT PRIMARY KEY NOT NULL REFERENCES master_entries(cite_key) ON DELETE CASCADE,
+    title           VARCHAR NOT NULL,
+    publisher_id    TEXT REFERENCES publisher(publisher_id),
+    month_year_id   TEXT REFERENCES month_year(month_year_id),
+    note            VARCHAR NOT NULL DEFAULT '',
+    book_title      VARCHAR NOT NULL DEFAULT '',
+    chapter         VARCHAR NOT NULL DEFAULT '',
+    pages           VARCHAR NOT NULL DEFAULT ''
+);
+CREATE INDEX book_chapter_publisher_id ON book_chapter(publisher_id);
+CREATE INDEX book_chapter_month_year_id ON book_chapter(month_year_id);
+
+CREATE TABLE report
+(
+    cite_key        TEXT PRIMARY KEY NOT NULL REFERENCES master_entries(cite_key) ON DELETE CASCADE,
+    title           VARCHAR NOT NULL,
+    publisher_id    TEXT REFERENCES publisher(publisher_id),
+    month_year_id   TEXT REFERENCES month_year(month_year_id),
+    note            VARCHAR NOT NULL DEFAULT '',
+    report_type     VARCHAR NOT NULL DEFAULT '',
+    number          VARCHAR NOT NULL DEFAULT '',
+    pages           VARCHAR NOT NULL DEFAULT ''
+);
+CREATE INDEX report_publisher_id ON report(publisher_id);
+CREATE INDEX report_month_year_id ON report(month_year_id);
+
+CREATE TABLE map
+(
+    cite_key        TEXT PRIMARY KEY NOT NULL REFERENCES master_entries(cite_key) ON DELETE CASCADE,
+    title           VARCHAR NOT NULL,
+    publisher_id    TEXT REFERENCES publisher(publisher_id),
+    month_year_id   TEXT REFERENCES month_year(month_year_id),
+    note            VARCHAR NOT NULL DEFAULT '',
+    scale           VARCHAR NOT NULL DEFAULT '',
+    projection      VARCHAR NOT NULL DEFAULT '',
+    dimensions      VARCHAR NOT NULL DEFAULT ''
+);
+CREATE INDEX map_publisher_id ON map(publisher_id);
+CREATE INDEX map_month_year_id ON map(month_year_id);
+
+CREATE TABLE audio_recording
+(
+    cite_key        TEXT PRIMARY KEY NOT NULL REFERENCES master_entries(cite_key) ON DELETE CASCADE,
+    title           VARCHAR NOT NULL,
+    publisher_id    TEXT REFERENCES publisher(publisher_id),
+    month_year_id   TEXT REFERENCES month_year(month_year_id),
+    note            VARCHAR NOT NULL DEFAULT '',
+    medium          VARCHAR NOT NULL DEFAULT '',
+    duration        VARCHAR NOT NULL DEFAULT '',
+    catalog_number  VARCHAR NOT NULL DEFAULT ''
+);
+CREATE INDEX audio_recording_publisher_id ON audio_recording(publisher_id);
+CREATE INDEX audio_recording_month_year_id ON audio_recording(month_year_id);
+
+CREATE TABLE video
+(
+    cite_key        TEXT PRIMARY KEY NOT NULL REFERENCES master_entries(cite_key) ON DELETE CASCADE,
+    title           VARCHAR NOT NULL,
+    publisher_id    TEXT REFERENCES publisher(publisher_id),
+    month_year_id   TEXT REFERENCES month_year(month_year_id),
+    note            VARCHAR NOT NULL DEFAULT '',
+    medium          VARCHAR NOT NULL DEFAULT '',
+    duration        VARCHAR NOT NULL DEFAULT ''
+);
+CREATE INDEX video_publisher_id ON video(publisher_id);
+CREATE INDEX video_month_year_id ON video(month_year_id);
+
+CREATE TABLE web_resource
+(
+    cite_key        TEXT PRIMARY KEY NOT NULL REFERENCES master_entries(cite_key) ON DELETE CASCADE,
+    title           VARCHAR NOT NULL,
+    publisher_id    TEXT REFERENCES publisher(publisher_id),
+    month_year_id   TEXT REFERENCES month_year(month_year_id),
+    note            VARCHAR NOT NULL DEFAULT '',
+    url             VARCHAR NOT NULL DEFAULT '',
+    site_name       VARCHAR NOT NULL DEFAULT '',
+    accessed        VARCHAR NOT NULL DEFAULT ''
+);
+CREATE INDEX web_resource_publisher_id ON web_resource(publisher_id);
+CREATE INDEX web_resource_month_year_id ON web_resource(month_year_id);
+
+CREATE TABLE software
+(
+    cite_key        TEXT PRIMARY KEY NOT NULL REFERENCES master_entries(cite_key) ON DELETE CASCADE,
+    title           VARCHAR NOT NULL,
+    publisher_id    TEXT REFERENCES publisher(publisher_id),
+    month_year_id   TEXT REFERENCES month_year(month_year_id),
+    note            VARCHAR NOT NULL DEFAULT '',
+    version         VARCHAR NOT NULL DEFAULT '',
+    platform        VARCHAR NOT NULL DEFAULT '',
+    url             VARCHAR NOT NULL DEFAULT ''
+);
+CREATE INDEX software_publisher_id ON software(publisher_id);
+CREATE INDEX software_month_year_id ON software(month_year_id);
+
+CREATE VIEW item_records AS
+SELECT cite_key, 'THESIS' AS entry_type, title, publisher_id, month_year_id FROM thesis
+UNION ALL SELECT cite_key, 'CONFERENCE_PAPER' AS entry_type, title, publisher_id, month_year_id FROM conference_paper
+UNION ALL SELECT cite_key, 'BOOK_CHAPTER' AS entry_type, title, publisher_id, month_year_id FROM book_chapter
+UNION ALL SELECT cite_key, 'REPORT' AS entry_type, title, publisher_id, month_year_id FROM report
+UNION ALL SELECT cite_key, 'MAP' AS entry_type, title, publisher_id, month_year_id FROM map
+UNION ALL SELECT cite_key, 'AUDIO_RECORDING' AS entry_type, title, publisher_id, month_year_id FROM audio_recording
+UNION ALL SELECT cite_key, 'VIDEO' AS entry_type, title, publisher_id, month_year_id FROM video
+UNION ALL SELECT cite_key, 'WEB_RESOURCE' AS entry_type, title, publisher_id, month_year_id FROM web_resource
+UNION ALL SELECT cite_key, 'SOFTWARE' AS entry_type, title, publisher_id, month_year_id FROM software;
+CREATE VIEW catalog_records AS
+SELECT cite_key, 'BOOK' AS entry_type, title, publisher_id, month_year_id FROM book
+UNION ALL SELECT cite_key, 'ARTICLE' AS entry_type, title, publisher_id, month_year_id FROM article
+UNION ALL SELECT cite_key, entry_type, title, publisher_id, month_year_id FROM item_records;",
+        data: None,
+    },
+    Migration {
+        version: 11,
+        description: "catalog_search full-text index over titles, authors, journal, series, publisher and notes",
+        sql: "
+DROP VIEW catalog_records;
+DROP VIEW item_records;
+CREATE VIEW item_records AS
+SELECT cite_key, 'THESIS' AS entry_type, title, publisher_id, month_year_id, note FROM thesis
+UNION ALL SELECT cite_key, 'CONFERENCE_PAPER' AS entry_type, title, publisher_id, month_year_id, note FROM conference_paper
+UNION ALL SELECT cite_key, 'BOOK_CHAPTER' AS entry_type, title, publisher_id, month_year_id, note FROM book_chapter
+UNION ALL SELECT cite_key, 'REPORT' AS entry_type, title, publisher_id, month_year_id, note FROM report
+UNION ALL SELECT cite_key, 'MAP' AS entry_type, title, publisher_id, month_year_id, note FROM map
+UNION ALL SELECT cite_key, 'AUDIO_RECORDING' AS entry_type, title, publisher_id, month_year_id, note FROM audio_recording
+UNION ALL SELECT cite_key, 'VIDEO' AS entry_type, title, publisher_id, month_year_id, note FROM video
+UNION ALL SELECT cite_key, 'WEB_RESOURCE' AS entry_type, title, publisher_id, month_year_id, note FROM web_resource
+UNION ALL SELECT cite_key, 'SOFTWARE' AS entry_type, title, publisher_id, month_year_id, note FROM software;
+CREATE VIEW catalog_records AS
+SELECT cite_key, 'BOOK' AS entry_type, title, publisher_id, month_year_id, note FROM book
+UNION ALL SELECT cite_key, 'ARTICLE' AS entry_type, title, publisher_id, month_year_id, note FROM article
+UNION ALL SELECT cite_key, entry_type, title, publisher_id, month_year_id, note FROM item_records;
+
+CREATE VIRTUAL TABLE catalog_search USING fts5
+(
+    cite_key UNINDEXED,
+    title,
+    authors,
+    journal,
+    series,
+    publisher,
+    note,
+    tokenize = 'unicode61 remove_diacritics 2'
+);",
+        data: Some(rebuild_search_index),
+    },
+    Migration {
+        version: 12,
+        description: "master_entries.added order in which records were added",
+        // rowids may change on VACUUM, so the order is kept in a column of its own
+        sql: "
+ALTER TABLE master_entries ADD COLUMN added INTEGER;
+UPDATE master_entries SET added = rowid;
+CREATE INDEX master_entries_added ON master_entries(added);",
+        data: None,
+    },
+    Migration {
+        version: 13,
+        description: "marc_record imported MARC records kept for re-export",
+        // cite_key is NULL for a record that could not be mapped onto a book or article
+        sql: "
+CREATE TABLE marc_record
+(
+    marc_record_id  TEXT PRIMARY KEY NOT NULL,
+    cite_key        TEXT UNIQUE REFERENCES master_entries(cite_key) ON DELETE CASCADE,
+    added           INTEGER NOT NULL,
+    record          BLOB NOT NULL
+);
+CREATE INDEX marc_record_added ON marc_record(added);",
+        data: None,
+    },
+    Migration {
+        version: 14,
+        description: "master_entries.doi DOI of each record",
+        sql: "
+ALTER TABLE master_entries ADD COLUMN doi VARCHAR NOT NULL DEFAULT '';",
+        data: None,
+    },
+];
+
+/// Version of the newest migration this program knows about
+pub fn latest_version() -> i64 {
+    MIGRATIONS.last().map_or(0, |migration| migration.version)
+}
+
+/// Highest migration applied to the database; 0 for a new database
+pub fn current_version(catalog: &Catalog) -> DbResult<i64> {
+    catalog.execute(
+        "CREATE TABLE IF NOT EXISTS schema_version
+        (
+            version     INTEGER PRIMARY KEY NOT NULL,
+            description VARCHAR NOT NULL,
+            applied_at  TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+        );",
+    )?;
+    let query = "SELECT COALESCE(MAX(version), 0) AS version FROM schema_version";
+    catalog.with_statement(query, |statement| match statement.next()? {
+        State::Row => Ok(statement.read::<i64, _>("version")?),
+        State::Done => Ok(0),
+    })
+}
+
+/// Apply every migration newer than the database, each in its own transaction.
+/// Returns the version the database is at afterwards.
+pub fn migrate(catalog: &Catalog) -> DbResult<i64> {
+    let current = current_version(catalog)?;
+    if current > latest_version() {
+        return Err(DbError::Schema(format!(
+            "database is at schema version {current} but this program only knows up to {}; use a newer bib_db",
+            latest_version()
+        )));
+    }
+
+    for migration in MIGRATIONS.iter().filter(|m| m.version > current) {
+        catalog.transaction(|catalog| {
+            catalog.execute(migration.sql)?;
+            if let Some(data) = migration.data {
+                data(catalog)?;
+            }
+            let query =
+                "INSERT INTO schema_version (version, description) VALUES (:version, :description)";
+            catalog.with_statement(query, |statement| {
+                statement.bind((":version", migration.version))?;
+                statement.bind((":description", migration.description))?;
+                statement.next()?;
+                Ok(())
+            })
+        })?;
+    }
+    Ok(latest_version())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::db::{Database, TestDbFile};
+
+    #[test]
+    fn versions_are_increasing() {
+        let versions: Vec<i64> = MIGRATIONS.iter().map(|m| m.version).collect();
+        let expected: Vec<i64> = (1..=MIGRATIONS.len() as i64).collect();
+        assert_eq!(versions, expected);
+    }
+
+    #[test]
+    fn new_database_is_at_latest_version() {
+        let file = TestDbFile::new();
+        let db_url = &file.db_url;
+        let database = Database::open(db_url).unwrap();
+        assert_eq!(
+            current_version(&database.catalog()).unwrap(),
+            latest_version()
+        );
+
+        // opening again applies nothing
+        drop(database);
+        let database = Database::open(db_url).unwrap();
+        assert_eq!(
+            current_version(&database.catalog()).unwrap(),
+            latest_version()
+        );
+    }
+
+    #[test]
+    // A catalog made by the old one-shot init_db keeps its rows and gains the new columns
+    fn upgrades_database_without_schema_version() {
+        let file = TestDbFile::new();
+        let db_url = &file.db_url;
+        let connection = sqlite::open(db_url).unwrap();
+        connection.execute(MIGRATIONS[0].sql).unwrap();
+        connection
+            .execute(
+                "INSERT INTO master_entries (cite_key, entry_type) VALUES ('c1', 'BOOK');
+                INSERT INTO publisher (publisher_id, publisher, address) VALUES ('p1', 'Addison-Wesley', 'n/a');
+                INSERT INTO publisher (publisher_id, publisher, address) VALUES ('p2', 'addison wesley', 'n/a');
+                INSERT INTO book (book_id, cite_key, publisher_id, month_year_id, author, title, year, publisher) VALUES ('b1', 'c1', 'p2', 'm1', 'Knuth, Donald', 'Old Title', 'May 1968', 'addison wesley');
+                INSERT INTO month_year (month_year_id, month, year) VALUES ('m1', '01', 'May 1968');
+                INSERT INTO master_entries (cite_key, entry_type) VALUES ('c2', 'ARTICLE');
+                INSERT INTO article (cite_key, article_id, month_year_id, title, note, year) VALUES ('c2', 'a1', 'm2', 'Undated', 'Reprint.', 'someday');
+                INSERT INTO month_year (month_year_id, month, year) VALUES ('m2', '01', 'someday');
+                INSERT INTO master_entries (cite_key, entry_type) VALUES ('c3', 'ARTICLE');
+                INSERT INTO article (cite_key, article_id, month_year_id, title, note, year) VALUES ('c3', 'a3', 'm3', 'Harmful', 'Letter. doi:10.1145/362929.362947', '1968');
+                INSERT INTO month_year (month_year_id, month, year) VALUES ('m3', '01', '1968')",
+            )
+            .unwrap();
+        drop(connection);
+
+        let database = Database::open(db_url).unwrap();
+        let catalog = database.catalog();
+        assert_eq!(current_version(&catalog).unwrap(), latest_version());
+        let query = "SELECT title, isbn FROM book WHERE book_id = 'b1'";
+        let (title, isbn) = catalog
+            .with_statement(query, |statement| {
+                statement.next()?;
+                Ok((
+                    statement.read::<String, _>("title")?,
+                    statement.read::<String, _>("isbn")?,
+                ))
+            })
+            .unwrap();
+        assert_eq!(title, "Old Title");
+        assert_eq!(isbn, "");
+
+        // duplicate publishers are merged into the first one
+        let books = crate::db::read_sqlite_book_table(&catalog).unwrap();
+        assert_eq!(books[0].publisher_id, "p1");
+        assert_eq!(books[0].publisher, "Addison-Wesley");
+        assert_eq!(
+            crate::db::read_sqlite_publisher_table(&catalog)
+                .unwrap()
+                .len(),
+            1
+        );
+
+        let authors = crate::author::select_authors(&catalog, "c1").unwrap();
+        assert_eq!(authors.len(), 1);
+        assert_eq!(authors[0].family, "Knuth");
+        assert_eq!(authors[0].given, "Donald");
+
+        // free-text years are parsed, and a year that cannot be read is kept in the note
+        assert_eq!(books[0].year, "1968-05");
+        let articles = crate::db::read_sqlite_article_table(&catalog).unwrap();
+        assert_eq!(articles[0].year, "");
+        assert_eq!(articles[0].note, "Reprint. Date: someday");
+
+        // a DOI typed into a note is left there; only imports fill in the DOI column
+        assert_eq!(articles[1].note, "Letter. doi:10.1145/362929.362947");
+        assert_eq!(crate::db::read_doi(&catalog, "c3").unwrap(), "");
+
+        // records already in the catalog are indexed for search
+        let found = crate::search::search_catalog(&catalog, "knuth", 10).unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].cite_key, "c1");
+
+        // records keep the order they were added in
+        let view = crate::listing::ListView::new(crate::listing::SortKey::Added);
+        assert_eq!(
+            view.cite_keys(&catalog, None).unwrap(),
+            vec!["c1", "c2", "c3"]
+        );
+    }
+
+    #[test]
+    fn refuses_newer_database() {
+        let file = TestDbFile::new();
+        let db_url = &file.db_url;
+        drop(Database::open(db_url).unwrap());
+        let connection = sqlite::open(db_url).unwrap();
+        connection
+            .execute("INSERT INTO schema_version (version, description) VALUES (9999, 'from the future')")
+            .unwrap();
+        drop(connection);
+
+        assert!(matches!(Database::open(db_url), Err(DbError::Schema(_))));
+    }
+}
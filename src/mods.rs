@@ -0,0 +1,562 @@
+use crate::author::{parse_authors, Role};
+use crate::db::{read_records_with_keys, Catalog, DbResult, EntryType, MonthYear};
+use crate::import::{import_stream, record_form, ImportRecord, ImportReport, IMPORT_BATCH};
+use crate::organization::{parse_organizations, OrgRole};
+use crate::xml::{escape, Elements, XmlElement};
+use std::error::Error;
+use std::io::{BufRead, Write};
+
+pub const MODS_NAMESPACE: &str = "http://www.loc.gov/mods/v3";
+
+/// Text written before the first record of a collection
+pub fn collection_start() -> String {
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<modsCollection xmlns=\"{MODS_NAMESPACE}\">\n"
+    )
+}
+
+pub const COLLECTION_END: &str = "</modsCollection>\n";
+
+/// MARC relator codes for the person roles, as some MODS records give them instead of terms
+const ROLE_CODES: [(&str, Role); 4] = [
+    ("aut", Role::Author),
+    ("edt", Role::Editor),
+    ("trl", Role::Translator),
+    ("ill", Role::Illustrator),
+];
+
+/// `<name>value</name>` on its own line at `depth`, or nothing when `value` is empty
+fn leaf(depth: usize, name: &str, attributes: &str, value: &str) -> String {
+    match value.trim() {
+        "" => String::new(),
+        value => format!(
+            "{}<{name}{attributes}>{}</{name}>\n",
+            "  ".repeat(depth),
+            escape(value)
+        ),
+    }
+}
+
+/// `inner` inside `<name>` at `depth`, or nothing when `inner` is empty
+fn wrap(depth: usize, name: &str, attributes: &str, inner: &str) -> String {
+    match inner {
+        "" => String::new(),
+        inner => {
+            let indent = "  ".repeat(depth);
+            format!("{indent}<{name}{attributes}>\n{inner}{indent}</{name}>\n")
+        }
+    }
+}
+
+/// `<part>` holding a volume number and a page range, at `depth`
+fn part(depth: usize, volume: &str, pages: &str) -> String {
+    let volume = wrap(
+        depth + 1,
+        "detail",
+        " type=\"volume\"",
+        &leaf(depth + 2, "number", "", volume),
+    );
+    let (start, end) = pages.split_once(['-', '–']).unwrap_or((pages, ""));
+    let extent = leaf(depth + 2, "start", "", start) + &leaf(depth + 2, "end", "", end);
+    let extent = wrap(depth + 1, "extent", " unit=\"pages\"", &extent);
+    wrap(depth, "part", "", &(volume + &extent))
+}
+
+/// The form of a book or article as a `<mods>` element, indented to sit inside a collection
+pub fn mods_xml(form: &ImportRecord, cite_key: &str) -> DbResult<String> {
+    let mut xml = String::new();
+    let (title, subtitle) = form
+        .get("Title")
+        .split_once(": ")
+        .unwrap_or((form.get("Title"), ""));
+    xml += &wrap(
+        2,
+        "titleInfo",
+        "",
+        &(leaf(3, "title", "", title) + &leaf(3, "subTitle", "", subtitle)),
+    );
+    for author in parse_authors(cite_key, form.get("Authors"))? {
+        let name = leaf(3, "namePart", " type=\"family\"", &author.family)
+            + &leaf(3, "namePart", " type=\"given\"", &author.given)
+            + &wrap(
+                3,
+                "role",
+                "",
+                &leaf(4, "roleTerm", " type=\"text\"", author.role.as_str()),
+            );
+        xml += &wrap(2, "name", " type=\"personal\"", &name);
+    }
+    for organization in parse_organizations(cite_key, form.get("Organizations"))? {
+        let role = match organization.role {
+            OrgRole::Author => "author".to_string(),
+            role => role.to_string().replace('-', " "),
+        };
+        let name = leaf(3, "namePart", "", &organization.organization)
+            + &wrap(3, "role", "", &leaf(4, "roleTerm", " type=\"text\"", &role));
+        xml += &wrap(2, "name", " type=\"corporate\"", &name);
+    }
+    xml += &leaf(2, "typeOfResource", "", "text");
+    let date = form.get("Date");
+    let date = match MonthYear::parse(date) {
+        Ok(parsed) => leaf(3, "dateIssued", " encoding=\"w3cdtf\"", &parsed.to_string()),
+        Err(_) => leaf(3, "dateIssued", "", date),
+    };
+    let origin = leaf(3, "publisher", "", form.get("Publisher"))
+        + &date
+        + &leaf(3, "edition", "", form.get("Edition"));
+    xml += &wrap(2, "originInfo", "", &origin);
+    match form.entry_type {
+        EntryType::Article => {
+            let host = wrap(
+                3,
+                "titleInfo",
+                "",
+                &leaf(4, "title", "", form.get("Journal")),
+            ) + &part(3, form.get("Volume"), form.get("Pages"));
+            xml += &wrap(2, "relatedItem", " type=\"host\"", &host);
+        }
+        _ => {
+            xml += &wrap(
+                2,
+                "physicalDescription",
+                "",
+                &leaf(3, "extent", "", form.get("Pages")),
+            );
+            let series = wrap(
+                3,
+                "titleInfo",
+                "",
+                &leaf(4, "title", "", form.get("Series")),
+            );
+            xml += &wrap(2, "relatedItem", " type=\"series\"", &series);
+            xml += &part(2, form.get("Volume"), "");
+        }
+    }
+    xml += &leaf(2, "note", "", form.get("Note"));
+    xml += &leaf(2, "identifier", " type=\"isbn\"", form.get("ISBN"));
+    xml += &wrap(
+        2,
+        "recordInfo",
+        "",
+        &leaf(3, "recordIdentifier", "", cite_key),
+    );
+    Ok(wrap(1, "mods", " version=\"3.7\"", &xml))
+}
+
+/// Write the books and articles with `cite_keys` as a MODS collection, reading them from the
+/// catalog a batch at a time; other records have no MODS form and are left out
+pub fn write_mods<W: Write>(
+    catalog: &Catalog,
+    cite_keys: &[&str],
+    out: &mut W,
+) -> Result<usize, Box<dyn Error>> {
+    out.write_all(collection_start().as_bytes())?;
+    let mut count = 0;
+    for batch in cite_keys.chunks(IMPORT_BATCH) {
+        for record in read_records_with_keys(catalog, batch)? {
+            if let Some(form) = record_form(catalog, &record)? {
+                out.write_all(mods_xml(&form, record.cite_key())?.as_bytes())?;
+                count += 1;
+            }
+        }
+    }
+    out.write_all(COLLECTION_END.as_bytes())?;
+    Ok(count)
+}
+
+/// A personal name as a line of the authors form, e.g. `Knuth, Donald E. (editor)`. Roles the
+/// catalog has no place for, such as `creator`, are read as author.
+fn person(name: &XmlElement) -> String {
+    let part = |kind: &str| {
+        name.children("namePart")
+            .filter(|part| part.attribute("type") == Some(kind))
+            .map(|part| part.text.trim())
+            .collect::<Vec<_>>()
+            .join(" ")
+    };
+    let (family, given) = (part("family"), part("given"));
+    let name_line = match (family.is_empty(), given.is_empty()) {
+        (true, _) => name
+            .children("namePart")
+            .find(|part| part.attribute("type").is_none())
+            .map(|part| part.text.trim().to_string())
+            .unwrap_or(given),
+        (false, true) => family,
+        (false, false) => format!("{family}, {given}"),
+    };
+    let role = name
+        .children("role")
+        .flat_map(|role| role.children("roleTerm"))
+        .find_map(|term| {
+            let term = term.text.trim().to_lowercase();
+            ROLE_CODES
+                .iter()
+                .find(|(code, _)| *code == term)
+                .map(|(_, role)| *role)
+                .or_else(|| term.parse().ok())
+        })
+        .unwrap_or(Role::Author);
+    match role {
+        Role::Author => name_line,
+        role => format!("{name_line} ({role})"),
+    }
+}
+
+/// A corporate name as a line of the organizations form, e.g. `NSF (sponsor)`
+fn organization(name: &XmlElement) -> String {
+    let name_line = name
+        .children("namePart")
+        .map(|part| part.text.trim())
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join(". ");
+    let role = name
+        .children("role")
+        .flat_map(|role| role.children("roleTerm"))
+        .find_map(|term| match term.text.trim().to_lowercase().as_str() {
+            "spn" | "sponsor" => Some(OrgRole::Sponsor),
+            "dgg" | "degree granting" | "degree granting institution" | "degree-granting" => {
+                Some(OrgRole::DegreeGranting)
+            }
+            _ => None,
+        });
+    match role {
+        Some(role) => format!("{name_line} ({role})"),
+        None => name_line,
+    }
+}
+
+/// The volume number and page range in an element's `<part>`
+fn part_values(element: &XmlElement) -> (String, String) {
+    let Some(part) = element.child("part") else {
+        return (String::new(), String::new());
+    };
+    let volume = part
+        .children("detail")
+        .find(|detail| detail.attribute("type") == Some("volume"))
+        .map(|detail| detail.text_at(&["number"]))
+        .unwrap_or_default();
+    let pages = match part.child("extent") {
+        Some(extent) => match (extent.text_at(&["start"]), extent.text_at(&["end"])) {
+            (start, "") => start.to_string(),
+            (start, end) => format!("{start}-{end}"),
+        },
+        None => String::new(),
+    };
+    (volume.to_string(), pages)
+}
+
+/// `title` of a `<titleInfo>`, with its `nonSort` article and `subTitle`
+fn title(element: &XmlElement) -> String {
+    let Some(info) = element
+        .children("titleInfo")
+        .find(|info| info.attribute("type").is_none())
+        .or(element.child("titleInfo"))
+    else {
+        return String::new();
+    };
+    let non_sort = info.child("nonSort").map_or("", |part| part.text.as_str());
+    let title = format!("{non_sort}{}", info.text_at(&["title"]));
+    match info.text_at(&["subTitle"]) {
+        "" => title,
+        subtitle => format!("{title}: {subtitle}"),
+    }
+}
+
+/// The form for the `number`th `<mods>` element of a file, or why it has none: a record with a
+/// host item is an article, any other text a book
+pub fn import_record(number: usize, mods: &XmlElement) -> Result<ImportRecord, String> {
+    let source = match mods.text_at(&["recordInfo", "recordIdentifier"]) {
+        "" => format!("record {number}"),
+        identifier => format!("record {number} ({identifier})"),
+    };
+    let resource = mods.text_at(&["typeOfResource"]);
+    if !resource.is_empty() && resource != "text" {
+        return Err(format!(
+            "type of resource `{resource}` is not a book or article"
+        ));
+    }
+    let host = mods
+        .children("relatedItem")
+        .find(|item| item.attribute("type") == Some("host"));
+    let entry_type = match host {
+        Some(_) => EntryType::Article,
+        None => EntryType::Book,
+    };
+    let names = |kind| {
+        mods.children("name")
+            .filter(move |name| name.attribute("type") == Some(kind))
+    };
+    let people: Vec<String> = names("personal").map(person).collect();
+    let organizations: Vec<String> = names("corporate").map(organization).collect();
+    let origin = |name: &str| {
+        mods.children("originInfo")
+            .map(|origin| origin.text_at(&[name]))
+            .find(|value| !value.is_empty())
+            .unwrap_or_default()
+    };
+    let note = mods
+        .children("note")
+        .map(|note| note.text.trim())
+        .collect::<Vec<_>>()
+        .join(" ");
+    let mut form = ImportRecord::new(source, entry_type);
+    form.set("Authors", &people.join("; "));
+    form.set("Title", &title(mods));
+    form.set("Edition", origin("edition"));
+    form.set("Date", origin("dateIssued"));
+    form.set("Publisher", origin("publisher"));
+    form.set("Note", &note);
+    form.set("Organizations", &organizations.join("; "));
+    match host {
+        Some(host) => {
+            let (volume, pages) = part_values(host);
+            form.set("Journal", &title(host));
+            form.set("Volume", &volume);
+            form.set("Pages", &pages);
+            if form.get("Publisher").is_empty() {
+                form.set("Publisher", host.text_at(&["originInfo", "publisher"]));
+            }
+        }
+        None => {
+            let series = mods
+                .children("relatedItem")
+                .find(|item| item.attribute("type") == Some("series"));
+            let volume = match series.map(part_values) {
+                Some((volume, _)) if !volume.is_empty() => volume,
+                _ => part_values(mods).0,
+            };
+            form.set("Series", &series.map(title).unwrap_or_default());
+            form.set("Volume", &volume);
+            form.set("Pages", mods.text_at(&["physicalDescription", "extent"]));
+            let isbn = mods
+                .children("identifier")
+                .find(|identifier| identifier.attribute("type") == Some("isbn"))
+                .map_or("", |identifier| identifier.text.trim());
+            form.set("ISBN", isbn);
+        }
+    }
+    Ok(form)
+}
+
+/// Import the `<mods>` records of a file, a `<modsCollection>` or a single record, reading one
+/// record at a time and saving them in batches
+pub fn import_mods<R: BufRead>(
+    catalog: &Catalog,
+    reader: R,
+    dry_run: bool,
+) -> DbResult<ImportReport> {
+    let records = Elements::new(reader, "mods")
+        .enumerate()
+        .map(|(index, element)| {
+            let number = index + 1;
+            element
+                .and_then(|element| import_record(number, &element))
+                .map_err(|reason| (format!("record {number}"), reason))
+        });
+    import_stream(catalog, records, dry_run)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::db::{Article, Book, RowSelect, TestDatabase};
+    use crate::listing::{ListView, SortKey};
+    use serial_test::serial;
+
+    const COLLECTION: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<modsCollection xmlns="http://www.loc.gov/mods/v3">
+  <mods version="3.7">
+    <titleInfo>
+      <nonSort>The </nonSort>
+      <title>art of computer programming</title>
+      <subTitle>fundamental algorithms</subTitle>
+    </titleInfo>
+    <name type="personal">
+      <namePart type="given">Donald E.</namePart>
+      <namePart type="family">Knuth</namePart>
+      <role><roleTerm authority="marcrelator" type="text">creator</roleTerm></role>
+    </name>
+    <name type="personal">
+      <namePart>Gödel, Kurt</namePart>
+      <role><roleTerm authority="marcrelator" type="code">edt</roleTerm></role>
+    </name>
+    <name type="corporate">
+      <namePart>National Science Foundation</namePart>
+      <role><roleTerm type="text">sponsor</roleTerm></role>
+    </name>
+    <typeOfResource>text</typeOfResource>
+    <originInfo>
+      <place><placeTerm type="text">Reading, Mass.</placeTerm></place>
+      <publisher>Addison-Wesley</publisher>
+      <dateIssued>1968</dateIssued>
+      <edition>2nd ed.</edition>
+    </originInfo>
+    <physicalDescription><extent>634</extent></physicalDescription>
+    <relatedItem type="series">
+      <titleInfo><title>Addison-Wesley series in computer science</title></titleInfo>
+      <part><detail type="volume"><number>1</number></detail></part>
+    </relatedItem>
+    <subject><topic>Computer programming</topic></subject>
+    <identifier type="isbn">0201038013</identifier>
+    <recordInfo><recordIdentifier>knuth1968</recordIdentifier></recordInfo>
+  </mods>
+  <mods version="3.7">
+    <titleInfo><title>Go to statement considered harmful</title></titleInfo>
+    <name type="personal">
+      <namePart type="family">Dijkstra</namePart>
+      <namePart type="given">Edsger W.</namePart>
+    </name>
+    <typeOfResource>text</typeOfResource>
+    <originInfo><dateIssued encoding="w3cdtf">1968-03</dateIssued></originInfo>
+    <relatedItem type="host">
+      <titleInfo><title>Communications of the ACM</title></titleInfo>
+      <originInfo><publisher>ACM</publisher></originInfo>
+      <part>
+        <detail type="volume"><number>11</number></detail>
+        <detail type="issue"><number>3</number></detail>
+        <extent unit="pages"><start>147</start><end>148</end></extent>
+      </part>
+    </relatedItem>
+    <note>Letter to the editor &amp; reply</note>
+  </mods>
+  <mods version="3.7">
+    <titleInfo><title>Yosemite Valley</title></titleInfo>
+    <typeOfResource>cartographic</typeOfResource>
+  </mods>
+</modsCollection>
+"#;
+
+    #[test]
+    fn reads_mods_records() {
+        let records: Vec<Result<ImportRecord, String>> =
+            Elements::new(COLLECTION.as_bytes(), "mods")
+                .enumerate()
+                .map(|(index, element)| import_record(index + 1, &element.unwrap()))
+                .collect();
+        let book = records[0].as_ref().unwrap();
+        assert_eq!(book.source, "record 1 (knuth1968)");
+        assert_eq!(book.entry_type, EntryType::Book);
+        assert_eq!(
+            book.lines,
+            vec![
+                "Knuth, Donald E.; Gödel, Kurt (editor)",
+                "The art of computer programming: fundamental algorithms",
+                "634",
+                "1",
+                "2nd ed.",
+                "1968",
+                "Addison-Wesley series in computer science",
+                "Addison-Wesley",
+                "",
+                "0201038013",
+                "National Science Foundation (sponsor)",
+            ]
+        );
+        let article = records[1].as_ref().unwrap();
+        assert_eq!(article.entry_type, EntryType::Article);
+        assert_eq!(article.get("Journal"), "Communications of the ACM");
+        assert_eq!(article.get("Volume"), "11");
+        assert_eq!(article.get("Pages"), "147-148");
+        assert_eq!(article.get("Publisher"), "ACM");
+        assert_eq!(article.get("Note"), "Letter to the editor & reply");
+        assert_eq!(
+            records[2].as_ref().unwrap_err(),
+            "type of resource `cartographic` is not a book or article"
+        );
+    }
+
+    #[test]
+    #[serial]
+    fn round_trips_mods() {
+        let database = TestDatabase::new();
+        let catalog = database.catalog();
+
+        let report = import_mods(&catalog, COLLECTION.as_bytes(), true).unwrap();
+        assert_eq!(report.imported(), 2);
+        let report = import_mods(&catalog, COLLECTION.as_bytes(), false).unwrap();
+        assert_eq!(report.imported(), 2);
+        assert_eq!(report.skipped.len(), 1);
+
+        let cite_keys = ListView::new(SortKey::Added)
+            .cite_keys(&catalog, None)
+            .unwrap();
+        let cite_keys: Vec<&str> = cite_keys.iter().map(String::as_str).collect();
+        let mut out = Vec::new();
+        assert_eq!(write_mods(&catalog, &cite_keys, &mut out).unwrap(), 2);
+        let exported = String::from_utf8(out).unwrap();
+        assert!(exported.contains("<dateIssued encoding=\"w3cdtf\">1968-03</dateIssued>"));
+        assert!(exported.contains("Letter to the editor &amp; reply"));
+
+        // what is written reads back as the forms in the catalog
+        let read: Vec<ImportRecord> = Elements::new(exported.as_bytes(), "mods")
+            .enumerate()
+            .map(|(index, element)| import_record(index + 1, &element.unwrap()).unwrap())
+            .collect();
+        assert_eq!(read[0].lines, Book::select(&catalog, cite_keys[0]).unwrap());
+        assert_eq!(
+            read[1].lines,
+            Article::select(&catalog, cite_keys[1]).unwrap()
+        );
+        assert_eq!(read[1].source, format!("record 2 ({})", cite_keys[1]));
+
+        let report = import_mods(&catalog, exported.as_bytes(), false).unwrap();
+        assert_eq!(report.duplicates.len(), 2);
+    }
+
+    #[test]
+    #[serial]
+    fn reports_skipped_records_in_order() {
+        let database = TestDatabase::new();
+        let catalog = database.catalog();
+        let collection = r#"<modsCollection xmlns="http://www.loc.gov/mods/v3">
+  <mods><titleInfo><title>Notes on structured programming</title></titleInfo></mods>
+  <mods>
+    <titleInfo><title>Yosemite Valley</title></titleInfo>
+    <typeOfResource>cartographic</typeOfResource>
+  </mods>
+  <mods>
+    <titleInfo><title>Go to statement considered harmful</title></titleInfo>
+    <relatedItem type="host"><part><detail type="volume"><number>11</number></detail></part></relatedItem>
+  </mods>
+</modsCollection>"#;
+        let report = import_mods(&catalog, collection.as_bytes(), false).unwrap();
+        assert_eq!(report.imported(), 1);
+        let sources: Vec<&str> = report
+            .skipped
+            .iter()
+            .map(|(source, _)| source.as_str())
+            .collect();
+        assert_eq!(sources, vec!["record 2", "record 3"]);
+    }
+
+    #[test]
+    #[serial]
+    // Records with a title and little else: no extent, date, publisher or volume
+    fn imports_sparse_records() {
+        let database = TestDatabase::new();
+        let catalog = database.catalog();
+        let sparse = format!(
+            "{}<mods version=\"3.7\">
+  <titleInfo><title>Notes on structured programming</title></titleInfo>
+  <typeOfResource>text</typeOfResource>
+</mods>
+<mods version=\"3.7\">
+  <titleInfo><title>Monitors</title></titleInfo>
+  <relatedItem type=\"host\">
+    <titleInfo><title>Communications of the ACM</title></titleInfo>
+  </relatedItem>
+</mods>
+{COLLECTION_END}",
+            collection_start()
+        );
+        let report = import_mods(&catalog, sparse.as_bytes(), false).unwrap();
+        assert_eq!(report.skipped, vec![]);
+        assert_eq!(report.imported(), 2);
+        let book = Book::select(&catalog, &report.imported[0].1).unwrap();
+        assert_eq!(book[1], "Notes on structured programming");
+        let article = Article::select(&catalog, &report.imported[1].1).unwrap();
+        assert_eq!(article[1], "Communications of the ACM");
+        assert_eq!((article[2].as_str(), article[7].as_str()), ("", ""));
+    }
+}
@@ -0,0 +1,507 @@
+use crate::db::{
+    form_fields, read_text, Catalog, DbError, DbResult, RowSelect, RowUpdate, TableInsert,
+};
+use sqlite::{State, Value};
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use uuid::Uuid;
+
+/// Labels of the lines in the `organizations` form, in order
+pub const ORGANIZATION_FIELDS: [&str; 3] = ["Name", "Address", "Parent body"];
+const ORGANIZATION_REQUIRED: [usize; 1] = [0];
+
+/// What a corporate body contributed to a record
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum OrgRole {
+    Author,
+    Sponsor,
+    DegreeGranting,
+}
+
+impl OrgRole {
+    /// Value stored in the `record_organization.role` column
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OrgRole::Author => "author",
+            OrgRole::Sponsor => "sponsor",
+            OrgRole::DegreeGranting => "degree-granting",
+        }
+    }
+}
+
+impl FromStr for OrgRole {
+    type Err = DbError;
+
+    fn from_str(s: &str) -> DbResult<OrgRole> {
+        match s.trim().to_lowercase().replace(' ', "-").as_str() {
+            "author" => Ok(OrgRole::Author),
+            "sponsor" => Ok(OrgRole::Sponsor),
+            "degree-granting" => Ok(OrgRole::DegreeGranting),
+            other => Err(DbError::InvalidInput(format!(
+                "unknown organization role `{other}`"
+            ))),
+        }
+    }
+}
+
+impl fmt::Display for OrgRole {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Corporate body such as a university, agency or society; `parent_id` is the body it belongs to
+#[derive(Clone, Debug, PartialEq)]
+pub struct Organization {
+    pub(crate) organization_id: String,
+    pub(crate) organization: String,
+    pub(crate) address: String,
+    pub(crate) parent_id: Option<String>,
+}
+
+impl Organization {
+    pub fn new(organization: &str) -> Organization {
+        Organization {
+            organization_id: Uuid::new_v4().to_string(),
+            organization: organization.trim().to_string(),
+            address: String::new(),
+            parent_id: None,
+        }
+    }
+
+    /// The organization named `name`, ignoring case
+    pub fn find(catalog: &Catalog, name: &str) -> DbResult<Option<Organization>> {
+        let query = "SELECT organization_id, organization, address, parent_id FROM organizations WHERE lower(organization) = lower(?) ORDER BY rowid LIMIT 1";
+        catalog.with_statement(query, |statement| {
+            statement.bind((1, name.trim()))?;
+            match statement.next()? {
+                State::Row => Ok(Some(read_organization(statement)?)),
+                State::Done => Ok(None),
+            }
+        })
+    }
+
+    /// The organization named `name`, added to the table if there is none yet
+    pub fn find_or_create(catalog: &Catalog, name: &str) -> DbResult<Organization> {
+        match Organization::find(catalog, name)? {
+            Some(organization) => Ok(organization),
+            None => {
+                let organization = Organization::new(name);
+                organization.insert(catalog)?;
+                Ok(organization)
+            }
+        }
+    }
+
+    /// Add the organization entered in the `organizations` form; returns its `organization_id`
+    pub fn organization_transaction(catalog: &Catalog, textarea: Vec<String>) -> DbResult<String> {
+        let textarea = form_fields(textarea, &ORGANIZATION_FIELDS, &ORGANIZATION_REQUIRED)?;
+        let organization = Organization::new(&textarea[0]);
+        catalog.transaction(|catalog| {
+            organization.insert(catalog)?;
+            Organization::organization_update(
+                catalog,
+                textarea,
+                organization.organization_id.clone(),
+            )?;
+            Ok(organization.organization_id.clone())
+        })
+    }
+
+    /// Update the name, address and parent body of an organization from the `organizations` form.
+    /// A parent body not in the table yet is added; a body cannot be its own parent.
+    pub fn organization_update(
+        catalog: &Catalog,
+        textarea: Vec<String>,
+        organization_id: String,
+    ) -> DbResult<()> {
+        let textarea = form_fields(textarea, &ORGANIZATION_FIELDS, &ORGANIZATION_REQUIRED)?;
+        catalog.transaction(|catalog| {
+            if let Some(existing) = Organization::find(catalog, &textarea[0])? {
+                if existing.organization_id != organization_id {
+                    return Err(DbError::InvalidInput(format!(
+                        "organization {} already exists",
+                        existing.organization
+                    )));
+                }
+            }
+            let parent_id = match textarea[2].trim() {
+                "" => None,
+                parent => {
+                    let parent = Organization::find_or_create(catalog, parent)?;
+                    if ancestors(catalog, &parent.organization_id)?.contains(&organization_id) {
+                        return Err(DbError::InvalidInput(format!(
+                            "{} cannot be part of itself",
+                            textarea[0].trim()
+                        )));
+                    }
+                    Some(parent.organization_id)
+                }
+            };
+            let organization = Organization {
+                organization_id: organization_id.clone(),
+                organization: textarea[0].trim().to_string(),
+                address: textarea[1].trim().to_string(),
+                parent_id,
+            };
+            organization.update(catalog, organization_id.clone())?;
+            Ok(())
+        })
+    }
+
+    /// Remove an organization and its links to records; bodies that were part of it keep no parent
+    pub fn delete_organization(catalog: &Catalog, organization_id: String) -> DbResult<()> {
+        let query = "DELETE FROM organizations WHERE organization_id = ?";
+        catalog.with_statement(query, |statement| {
+            statement.bind((1, organization_id.as_str()))?;
+            statement.next()?;
+            Ok(())
+        })?;
+        catalog.expect_changed(&organization_id)
+    }
+}
+
+/// `organization_id` followed by the ids of every body above it
+fn ancestors(catalog: &Catalog, organization_id: &str) -> DbResult<Vec<String>> {
+    let query = "WITH RECURSIVE up(id) AS (SELECT :id UNION SELECT o.parent_id FROM organizations o JOIN up ON o.organization_id = up.id WHERE o.parent_id IS NOT NULL) SELECT id FROM up";
+    catalog.with_statement(query, |statement| {
+        statement.bind((":id", organization_id))?;
+        let mut ids = Vec::new();
+        while let State::Row = statement.next()? {
+            ids.push(read_text(statement, "id")?);
+        }
+        Ok(ids)
+    })
+}
+
+fn read_organization(statement: &sqlite::Statement) -> DbResult<Organization> {
+    Ok(Organization {
+        organization_id: read_text(statement, "organization_id")?,
+        organization: read_text(statement, "organization")?,
+        address: read_text(statement, "address")?,
+        parent_id: statement.read::<Option<String>, _>("parent_id")?,
+    })
+}
+
+impl TableInsert for Organization {
+    fn insert(&self, catalog: &Catalog) -> DbResult<State> {
+        let query = "INSERT INTO organizations (organization_id, organization, address, parent_id) VALUES (:organization_id, :organization, :address, :parent_id)";
+        catalog.with_statement(query, |statement| {
+            statement.bind_iter::<_, (_, Value)>([
+                (":organization_id", self.organization_id.clone().into()),
+                (":organization", self.organization.clone().into()),
+                (":address", self.address.clone().into()),
+                (
+                    ":parent_id",
+                    self.parent_id.clone().map_or(Value::Null, Value::String),
+                ),
+            ])?;
+            Ok(statement.next()?)
+        })
+    }
+}
+
+impl RowUpdate for Organization {
+    fn update(&self, catalog: &Catalog, item_id: String) -> DbResult<State> {
+        let query = "UPDATE organizations SET organization = :organization, address = :address, parent_id = :parent_id WHERE organization_id = :organization_id";
+        catalog.with_statement(query, |statement| {
+            statement.bind_iter::<_, (_, Value)>([
+                (":organization", self.organization.clone().into()),
+                (":address", self.address.clone().into()),
+                (
+                    ":parent_id",
+                    self.parent_id.clone().map_or(Value::Null, Value::String),
+                ),
+                (":organization_id", item_id.clone().into()),
+            ])?;
+            let state = statement.next()?;
+            catalog.expect_changed(&item_id)?;
+            Ok(state)
+        })
+    }
+}
+
+impl RowSelect for Organization {
+    /// Lines of the `organizations` form: name, address and the name of the parent body
+    fn select(catalog: &Catalog, item_id: &str) -> DbResult<Vec<String>> {
+        let query = "SELECT o.organization, o.address, p.organization AS parent FROM organizations o LEFT JOIN organizations p ON p.organization_id = o.parent_id WHERE o.organization_id = ?";
+        catalog.with_statement(query, |statement| {
+            statement.bind((1, item_id))?;
+            match statement.next()? {
+                State::Row => ["organization", "address", "parent"]
+                    .into_iter()
+                    .map(|column| read_text(statement, column))
+                    .collect(),
+                State::Done => Err(DbError::NotFound(item_id.to_string())),
+            }
+        })
+    }
+}
+
+/// Every organization with the name of its parent body and the number of records it is linked to,
+/// sorted by name
+pub fn read_organization_table(catalog: &Catalog) -> DbResult<Vec<(Organization, String, i64)>> {
+    let query = "SELECT o.organization_id, o.organization, o.address, o.parent_id, p.organization AS parent, (SELECT COUNT(DISTINCT cite_key) FROM record_organization r WHERE r.organization_id = o.organization_id) AS records FROM organizations o LEFT JOIN organizations p ON p.organization_id = o.parent_id ORDER BY lower(o.organization)";
+    catalog.with_statement(query, |statement| {
+        let mut parsed = Vec::new();
+        while let State::Row = statement.next()? {
+            parsed.push((
+                read_organization(statement)?,
+                read_text(statement, "parent")?,
+                statement.read::<i64, _>("records")?,
+            ));
+        }
+        Ok(parsed)
+    })
+}
+
+/// An organization named on a `master_entries` record; `position` orders them as entered
+#[derive(Clone, Debug, PartialEq)]
+pub struct RecordOrganization {
+    pub(crate) cite_key: String,
+    /// Empty until the name is looked up by `replace_organizations`
+    pub(crate) organization_id: String,
+    pub(crate) organization: String,
+    pub(crate) position: i64,
+    pub(crate) role: OrgRole,
+}
+
+impl TableInsert for RecordOrganization {
+    fn insert(&self, catalog: &Catalog) -> DbResult<State> {
+        let query = "INSERT INTO record_organization (cite_key, organization_id, position, role) VALUES (:cite_key, :organization_id, :position, :role)";
+        catalog.with_statement(query, |statement| {
+            statement.bind_iter::<_, (_, Value)>([
+                (":cite_key", self.cite_key.clone().into()),
+                (":organization_id", self.organization_id.clone().into()),
+                (":position", self.position.into()),
+                (":role", self.role.as_str().into()),
+            ])?;
+            Ok(statement.next()?)
+        })
+    }
+}
+
+/// Parse the organizations line of a form, e.g. `Stanford University (degree-granting); NSF (sponsor)`.
+/// Names are separated by `;` and a role other than author goes in parentheses after the name.
+/// Parentheses holding anything but a role are part of the name, as in `Association for
+/// Computing Machinery (ACM)`.
+pub fn parse_organizations(cite_key: &str, line: &str) -> DbResult<Vec<RecordOrganization>> {
+    Ok(line
+        .split(';')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .enumerate()
+        .map(|(position, entry)| {
+            let (name, role) = match entry
+                .strip_suffix(')')
+                .and_then(|rest| rest.rsplit_once('('))
+                .and_then(|(name, role)| Some((name.trim(), role.parse().ok()?)))
+            {
+                Some((name, role)) if !name.is_empty() => (name, role),
+                _ => (entry, OrgRole::Author),
+            };
+            RecordOrganization {
+                cite_key: cite_key.to_string(),
+                organization_id: String::new(),
+                organization: name.to_string(),
+                position: position as i64,
+                role,
+            }
+        })
+        .collect())
+}
+
+/// Organizations as one form line, the inverse of `parse_organizations`
+pub fn format_organizations(organizations: &[RecordOrganization]) -> String {
+    organizations
+        .iter()
+        .map(|link| match link.role {
+            OrgRole::Author => link.organization.clone(),
+            role => format!("{} ({role})", link.organization),
+        })
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+fn read_record_organization(statement: &sqlite::Statement) -> DbResult<RecordOrganization> {
+    Ok(RecordOrganization {
+        cite_key: read_text(statement, "cite_key")?,
+        organization_id: read_text(statement, "organization_id")?,
+        organization: read_text(statement, "organization")?,
+        position: statement.read::<i64, _>("position")?,
+        role: read_text(statement, "role")?.parse()?,
+    })
+}
+
+/// Organizations named on the record with `cite_key`, in order
+pub fn select_record_organizations(
+    catalog: &Catalog,
+    cite_key: &str,
+) -> DbResult<Vec<RecordOrganization>> {
+    let query = "SELECT r.cite_key, r.organization_id, o.organization, r.position, r.role FROM record_organization r JOIN organizations o ON o.organization_id = r.organization_id WHERE r.cite_key = ? ORDER BY r.position";
+    catalog.with_statement(query, |statement| {
+        statement.bind((1, cite_key))?;
+        let mut organizations = Vec::new();
+        while let State::Row = statement.next()? {
+            organizations.push(read_record_organization(statement)?);
+        }
+        Ok(organizations)
+    })
+}
+
+/// Every organization named on a record, grouped by `cite_key`, each list in order
+pub fn read_record_organization_table(
+    catalog: &Catalog,
+) -> DbResult<HashMap<String, Vec<RecordOrganization>>> {
+    let query = "SELECT r.cite_key, r.organization_id, o.organization, r.position, r.role FROM record_organization r JOIN organizations o ON o.organization_id = r.organization_id ORDER BY r.cite_key, r.position";
+    catalog.with_statement(query, |statement| {
+        let mut organizations: HashMap<String, Vec<RecordOrganization>> = HashMap::new();
+        while let State::Row = statement.next()? {
+            let link = read_record_organization(statement)?;
+            organizations
+                .entry(link.cite_key.clone())
+                .or_default()
+                .push(link);
+        }
+        Ok(organizations)
+    })
+}
+
+/// Replace the organizations named on the record with `cite_key`, adding any organization
+/// not in the table yet
+pub fn replace_organizations(
+    catalog: &Catalog,
+    cite_key: &str,
+    organizations: &[RecordOrganization],
+) -> DbResult<()> {
+    catalog.transaction(|catalog| {
+        let query = "DELETE FROM record_organization WHERE cite_key = ?";
+        catalog.with_statement(query, |statement| {
+            statement.bind((1, cite_key))?;
+            statement.next()?;
+            Ok(())
+        })?;
+        for link in organizations {
+            let organization = Organization::find_or_create(catalog, &link.organization)?;
+            RecordOrganization {
+                cite_key: cite_key.to_string(),
+                organization_id: organization.organization_id,
+                organization: organization.organization,
+                ..link.clone()
+            }
+            .insert(catalog)?;
+        }
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::db::{Book, TestDatabase};
+    use serial_test::serial;
+
+    #[test]
+    fn parse_and_format_organizations() {
+        let links = parse_organizations(
+            "key",
+            "Stanford University (Degree Granting); Association for Computing Machinery (ACM); NSF (sponsor)",
+        )
+        .unwrap();
+        assert_eq!(links.len(), 3);
+        assert_eq!(links[0].organization, "Stanford University");
+        assert_eq!(links[0].role, OrgRole::DegreeGranting);
+        assert_eq!(
+            links[1].organization,
+            "Association for Computing Machinery (ACM)"
+        );
+        assert_eq!(links[1].role, OrgRole::Author);
+        assert_eq!(links[2].position, 2);
+        assert_eq!(
+            format_organizations(&links),
+            "Stanford University (degree-granting); Association for Computing Machinery (ACM); NSF (sponsor)"
+        );
+    }
+
+    #[test]
+    #[serial]
+    fn organizations_link_to_records() {
+        let database = TestDatabase::new();
+        let catalog = database.catalog();
+        let mut book_textarea: Vec<String> = vec![
+            "Knuth, Donald".to_string(),
+            "A Thesis".to_string(),
+            "120".to_string(),
+            "".to_string(),
+            "".to_string(),
+            "1963".to_string(),
+            "".to_string(),
+            "Caltech".to_string(),
+            "".to_string(),
+            "".to_string(),
+            "California Institute of Technology (degree-granting); NSF (sponsor)".to_string(),
+        ];
+        let cite_key = Book::book_transaction(&catalog, book_textarea.clone()).unwrap();
+        let links = select_record_organizations(&catalog, &cite_key).unwrap();
+        assert_eq!(links.len(), 2);
+        assert_eq!(links[1].organization, "NSF");
+        assert_eq!(
+            Book::select(&catalog, &cite_key).unwrap()[10],
+            book_textarea[10]
+        );
+
+        // the sponsor gets a parent body, and a body cannot end up inside itself
+        let nsf = Organization::find(&catalog, "nsf").unwrap().unwrap();
+        Organization::organization_update(
+            &catalog,
+            vec![
+                "NSF".to_string(),
+                "Alexandria, VA".to_string(),
+                "United States Government".to_string(),
+            ],
+            nsf.organization_id.clone(),
+        )
+        .unwrap();
+        assert_eq!(
+            Organization::select(&catalog, &nsf.organization_id).unwrap(),
+            vec!["NSF", "Alexandria, VA", "United States Government"]
+        );
+        let government = Organization::find(&catalog, "United States Government")
+            .unwrap()
+            .unwrap();
+        assert!(matches!(
+            Organization::organization_update(
+                &catalog,
+                vec![
+                    "United States Government".to_string(),
+                    "".to_string(),
+                    "NSF".to_string(),
+                ],
+                government.organization_id.clone(),
+            ),
+            Err(DbError::InvalidInput(_))
+        ));
+
+        // updating the record replaces its organizations
+        book_textarea[10] = "NSF (sponsor)".to_string();
+        Book::book_update(&catalog, book_textarea, cite_key.clone()).unwrap();
+        assert_eq!(
+            select_record_organizations(&catalog, &cite_key)
+                .unwrap()
+                .len(),
+            1
+        );
+
+        // removing the parent body leaves the sponsor without one
+        Organization::delete_organization(&catalog, government.organization_id).unwrap();
+        let table = read_organization_table(&catalog).unwrap();
+        let (nsf, parent, records) = table
+            .iter()
+            .find(|(organization, _, _)| organization.organization == "NSF")
+            .unwrap();
+        assert_eq!(nsf.parent_id, None);
+        assert_eq!(parent, "");
+        assert_eq!(*records, 1);
+    }
+}
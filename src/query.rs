@@ -0,0 +1,622 @@
+use crate::db::{
+    read_catalog_entry, Catalog, CatalogEntry, DbError, DbResult, EntryType, CATALOG_ENTRY_COLUMNS,
+};
+use crate::listing::{ListView, SortKey};
+use crate::search::match_expression;
+use sqlite::{State, Value};
+use std::fmt;
+
+/// Field names accepted before `:`, and what each one searches
+const FIELD_NAMES: [(&str, Prefix); 16] = [
+    ("au", Prefix::Text(Field::Author)),
+    ("author", Prefix::Text(Field::Author)),
+    ("ti", Prefix::Text(Field::Title)),
+    ("title", Prefix::Text(Field::Title)),
+    ("jo", Prefix::Text(Field::Journal)),
+    ("journal", Prefix::Text(Field::Journal)),
+    ("se", Prefix::Text(Field::Series)),
+    ("series", Prefix::Text(Field::Series)),
+    ("pub", Prefix::Text(Field::Publisher)),
+    ("publisher", Prefix::Text(Field::Publisher)),
+    ("note", Prefix::Text(Field::Note)),
+    ("isbn", Prefix::Text(Field::Isbn)),
+    ("yr", Prefix::Year),
+    ("year", Prefix::Year),
+    ("type", Prefix::Type),
+    ("key", Prefix::Key),
+];
+
+#[derive(Copy, Clone, Debug)]
+enum Prefix {
+    Text(Field),
+    Year,
+    Type,
+    Key,
+}
+
+/// A query that could not be parsed, and where the problem is
+#[derive(Clone, Debug, PartialEq)]
+pub struct QueryError {
+    /// Characters of the query before the problem
+    pub position: usize,
+    pub message: String,
+}
+
+impl QueryError {
+    fn new(position: usize, message: impl Into<String>) -> QueryError {
+        QueryError {
+            position,
+            message: message.into(),
+        }
+    }
+
+    /// The query with a `^` under the problem, for a monospaced display
+    pub fn pointer(&self, query: &str) -> String {
+        format!("{query}\n{}^", " ".repeat(self.position))
+    }
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "column {}: {}", self.position + 1, self.message)
+    }
+}
+
+impl From<QueryError> for DbError {
+    fn from(err: QueryError) -> DbError {
+        DbError::InvalidInput(err.to_string())
+    }
+}
+
+/// Text searched by a `field:` prefix
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Field {
+    Author,
+    Title,
+    Journal,
+    Series,
+    Publisher,
+    Note,
+    Isbn,
+}
+
+impl Field {
+    /// Condition on one record matching the `LIKE` pattern bound to its `?`
+    fn like_condition(&self) -> &'static str {
+        match self {
+            Field::Author => "EXISTS (SELECT 1 FROM author a WHERE a.cite_key = m.cite_key AND (a.given || ' ' || a.family) LIKE ? ESCAPE '\\')",
+            Field::Title => "coalesce(c.title, '') LIKE ? ESCAPE '\\'",
+            Field::Journal => "EXISTS (SELECT 1 FROM article x WHERE x.cite_key = m.cite_key AND x.journal LIKE ? ESCAPE '\\')",
+            Field::Series => "EXISTS (SELECT 1 FROM book x WHERE x.cite_key = m.cite_key AND x.series LIKE ? ESCAPE '\\')",
+            Field::Publisher => "coalesce(p.publisher, '') LIKE ? ESCAPE '\\'",
+            Field::Note => "coalesce(c.note, '') LIKE ? ESCAPE '\\'",
+            Field::Isbn => "EXISTS (SELECT 1 FROM book x WHERE x.cite_key = m.cite_key AND x.isbn LIKE ? ESCAPE '\\')",
+        }
+    }
+}
+
+/// A parsed search, see `parse_query` for the syntax
+#[derive(Clone, Debug, PartialEq)]
+pub enum Query {
+    /// Words without a field, matched through the full-text index like the keyword search
+    Any(String),
+    /// `field:value` for a text field; matches when the field contains the value, ignoring case
+    Contains(Field, String),
+    /// `yr:1968..1975`; an open end is `None`
+    Years(Option<i64>, Option<i64>),
+    Type(EntryType),
+    Key(String),
+    Not(Box<Query>),
+    And(Vec<Query>),
+    Or(Vec<Query>),
+}
+
+impl Query {
+    /// `WHERE` condition over `master_entries m`, `catalog_records c`, `month_year y` and
+    /// `publisher p`, with the values to bind to its `?` in order
+    pub fn to_sql(&self) -> (String, Vec<Value>) {
+        let mut values = Vec::new();
+        let condition = self.condition(&mut values);
+        (condition, values)
+    }
+
+    fn condition(&self, values: &mut Vec<Value>) -> String {
+        match self {
+            Query::Any(expression) => {
+                values.push(expression.clone().into());
+                "m.cite_key IN (SELECT cite_key FROM catalog_search WHERE catalog_search MATCH ?)"
+                    .to_string()
+            }
+            Query::Contains(field, text) => {
+                values.push(like_pattern(text).into());
+                field.like_condition().to_string()
+            }
+            Query::Years(from, to) => {
+                values.extend(from.iter().chain(to).map(|&year| Value::Integer(year)));
+                match (from, to) {
+                    (Some(_), Some(_)) => "coalesce(y.year BETWEEN ? AND ?, 0)",
+                    (Some(_), None) => "coalesce(y.year >= ?, 0)",
+                    (None, Some(_)) => "coalesce(y.year <= ?, 0)",
+                    (None, None) => "y.year IS NOT NULL",
+                }
+                .to_string()
+            }
+            Query::Type(entry_type) => {
+                values.push(entry_type.as_str().into());
+                "m.entry_type = ?".to_string()
+            }
+            Query::Key(cite_key) => {
+                values.push(cite_key.clone().into());
+                "m.cite_key = ?".to_string()
+            }
+            Query::Not(query) => format!("NOT ({})", query.condition(values)),
+            Query::And(queries) => join(queries, " AND ", values),
+            Query::Or(queries) => join(queries, " OR ", values),
+        }
+    }
+
+    /// Records matching the query, ordered by title
+    pub fn search(&self, catalog: &Catalog, limit: i64) -> DbResult<Vec<CatalogEntry>> {
+        let (condition, mut values) = self.to_sql();
+        values.push(Value::Integer(limit));
+        let query = format!(
+            "SELECT {CATALOG_ENTRY_COLUMNS}
+            FROM master_entries m
+            LEFT JOIN catalog_records c ON c.cite_key = m.cite_key
+            LEFT JOIN month_year y ON y.month_year_id = c.month_year_id
+            LEFT JOIN publisher p ON p.publisher_id = c.publisher_id
+            WHERE {condition}
+            ORDER BY lower(title), m.cite_key
+            LIMIT ?"
+        );
+        catalog.with_uncached_statement(&query, |statement| {
+            statement.bind_iter(values.into_iter().enumerate().map(|(i, v)| (i + 1, v)))?;
+            let mut entries = Vec::new();
+            while let State::Row = statement.next()? {
+                entries.push(read_catalog_entry(statement)?);
+            }
+            Ok(entries)
+        })
+    }
+}
+
+fn join(queries: &[Query], operator: &str, values: &mut Vec<Value>) -> String {
+    let conditions: Vec<String> = queries
+        .iter()
+        .map(|query| query.condition(values))
+        .collect();
+    format!("({})", conditions.join(operator))
+}
+
+/// `LIKE` pattern matching `text` anywhere, with `%` and `_` in it taken literally
+pub(crate) fn like_pattern(text: &str) -> String {
+    let mut pattern = String::from("%");
+    for c in text.chars() {
+        if matches!(c, '%' | '_' | '\\') {
+            pattern.push('\\');
+        }
+        pattern.push(c);
+    }
+    pattern.push('%');
+    pattern
+}
+
+/// Parse `text` and run it; blank text finds nothing
+pub fn advanced_search(catalog: &Catalog, text: &str, limit: i64) -> DbResult<Vec<CatalogEntry>> {
+    if text.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+    parse_query(text)?.search(catalog, limit)
+}
+
+/// `cite_key` of every record matching `text`, by title; every record in the order they were
+/// added when `text` is blank
+pub fn matching_keys(catalog: &Catalog, text: &str) -> DbResult<Vec<String>> {
+    if text.trim().is_empty() {
+        return ListView::new(SortKey::Added).cite_keys(catalog, None);
+    }
+    Ok(parse_query(text)?
+        .search(catalog, i64::MAX)?
+        .into_iter()
+        .map(|entry| entry.cite_key)
+        .collect())
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Open,
+    Close,
+    And,
+    Or,
+    Not,
+    /// A name followed by `:`
+    Field(String),
+    Word(String),
+    Quoted(String),
+}
+
+impl Token {
+    fn describe(&self) -> &'static str {
+        match self {
+            Token::Open => "`(`",
+            Token::Close => "`)`",
+            Token::And => "`AND`",
+            Token::Or => "`OR`",
+            Token::Not => "`NOT`",
+            Token::Field(_) | Token::Word(_) | Token::Quoted(_) => "a search term",
+        }
+    }
+}
+
+/// Split `text` into tokens, each with the number of characters before it
+fn tokenize(text: &str) -> Result<Vec<(usize, Token)>, QueryError> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let start = i;
+        match chars[i] {
+            c if c.is_whitespace() => i += 1,
+            '(' => {
+                tokens.push((start, Token::Open));
+                i += 1;
+            }
+            ')' => {
+                tokens.push((start, Token::Close));
+                i += 1;
+            }
+            '"' => {
+                let end = chars[start + 1..]
+                    .iter()
+                    .position(|&c| c == '"')
+                    .map(|length| start + 1 + length)
+                    .ok_or_else(|| QueryError::new(start, "this `\"` is never closed"))?;
+                tokens.push((start, Token::Quoted(chars[start + 1..end].iter().collect())));
+                i = end + 1;
+            }
+            ':' => return Err(QueryError::new(start, "a field name must come before `:`")),
+            _ => {
+                while i < chars.len()
+                    && !chars[i].is_whitespace()
+                    && !matches!(chars[i], '(' | ')' | '"' | ':')
+                {
+                    i += 1;
+                }
+                let word: String = chars[start..i].iter().collect();
+                if chars.get(i) == Some(&':') {
+                    i += 1;
+                    tokens.push((start, Token::Field(word)));
+                    continue;
+                }
+                let token = match word.as_str() {
+                    "AND" => Token::And,
+                    "OR" => Token::Or,
+                    "NOT" => Token::Not,
+                    _ => Token::Word(word),
+                };
+                tokens.push((start, token));
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+/// Parse a fielded search such as `au:knuth AND ti:"art of" AND yr:1968..1975 NOT type:article`.
+///
+/// Terms next to each other must all match, as if joined by `AND`; `OR` and `NOT` are written
+/// in capitals and parentheses group. A term is a word, a `"quoted phrase"` or `field:value`
+/// with one of the fields au, ti, jo, se, pub, note, isbn, yr, type and key (author, title,
+/// journal, series, publisher and year are also accepted). Years are `1968`, `1968..1975`,
+/// `1968..` or `..1975`.
+pub fn parse_query(text: &str) -> Result<Query, QueryError> {
+    let mut parser = Parser {
+        tokens: tokenize(text)?,
+        next: 0,
+        end: text.chars().count(),
+    };
+    let query = parser.or()?;
+    match parser.tokens.get(parser.next) {
+        None => Ok(query),
+        Some((position, Token::Close)) => {
+            Err(QueryError::new(*position, "this `)` has no matching `(`"))
+        }
+        Some((position, token)) => Err(QueryError::new(
+            *position,
+            format!("unexpected {}", token.describe()),
+        )),
+    }
+}
+
+struct Parser {
+    tokens: Vec<(usize, Token)>,
+    next: usize,
+    /// Length of the query, where an error at its end points
+    end: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.next).map(|(_, token)| token)
+    }
+
+    fn position(&self) -> usize {
+        self.tokens
+            .get(self.next)
+            .map_or(self.end, |(position, _)| *position)
+    }
+
+    fn or(&mut self) -> Result<Query, QueryError> {
+        let mut queries = vec![self.and()?];
+        while let Some(Token::Or) = self.peek() {
+            self.next += 1;
+            queries.push(self.and()?);
+        }
+        Ok(match queries.len() {
+            1 => queries.remove(0),
+            _ => Query::Or(queries),
+        })
+    }
+
+    fn and(&mut self) -> Result<Query, QueryError> {
+        let mut queries = vec![self.unary()?];
+        loop {
+            match self.peek() {
+                Some(Token::And) => {
+                    self.next += 1;
+                    queries.push(self.unary()?);
+                }
+                Some(Token::Or | Token::Close) | None => break,
+                Some(_) => queries.push(self.unary()?),
+            }
+        }
+        Ok(match queries.len() {
+            1 => queries.remove(0),
+            _ => Query::And(queries),
+        })
+    }
+
+    fn unary(&mut self) -> Result<Query, QueryError> {
+        let position = self.position();
+        let Some(token) = self.peek().cloned() else {
+            return Err(QueryError::new(
+                position,
+                "expected a search term, found the end of the query",
+            ));
+        };
+        self.next += 1;
+        match token {
+            Token::Not => Ok(Query::Not(Box::new(self.unary()?))),
+            Token::Open => {
+                let query = self.or()?;
+                match self.peek() {
+                    Some(Token::Close) => {
+                        self.next += 1;
+                        Ok(query)
+                    }
+                    _ => Err(QueryError::new(position, "this `(` is never closed")),
+                }
+            }
+            Token::Field(name) => self.field(position, &name),
+            Token::Word(word) => Ok(Query::Any(match_expression(&word).unwrap_or_default())),
+            Token::Quoted(phrase) => match phrase.split_whitespace().count() {
+                0 => Err(QueryError::new(position, "this phrase is empty")),
+                _ => Ok(Query::Any(format!(
+                    "\"{}\"",
+                    phrase.split_whitespace().collect::<Vec<_>>().join(" ")
+                ))),
+            },
+            Token::Close | Token::And | Token::Or => {
+                self.next -= 1;
+                Err(QueryError::new(
+                    position,
+                    format!("expected a search term before {}", token.describe()),
+                ))
+            }
+        }
+    }
+
+    /// The value after `name:` at `position`
+    fn field(&mut self, position: usize, name: &str) -> Result<Query, QueryError> {
+        let prefix = FIELD_NAMES
+            .iter()
+            .find(|(known, _)| known.eq_ignore_ascii_case(name))
+            .map(|(_, prefix)| *prefix)
+            .ok_or_else(|| {
+                QueryError::new(
+                    position,
+                    format!(
+                        "unknown field `{name}`; use au, ti, jo, se, pub, note, isbn, yr, type or key"
+                    ),
+                )
+            })?;
+        let value_position = self.position();
+        let value = match self.peek() {
+            Some(Token::Word(value) | Token::Quoted(value)) if !value.trim().is_empty() => {
+                value.trim().to_string()
+            }
+            _ => {
+                return Err(QueryError::new(
+                    value_position,
+                    format!("expected a value after `{name}:`"),
+                ))
+            }
+        };
+        self.next += 1;
+        match prefix {
+            Prefix::Year => parse_years(&value)
+                .map(|(from, to)| Query::Years(from, to))
+                .ok_or_else(|| {
+                    QueryError::new(
+                        value_position,
+                        format!("`{value}` is not a year or a range of years like 1968..1975"),
+                    )
+                }),
+            Prefix::Type => parse_type(&value).map(Query::Type).ok_or_else(|| {
+                let types: Vec<String> = EntryType::ALL
+                    .iter()
+                    .map(|entry_type| entry_type.as_str().to_lowercase().replace('_', "-"))
+                    .collect();
+                QueryError::new(
+                    value_position,
+                    format!("unknown type `{value}`; use {}", types.join(", ")),
+                )
+            }),
+            Prefix::Key => Ok(Query::Key(value)),
+            Prefix::Text(field) => Ok(Query::Contains(field, value)),
+        }
+    }
+}
+
+/// `1968`, `1968..1975`, `1968..` or `..1975`; `None` unless the range is in order
+pub(crate) fn parse_years(value: &str) -> Option<(Option<i64>, Option<i64>)> {
+    let year = |text: &str| -> Option<Option<i64>> {
+        match text {
+            "" => Some(None),
+            text if text.chars().all(|c| c.is_ascii_digit()) => text.parse().ok().map(Some),
+            _ => None,
+        }
+    };
+    let (from, to) = match value.split_once("..") {
+        Some((from, to)) => (year(from)?, year(to)?),
+        None => {
+            let single = year(value)?;
+            (single, single)
+        }
+    };
+    match (from, to) {
+        (None, None) if !value.contains("..") => None,
+        (Some(from), Some(to)) if from > to => None,
+        range => Some(range),
+    }
+}
+
+/// Entry type named by `value`, ignoring case and `-`, `_` or spaces between words
+pub(crate) fn parse_type(value: &str) -> Option<EntryType> {
+    let normal = |text: &str| text.to_lowercase().replace(['-', '_', ' '], "");
+    EntryType::ALL
+        .into_iter()
+        .find(|entry_type| normal(entry_type.as_str()) == normal(value))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::db::{Article, Book, TestDatabase};
+    use serial_test::serial;
+
+    #[test]
+    fn parses_fielded_boolean_queries() {
+        assert_eq!(
+            parse_query("au:knuth AND ti:\"art of\" AND yr:1968..1975 NOT type:article").unwrap(),
+            Query::And(vec![
+                Query::Contains(Field::Author, "knuth".to_string()),
+                Query::Contains(Field::Title, "art of".to_string()),
+                Query::Years(Some(1968), Some(1975)),
+                Query::Not(Box::new(Query::Type(EntryType::Article))),
+            ])
+        );
+        assert_eq!(
+            parse_query("(ti:art OR Title:structured) yr:..1970 web-resource").unwrap(),
+            Query::And(vec![
+                Query::Or(vec![
+                    Query::Contains(Field::Title, "art".to_string()),
+                    Query::Contains(Field::Title, "structured".to_string()),
+                ]),
+                Query::Years(None, Some(1970)),
+                Query::Any("\"web-resource\"*".to_string()),
+            ])
+        );
+        assert_eq!(
+            parse_query("type:\"Conference Paper\"").unwrap(),
+            Query::Type(EntryType::ConferencePaper)
+        );
+    }
+
+    #[test]
+    fn errors_point_at_the_problem() {
+        let error = |text: &str| parse_query(text).unwrap_err();
+        assert_eq!(error("au:knuth AND").position, 12);
+        assert_eq!(error("au:knuth OR ) ti:art").position, 12);
+        assert_eq!(error("(au:knuth ti:art").position, 0);
+        assert_eq!(error("au:knuth)").position, 8);
+        assert_eq!(error("ti:\"art of").position, 3);
+        assert_eq!(error("xx:knuth").position, 0);
+        assert_eq!(error("au:knuth yr:1975..1968").position, 12);
+        assert_eq!(error("type:novel").position, 5);
+        assert_eq!(error("ti: AND au:knuth").position, 4);
+        assert_eq!(error("").position, 0);
+
+        let err = error("au:knuth yr:soon");
+        assert_eq!(
+            err.to_string(),
+            "column 13: `soon` is not a year or a range of years like 1968..1975"
+        );
+        assert_eq!(
+            err.pointer("au:knuth yr:soon"),
+            "au:knuth yr:soon\n            ^"
+        );
+    }
+
+    #[test]
+    #[serial]
+    fn compiles_to_parameterized_sql() {
+        let database = TestDatabase::new();
+        let catalog = database.catalog();
+        let book = Book::book_transaction(
+            &catalog,
+            vec![
+                "Knuth, Donald".to_string(),
+                "The Art of Computer Programming".to_string(),
+                "650".to_string(),
+                "1".to_string(),
+                "".to_string(),
+                "1968".to_string(),
+                "".to_string(),
+                "Addison-Wesley".to_string(),
+                "100% cotton paper".to_string(),
+            ],
+        )
+        .unwrap();
+        let article = Article::article_transaction(
+            &catalog,
+            vec![
+                "The Art of Structured Programming".to_string(),
+                "Computing Surveys".to_string(),
+                "6".to_string(),
+                "261".to_string(),
+                "".to_string(),
+                "1974".to_string(),
+                "".to_string(),
+                "ACM".to_string(),
+                "Knuth, Donald".to_string(),
+            ],
+        )
+        .unwrap();
+
+        let keys = |text: &str| -> Vec<String> {
+            advanced_search(&catalog, text, 10)
+                .unwrap()
+                .into_iter()
+                .map(|entry| entry.cite_key)
+                .collect()
+        };
+        assert_eq!(
+            keys("au:knuth AND ti:\"art of\" AND yr:1968..1975 NOT type:article"),
+            vec![book.clone()]
+        );
+        assert_eq!(keys("au:knuth"), vec![book.clone(), article.clone()]);
+        assert_eq!(keys("jo:surveys OR pub:addison").len(), 2);
+        assert_eq!(keys("yr:1970.."), vec![article.clone()]);
+        assert_eq!(keys("structured programming"), vec![article.clone()]);
+        assert_eq!(keys(&format!("key:{book}")), vec![book.clone()]);
+        // `%` is matched literally, and a quote in a value is only data
+        assert_eq!(keys("note:\"100%\""), vec![book.clone()]);
+        assert!(keys("note:\"10%0\"").is_empty());
+        assert!(keys("ti:\"art' OR 1=1 --\"").is_empty());
+        assert!(keys("   ").is_empty());
+        assert!(matches!(
+            advanced_search(&catalog, "au:", 10),
+            Err(DbError::InvalidInput(_))
+        ));
+    }
+}
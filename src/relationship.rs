@@ -0,0 +1,349 @@
+use crate::db::{form_fields, read_text, Catalog, DbError, DbResult, EntryType, TableInsert};
+use sqlite::{State, Value};
+use std::fmt;
+use std::str::FromStr;
+use uuid::Uuid;
+
+/// Labels of the lines in the link form, in order
+pub const LINK_FIELDS: [&str; 2] = ["Relation", "Related record"];
+const LINK_REQUIRED: [usize; 2] = [0, 1];
+
+/// How one `master_entries` record relates to another
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Relation {
+    PartOf,
+    HasPart,
+    EditionOf,
+    TranslationOf,
+    Reviews,
+    Continues,
+}
+
+impl Relation {
+    pub const ALL: [Relation; 6] = [
+        Relation::PartOf,
+        Relation::HasPart,
+        Relation::EditionOf,
+        Relation::TranslationOf,
+        Relation::Reviews,
+        Relation::Continues,
+    ];
+
+    /// Value stored in the `relationship.relation` column
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Relation::PartOf => "part-of",
+            Relation::HasPart => "has-part",
+            Relation::EditionOf => "edition-of",
+            Relation::TranslationOf => "translation-of",
+            Relation::Reviews => "reviews",
+            Relation::Continues => "continues",
+        }
+    }
+
+    /// Label shown on the record the link starts from
+    pub fn label(&self) -> &'static str {
+        match self {
+            Relation::PartOf => "Part of",
+            Relation::HasPart => "Has part",
+            Relation::EditionOf => "Edition of",
+            Relation::TranslationOf => "Translation of",
+            Relation::Reviews => "Reviews",
+            Relation::Continues => "Continues",
+        }
+    }
+
+    /// Label shown on the record the link points to
+    pub fn inverse_label(&self) -> &'static str {
+        match self {
+            Relation::PartOf => "Has part",
+            Relation::HasPart => "Part of",
+            Relation::EditionOf => "Has edition",
+            Relation::TranslationOf => "Translated as",
+            Relation::Reviews => "Reviewed by",
+            Relation::Continues => "Continued by",
+        }
+    }
+}
+
+impl FromStr for Relation {
+    type Err = DbError;
+
+    fn from_str(s: &str) -> DbResult<Relation> {
+        let s = s.trim().to_lowercase().replace([' ', '_'], "-");
+        Relation::ALL
+            .into_iter()
+            .find(|relation| relation.as_str() == s)
+            .ok_or_else(|| {
+                DbError::InvalidInput(format!(
+                    "unknown relation `{s}`; use part-of, has-part, edition-of, translation-of, reviews or continues"
+                ))
+            })
+    }
+}
+
+impl fmt::Display for Relation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A typed link from the record with `cite_key` to the record with `related_key`
+#[derive(Clone, Debug, PartialEq)]
+pub struct Relationship {
+    pub(crate) relationship_id: String,
+    pub(crate) cite_key: String,
+    pub(crate) related_key: String,
+    pub(crate) relation: Relation,
+}
+
+impl Relationship {
+    /// `has-part` is stored as the `part-of` link it implies, so each link is kept only once
+    pub fn new(cite_key: &str, relation: Relation, related_key: &str) -> Relationship {
+        let (cite_key, relation, related_key) = match relation {
+            Relation::HasPart => (related_key, Relation::PartOf, cite_key),
+            _ => (cite_key, relation, related_key),
+        };
+        Relationship {
+            relationship_id: Uuid::new_v4().to_string(),
+            cite_key: cite_key.to_string(),
+            related_key: related_key.to_string(),
+            relation,
+        }
+    }
+
+    /// Link the record with `cite_key` to the one named on the link form, by cite key or title
+    pub fn link_from_form(
+        catalog: &Catalog,
+        textarea: Vec<String>,
+        cite_key: &str,
+    ) -> DbResult<Relationship> {
+        let textarea = form_fields(textarea, &LINK_FIELDS, &LINK_REQUIRED)?;
+        let relation: Relation = textarea[0].parse()?;
+        catalog.transaction(|catalog| {
+            let related_key = find_record(catalog, &textarea[1])?;
+            let relationship = Relationship::new(cite_key, relation, &related_key);
+            if relationship.cite_key == relationship.related_key {
+                return Err(DbError::InvalidInput(
+                    "a record cannot be linked to itself".to_string(),
+                ));
+            }
+            relationship.insert(catalog)?;
+            Ok(relationship)
+        })
+    }
+
+    /// Remove the link with `relationship_id`
+    pub fn unlink(catalog: &Catalog, relationship_id: &str) -> DbResult<()> {
+        let query = "DELETE FROM relationship WHERE relationship_id = ?";
+        catalog.with_statement(query, |statement| {
+            statement.bind((1, relationship_id))?;
+            statement.next()?;
+            Ok(())
+        })?;
+        catalog.expect_changed(relationship_id)
+    }
+}
+
+impl TableInsert for Relationship {
+    fn insert(&self, catalog: &Catalog) -> DbResult<State> {
+        let query = "INSERT INTO relationship (relationship_id, cite_key, related_key, relation) VALUES (:relationship_id, :cite_key, :related_key, :relation)";
+        catalog.with_statement(query, |statement| {
+            statement.bind_iter::<_, (_, Value)>([
+                (":relationship_id", self.relationship_id.clone().into()),
+                (":cite_key", self.cite_key.clone().into()),
+                (":related_key", self.related_key.clone().into()),
+                (":relation", self.relation.as_str().into()),
+            ])?;
+            Ok(statement.next()?)
+        })
+    }
+}
+
+/// A link as seen from one record: the relation read in its direction and the record at the other end
+#[derive(Clone, Debug, PartialEq)]
+pub struct Link {
+    pub(crate) relationship_id: String,
+    pub(crate) relation: Relation,
+    /// `false` when the link was made from the other record
+    pub(crate) outgoing: bool,
+    pub(crate) cite_key: String,
+    pub(crate) entry_type: EntryType,
+    pub(crate) title: String,
+}
+
+impl Link {
+    /// `Part of: The Art of Computer Programming`
+    pub fn describe(&self) -> String {
+        let label = match self.outgoing {
+            true => self.relation.label(),
+            false => self.relation.inverse_label(),
+        };
+        format!("{label}: {}", self.title)
+    }
+}
+
+/// Every link to or from the record with `cite_key`, links made from it first
+pub fn select_links(catalog: &Catalog, cite_key: &str) -> DbResult<Vec<Link>> {
+    let query = "SELECT r.relationship_id, r.relation, 1 AS outgoing, m.cite_key, m.entry_type, coalesce(c.title, '') AS title
+        FROM relationship r JOIN master_entries m ON m.cite_key = r.related_key
+        LEFT JOIN catalog_records c ON c.cite_key = m.cite_key
+        WHERE r.cite_key = :cite_key
+        UNION ALL
+        SELECT r.relationship_id, r.relation, 0 AS outgoing, m.cite_key, m.entry_type, coalesce(c.title, '') AS title
+        FROM relationship r JOIN master_entries m ON m.cite_key = r.cite_key
+        LEFT JOIN catalog_records c ON c.cite_key = m.cite_key
+        WHERE r.related_key = :cite_key
+        ORDER BY outgoing DESC, relation, title";
+    catalog.with_statement(query, |statement| {
+        statement.bind((":cite_key", cite_key))?;
+        let mut links = Vec::new();
+        while let State::Row = statement.next()? {
+            links.push(Link {
+                relationship_id: read_text(statement, "relationship_id")?,
+                relation: read_text(statement, "relation")?.parse()?,
+                outgoing: statement.read::<i64, _>("outgoing")? == 1,
+                cite_key: read_text(statement, "cite_key")?,
+                entry_type: read_text(statement, "entry_type")?.parse()?,
+                title: read_text(statement, "title")?,
+            });
+        }
+        Ok(links)
+    })
+}
+
+/// `cite_key` of the record named by `name`: its cite key, or a title shared by no other record
+pub fn find_record(catalog: &Catalog, name: &str) -> DbResult<String> {
+    let query = "SELECT cite_key FROM master_entries WHERE cite_key = :name
+        UNION SELECT cite_key FROM catalog_records WHERE lower(title) = lower(:name)";
+    let name = name.trim();
+    let found = catalog.with_statement(query, |statement| {
+        statement.bind((":name", name))?;
+        let mut found = Vec::new();
+        while let State::Row = statement.next()? {
+            found.push(read_text(statement, "cite_key")?);
+        }
+        Ok(found)
+    })?;
+    match found.as_slice() {
+        [cite_key] => Ok(cite_key.clone()),
+        [] => Err(DbError::NotFound(format!("record `{name}`"))),
+        _ => Err(DbError::InvalidInput(format!(
+            "{} records are titled `{name}`; use the cite key",
+            found.len()
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::db::{Article, Book, TestDatabase};
+    use serial_test::serial;
+
+    #[test]
+    #[serial]
+    fn link_and_navigate_records() {
+        let database = TestDatabase::new();
+        let catalog = database.catalog();
+        let set = Book::book_transaction(
+            &catalog,
+            vec![
+                "Knuth, Donald".to_string(),
+                "The Art of Computer Programming".to_string(),
+                "2000".to_string(),
+                "".to_string(),
+                "".to_string(),
+                "1968".to_string(),
+                "".to_string(),
+                "Addison-Wesley".to_string(),
+            ],
+        )
+        .unwrap();
+        let volume = Book::book_transaction(
+            &catalog,
+            vec![
+                "Knuth, Donald".to_string(),
+                "Fundamental Algorithms".to_string(),
+                "650".to_string(),
+                "1".to_string(),
+                "".to_string(),
+                "1968".to_string(),
+                "".to_string(),
+                "Addison-Wesley".to_string(),
+            ],
+        )
+        .unwrap();
+        let review = Article::article_transaction(
+            &catalog,
+            vec![
+                "A review".to_string(),
+                "Computing Reviews".to_string(),
+                "9".to_string(),
+                "301".to_string(),
+                "".to_string(),
+                "1969".to_string(),
+                "".to_string(),
+                "ACM".to_string(),
+            ],
+        )
+        .unwrap();
+
+        // has-part is kept as the part-of link it implies
+        let part = Relationship::link_from_form(
+            &catalog,
+            vec!["has part".to_string(), "fundamental algorithms".to_string()],
+            &set,
+        )
+        .unwrap();
+        assert_eq!(part.cite_key, volume);
+        assert_eq!(part.relation, Relation::PartOf);
+        Relationship::link_from_form(
+            &catalog,
+            vec!["reviews".to_string(), volume.clone()],
+            &review,
+        )
+        .unwrap();
+
+        let links = select_links(&catalog, &volume).unwrap();
+        assert_eq!(links.len(), 2);
+        assert_eq!(
+            links[0].describe(),
+            "Part of: The Art of Computer Programming"
+        );
+        assert_eq!(links[0].cite_key, set);
+        assert_eq!(links[1].describe(), "Reviewed by: A review");
+        assert_eq!(links[1].entry_type, EntryType::Article);
+        assert_eq!(
+            select_links(&catalog, &set).unwrap()[0].describe(),
+            "Has part: Fundamental Algorithms"
+        );
+
+        assert!(matches!(
+            Relationship::link_from_form(&catalog, vec!["cites".to_string(), set.clone()], &volume),
+            Err(DbError::InvalidInput(_))
+        ));
+        assert!(matches!(
+            Relationship::link_from_form(
+                &catalog,
+                vec!["continues".to_string(), "No Such Book".to_string()],
+                &volume
+            ),
+            Err(DbError::NotFound(_))
+        ));
+        assert!(matches!(
+            Relationship::link_from_form(
+                &catalog,
+                vec!["edition-of".to_string(), set.clone()],
+                &set
+            ),
+            Err(DbError::InvalidInput(_))
+        ));
+
+        // links go with the records they join
+        Article::delete_article(&catalog, review).unwrap();
+        assert_eq!(select_links(&catalog, &volume).unwrap().len(), 1);
+        Relationship::unlink(&catalog, &part.relationship_id).unwrap();
+        assert!(select_links(&catalog, &volume).unwrap().is_empty());
+    }
+}
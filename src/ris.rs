@@ -0,0 +1,646 @@
+use crate::author::{Author, Role};
+use crate::bibtex::citation_keys;
+use crate::db::{
+    read_doi, read_records_with_keys, Catalog, DbResult, EntryType, MonthYear, Record,
+};
+use crate::import::{import_records, ImportRecord, ImportReport};
+use crate::organization::{OrgRole, RecordOrganization};
+use std::fmt;
+
+/// RIS reference types written for each kind of record, in `EntryType::ALL` order
+const EXPORT_TYPES: [(EntryType, &str); 11] = [
+    (EntryType::Book, "BOOK"),
+    (EntryType::Article, "JOUR"),
+    (EntryType::Thesis, "THES"),
+    (EntryType::ConferencePaper, "CPAPER"),
+    (EntryType::BookChapter, "CHAP"),
+    (EntryType::Report, "RPRT"),
+    (EntryType::Map, "MAP"),
+    (EntryType::AudioRecording, "SOUND"),
+    (EntryType::Video, "VIDEO"),
+    (EntryType::WebResource, "ELEC"),
+    (EntryType::Software, "COMP"),
+];
+
+/// Other RIS reference types read and the kind of record each becomes
+const IMPORT_TYPES: [(&str, EntryType); 11] = [
+    ("EBOOK", EntryType::Book),
+    ("EDBOOK", EntryType::Book),
+    ("ECHAP", EntryType::BookChapter),
+    ("EJOUR", EntryType::Article),
+    ("MGZN", EntryType::Article),
+    ("NEWS", EntryType::Article),
+    ("CONF", EntryType::ConferencePaper),
+    ("MUSIC", EntryType::AudioRecording),
+    ("MPCT", EntryType::Video),
+    ("WEB", EntryType::WebResource),
+    ("BLOG", EntryType::WebResource),
+];
+
+/// Tags a person is written under for each role; illustrators have no RIS tag
+const ROLE_TAGS: [(Role, &str); 3] = [
+    (Role::Author, "AU"),
+    (Role::Editor, "ED"),
+    (Role::Translator, "A4"),
+];
+
+/// Custom field tags that keep an item type's columns RIS has no tag for, in column order
+const CUSTOM_TAGS: [&str; 8] = ["C1", "C2", "C3", "C4", "C5", "C6", "C7", "C8"];
+
+/// One RIS reference: its type and tagged values in order, several values for a tag allowed
+#[derive(Clone, Debug, PartialEq)]
+pub struct RisReference {
+    pub(crate) reference_type: String,
+    pub(crate) fields: Vec<(String, String)>,
+}
+
+impl RisReference {
+    pub fn new(reference_type: &str) -> RisReference {
+        RisReference {
+            reference_type: reference_type.to_string(),
+            fields: Vec::new(),
+        }
+    }
+
+    /// Add a value; empty values are left out
+    pub fn push(&mut self, tag: &str, value: &str) {
+        let value = value.trim();
+        if !value.is_empty() {
+            self.fields.push((tag.to_string(), value.to_string()));
+        }
+    }
+
+    /// The first value of `tag`
+    pub fn field(&self, tag: &str) -> Option<&str> {
+        self.fields
+            .iter()
+            .find(|(name, _)| name == tag)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Every value of `tag`, in order
+    pub fn values<'a>(&'a self, tag: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        self.fields
+            .iter()
+            .filter(move |(name, _)| name == tag)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+impl fmt::Display for RisReference {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "TY  - {}", self.reference_type)?;
+        for (tag, value) in &self.fields {
+            writeln!(f, "{tag}  - {value}")?;
+        }
+        writeln!(f, "ER  - ")
+    }
+}
+
+/// RIS tag an item type's own column is written to, or `None` for a custom field
+fn item_tag(entry_type: EntryType, column: &str) -> Option<&'static str> {
+    match (entry_type, column) {
+        (_, "degree" | "report_type" | "medium") => Some("M3"),
+        (_, "proceedings" | "book_title" | "site_name") => Some("T2"),
+        (_, "location") => Some("CY"),
+        (_, "pages") => Some("SP"),
+        (_, "chapter") => Some("SE"),
+        (_, "number") => Some("IS"),
+        (_, "url") => Some("UR"),
+        (_, "accessed") => Some("Y2"),
+        (EntryType::Software, "version") => Some("ET"),
+        _ => None,
+    }
+}
+
+/// Tag of each of an item type's own columns, custom fields numbered in column order
+fn item_tags(record: &Record) -> Vec<&'static str> {
+    let Record::Item(item) = record else {
+        return Vec::new();
+    };
+    let entry_type = item.item_type.entry_type;
+    let mut custom = CUSTOM_TAGS.iter();
+    item.item_type
+        .fields
+        .iter()
+        .map(|field| match item_tag(entry_type, field.column) {
+            Some(tag) => tag,
+            None => custom.next().copied().unwrap_or("M1"),
+        })
+        .collect()
+}
+
+/// People under their role's tag and organizations credited as authors under `AU`, written
+/// with a trailing comma as reference managers write a corporate author
+fn push_names(reference: &mut RisReference, authors: &[Author], bodies: &[RecordOrganization]) {
+    for (role, tag) in ROLE_TAGS {
+        for author in authors.iter().filter(|author| author.role == role) {
+            reference.push(tag, &author.name());
+        }
+        if role == Role::Author {
+            for body in bodies.iter().filter(|body| body.role == OrgRole::Author) {
+                reference.push(tag, &format!("{},", body.organization));
+            }
+        }
+    }
+}
+
+/// `PY` with the year and `DA` as `YYYY/MM/DD/` when the date has a month
+fn push_date(reference: &mut RisReference, date: &str) {
+    let Ok(date) = MonthYear::parse(date) else {
+        return;
+    };
+    reference.push("PY", &date.year.to_string());
+    if let Some(month) = date.month {
+        let day = date.day.map(|day| format!("{day:02}")).unwrap_or_default();
+        reference.push("DA", &format!("{}/{month:02}/{day}/", date.year));
+    }
+}
+
+/// `SP` and `EP` from a page range such as `147-148`; a single number goes in `SP`
+fn push_pages(reference: &mut RisReference, pages: &str) {
+    match pages.split_once(['-', '–']) {
+        Some((start, end)) => {
+            reference.push("SP", start);
+            reference.push("EP", end.trim_start_matches('-'));
+        }
+        None => reference.push("SP", pages),
+    }
+}
+
+/// A record and its DOI as a RIS reference with `ID` set to `key`
+pub fn ris_reference(record: &Record, key: &str, doi: &str) -> RisReference {
+    let entry_type = match record {
+        Record::Book(_) => EntryType::Book,
+        Record::Article(_) => EntryType::Article,
+        Record::Item(item) => item.item_type.entry_type,
+    };
+    let reference_type = EXPORT_TYPES
+        .iter()
+        .find(|(export_type, _)| *export_type == entry_type)
+        .map_or("GEN", |(_, name)| name);
+    let mut reference = RisReference::new(reference_type);
+    reference.push("ID", key);
+    match record {
+        Record::Book(book) => {
+            push_names(&mut reference, &book.authors, &book.organizations);
+            reference.push("TI", &book.title);
+            reference.push("T2", &book.series);
+            push_date(&mut reference, &book.year);
+            reference.push("VL", &book.volume);
+            reference.push("ET", &book.edition);
+            push_pages(&mut reference, &book.pages);
+            reference.push("PB", &book.publisher);
+            reference.push("SN", &book.isbn);
+            reference.push("N1", &book.note);
+        }
+        Record::Article(article) => {
+            push_names(&mut reference, &article.authors, &article.organizations);
+            reference.push("TI", &article.title);
+            reference.push("T2", &article.journal);
+            push_date(&mut reference, &article.year);
+            reference.push("VL", &article.volume);
+            reference.push("ET", &article.edition);
+            push_pages(&mut reference, &article.pages);
+            reference.push("PB", &article.publisher);
+            reference.push("N1", &article.note);
+        }
+        Record::Item(item) => {
+            push_names(&mut reference, &item.authors, &item.organizations);
+            reference.push("TI", &item.title);
+            push_date(&mut reference, &item.year);
+            for (tag, value) in item_tags(record).into_iter().zip(&item.fields) {
+                match tag {
+                    "SP" => push_pages(&mut reference, value),
+                    tag => reference.push(tag, value),
+                }
+            }
+            let publisher = match item.publisher.as_str() {
+                "" => item
+                    .organizations
+                    .iter()
+                    .find(|body| body.role == OrgRole::DegreeGranting)
+                    .map_or("", |body| body.organization.as_str()),
+                publisher => publisher,
+            };
+            reference.push("PB", publisher);
+            reference.push("N1", &item.note);
+        }
+    }
+    reference.push("DO", doi);
+    reference
+}
+
+/// The records with `cite_keys` as a RIS file, in that order, with the same `ID`s as the
+/// BibTeX keys; keys not in the catalog are left out. Returns the text and the number written.
+pub fn export_ris(catalog: &Catalog, cite_keys: &[&str]) -> DbResult<(String, usize)> {
+    let keys = citation_keys(catalog)?;
+    let records = read_records_with_keys(catalog, cite_keys)?;
+    let mut references = Vec::new();
+    for record in &records {
+        let cite_key = record.cite_key();
+        let key = keys.get(cite_key).map_or(cite_key, String::as_str);
+        let doi = read_doi(catalog, cite_key)?;
+        references.push(ris_reference(record, key, &doi).to_string());
+    }
+    let text = references.join("\n");
+    Ok((text, records.len()))
+}
+
+/// Tag and value of a `XX  - value` line
+fn tagged_line(line: &str) -> Option<(&str, &str)> {
+    let tag = line.get(..2)?;
+    let rest = line.get(2..)?.trim_start_matches(' ');
+    let valid_tag = tag.starts_with(|c: char| c.is_ascii_uppercase())
+        && tag
+            .chars()
+            .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit());
+    match (valid_tag, rest.strip_prefix('-')) {
+        (true, Some(value)) => Some((tag, value.trim())),
+        _ => None,
+    }
+}
+
+/// A reference and the line of the file its `TY` is on
+pub type LinedReference = (usize, RisReference);
+
+/// References of a RIS file with the line each starts on, and the references that could not be
+/// read with the reason. A line without a tag continues the value before it.
+pub fn parse_ris(text: &str) -> (Vec<LinedReference>, Vec<(String, String)>) {
+    let mut references = Vec::new();
+    let mut problems = Vec::new();
+    let mut current: Option<(usize, RisReference)> = None;
+    let text = text.trim_start_matches('\u{feff}');
+    for (index, line) in text.lines().enumerate() {
+        let number = index + 1;
+        let Some((tag, value)) = tagged_line(line) else {
+            if let Some((_, reference)) = current.as_mut() {
+                if let Some((_, last)) = reference.fields.last_mut() {
+                    if !line.trim().is_empty() {
+                        last.push(' ');
+                        last.push_str(line.trim());
+                    }
+                }
+            }
+            continue;
+        };
+        match (tag, current.as_mut()) {
+            ("TY", Some((start, _))) => {
+                problems.push((
+                    format!("reference on line {start}"),
+                    "`ER` missing before the next `TY`".to_string(),
+                ));
+                current = Some((number, RisReference::new(value)));
+            }
+            ("TY", None) => current = Some((number, RisReference::new(value))),
+            ("ER", Some(_)) => references.extend(current.take()),
+            (tag, Some((_, reference))) => reference.push(tag, value),
+            (tag, None) => problems.push((
+                format!("line {number}"),
+                format!("`{tag}` outside a reference"),
+            )),
+        }
+    }
+    if let Some((start, _)) = current {
+        problems.push((
+            format!("reference on line {start}"),
+            "`ER` missing at the end of the file".to_string(),
+        ));
+    }
+    (references, problems)
+}
+
+/// A RIS author as `Family, Given` for the authors line, or `None` for a corporate author
+/// written with a trailing comma. `Last, First, Suffix` keeps the suffix after the given name.
+fn person(name: &str) -> Option<String> {
+    let parts: Vec<&str> = name.split(',').map(str::trim).collect();
+    match parts.as_slice() {
+        [body, ""] if !body.is_empty() => None,
+        [family, given] => Some(format!("{family}, {given}")),
+        [family, given, suffix, ..] => Some(format!("{family}, {given} {suffix}")),
+        _ => match name.trim().rsplit_once(' ') {
+            Some((given, family)) => Some(format!("{family}, {given}")),
+            None => Some(name.trim().to_string()),
+        },
+    }
+}
+
+/// The date line of a reference from `DA` or `PY`, either `YYYY/MM/DD/` or a bare year
+fn date_line(reference: &RisReference) -> String {
+    let date = reference
+        .field("DA")
+        .filter(|date| date.starts_with(|c: char| c.is_ascii_digit()))
+        .or(reference.field("PY"))
+        .or(reference.field("Y1"))
+        .unwrap_or_default();
+    date.split('/')
+        .map(str::trim)
+        .take(3)
+        .take_while(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// The form for a reference, or why it has none
+fn import_record(line: usize, reference: &RisReference) -> Result<ImportRecord, String> {
+    let source = match reference.field("ID") {
+        Some(id) => format!("reference `{id}` on line {line}"),
+        None => format!("reference on line {line}"),
+    };
+    let name = reference.reference_type.as_str();
+    let entry_type = EXPORT_TYPES
+        .iter()
+        .find(|(_, export_name)| *export_name == name)
+        .map(|(entry_type, _)| *entry_type)
+        .or_else(|| {
+            IMPORT_TYPES
+                .iter()
+                .find(|(import_name, _)| *import_name == name)
+                .map(|(_, entry_type)| *entry_type)
+        })
+        .ok_or_else(|| format!("TY {name} has no matching record type"))?;
+    let first = |tags: &[&str]| {
+        tags.iter()
+            .find_map(|tag| reference.field(tag))
+            .unwrap_or_default()
+            .to_string()
+    };
+    let mut people = Vec::new();
+    let mut organizations = Vec::new();
+    for (tag, role) in [
+        ("AU", ""),
+        ("A1", ""),
+        ("ED", " (editor)"),
+        ("A2", " (editor)"),
+        ("A4", " (translator)"),
+    ] {
+        for name in reference.values(tag) {
+            match person(name) {
+                Some(name) => people.push(format!("{name}{role}")),
+                None if role.is_empty() => organizations.push(name.trim_end_matches(',').trim()),
+                None => people.push(format!("{}{role}", name.trim_end_matches(','))),
+            }
+        }
+    }
+    let pages = match (first(&["SP"]), first(&["EP"])) {
+        (start, end) if end.is_empty() => start,
+        (start, end) => format!("{start}-{end}"),
+    };
+    let mut record = ImportRecord::new(source, entry_type);
+    record.set("Authors", &people.join("; "));
+    record.set("Title", &first(&["TI", "T1", "CT", "BT"]));
+    record.set("Date", &date_line(reference));
+    record.set("Publisher", &first(&["PB"]));
+    record.set("Note", &first(&["N1"]));
+    record.set("Organizations", &organizations.join("; "));
+    record.doi = first(&["DO"]);
+    match entry_type {
+        EntryType::Book => {
+            record.set("Pages", &pages);
+            record.set("Volume", &first(&["VL"]));
+            record.set("Edition", &first(&["ET"]));
+            record.set("Series", &first(&["T2", "T3"]));
+            record.set("ISBN", &first(&["SN"]));
+        }
+        EntryType::Article => {
+            record.set("Journal", &first(&["T2", "JO", "JF", "JA"]));
+            record.set("Volume", &first(&["VL"]));
+            record.set("Pages", &pages);
+            record.set("Edition", &first(&["ET"]));
+        }
+        _ => {}
+    }
+    if let Some(item_type) = entry_type.item_type() {
+        let mut custom = CUSTOM_TAGS.iter();
+        for field in item_type.fields {
+            let value = match item_tag(entry_type, field.column) {
+                Some("SP") => pages.clone(),
+                Some(tag) => first(&[tag]),
+                None => first(&[custom.next().copied().unwrap_or("M1")]),
+            };
+            record.set(field.label, &value);
+        }
+    }
+    Ok(record)
+}
+
+/// Import the references of a RIS file through `import_records`. References that cannot be
+/// read, or whose type has no matching record type, are reported as skipped.
+pub fn import_ris(catalog: &Catalog, text: &str, dry_run: bool) -> DbResult<ImportReport> {
+    let (references, mut skipped) = parse_ris(text);
+    let mut records = Vec::new();
+    for (line, reference) in &references {
+        match import_record(*line, reference) {
+            Ok(record) => records.push(record),
+            Err(reason) => skipped.push((format!("reference on line {line}"), reason)),
+        }
+    }
+    let mut report = import_records(catalog, &records, dry_run)?;
+    for (source, reason) in skipped {
+        report.skip(source, reason);
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::db::TestDatabase;
+    use crate::listing::{ListView, SortKey};
+    use serial_test::serial;
+
+    const LIBRARY: &str = "\u{feff}TY  - BOOK
+ID  - knuth1968art
+AU  - Knuth, Donald E.
+AU  - Graham, Ronald
+AU  - Stanford Computer Science Department,
+ED  - Gödel, Kurt
+TI  - The Art of Computer
+  Programming
+T2  - Series in Computer Science
+PY  - 1968
+DA  - 1968/03//
+VL  - 1
+ET  - 2nd
+SP  - 650
+PB  - Addison-Wesley
+SN  - 0-201-03801-3
+N1  - Fascicle one
+ER  - 
+
+TY  - JOUR
+AU  - Edsger W. Dijkstra
+TI  - Go to statement considered harmful
+JO  - Communications of the ACM
+PY  - 1968/03/01/
+VL  - 11
+SP  - 147
+EP  - 148
+PB  - ACM
+DO  - 10.1145/362929.362947
+ER  - 
+
+TY  - MAP
+AU  - Ordnance Survey,
+TI  - Lake District
+PY  - 2015
+PB  - Ordnance Survey
+C1  - 1:25000
+C3  - 84 x 102 cm
+ER  - 
+
+TY  - PAT
+TI  - A patent
+ER  - 
+TY  - CPAPER
+TI  - Unfinished
+";
+
+    #[test]
+    fn reads_ris_files() {
+        let (references, problems) = parse_ris(LIBRARY);
+        assert_eq!(references.len(), 4);
+        assert_eq!(
+            problems,
+            vec![(
+                "reference on line 44".to_string(),
+                "`ER` missing at the end of the file".to_string()
+            )]
+        );
+        let (line, book) = &references[0];
+        assert_eq!(*line, 1);
+        assert_eq!(book.values("AU").count(), 3);
+        assert_eq!(book.field("TI"), Some("The Art of Computer Programming"));
+
+        let record = import_record(*line, book).unwrap();
+        assert_eq!(record.source, "reference `knuth1968art` on line 1");
+        assert_eq!(
+            record.get("Authors"),
+            "Knuth, Donald E.; Graham, Ronald; Gödel, Kurt (editor)"
+        );
+        assert_eq!(
+            record.get("Organizations"),
+            "Stanford Computer Science Department"
+        );
+        assert_eq!(record.get("Date"), "1968-03");
+        assert_eq!(record.get("Series"), "Series in Computer Science");
+
+        let record = import_record(references[1].0, &references[1].1).unwrap();
+        assert_eq!(record.get("Authors"), "Dijkstra, Edsger W.");
+        assert_eq!(record.get("Journal"), "Communications of the ACM");
+        assert_eq!(record.get("Pages"), "147-148");
+        assert_eq!(record.get("Date"), "1968-03-01");
+        assert_eq!(record.get("Note"), "");
+        assert_eq!(record.doi, "10.1145/362929.362947");
+
+        let record = import_record(references[2].0, &references[2].1).unwrap();
+        assert_eq!(record.get("Scale"), "1:25000");
+        assert_eq!(record.get("Projection"), "");
+        assert_eq!(record.get("Dimensions"), "84 x 102 cm");
+        assert_eq!(
+            import_record(41, &references[3].1).unwrap_err(),
+            "TY PAT has no matching record type"
+        );
+        assert_eq!(
+            person("Knuth, Donald E., Jr."),
+            Some("Knuth, Donald E. Jr.".to_string())
+        );
+    }
+
+    #[test]
+    #[serial]
+    fn round_trips_ris() {
+        let export = |catalog: &Catalog| {
+            let cite_keys = ListView::new(SortKey::Added)
+                .cite_keys(catalog, None)
+                .unwrap();
+            let cite_keys: Vec<&str> = cite_keys.iter().map(String::as_str).collect();
+            export_ris(catalog, &cite_keys).unwrap()
+        };
+        let first = TestDatabase::new();
+        let catalog = first.catalog();
+
+        let report = import_ris(&catalog, LIBRARY, false).unwrap();
+        assert_eq!(report.imported(), 3);
+        assert_eq!(report.skipped.len(), 2);
+        let (text, count) = export(&catalog);
+        assert_eq!(count, 3);
+        assert!(text.starts_with(
+            "TY  - BOOK
+ID  - knuth1968art
+AU  - Knuth, Donald E.
+AU  - Graham, Ronald
+AU  - Stanford Computer Science Department,
+ED  - Gödel, Kurt
+TI  - The Art of Computer Programming
+T2  - Series in Computer Science
+PY  - 1968
+DA  - 1968/03//
+VL  - 1
+ET  - 2nd
+SP  - 650
+PB  - Addison-Wesley
+SN  - 0-201-03801-3
+N1  - Fascicle one
+ER  - 
+"
+        ));
+        assert!(text.contains("SP  - 147\nEP  - 148\n"));
+        assert!(text.contains("DO  - 10.1145/362929.362947\n"));
+        assert!(text.contains("C1  - 1:25000\nC3  - 84 x 102 cm\n"));
+
+        // the export reads back into another catalog as the same records
+        let second = TestDatabase::new();
+        let report = import_ris(&second.catalog(), &text, false).unwrap();
+        assert_eq!(report.imported(), 3);
+        assert_eq!(export(&second.catalog()), (text.clone(), 3));
+
+        let report = import_ris(&catalog, &text, false).unwrap();
+        assert_eq!(report.imported(), 0);
+        assert_eq!(report.duplicates.len(), 3);
+    }
+
+    #[test]
+    #[serial]
+    // Vendor exports often leave out the date, pages and publisher
+    fn round_trips_minimal_reference() {
+        let database = TestDatabase::new();
+        let catalog = database.catalog();
+        let text =
+            "TY  - BOOK\nAU  - Hoare, C. A. R.\nTI  - Communicating sequential processes\nER  - \n";
+        let report = import_ris(&catalog, text, false).unwrap();
+        assert_eq!(report.skipped, vec![]);
+        assert_eq!(report.imported(), 1);
+
+        let cite_key = report.imported[0].1.as_str();
+        let (exported, count) = export_ris(&catalog, &[cite_key]).unwrap();
+        assert_eq!(count, 1);
+        let (references, problems) = parse_ris(&exported);
+        assert_eq!(problems, vec![]);
+        let reference = &references[0].1;
+        assert_eq!(reference.reference_type, "BOOK");
+        assert_eq!(reference.field("AU"), Some("Hoare, C. A. R."));
+        assert_eq!(
+            reference.field("TI"),
+            Some("Communicating sequential processes")
+        );
+        assert_eq!(reference.field("PY"), None);
+        assert_eq!(reference.field("SP"), None);
+        assert_eq!(reference.field("PB"), None);
+
+        // an article needs its journal, but not the volume, pages or publisher
+        let text = "TY  - JOUR\nTI  - Monitors\nJO  - Communications of the ACM\nER  - \n";
+        let report = import_ris(&catalog, text, true).unwrap();
+        assert_eq!((report.imported(), report.skipped.len()), (1, 0));
+
+        let other = TestDatabase::new();
+        let report = import_ris(&other.catalog(), &exported, false).unwrap();
+        assert_eq!(report.imported(), 1);
+        assert_eq!(
+            export_ris(&other.catalog(), &[report.imported[0].1.as_str()])
+                .unwrap()
+                .0,
+            exported
+        );
+    }
+}
@@ -0,0 +1,310 @@
+use crate::db::{read_catalog_entry, Catalog, CatalogEntry, DbResult, CATALOG_ENTRY_COLUMNS};
+use crate::query::advanced_search;
+use sqlite::State;
+
+/// Most results shown for a search in the TUI
+pub const SEARCH_LIMIT: i64 = 100;
+
+/// Fill `catalog_search` with the text of the records selected by the `WHERE` clause that follows
+const INDEX_RECORDS: &str = "INSERT INTO catalog_search (cite_key, title, authors, journal, series, publisher, note)
+    SELECT c.cite_key, c.title,
+        coalesce((SELECT group_concat(a.given || ' ' || a.family, '; ') FROM author a WHERE a.cite_key = c.cite_key), ''),
+        coalesce((SELECT journal FROM article WHERE cite_key = c.cite_key), ''),
+        coalesce((SELECT series FROM book WHERE cite_key = c.cite_key), ''),
+        coalesce(p.publisher, ''),
+        coalesce(c.note, '')
+    FROM catalog_records c LEFT JOIN publisher p ON p.publisher_id = c.publisher_id";
+
+/// Bring the search text of the record with `cite_key` up to date; call after every write to it
+pub fn index_record(catalog: &Catalog, cite_key: &str) -> DbResult<()> {
+    unindex_record(catalog, cite_key)?;
+    let query = format!("{INDEX_RECORDS} WHERE c.cite_key = ?");
+    catalog.with_statement(&query, |statement| {
+        statement.bind((1, cite_key))?;
+        statement.next()?;
+        Ok(())
+    })
+}
+
+/// Drop the search text of the record with `cite_key`
+pub fn unindex_record(catalog: &Catalog, cite_key: &str) -> DbResult<()> {
+    let query = "DELETE FROM catalog_search WHERE cite_key = ?";
+    catalog.with_statement(query, |statement| {
+        statement.bind((1, cite_key))?;
+        statement.next()?;
+        Ok(())
+    })
+}
+
+/// Re-index every record using the publisher with `publisher_id`, after it was renamed
+pub fn index_publisher_records(catalog: &Catalog, publisher_id: &str) -> DbResult<()> {
+    let query = "SELECT cite_key FROM catalog_records WHERE publisher_id = ?";
+    let cite_keys = catalog.with_statement(query, |statement| {
+        statement.bind((1, publisher_id))?;
+        let mut cite_keys = Vec::new();
+        while let State::Row = statement.next()? {
+            cite_keys.push(statement.read::<String, _>(0)?);
+        }
+        Ok(cite_keys)
+    })?;
+    for cite_key in cite_keys {
+        index_record(catalog, &cite_key)?;
+    }
+    Ok(())
+}
+
+/// Rebuild `catalog_search` from every record in the catalog
+pub(crate) fn rebuild_search_index(catalog: &Catalog) -> DbResult<()> {
+    catalog.execute("DELETE FROM catalog_search")?;
+    catalog.execute(INDEX_RECORDS)
+}
+
+/// FTS5 query for the words typed: each word must appear, as a word or the start of one.
+/// Quoting every word keeps FTS5 operators and punctuation typed by the user literal.
+pub fn match_expression(text: &str) -> Option<String> {
+    let words: Vec<String> = text
+        .split_whitespace()
+        .map(|word| word.replace('"', ""))
+        .filter(|word| !word.is_empty())
+        .map(|word| format!("\"{word}\"*"))
+        .collect();
+    match words.is_empty() {
+        true => None,
+        false => Some(words.join(" ")),
+    }
+}
+
+/// Records matching every word of `text`, best match first; titles count most, then authors
+pub fn search_catalog(catalog: &Catalog, text: &str, limit: i64) -> DbResult<Vec<CatalogEntry>> {
+    let Some(expression) = match_expression(text) else {
+        return Ok(Vec::new());
+    };
+    let query = format!(
+        "SELECT {CATALOG_ENTRY_COLUMNS}
+        FROM catalog_search s
+        JOIN master_entries m ON m.cite_key = s.cite_key
+        LEFT JOIN catalog_records c ON c.cite_key = m.cite_key
+        LEFT JOIN month_year y ON y.month_year_id = c.month_year_id
+        WHERE catalog_search MATCH :expression
+        ORDER BY bm25(catalog_search, 0.0, 10.0, 5.0, 2.0, 2.0, 1.0, 1.0), lower(c.title)
+        LIMIT :limit"
+    );
+    catalog.with_statement(&query, |statement| {
+        statement.bind((":expression", expression.as_str()))?;
+        statement.bind((":limit", limit))?;
+        let mut entries = Vec::new();
+        while let State::Row = statement.next()? {
+            entries.push(read_catalog_entry(statement)?);
+        }
+        Ok(entries)
+    })
+}
+
+/// Records found by the search typed in the TUI, searched again only when the text, the kind of
+/// search or the catalog changes, whichever connection wrote to it
+#[derive(Clone, Debug)]
+pub struct SearchResults {
+    text: String,
+    /// Whether `text` was read as a fielded query, see `query::parse_query`
+    advanced: bool,
+    /// Records found, or why the search failed
+    found: Result<Vec<CatalogEntry>, String>,
+    /// `Catalog::version` when the search ran; `None` until it does
+    read_at: Option<(i64, usize)>,
+}
+
+impl SearchResults {
+    pub fn new() -> SearchResults {
+        SearchResults {
+            text: String::new(),
+            advanced: false,
+            found: Ok(Vec::new()),
+            read_at: None,
+        }
+    }
+
+    /// Records found by the last search, or why it failed
+    pub fn found(&self) -> Result<&[CatalogEntry], &str> {
+        match &self.found {
+            Ok(found) => Ok(found),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Records found by the last search; none if it failed
+    pub fn entries(&self) -> &[CatalogEntry] {
+        self.found().unwrap_or_default()
+    }
+
+    /// Search for `text` unless the last search was for the same text on the same catalog
+    pub fn refresh(&mut self, catalog: &Catalog, text: &str, advanced: bool) {
+        let version = catalog.version().ok();
+        if version.is_some()
+            && self.read_at == version
+            && self.advanced == advanced
+            && self.text == text
+        {
+            return;
+        }
+        let found = match advanced {
+            true => advanced_search(catalog, text, SEARCH_LIMIT),
+            false => search_catalog(catalog, text, SEARCH_LIMIT),
+        };
+        self.found = found.map_err(|err| err.to_string());
+        self.text = text.to_string();
+        self.advanced = advanced;
+        self.read_at = version;
+    }
+}
+
+impl Default for SearchResults {
+    fn default() -> SearchResults {
+        SearchResults::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::db::{Article, Book, Database, EntryType, Publisher, RowSelect, TestDatabase};
+    use serial_test::serial;
+
+    #[test]
+    fn typed_words_are_quoted() {
+        assert_eq!(match_expression("  "), None);
+        assert_eq!(
+            match_expression("art OR \"computer"),
+            Some("\"art\"* \"OR\"* \"computer\"*".to_string())
+        );
+    }
+
+    #[test]
+    #[serial]
+    fn search_follows_writes() {
+        let database = TestDatabase::new();
+        let catalog = database.catalog();
+        let book = Book::book_transaction(
+            &catalog,
+            vec![
+                "Knuth, Donald".to_string(),
+                "The Art of Computer Programming".to_string(),
+                "650".to_string(),
+                "1".to_string(),
+                "".to_string(),
+                "1968".to_string(),
+                "Computer Science and Information Processing".to_string(),
+                "Addison-Wesley".to_string(),
+                "".to_string(),
+            ],
+        )
+        .unwrap();
+        let article = Article::article_transaction(
+            &catalog,
+            vec![
+                "Structured programming with go to statements".to_string(),
+                "Computing Surveys".to_string(),
+                "6".to_string(),
+                "261".to_string(),
+                "Cites The Art of Computer Programming".to_string(),
+                "1974".to_string(),
+                "".to_string(),
+                "ACM".to_string(),
+                "Knuth, Donald".to_string(),
+            ],
+        )
+        .unwrap();
+        let thesis = EntryType::Thesis
+            .item_type()
+            .unwrap()
+            .item_transaction(
+                &catalog,
+                vec![
+                    "Café, Zoë".to_string(),
+                    "Programming résumés".to_string(),
+                    "".to_string(),
+                    "".to_string(),
+                    "".to_string(),
+                    "".to_string(),
+                    "PhD".to_string(),
+                ],
+            )
+            .unwrap();
+
+        let keys = |text: &str| -> Vec<String> {
+            search_catalog(&catalog, text, 10)
+                .unwrap()
+                .into_iter()
+                .map(|entry| entry.cite_key)
+                .collect()
+        };
+        // a title match ranks above the same words in a note
+        assert_eq!(keys("art computer"), vec![book.clone(), article.clone()]);
+        assert_eq!(keys("knuth").len(), 2);
+        assert_eq!(keys("surveys"), vec![article.clone()]);
+        assert_eq!(keys("information processing"), vec![book.clone()]);
+        // prefixes and accents
+        assert_eq!(keys("resume zoe"), vec![thesis.clone()]);
+        assert_eq!(keys("addison"), vec![book.clone()]);
+        assert!(keys("nothing like this").is_empty());
+
+        let mut form = Book::select(&catalog, &book).unwrap();
+        form[1] = "Fundamental Algorithms".to_string();
+        Book::book_update(&catalog, form, book.clone()).unwrap();
+        assert_eq!(keys("fundamental"), vec![book.clone()]);
+        assert_eq!(keys("art computer"), vec![article.clone()]);
+
+        let publisher = Publisher::find(&catalog, "Addison-Wesley")
+            .unwrap()
+            .unwrap();
+        Publisher::publisher_update(
+            &catalog,
+            vec!["Pearson".to_string(), "".to_string(), "".to_string()],
+            publisher.publisher_id,
+        )
+        .unwrap();
+        assert_eq!(keys("pearson"), vec![book.clone()]);
+        assert!(keys("addison").is_empty());
+
+        Book::delete_book(&catalog, book).unwrap();
+        assert!(keys("fundamental").is_empty());
+    }
+
+    #[test]
+    #[serial]
+    fn results_are_kept_until_the_search_changes() {
+        let database = TestDatabase::new();
+        let catalog = database.catalog();
+        let book = Book::book_transaction(
+            &catalog,
+            vec![
+                "Knuth, Donald".to_string(),
+                "The Art of Computer Programming".to_string(),
+                "650".to_string(),
+                "1".to_string(),
+                "".to_string(),
+                "1968".to_string(),
+                "".to_string(),
+                "Addison-Wesley".to_string(),
+                "".to_string(),
+            ],
+        )
+        .unwrap();
+        let mut results = SearchResults::new();
+        results.refresh(&catalog, "art", false);
+        assert_eq!(results.entries().len(), 1);
+
+        // a write from a second connection shows up without changing the search
+        let other = Database::open(database.db_url()).unwrap();
+        unindex_record(&other.catalog(), &book).unwrap();
+        results.refresh(&catalog, "art", false);
+        assert!(results.entries().is_empty());
+
+        index_record(&catalog, &book).unwrap();
+        results.refresh(&catalog, "art", false);
+        assert_eq!(results.entries().len(), 1);
+
+        results.refresh(&catalog, "title:(art", true);
+        assert!(results.found().is_err());
+        assert!(results.entries().is_empty());
+    }
+}
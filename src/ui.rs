@@ -1,14 +1,22 @@
-use crate::db::{read_sqlite_article_table, read_sqlite_book_table};
+use crate::author::{format_authors, Author};
+use crate::csv::{preview, table_records, ColumnMapping, Table, MAPPING_FIELDS, PREVIEW_ROWS};
+use crate::db::{CatalogEntry, Publisher};
+use crate::item::{ItemType, ITEM_COMMON_FIELDS};
+use crate::listing::{ListWindow, FILTER_FIELDS};
+use crate::organization::{format_organizations, Organization};
+use crate::query::parse_query;
+use crate::relationship::Link;
 use ratatui::layout::Rect;
 use ratatui::prelude::{Alignment, Color, Constraint, Direction, Layout, Modifier, Style};
 use ratatui::text::{Line, Span};
 use ratatui::widgets::{Block, BorderType, Borders, List, ListItem, ListState, Paragraph, Tabs};
+use std::collections::HashSet;
 use std::iter::Cloned;
 use std::rc::Rc;
 use std::slice::Iter;
 use std::sync::{Arc, Mutex};
 
-/// UI for the tui app
+// UI for the tui app
 
 /// UI for Menu bar
 // todo! change underlined letter on menu bar?
@@ -28,12 +36,12 @@ pub fn menu<'a>(titles: Cloned<Iter<'a, &'static str>>, select: usize) -> Tabs<'
         })
         .collect();
 
-    return Tabs::new(menu)
+    Tabs::new(menu)
         .select(select)
         .block(Block::default().title("Menu").borders(Borders::ALL))
         .style(Style::default().fg(Color::White))
         .highlight_style(Style::default().fg(Color::Rgb(35, 70, 184)))
-        .divider(Span::raw("|"));
+        .divider(Span::raw("|"))
 }
 
 /// UI for `new_book`
@@ -75,9 +83,71 @@ pub fn new_article_block(x: bool) -> Block<'static> {
     }
 }
 
+/// UI for editing a `publisher`
+pub fn edit_publisher_block() -> Block<'static> {
+    Block::default()
+        .borders(Borders::ALL)
+        .style(Style::default().fg(Color::LightCyan))
+        .title("Update Publisher:     Press 'F2' to enter edit mode and 'F9' to save     ")
+        .border_type(BorderType::Plain)
+}
+
+/// UI for adding or updating an item of `item_type`
+pub fn new_item_block(item_type: &ItemType, x: bool) -> Block<'static> {
+    let action = if x { "Update" } else { "New" };
+    Block::default()
+        .borders(Borders::ALL)
+        .style(Style::default().fg(Color::LightCyan))
+        .title(format!(
+            "{action} {}:     Press 'F2' to enter edit mode and 'F9' to save     ",
+            item_type.entry_type.name()
+        ))
+        .border_type(BorderType::Plain)
+}
+
+/// UI for adding or editing an `organization`
+pub fn edit_organization_block(x: bool) -> Block<'static> {
+    let title = match x {
+        false => "New Organization:     Press 'F2' to enter edit mode and 'F9' to save     ",
+        true => "Update Organization:     Press 'F2' to enter edit mode and 'F9' to save     ",
+    };
+    Block::default()
+        .borders(Borders::ALL)
+        .style(Style::default().fg(Color::LightCyan))
+        .title(title)
+        .border_type(BorderType::Plain)
+}
+
+/// UI for linking the selected record to another one
+pub fn edit_link_block() -> Block<'static> {
+    Block::default()
+        .borders(Borders::ALL)
+        .style(Style::default().fg(Color::LightCyan))
+        .title("New Link:     Press 'F2' to enter edit mode and 'F9' to save     ")
+        .border_type(BorderType::Plain)
+}
+
+/// UI for the filter form of a list of records
+pub fn edit_filter_block() -> Block<'static> {
+    Block::default()
+        .borders(Borders::ALL)
+        .style(Style::default().fg(Color::LightCyan))
+        .title("Filter:     Press 'F2' to enter edit mode and 'F9' to apply     ")
+        .border_type(BorderType::Plain)
+}
+
+/// UI for the column mapping of a spreadsheet being imported
+pub fn csv_mapping_block() -> Block<'static> {
+    Block::default()
+        .borders(Borders::ALL)
+        .style(Style::default().fg(Color::LightCyan))
+        .title("Column Mapping:     Press 'F2' to enter edit mode and 'F9' to import     ")
+        .border_type(BorderType::Plain)
+}
+
 /// UI for rendering the `copyright` section
 pub fn copyright() -> Paragraph<'static> {
-    return Paragraph::new("Library DB 2023 - all rights reserved")
+    Paragraph::new("Library DB 2023 - all rights reserved")
         .style(Style::default().fg(Color::Rgb(35, 70, 184)))
         .alignment(Alignment::Center)
         .block(
@@ -86,12 +156,40 @@ pub fn copyright() -> Paragraph<'static> {
                 .style(Style::default().fg(Color::White))
                 .title("Copyright")
                 .border_type(BorderType::Plain),
-        );
+        )
+}
+
+/// UI for an error message shown in place of the `copyright` section or a list
+pub fn error_message(message: String) -> Paragraph<'static> {
+    Paragraph::new(message)
+        .style(Style::default().fg(Color::LightRed))
+        .alignment(Alignment::Center)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .style(Style::default().fg(Color::White))
+                .title("Error")
+                .border_type(BorderType::Plain),
+        )
+}
+
+/// UI for a message about something done, shown in place of the `copyright` section
+pub fn notice_message(message: String) -> Paragraph<'static> {
+    Paragraph::new(message)
+        .style(Style::default().fg(Color::LightGreen))
+        .alignment(Alignment::Center)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .style(Style::default().fg(Color::White))
+                .title("Done")
+                .border_type(BorderType::Plain),
+        )
 }
 
 /// Define terminal sections
 pub fn panes(rect: Rect) -> Rc<[Rect]> {
-    return Layout::default()
+    Layout::default()
         .direction(Direction::Vertical)
         .margin(2)
         .constraints(
@@ -102,7 +200,7 @@ pub fn panes(rect: Rect) -> Rc<[Rect]> {
             ]
             .as_ref(),
         )
-        .split(rect);
+        .split(rect)
 }
 
 /// Define `home` sections
@@ -139,9 +237,48 @@ pub fn add_panes(rect: Rc<[Rect]>) -> Rc<[Rect]> {
     chunks
 }
 
+/// Split the text area of an `add_` section to show publisher suggestions below it
+pub fn suggestion_panes(rect: Rect) -> Rc<[Rect]> {
+    Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(5), Constraint::Length(7)].as_ref())
+        .split(rect)
+}
+
+/// Split the text area of the column mapping to show the preview below it
+pub fn mapping_panes(rect: Rect) -> Rc<[Rect]> {
+    Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(
+            [
+                Constraint::Length(MAPPING_FIELDS.len() as u16 + 2),
+                Constraint::Min(5),
+            ]
+            .as_ref(),
+        )
+        .split(rect)
+}
+
+/// UI for the publishers matching the Publisher line being typed
+pub fn render_suggestions(names: Vec<String>) -> List<'static> {
+    let items: Vec<_> = names
+        .into_iter()
+        .map(|name| ListItem::new(Line::from(Span::raw(name))))
+        .collect();
+    List::new(items).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .style(Style::default().fg(Color::White))
+            .title("Publishers   Press 'Tab' to use the first one")
+            .border_type(BorderType::Plain),
+    )
+}
+
 /// UI for rendering all books in the database
 pub fn render_books(
-    book_list_state: Arc<Mutex<ListState>>,
+    book_list: &ListWindow,
+    link_index: Option<usize>,
+    marked: &HashSet<String>,
 ) -> (List<'static>, Paragraph<'static>, Paragraph<'static>) {
     let header = Paragraph::new(vec![
         Line::from(vec![Span::raw("")]),
@@ -152,7 +289,7 @@ pub fn render_books(
                 .add_modifier(Modifier::BOLD),
         )]),
         Line::from(vec![Span::styled(
-            "Author ",
+            "Authors ",
             Style::default()
                 .fg(Color::LightBlue)
                 .add_modifier(Modifier::BOLD),
@@ -182,7 +319,7 @@ pub fn render_books(
                 .add_modifier(Modifier::BOLD),
         )]),
         Line::from(vec![Span::styled(
-            "Year ",
+            "Date ",
             Style::default()
                 .fg(Color::LightBlue)
                 .add_modifier(Modifier::BOLD),
@@ -205,6 +342,24 @@ pub fn render_books(
                 .fg(Color::LightBlue)
                 .add_modifier(Modifier::BOLD),
         )]),
+        Line::from(vec![Span::styled(
+            "ISBN ",
+            Style::default()
+                .fg(Color::LightBlue)
+                .add_modifier(Modifier::BOLD),
+        )]),
+        Line::from(vec![Span::styled(
+            "Organizations ",
+            Style::default()
+                .fg(Color::LightBlue)
+                .add_modifier(Modifier::BOLD),
+        )]),
+        Line::from(vec![Span::styled(
+            "Related ",
+            Style::default()
+                .fg(Color::LightBlue)
+                .add_modifier(Modifier::BOLD),
+        )]),
     ])
     .alignment(Alignment::Right)
     .block(
@@ -216,17 +371,23 @@ pub fn render_books(
     let books = Block::default()
         .borders(Borders::ALL)
         .style(Style::default().fg(Color::White))
-        .title("Books   Delete selected book with `Ctrl-D`")
+        .title(format!(
+            "Books ({}) {}   {LIST_KEYS}, delete selected book with `Ctrl-D`",
+            book_list.count(),
+            book_list.view.describe()
+        ))
         .border_type(BorderType::Plain);
 
-    let items: Vec<_> = read_sqlite_book_table()
-        .expect("should fetch book list")
+    let books_shown = book_list.books();
+    let items: Vec<_> = books_shown
         .iter()
         .map(|book| {
-            ListItem::new(Line::from(vec![Span::styled(
-                book.title.clone(),
-                Style::default(),
-            )]))
+            record_item(
+                &book.title,
+                &book.authors,
+                &book.year,
+                marked.contains(&book.cite_key),
+            )
         })
         .collect();
 
@@ -237,25 +398,18 @@ pub fn render_books(
             .add_modifier(Modifier::BOLD),
     );
 
-    let book_list = read_sqlite_book_table().expect("should fetch book list");
-    if book_list.is_empty() {
-        let book_detail = Paragraph::default();
-        return (list, header, book_detail);
-    }
-
-    let selected = book_list_state
-        .lock()
-        .expect("should lock state")
-        .selected();
-    let selected_book = book_list
-        .get(selected.unwrap_or(0))
-        .expect("exists")
-        .clone();
+    let Some(selected_book) = book_list
+        .selected_row()
+        .and_then(|selected| books_shown.get(selected))
+        .cloned()
+    else {
+        return (list, header, Paragraph::default());
+    };
 
-    let book_detail = Paragraph::new(vec![
+    let mut lines = vec![
         Line::from(Span::raw(selected_book.book_id)),
+        Line::from(Span::raw(format_authors(&selected_book.authors))),
         Line::from(Span::raw(selected_book.title)),
-        Line::from(Span::raw(selected_book.author)),
         Line::from(Span::raw(selected_book.pages)),
         Line::from(Span::raw(selected_book.volume)),
         Line::from(Span::raw(selected_book.edition)),
@@ -263,13 +417,18 @@ pub fn render_books(
         Line::from(Span::raw(selected_book.series)),
         Line::from(Span::raw(selected_book.publisher)),
         Line::from(Span::raw(selected_book.note)),
-    ])
-    .alignment(Alignment::Left)
-    .block(
+        Line::from(Span::raw(selected_book.isbn)),
+        Line::from(Span::raw(format_organizations(
+            &selected_book.organizations,
+        ))),
+    ];
+    lines.extend(related_lines(book_list.links(), link_index));
+
+    let book_detail = Paragraph::new(lines).alignment(Alignment::Left).block(
         Block::default()
             .borders(Borders::ALL)
             .style(Style::default().fg(Color::White))
-            .title("Book Detail")
+            .title("Book Detail   `Ctrl-L` link, `Tab` pick link, `Enter` open it, `X` unlink")
             .border_type(BorderType::Plain),
     );
 
@@ -278,7 +437,9 @@ pub fn render_books(
 
 /// UI for rendering all articles in the database
 pub fn render_articles(
-    article_list_state: Arc<Mutex<ListState>>,
+    article_list: &ListWindow,
+    link_index: Option<usize>,
+    marked: &HashSet<String>,
 ) -> (List<'static>, Paragraph<'static>, Paragraph<'static>) {
     let header = Paragraph::new(vec![
         Line::from(vec![Span::raw("")]),
@@ -319,7 +480,7 @@ pub fn render_articles(
                 .add_modifier(Modifier::BOLD),
         )]),
         Line::from(vec![Span::styled(
-            "Year ",
+            "Date ",
             Style::default()
                 .fg(Color::LightBlue)
                 .add_modifier(Modifier::BOLD),
@@ -336,6 +497,24 @@ pub fn render_articles(
                 .fg(Color::LightBlue)
                 .add_modifier(Modifier::BOLD),
         )]),
+        Line::from(vec![Span::styled(
+            "Authors ",
+            Style::default()
+                .fg(Color::LightBlue)
+                .add_modifier(Modifier::BOLD),
+        )]),
+        Line::from(vec![Span::styled(
+            "Organizations ",
+            Style::default()
+                .fg(Color::LightBlue)
+                .add_modifier(Modifier::BOLD),
+        )]),
+        Line::from(vec![Span::styled(
+            "Related ",
+            Style::default()
+                .fg(Color::LightBlue)
+                .add_modifier(Modifier::BOLD),
+        )]),
     ])
     .alignment(Alignment::Right)
     .block(
@@ -347,17 +526,23 @@ pub fn render_articles(
     let articles = Block::default()
         .borders(Borders::ALL)
         .style(Style::default().fg(Color::White))
-        .title("Articles   Delete selected book with `Ctrl-D`")
+        .title(format!(
+            "Articles ({}) {}   {LIST_KEYS}, delete selected article with `Ctrl-D`",
+            article_list.count(),
+            article_list.view.describe()
+        ))
         .border_type(BorderType::Plain);
 
-    let items: Vec<_> = read_sqlite_article_table()
-        .expect("should fetch article list")
+    let articles_shown = article_list.articles();
+    let items: Vec<_> = articles_shown
         .iter()
         .map(|article| {
-            ListItem::new(Line::from(vec![Span::styled(
-                article.title.clone(),
-                Style::default(),
-            )]))
+            record_item(
+                &article.title,
+                &article.authors,
+                &article.year,
+                marked.contains(&article.cite_key),
+            )
         })
         .collect();
 
@@ -368,22 +553,15 @@ pub fn render_articles(
             .add_modifier(Modifier::BOLD),
     );
 
-    let article_list = read_sqlite_article_table().expect("should fetch article list");
-    if article_list.is_empty() {
-        let article_detail = Paragraph::default();
-        return (list, header, article_detail);
-    }
-
-    let selected = article_list_state
-        .lock()
-        .expect("should lock article state")
-        .selected();
-    let selected_article = article_list
-        .get(selected.unwrap_or(0))
-        .expect("exists")
-        .clone();
+    let Some(selected_article) = article_list
+        .selected_row()
+        .and_then(|selected| articles_shown.get(selected))
+        .cloned()
+    else {
+        return (list, header, Paragraph::default());
+    };
 
-    let article_detail = Paragraph::new(vec![
+    let mut lines = vec![
         Line::from(Span::raw(selected_article.article_id)),
         Line::from(Span::raw(selected_article.title)),
         Line::from(Span::raw(selected_article.journal)),
@@ -393,105 +571,604 @@ pub fn render_articles(
         Line::from(Span::raw(selected_article.year)),
         Line::from(Span::raw(selected_article.edition)),
         Line::from(Span::raw(selected_article.publisher)),
-    ])
-    .alignment(Alignment::Left)
-    .block(
+        Line::from(Span::raw(format_authors(&selected_article.authors))),
+        Line::from(Span::raw(format_organizations(
+            &selected_article.organizations,
+        ))),
+    ];
+    lines.extend(related_lines(article_list.links(), link_index));
+
+    let article_detail = Paragraph::new(lines).alignment(Alignment::Left).block(
         Block::default()
             .borders(Borders::ALL)
             .style(Style::default().fg(Color::White))
-            .title("Article Detail")
+            .title("Article Detail   `Ctrl-L` link, `Tab` pick link, `Enter` open it, `X` unlink")
             .border_type(BorderType::Plain),
     );
 
     (list, header, article_detail)
 }
 
-/// UI for rendering the `home` section
-pub fn render_home() -> (Paragraph<'static>, Paragraph<'static>) {
-    let left = Paragraph::new(vec![
-        Line::from(vec![Span::raw("")]),
-        Line::from(vec![Span::raw("Welcome to")]),
-        Line::from(vec![Span::raw("")]),
-        Line::from(vec![Span::styled(
-            "Library DB",
-            Style::default().fg(Color::LightBlue),
-        )]),
-        Line::from(vec![Span::raw("")]),
-        Line::from(vec![Span::styled(
-            "To return to this Home page press 'H'",
-            Style::default().fg(Color::Cyan),
-        )]),
-        Line::from(vec![Span::raw("")]),
-        Line::from(vec![Span::styled(
-            "Press 'S' to Show a list of books",
-            Style::default().fg(Color::Cyan),
-        )]),
-        Line::from(vec![Span::styled(
-            "Press 'B' to add a new Book",
-            Style::default().fg(Color::Cyan),
-        )]),
-        Line::from(vec![Span::styled(
-            "Press 'L' to show a List of articles",
-            Style::default().fg(Color::Cyan),
-        )]),
-        Line::from(vec![Span::styled(
-            "Press 'A' to add a new Article",
-            Style::default().fg(Color::Cyan),
-        )]),
-        Line::from(vec![Span::styled(
-            "Press 'Q' to Quit",
-            Style::default().fg(Color::Cyan),
-        )]),
+/// Keys that sort and filter a list of records, for the title of the list
+const LIST_KEYS: &str = "`R` sort, `V` reverse, `F` filter, `M` mark, `Ctrl-E` export";
+
+/// List line of a record: its title, first author and date
+fn record_item(title: &str, authors: &[Author], year: &str, marked: bool) -> ListItem<'static> {
+    let first_author = authors
+        .first()
+        .map(|author| author.name())
+        .unwrap_or_default();
+    ListItem::new(Line::from(vec![
+        mark(marked),
+        Span::styled(title.to_string(), Style::default()),
+        Span::styled(
+            format!("  {first_author}  {year}"),
+            Style::default().fg(Color::Gray),
+        ),
+    ]))
+}
+
+/// `*` before a record marked for export
+fn mark(marked: bool) -> Span<'static> {
+    match marked {
+        true => Span::styled("* ", Style::default().fg(Color::LightYellow)),
+        false => Span::raw(""),
+    }
+}
+
+/// One list line per record: type, title, first author and year
+fn catalog_entry_items(
+    entries: &[CatalogEntry],
+    marked: &HashSet<String>,
+) -> Vec<ListItem<'static>> {
+    entries
+        .iter()
+        .map(|entry| {
+            let year = entry.year.map(|year| year.to_string()).unwrap_or_default();
+            ListItem::new(Line::from(vec![
+                mark(marked.contains(&entry.cite_key)),
+                Span::styled(
+                    format!("{:<16} ", entry.entry_type.name()),
+                    Style::default().fg(Color::LightBlue),
+                ),
+                Span::styled(entry.title.clone(), Style::default()),
+                Span::styled(
+                    format!("  {}  {year}", entry.first_author),
+                    Style::default().fg(Color::Gray),
+                ),
+            ]))
+        })
+        .collect()
+}
+
+/// Define `search` sections: the prompt above its results
+pub fn search_panes(rect: Rc<[Rect]>) -> Rc<[Rect]> {
+    Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(3), Constraint::Min(2)].as_ref())
+        .split(rect[1])
+}
+
+/// UI for the search prompt, in keyword or advanced mode
+pub fn search_block(advanced: bool) -> Block<'static> {
+    let title = match advanced {
+        false => "Search titles, authors, journals, series, publishers and notes:     'Tab' for advanced search, 'Enter' to pick a result, 'F2' to edit the search",
+        true => "Advanced search, e.g. au:knuth AND ti:\"art of\" AND yr:1968..1975 NOT type:article     'Tab' for keyword search, 'Enter' to pick a result",
+    };
+    Block::default()
+        .borders(Borders::ALL)
+        .style(Style::default().fg(Color::LightCyan))
+        .title(title)
+        .border_type(BorderType::Plain)
+}
+
+/// UI for the records matching the search typed: best match first for keywords, by title for
+/// an advanced search. A query that cannot be parsed is shown with a `^` under the problem.
+pub fn render_search_results(
+    found: Result<&[CatalogEntry], &str>,
+    text: &str,
+    advanced: bool,
+) -> Result<List<'static>, Box<Paragraph<'static>>> {
+    let found = match (found, advanced.then(|| parse_query(text))) {
+        (Ok(found), _) => found,
+        (Err(_), Some(Err(err))) => {
+            let lines: Vec<Line> = err
+                .pointer(text)
+                .lines()
+                .map(|line| Line::from(Span::raw(line.to_string())))
+                .collect();
+            return Err(Box::new(
+                Paragraph::new(lines)
+                    .style(Style::default().fg(Color::LightRed))
+                    .block(
+                        Block::default()
+                            .borders(Borders::ALL)
+                            .style(Style::default().fg(Color::White))
+                            .title(format!("Cannot search, {err}"))
+                            .border_type(BorderType::Plain),
+                    ),
+            ));
+        }
+        (Err(err), _) => return Err(Box::new(error_message(err.to_string()))),
+    };
+    let title = match (text.trim().is_empty(), found.len()) {
+        (true, _) => "Results".to_string(),
+        (false, 0) => "Results   nothing found".to_string(),
+        (false, amount) => format!("Results   {amount} found, `Enter` open, `Ctrl-U` update"),
+    };
+    Ok(List::new(catalog_entry_items(found, &HashSet::new()))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .style(Style::default().fg(Color::White))
+                .title(title)
+                .border_type(BorderType::Plain),
+        )
+        .highlight_style(
+            Style::default()
+                .bg(Color::LightBlue)
+                .fg(Color::Black)
+                .add_modifier(Modifier::BOLD),
+        ))
+}
+
+/// UI for rendering every record in the catalog, whatever its type
+pub fn render_all_items(
+    entry_list: &ListWindow,
+    marked: &HashSet<String>,
+) -> (List<'static>, Paragraph<'static>, Paragraph<'static>) {
+    let header = Paragraph::new(
+        ["", "Type ", "Title ", "First author ", "Year ", "ID "]
+            .into_iter()
+            .map(|label| {
+                Line::from(vec![Span::styled(
+                    label,
+                    Style::default()
+                        .fg(Color::LightBlue)
+                        .add_modifier(Modifier::BOLD),
+                )])
+            })
+            .collect::<Vec<_>>(),
+    )
+    .alignment(Alignment::Right)
+    .block(
+        Block::default()
+            .style(Style::default().fg(Color::White))
+            .border_type(BorderType::Plain),
+    );
+
+    let items = Block::default()
+        .borders(Borders::ALL)
+        .style(Style::default().fg(Color::White))
+        .title(format!(
+            "All Items ({}) {}   `Enter` open, `Ctrl-U` update, {LIST_KEYS}",
+            entry_list.count(),
+            entry_list.view.describe()
+        ))
+        .border_type(BorderType::Plain);
+
+    let entries_shown = entry_list.entries();
+    let list = List::new(catalog_entry_items(entries_shown, marked))
+        .block(items)
+        .highlight_style(
+            Style::default()
+                .bg(Color::LightBlue)
+                .fg(Color::Black)
+                .add_modifier(Modifier::BOLD),
+        );
+
+    let Some(selected_entry) = entry_list
+        .selected_row()
+        .and_then(|selected| entries_shown.get(selected))
+        .cloned()
+    else {
+        return (list, header, Paragraph::default());
+    };
+
+    let entry_detail = Paragraph::new(vec![
+        Line::from(Span::raw(selected_entry.entry_type.name())),
+        Line::from(Span::raw(selected_entry.title)),
+        Line::from(Span::raw(selected_entry.first_author)),
+        Line::from(Span::raw(
+            selected_entry
+                .year
+                .map(|year| year.to_string())
+                .unwrap_or_default(),
+        )),
+        Line::from(Span::raw(selected_entry.cite_key)),
     ])
     .alignment(Alignment::Left)
     .block(
         Block::default()
             .borders(Borders::ALL)
             .style(Style::default().fg(Color::White))
-            .title("Home")
+            .title("Detail")
             .border_type(BorderType::Plain),
     );
 
-    let right = Paragraph::new(vec![
-        Line::from(vec![Span::raw("")]),
-        Line::from(vec![Span::raw("Editing Tips")]),
-        Line::from(vec![Span::raw("")]),
-        Line::from(vec![Span::raw("'F2' to begin editing")]),
-        Line::from(vec![Span::raw("'F9' to save to database")]),
-        Line::from(vec![Span::raw("'F12' to exit editing without saving")]),
-        Line::from(vec![Span::raw("")]),
-        Line::from(vec![Span::raw("'Ctrl-D' to Delete current item in list")]),
-        Line::from(vec![Span::raw("'Ctrl-U' to Update current item in list")]),
-    ])
-    .alignment(Alignment::Left)
+    (list, header, entry_detail)
+}
+
+/// UI for rendering all items of `item_type` in the database
+pub fn render_items(
+    item_list: &ListWindow,
+    item_type: &'static ItemType,
+    link_index: Option<usize>,
+    marked: &HashSet<String>,
+) -> (List<'static>, Paragraph<'static>, Paragraph<'static>) {
+    let labels = item_type.form_labels();
+    let header = Paragraph::new(
+        ["", "ID"]
+            .into_iter()
+            .chain(labels)
+            .chain(["Related"])
+            .map(|label| {
+                let label = match label {
+                    "" => String::new(),
+                    label => format!("{label} "),
+                };
+                Line::from(vec![Span::styled(
+                    label,
+                    Style::default()
+                        .fg(Color::LightBlue)
+                        .add_modifier(Modifier::BOLD),
+                )])
+            })
+            .collect::<Vec<_>>(),
+    )
+    .alignment(Alignment::Right)
     .block(
+        Block::default()
+            .style(Style::default().fg(Color::White))
+            .border_type(BorderType::Plain),
+    );
+
+    let items = Block::default()
+        .borders(Borders::ALL)
+        .style(Style::default().fg(Color::White))
+        .title(format!(
+            "< {} > ({}) {}   `Left`/`Right` change type, `Ctrl-N` add, `Ctrl-U` update, `Ctrl-D` delete, {LIST_KEYS}",
+            item_type.entry_type.name(),
+            item_list.count(),
+            item_list.view.describe()
+        ))
+        .border_type(BorderType::Plain);
+
+    let items_shown = item_list.items();
+    let list_items: Vec<_> = items_shown
+        .iter()
+        .map(|item| {
+            record_item(
+                &item.title,
+                &item.authors,
+                &item.year,
+                marked.contains(&item.cite_key),
+            )
+        })
+        .collect();
+
+    let list = List::new(list_items).block(items).highlight_style(
+        Style::default()
+            .bg(Color::LightBlue)
+            .fg(Color::Black)
+            .add_modifier(Modifier::BOLD),
+    );
+
+    let Some(selected_item) = item_list
+        .selected_row()
+        .and_then(|selected| items_shown.get(selected))
+        .cloned()
+    else {
+        return (list, header, Paragraph::default());
+    };
+
+    let mut lines = vec![Line::from(Span::raw(selected_item.cite_key.clone()))];
+    lines.extend(
+        selected_item
+            .form_lines()
+            .into_iter()
+            .map(|line| Line::from(Span::raw(line))),
+    );
+    lines.extend(related_lines(item_list.links(), link_index));
+
+    let item_detail = Paragraph::new(lines).alignment(Alignment::Left).block(
         Block::default()
             .borders(Borders::ALL)
             .style(Style::default().fg(Color::White))
-            .title("Editing Hot Keys")
+            .title(format!(
+                "{} Detail   `Ctrl-L` link, `Tab` pick link, `Enter` open it, `X` unlink",
+                item_type.entry_type.name()
+            ))
             .border_type(BorderType::Plain),
     );
-    (left, right)
+
+    (list, header, item_detail)
 }
 
-/// UI for adding a new `article`
-pub fn render_add_article() -> Paragraph<'static> {
-    return Paragraph::new(vec![
-        Line::from(vec![Span::raw("")]),
-        Line::from(vec![Span::styled(
-            "Title: ",
-            Style::default().fg(Color::LightRed),
-        )]),
-        Line::from(vec![Span::styled(
-            "Journal: ",
-            Style::default().fg(Color::LightRed),
-        )]),
-        Line::from(vec![Span::styled(
-            "Volume: ",
-            Style::default().fg(Color::LightRed),
-        )]),
-        Line::from(vec![Span::styled(
-            "Pages: ",
+/// One line per related record, the link picked with `Tab` highlighted
+fn related_lines(links: &[Link], link_index: Option<usize>) -> Vec<Line<'static>> {
+    links
+        .iter()
+        .enumerate()
+        .map(|(index, link)| {
+            let style = match link_index {
+                Some(picked) if picked == index => Style::default()
+                    .bg(Color::LightBlue)
+                    .fg(Color::Black)
+                    .add_modifier(Modifier::BOLD),
+                _ => Style::default(),
+            };
+            Line::from(Span::styled(link.describe(), style))
+        })
+        .collect()
+}
+
+/// UI for rendering all publishers in the database
+pub fn render_publishers(
+    publisher_list_state: Arc<Mutex<ListState>>,
+    publisher_list: &[(Publisher, i64)],
+) -> (List<'static>, Paragraph<'static>, Paragraph<'static>) {
+    let header = Paragraph::new(
+        ["", "ID ", "Name ", "Address ", "Place ", "Records "]
+            .into_iter()
+            .map(|label| {
+                Line::from(vec![Span::styled(
+                    label,
+                    Style::default()
+                        .fg(Color::LightBlue)
+                        .add_modifier(Modifier::BOLD),
+                )])
+            })
+            .collect::<Vec<_>>(),
+    )
+    .alignment(Alignment::Right)
+    .block(
+        Block::default()
+            .style(Style::default().fg(Color::White))
+            .border_type(BorderType::Plain),
+    );
+
+    let publishers = Block::default()
+        .borders(Borders::ALL)
+        .style(Style::default().fg(Color::White))
+        .title("Publishers   Update selected publisher with `Ctrl-U`")
+        .border_type(BorderType::Plain);
+
+    let items: Vec<_> = publisher_list
+        .iter()
+        .map(|(publisher, _)| {
+            ListItem::new(Line::from(vec![Span::styled(
+                publisher.publisher.clone(),
+                Style::default(),
+            )]))
+        })
+        .collect();
+
+    let list = List::new(items).block(publishers).highlight_style(
+        Style::default()
+            .bg(Color::LightBlue)
+            .fg(Color::Black)
+            .add_modifier(Modifier::BOLD),
+    );
+
+    if publisher_list.is_empty() {
+        return (list, header, Paragraph::default());
+    }
+
+    let selected = publisher_list_state
+        .lock()
+        .expect("should lock publisher state")
+        .selected();
+    let (publisher, records) = publisher_list
+        .get(selected.unwrap_or(0))
+        .unwrap_or(&publisher_list[0])
+        .clone();
+
+    let publisher_detail = Paragraph::new(vec![
+        Line::from(Span::raw(publisher.publisher_id)),
+        Line::from(Span::raw(publisher.publisher)),
+        Line::from(Span::raw(publisher.address)),
+        Line::from(Span::raw(publisher.place)),
+        Line::from(Span::raw(records.to_string())),
+    ])
+    .alignment(Alignment::Left)
+    .block(
+        Block::default()
+            .borders(Borders::ALL)
+            .style(Style::default().fg(Color::White))
+            .title("Publisher Detail")
+            .border_type(BorderType::Plain),
+    );
+
+    (list, header, publisher_detail)
+}
+
+/// UI for rendering all organizations in the database
+pub fn render_organizations(
+    organization_list_state: Arc<Mutex<ListState>>,
+    organization_list: &[(Organization, String, i64)],
+) -> (List<'static>, Paragraph<'static>, Paragraph<'static>) {
+    let header = Paragraph::new(
+        ["", "ID ", "Name ", "Address ", "Parent body ", "Records "]
+            .into_iter()
+            .map(|label| {
+                Line::from(vec![Span::styled(
+                    label,
+                    Style::default()
+                        .fg(Color::LightBlue)
+                        .add_modifier(Modifier::BOLD),
+                )])
+            })
+            .collect::<Vec<_>>(),
+    )
+    .alignment(Alignment::Right)
+    .block(
+        Block::default()
+            .style(Style::default().fg(Color::White))
+            .border_type(BorderType::Plain),
+    );
+
+    let organizations = Block::default()
+        .borders(Borders::ALL)
+        .style(Style::default().fg(Color::White))
+        .title("Organizations   `Ctrl-N` new, `Ctrl-U` update, `Ctrl-D` delete")
+        .border_type(BorderType::Plain);
+
+    let items: Vec<_> = organization_list
+        .iter()
+        .map(|(organization, _, _)| {
+            ListItem::new(Line::from(vec![Span::styled(
+                organization.organization.clone(),
+                Style::default(),
+            )]))
+        })
+        .collect();
+
+    let list = List::new(items).block(organizations).highlight_style(
+        Style::default()
+            .bg(Color::LightBlue)
+            .fg(Color::Black)
+            .add_modifier(Modifier::BOLD),
+    );
+
+    if organization_list.is_empty() {
+        return (list, header, Paragraph::default());
+    }
+
+    let selected = organization_list_state
+        .lock()
+        .expect("should lock organization state")
+        .selected();
+    let (organization, parent, records) = organization_list
+        .get(selected.unwrap_or(0))
+        .unwrap_or(&organization_list[0])
+        .clone();
+
+    let organization_detail = Paragraph::new(vec![
+        Line::from(Span::raw(organization.organization_id)),
+        Line::from(Span::raw(organization.organization)),
+        Line::from(Span::raw(organization.address)),
+        Line::from(Span::raw(parent)),
+        Line::from(Span::raw(records.to_string())),
+    ])
+    .alignment(Alignment::Left)
+    .block(
+        Block::default()
+            .borders(Borders::ALL)
+            .style(Style::default().fg(Color::White))
+            .title("Organization Detail")
+            .border_type(BorderType::Plain),
+    );
+
+    (list, header, organization_detail)
+}
+
+/// UI for rendering the `home` section
+pub fn render_home() -> (Paragraph<'static>, Paragraph<'static>) {
+    let left = Paragraph::new(vec![
+        Line::from(vec![Span::raw("")]),
+        Line::from(vec![Span::raw("Welcome to")]),
+        Line::from(vec![Span::raw("")]),
+        Line::from(vec![Span::styled(
+            "Library DB",
+            Style::default().fg(Color::LightBlue),
+        )]),
+        Line::from(vec![Span::raw("")]),
+        Line::from(vec![Span::styled(
+            "To return to this Home page press 'H'",
+            Style::default().fg(Color::Cyan),
+        )]),
+        Line::from(vec![Span::raw("")]),
+        Line::from(vec![Span::styled(
+            "Press 'S' to Show a list of books",
+            Style::default().fg(Color::Cyan),
+        )]),
+        Line::from(vec![Span::styled(
+            "Press 'B' to add a new Book",
+            Style::default().fg(Color::Cyan),
+        )]),
+        Line::from(vec![Span::styled(
+            "Press 'L' to show a List of articles",
+            Style::default().fg(Color::Cyan),
+        )]),
+        Line::from(vec![Span::styled(
+            "Press 'A' to add a new Article",
+            Style::default().fg(Color::Cyan),
+        )]),
+        Line::from(vec![Span::styled(
+            "Press 'C' to browse the whole Catalog",
+            Style::default().fg(Color::Cyan),
+        )]),
+        Line::from(vec![Span::styled(
+            "Press '/' to search the catalog",
+            Style::default().fg(Color::Cyan),
+        )]),
+        Line::from(vec![Span::styled(
+            "Press 'I' to list other Item types: theses, reports, maps, ...",
+            Style::default().fg(Color::Cyan),
+        )]),
+        Line::from(vec![Span::styled(
+            "Press 'P' to list Publishers",
+            Style::default().fg(Color::Cyan),
+        )]),
+        Line::from(vec![Span::styled(
+            "Press 'O' to list Organizations",
+            Style::default().fg(Color::Cyan),
+        )]),
+        Line::from(vec![Span::styled(
+            "Press 'Q' to Quit",
+            Style::default().fg(Color::Cyan),
+        )]),
+    ])
+    .alignment(Alignment::Left)
+    .block(
+        Block::default()
+            .borders(Borders::ALL)
+            .style(Style::default().fg(Color::White))
+            .title("Home")
+            .border_type(BorderType::Plain),
+    );
+
+    let right = Paragraph::new(vec![
+        Line::from(vec![Span::raw("")]),
+        Line::from(vec![Span::raw("Editing Tips")]),
+        Line::from(vec![Span::raw("")]),
+        Line::from(vec![Span::raw("'F2' to begin editing")]),
+        Line::from(vec![Span::raw("'F9' to save to database")]),
+        Line::from(vec![Span::raw("'F12' to exit editing without saving")]),
+        Line::from(vec![Span::raw("")]),
+        Line::from(vec![Span::raw("'Ctrl-D' to Delete current item in list")]),
+        Line::from(vec![Span::raw("'Ctrl-U' to Update current item in list")]),
+    ])
+    .alignment(Alignment::Left)
+    .block(
+        Block::default()
+            .borders(Borders::ALL)
+            .style(Style::default().fg(Color::White))
+            .title("Editing Hot Keys")
+            .border_type(BorderType::Plain),
+    );
+    (left, right)
+}
+
+/// UI for adding a new `article`
+pub fn render_add_article() -> Paragraph<'static> {
+    Paragraph::new(vec![
+        Line::from(vec![Span::raw("")]),
+        Line::from(vec![Span::styled(
+            "Title: ",
+            Style::default().fg(Color::LightRed),
+        )]),
+        Line::from(vec![Span::styled(
+            "Journal: ",
+            Style::default().fg(Color::LightRed),
+        )]),
+        Line::from(vec![Span::styled(
+            "Volume: ",
+            Style::default().fg(Color::LightBlue),
+        )]),
+        Line::from(vec![Span::styled(
+            "Pages: ",
             Style::default().fg(Color::LightBlue),
         )]),
         Line::from(vec![Span::styled(
@@ -499,8 +1176,8 @@ pub fn render_add_article() -> Paragraph<'static> {
             Style::default().fg(Color::LightBlue),
         )]),
         Line::from(vec![Span::styled(
-            "Year: ",
-            Style::default().fg(Color::LightRed),
+            "Date: ",
+            Style::default().fg(Color::LightBlue),
         )]),
         Line::from(vec![Span::styled(
             "Edition: ",
@@ -508,9 +1185,33 @@ pub fn render_add_article() -> Paragraph<'static> {
         )]),
         Line::from(vec![Span::styled(
             "Publisher: ",
-            Style::default().fg(Color::LightRed),
+            Style::default().fg(Color::LightBlue),
+        )]),
+        Line::from(vec![Span::styled(
+            "Authors: ",
+            Style::default().fg(Color::LightBlue),
+        )]),
+        Line::from(vec![Span::styled(
+            "Organizations: ",
+            Style::default().fg(Color::LightBlue),
         )]),
         Line::from(vec![Span::styled("", Style::default())]),
+        Line::from(vec![Span::styled(
+            "Authors: Family, Given; ... ",
+            Style::default().fg(Color::Cyan),
+        )]),
+        Line::from(vec![Span::styled(
+            "add (editor), (translator) or (illustrator) ",
+            Style::default().fg(Color::Cyan),
+        )]),
+        Line::from(vec![Span::styled(
+            "Organizations: Name; ... add (sponsor) ",
+            Style::default().fg(Color::Cyan),
+        )]),
+        Line::from(vec![Span::styled(
+            "or (degree-granting) ",
+            Style::default().fg(Color::Cyan),
+        )]),
         Line::from(vec![Span::styled(
             "Required input is red ",
             Style::default().fg(Color::LightRed),
@@ -533,16 +1234,16 @@ pub fn render_add_article() -> Paragraph<'static> {
             Style::default().fg(Color::Cyan),
         )]),
     ])
-    .alignment(Alignment::Right);
+    .alignment(Alignment::Right)
 }
 
 /// UI for adding a new `book`
 pub fn render_add_book() -> Paragraph<'static> {
-    return Paragraph::new(vec![
+    Paragraph::new(vec![
         Line::from(vec![Span::raw("")]),
         Line::from(vec![Span::styled(
-            "Author: ",
-            Style::default().fg(Color::LightRed),
+            "Authors: ",
+            Style::default().fg(Color::LightBlue),
         )]),
         Line::from(vec![Span::styled(
             "Title: ",
@@ -550,7 +1251,7 @@ pub fn render_add_book() -> Paragraph<'static> {
         )]),
         Line::from(vec![Span::styled(
             "Pages: ",
-            Style::default().fg(Color::LightRed),
+            Style::default().fg(Color::LightBlue),
         )]),
         Line::from(vec![Span::styled(
             "Volume: ",
@@ -561,8 +1262,8 @@ pub fn render_add_book() -> Paragraph<'static> {
             Style::default().fg(Color::LightBlue),
         )]),
         Line::from(vec![Span::styled(
-            "Year: ",
-            Style::default().fg(Color::LightRed),
+            "Date: ",
+            Style::default().fg(Color::LightBlue),
         )]),
         Line::from(vec![Span::styled(
             "Series: ",
@@ -570,13 +1271,37 @@ pub fn render_add_book() -> Paragraph<'static> {
         )]),
         Line::from(vec![Span::styled(
             "Publisher: ",
-            Style::default().fg(Color::LightRed),
+            Style::default().fg(Color::LightBlue),
         )]),
         Line::from(vec![Span::styled(
             "Note: ",
             Style::default().fg(Color::LightBlue),
         )]),
+        Line::from(vec![Span::styled(
+            "ISBN: ",
+            Style::default().fg(Color::LightBlue),
+        )]),
+        Line::from(vec![Span::styled(
+            "Organizations: ",
+            Style::default().fg(Color::LightBlue),
+        )]),
         Line::from(vec![Span::raw("")]),
+        Line::from(vec![Span::styled(
+            "Authors: Family, Given; ... ",
+            Style::default().fg(Color::Cyan),
+        )]),
+        Line::from(vec![Span::styled(
+            "add (editor), (translator) or (illustrator) ",
+            Style::default().fg(Color::Cyan),
+        )]),
+        Line::from(vec![Span::styled(
+            "Organizations: Name; ... add (sponsor) ",
+            Style::default().fg(Color::Cyan),
+        )]),
+        Line::from(vec![Span::styled(
+            "or (degree-granting) ",
+            Style::default().fg(Color::Cyan),
+        )]),
         Line::from(vec![Span::styled(
             "Required input is red ",
             Style::default().fg(Color::LightRed),
@@ -599,5 +1324,286 @@ pub fn render_add_book() -> Paragraph<'static> {
             Style::default().fg(Color::Cyan),
         )]),
     ])
-    .alignment(Alignment::Right);
+    .alignment(Alignment::Right)
+}
+
+/// UI for editing a `publisher`
+pub fn render_edit_publisher() -> Paragraph<'static> {
+    Paragraph::new(vec![
+        Line::from(vec![Span::raw("")]),
+        Line::from(vec![Span::styled(
+            "Name: ",
+            Style::default().fg(Color::LightRed),
+        )]),
+        Line::from(vec![Span::styled(
+            "Address: ",
+            Style::default().fg(Color::LightBlue),
+        )]),
+        Line::from(vec![Span::styled(
+            "Place: ",
+            Style::default().fg(Color::LightBlue),
+        )]),
+        Line::from(vec![Span::raw("")]),
+        Line::from(vec![Span::styled(
+            "Press 'F2' to start editing ",
+            Style::default().fg(Color::Cyan),
+        )]),
+        Line::from(vec![Span::styled(
+            "Press 'F12' to stop editing ",
+            Style::default().fg(Color::Cyan),
+        )]),
+        Line::from(vec![Span::styled(
+            "Press 'F9' to save to database ",
+            Style::default().fg(Color::Cyan),
+        )]),
+    ])
+    .alignment(Alignment::Right)
+}
+
+/// UI for adding an item of `item_type`; required fields are red
+pub fn render_add_item(item_type: &ItemType) -> Paragraph<'static> {
+    let required = |index: usize| {
+        index == 1
+            || index
+                .checked_sub(ITEM_COMMON_FIELDS.len())
+                .is_some_and(|own| item_type.fields[own].required)
+    };
+    let mut lines = vec![Line::from(vec![Span::raw("")])];
+    lines.extend(
+        item_type
+            .form_labels()
+            .into_iter()
+            .enumerate()
+            .map(|(index, label)| {
+                let color = match required(index) {
+                    true => Color::LightRed,
+                    false => Color::LightBlue,
+                };
+                Line::from(vec![Span::styled(
+                    format!("{label}: "),
+                    Style::default().fg(color),
+                )])
+            }),
+    );
+    lines.extend([
+        Line::from(vec![Span::raw("")]),
+        Line::from(vec![Span::styled(
+            "Authors: Family, Given; ... ",
+            Style::default().fg(Color::Cyan),
+        )]),
+        Line::from(vec![Span::styled(
+            "Organizations: Name; ... add (sponsor) ",
+            Style::default().fg(Color::Cyan),
+        )]),
+        Line::from(vec![Span::styled(
+            "Required input is red ",
+            Style::default().fg(Color::LightRed),
+        )]),
+        Line::from(vec![Span::styled(
+            "Optional input is blue ",
+            Style::default().fg(Color::LightBlue),
+        )]),
+        Line::from(vec![Span::raw("")]),
+        Line::from(vec![Span::styled(
+            "Press 'F2' to start editing ",
+            Style::default().fg(Color::Cyan),
+        )]),
+        Line::from(vec![Span::styled(
+            "Press 'F12' to stop editing ",
+            Style::default().fg(Color::Cyan),
+        )]),
+        Line::from(vec![Span::styled(
+            "Press 'F9' to save to database ",
+            Style::default().fg(Color::Cyan),
+        )]),
+    ]);
+    Paragraph::new(lines).alignment(Alignment::Right)
+}
+
+/// UI for adding or editing an `organization`
+pub fn render_edit_organization() -> Paragraph<'static> {
+    Paragraph::new(vec![
+        Line::from(vec![Span::raw("")]),
+        Line::from(vec![Span::styled(
+            "Name: ",
+            Style::default().fg(Color::LightRed),
+        )]),
+        Line::from(vec![Span::styled(
+            "Address: ",
+            Style::default().fg(Color::LightBlue),
+        )]),
+        Line::from(vec![Span::styled(
+            "Parent body: ",
+            Style::default().fg(Color::LightBlue),
+        )]),
+        Line::from(vec![Span::raw("")]),
+        Line::from(vec![Span::styled(
+            "Parent body is the name of the organization ",
+            Style::default().fg(Color::Cyan),
+        )]),
+        Line::from(vec![Span::styled(
+            "this one is part of ",
+            Style::default().fg(Color::Cyan),
+        )]),
+        Line::from(vec![Span::raw("")]),
+        Line::from(vec![Span::styled(
+            "Press 'F2' to start editing ",
+            Style::default().fg(Color::Cyan),
+        )]),
+        Line::from(vec![Span::styled(
+            "Press 'F12' to stop editing ",
+            Style::default().fg(Color::Cyan),
+        )]),
+        Line::from(vec![Span::styled(
+            "Press 'F9' to save to database ",
+            Style::default().fg(Color::Cyan),
+        )]),
+    ])
+    .alignment(Alignment::Right)
+}
+
+/// UI for linking the selected record to another one
+pub fn render_edit_link() -> Paragraph<'static> {
+    Paragraph::new(vec![
+        Line::from(vec![Span::raw("")]),
+        Line::from(vec![Span::styled(
+            "Relation: ",
+            Style::default().fg(Color::LightRed),
+        )]),
+        Line::from(vec![Span::styled(
+            "Related record: ",
+            Style::default().fg(Color::LightRed),
+        )]),
+        Line::from(vec![Span::raw("")]),
+        Line::from(vec![Span::styled(
+            "Relation is one of part-of, has-part, edition-of, ",
+            Style::default().fg(Color::Cyan),
+        )]),
+        Line::from(vec![Span::styled(
+            "translation-of, reviews, continues ",
+            Style::default().fg(Color::Cyan),
+        )]),
+        Line::from(vec![Span::styled(
+            "Name the related record by its title or cite key ",
+            Style::default().fg(Color::Cyan),
+        )]),
+        Line::from(vec![Span::raw("")]),
+        Line::from(vec![Span::styled(
+            "Press 'F2' to start editing ",
+            Style::default().fg(Color::Cyan),
+        )]),
+        Line::from(vec![Span::styled(
+            "Press 'F12' to stop editing ",
+            Style::default().fg(Color::Cyan),
+        )]),
+        Line::from(vec![Span::styled(
+            "Press 'F9' to save to database ",
+            Style::default().fg(Color::Cyan),
+        )]),
+    ])
+    .alignment(Alignment::Right)
+}
+
+/// UI for rendering the labels of the filter form
+pub fn render_edit_filter() -> Paragraph<'static> {
+    let mut lines = vec![Line::from(vec![Span::raw("")])];
+    lines.extend(FILTER_FIELDS.iter().map(|label| {
+        Line::from(vec![Span::styled(
+            format!("{label}: "),
+            Style::default().fg(Color::LightRed),
+        )])
+    }));
+    lines.extend(
+        [
+            "",
+            "Years is one year or a range: 1968..1975, 1968.. or ..1975 ",
+            "Publisher matches any part of the name ",
+            "Type is an item type, e.g. thesis or book ",
+            "Leave a line empty to show every record ",
+            "",
+            "Press 'F2' to start editing ",
+            "Press 'F12' to stop editing ",
+            "Press 'F9' to apply the filter ",
+        ]
+        .into_iter()
+        .map(|text| Line::from(vec![Span::styled(text, Style::default().fg(Color::Cyan))])),
+    );
+    Paragraph::new(lines).alignment(Alignment::Right)
+}
+
+/// UI for rendering the labels of the column mapping form
+pub fn render_csv_mapping() -> Paragraph<'static> {
+    let mut lines = vec![Line::from(vec![Span::raw("")])];
+    lines.extend(MAPPING_FIELDS.iter().map(|label| {
+        Line::from(vec![Span::styled(
+            format!("{label}: "),
+            Style::default().fg(Color::LightRed),
+        )])
+    }));
+    lines.extend(
+        [
+            "",
+            "Name a column by its header or number ",
+            "Record type is book, article or a column ",
+            "On failure is skip or rollback ",
+            "",
+            "Press 'F2' to start editing ",
+            "Press 'F12' to stop editing ",
+            "Press 'Ctrl-D' to check every row ",
+            "Press 'F9' to import ",
+        ]
+        .into_iter()
+        .map(|text| Line::from(vec![Span::styled(text, Style::default().fg(Color::Cyan))])),
+    );
+    Paragraph::new(lines).alignment(Alignment::Right)
+}
+
+/// UI for the columns of the spreadsheet at `path` and its first rows as the mapping being typed
+/// reads them, or the report of the last import or check
+pub fn render_csv_preview(
+    path: &str,
+    table: &Table,
+    mapping_lines: &[String],
+    report: Option<&str>,
+) -> Paragraph<'static> {
+    let columns: Vec<String> = table
+        .header
+        .iter()
+        .enumerate()
+        .map(|(index, name)| format!("{} {name}", index + 1))
+        .collect();
+    let mut lines = vec![
+        Line::from(vec![Span::styled(
+            format!("{path}: {} rows", table.rows.len()),
+            Style::default().fg(Color::LightBlue),
+        )]),
+        Line::from(vec![Span::raw(format!("Columns: {}", columns.join(" | ")))]),
+        Line::from(vec![Span::raw("")]),
+    ];
+    let text = match report {
+        // the summary first, as a long list of skipped rows does not fit
+        Some(report) => match report.rsplit_once('\n') {
+            Some((details, summary)) => format!("{summary}\n\n{details}"),
+            None => report.to_string(),
+        },
+        None => ColumnMapping::from_form(mapping_lines.to_vec())
+            .map_err(|err| err.to_string())
+            .and_then(|mapping| table_records(&table.head(PREVIEW_ROWS), &mapping))
+            .map_or_else(|err| err, |records| preview(&records, PREVIEW_ROWS)),
+    };
+    lines.extend(
+        text.lines()
+            .map(|line| Line::from(vec![Span::raw(line.to_string())])),
+    );
+    let title = match report {
+        Some(_) => "Report   Edit the mapping to preview again",
+        None => "Preview",
+    };
+    Paragraph::new(lines).alignment(Alignment::Left).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .style(Style::default().fg(Color::White))
+            .title(title)
+            .border_type(BorderType::Plain),
+    )
 }
@@ -0,0 +1,364 @@
+use std::io::BufRead;
+
+/// One step through an XML document. Names are local: a `marc:record` is a `record`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum XmlEvent {
+    Start {
+        name: String,
+        attributes: Vec<(String, String)>,
+    },
+    End {
+        name: String,
+    },
+    /// Character data with entities decoded, including CDATA sections
+    Text(String),
+}
+
+/// An element read whole, with its children and the text directly inside it
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct XmlElement {
+    pub(crate) name: String,
+    pub(crate) attributes: Vec<(String, String)>,
+    pub(crate) children: Vec<XmlElement>,
+    pub(crate) text: String,
+}
+
+impl XmlElement {
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(attribute, _)| attribute == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Children named `name`, in order
+    pub fn children<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a XmlElement> + 'a {
+        self.children.iter().filter(move |child| child.name == name)
+    }
+
+    /// The first child named `name`
+    pub fn child(&self, name: &str) -> Option<&XmlElement> {
+        self.children.iter().find(|child| child.name == name)
+    }
+
+    /// Trimmed text of the element found by following `path` of child names, empty if there is none
+    pub fn text_at(&self, path: &[&str]) -> &str {
+        let mut element = self;
+        for name in path {
+            match element.child(name) {
+                Some(child) => element = child,
+                None => return "",
+            }
+        }
+        element.text.trim()
+    }
+}
+
+/// Reads XML one event at a time, so a file of any size can be read in constant memory.
+/// Comments, processing instructions and the document type are skipped.
+pub struct XmlReader<R: BufRead> {
+    reader: R,
+    /// A `<` has been read and the markup after it is next
+    in_markup: bool,
+    /// End of an empty element such as `<br/>`, due after its start
+    pending_end: Option<String>,
+}
+
+impl<R: BufRead> XmlReader<R> {
+    pub fn new(reader: R) -> XmlReader<R> {
+        XmlReader {
+            reader,
+            in_markup: false,
+            pending_end: None,
+        }
+    }
+
+    /// Bytes up to and including `end`, or to the end of the input
+    fn read_until(&mut self, end: u8) -> Result<Vec<u8>, String> {
+        let mut bytes = Vec::new();
+        self.reader
+            .read_until(end, &mut bytes)
+            .map_err(|err| err.to_string())?;
+        Ok(bytes)
+    }
+
+    /// Markup after a `<` up to the `>` that closes it, leaving out the `>`
+    fn read_markup(&mut self) -> Result<String, String> {
+        let mut bytes = self.read_until(b'>')?;
+        loop {
+            if bytes.pop() != Some(b'>') {
+                return Err("markup is not closed at the end of the file".to_string());
+            }
+            // a `>` inside a comment, CDATA section or quoted attribute value does not close it
+            let open = match &bytes {
+                markup if markup.starts_with(b"!--") => !markup.ends_with(b"--"),
+                markup if markup.starts_with(b"![CDATA[") => !markup.ends_with(b"]]"),
+                markup => {
+                    let quotes = |quote| markup.iter().filter(|byte| **byte == quote).count();
+                    quotes(b'"') % 2 == 1 || quotes(b'\'') % 2 == 1
+                }
+            };
+            if !open {
+                break;
+            }
+            let more = self.read_until(b'>')?;
+            if more.is_empty() {
+                return Err("markup is not closed at the end of the file".to_string());
+            }
+            bytes.push(b'>');
+            bytes.extend(more);
+        }
+        String::from_utf8(bytes).map_err(|_| "file is not UTF-8".to_string())
+    }
+
+    /// The next event, or `None` at the end of the document
+    pub fn next_event(&mut self) -> Result<Option<XmlEvent>, String> {
+        if let Some(name) = self.pending_end.take() {
+            return Ok(Some(XmlEvent::End { name }));
+        }
+        loop {
+            if !self.in_markup {
+                let mut bytes = self.read_until(b'<')?;
+                self.in_markup = bytes.last() == Some(&b'<');
+                if self.in_markup {
+                    bytes.pop();
+                }
+                if !self.in_markup && bytes.is_empty() {
+                    return Ok(None);
+                }
+                if !bytes.is_empty() {
+                    let text = String::from_utf8(bytes).map_err(|_| "file is not UTF-8")?;
+                    return Ok(Some(XmlEvent::Text(unescape(&text)?)));
+                }
+                continue;
+            }
+            self.in_markup = false;
+            let markup = self.read_markup()?;
+            if let Some(text) = markup
+                .strip_prefix("![CDATA[")
+                .and_then(|text| text.strip_suffix("]]"))
+            {
+                return Ok(Some(XmlEvent::Text(text.to_string())));
+            }
+            if markup.starts_with(['!', '?']) {
+                continue;
+            }
+            if let Some(name) = markup.strip_prefix('/') {
+                return Ok(Some(XmlEvent::End {
+                    name: local_name(name.trim()).to_string(),
+                }));
+            }
+            let (markup, empty) = match markup.strip_suffix('/') {
+                Some(markup) => (markup, true),
+                None => (markup.as_str(), false),
+            };
+            let (name, attributes) = start_tag(markup)?;
+            if empty {
+                self.pending_end = Some(name.clone());
+            }
+            return Ok(Some(XmlEvent::Start { name, attributes }));
+        }
+    }
+
+    /// The rest of an element whose start has just been read, up to its end
+    pub fn read_element(
+        &mut self,
+        name: String,
+        attributes: Vec<(String, String)>,
+    ) -> Result<XmlElement, String> {
+        let mut element = XmlElement {
+            name,
+            attributes,
+            ..XmlElement::default()
+        };
+        loop {
+            match self.next_event()? {
+                Some(XmlEvent::Start { name, attributes }) => {
+                    let child = self.read_element(name, attributes)?;
+                    element.children.push(child);
+                }
+                Some(XmlEvent::Text(text)) => element.text.push_str(&text),
+                Some(XmlEvent::End { name }) if name == element.name => return Ok(element),
+                Some(XmlEvent::End { name }) => {
+                    return Err(format!("`</{name}>` closes `<{}>`", element.name))
+                }
+                None => return Err(format!("`<{}>` is not closed", element.name)),
+            }
+        }
+    }
+}
+
+/// Every element named `name` in a document, read whole one at a time wherever it is nested,
+/// such as each `record` of a MARCXML collection. Reading stops at the first error.
+pub struct Elements<R: BufRead> {
+    reader: XmlReader<R>,
+    name: &'static str,
+    failed: bool,
+}
+
+impl<R: BufRead> Elements<R> {
+    pub fn new(reader: R, name: &'static str) -> Elements<R> {
+        Elements {
+            reader: XmlReader::new(reader),
+            name,
+            failed: false,
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for Elements<R> {
+    type Item = Result<XmlElement, String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.failed {
+            let element = match self.reader.next_event() {
+                Ok(Some(XmlEvent::Start { name, attributes })) if name == self.name => {
+                    self.reader.read_element(name, attributes)
+                }
+                Ok(Some(_)) => continue,
+                Ok(None) => return None,
+                Err(err) => Err(err),
+            };
+            self.failed = element.is_err();
+            return Some(element);
+        }
+        None
+    }
+}
+
+/// Name without its namespace prefix
+fn local_name(name: &str) -> &str {
+    name.rsplit(':').next().unwrap_or(name)
+}
+
+/// Name and attributes of a start tag; namespace declarations are left out
+fn start_tag(markup: &str) -> Result<(String, Vec<(String, String)>), String> {
+    let markup = markup.trim();
+    let (name, mut rest) = markup
+        .split_once(char::is_whitespace)
+        .unwrap_or((markup, ""));
+    let mut attributes = Vec::new();
+    loop {
+        rest = rest.trim_start();
+        if rest.is_empty() {
+            break;
+        }
+        let (attribute, value) = rest
+            .split_once('=')
+            .ok_or_else(|| format!("attribute in `<{name}>` has no value"))?;
+        let value = value.trim_start();
+        let quote = value
+            .chars()
+            .next()
+            .filter(|quote| *quote == '"' || *quote == '\'')
+            .ok_or_else(|| format!("attribute value in `<{name}>` is not quoted"))?;
+        let (value, after) = value[1..]
+            .split_once(quote)
+            .ok_or_else(|| format!("attribute value in `<{name}>` is not closed"))?;
+        let attribute = attribute.trim();
+        if attribute != "xmlns" && !attribute.starts_with("xmlns:") {
+            attributes.push((local_name(attribute).to_string(), unescape(value)?));
+        }
+        rest = after;
+    }
+    Ok((local_name(name).to_string(), attributes))
+}
+
+/// Text with `&amp;`, `&lt;`, `&#233;` and the other entities replaced by their characters
+pub fn unescape(text: &str) -> Result<String, String> {
+    let mut unescaped = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        unescaped.push_str(&rest[..start]);
+        let (entity, after) = rest[start + 1..]
+            .split_once(';')
+            .ok_or_else(|| "`&` does not start an entity".to_string())?;
+        let c = match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ => match entity.strip_prefix("#x").or(entity.strip_prefix("#X")) {
+                Some(hex) => u32::from_str_radix(hex, 16).ok().and_then(char::from_u32),
+                None => entity
+                    .strip_prefix('#')
+                    .and_then(|decimal| decimal.parse().ok())
+                    .and_then(char::from_u32),
+            },
+        };
+        unescaped.push(c.ok_or_else(|| format!("unknown entity `&{entity};`"))?);
+        rest = after;
+    }
+    unescaped.push_str(rest);
+    Ok(unescaped)
+}
+
+/// Text with the characters XML gives meaning to written as entities
+pub fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn reads_elements_one_at_a_time() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE collection>
+<!-- exported <records> -->
+<marc:collection xmlns:marc="http://www.loc.gov/MARC21/slim">
+  <marc:record>
+    <marc:datafield tag="245" ind1="1" ind2='0'>
+      <marc:subfield code="a">Fish &amp; chips &#233;t&#xE9; <![CDATA[<raw> & ]]></marc:subfield>
+      <marc:subfield code="b" note="a > b"/>
+    </marc:datafield>
+  </marc:record>
+  <marc:record><marc:leader>x</marc:leader></marc:record>
+  <marc:record><marc:leader>y</marc:record>
+  <marc:record/>
+</marc:collection>"#;
+        let mut records = Elements::new(xml.as_bytes(), "record");
+        let first = records.next().unwrap().unwrap();
+        let field = first.child("datafield").unwrap();
+        assert_eq!(field.attribute("tag"), Some("245"));
+        assert_eq!(field.attribute("ind2"), Some("0"));
+        let subfields: Vec<&XmlElement> = field.children("subfield").collect();
+        assert_eq!(subfields[0].text, "Fish & chips été <raw> & ");
+        assert_eq!(subfields[1].attribute("note"), Some("a > b"));
+        assert_eq!(subfields[1].text, "");
+        assert_eq!(records.next().unwrap().unwrap().text_at(&["leader"]), "x");
+        assert_eq!(
+            records.next().unwrap().unwrap_err(),
+            "`</record>` closes `<leader>`"
+        );
+        assert!(records.next().is_none());
+
+        // a file that ends inside a quoted attribute value after a `>`
+        let mut records = Elements::new("<collection><record a=\"x>".as_bytes(), "record");
+        assert_eq!(
+            records.next().unwrap().unwrap_err(),
+            "markup is not closed at the end of the file"
+        );
+        assert!(records.next().is_none());
+
+        assert_eq!(
+            escape("<a href=\"x\">R&D's</a>"),
+            "&lt;a href=&quot;x&quot;&gt;R&amp;D&apos;s&lt;/a&gt;"
+        );
+        assert_eq!(unescape(&escape("<\"&'>")).unwrap(), "<\"&'>");
+        assert!(unescape("AT&T").is_err());
+    }
+}